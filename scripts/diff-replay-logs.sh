@@ -0,0 +1,26 @@
+#!/bin/sh
+# Compares two logs written by the --replay-log option, without the syscall
+# arguments, which are mostly addresses changing from one run to another.
+#
+# Usage: diff-replay-logs.sh first.log second.log [diff options...]
+
+if [ $# -lt 2 ]; then
+    echo "usage: $0 first.log second.log [diff options...]" >&2
+    exit 2
+fi
+
+first="$1"
+second="$2"
+shift 2
+
+strip_args() {
+    sed 's/ args=(.*)$//' "$1"
+}
+
+first_stripped=$(mktemp)
+second_stripped=$(mktemp)
+trap 'rm -f "$first_stripped" "$second_stripped"' EXIT
+
+strip_args "$first" > "$first_stripped"
+strip_args "$second" > "$second_stripped"
+diff "$@" "$first_stripped" "$second_stripped"
@@ -1,12 +1,106 @@
+use std::time::Duration;
+
 use clap::{App, Arg};
 
-use crate::errors::*;
+use crate::config::{ProotConfig, SysinfoOverlay, TraceeLimitPolicy};
+use crate::filesystem::binding::BindingFlags;
 use crate::filesystem::validation::{binding_validator, path_validator};
-use crate::filesystem::FileSystem;
+use crate::filesystem::FallbackPolicy;
+use crate::kernel::execve::binfmt::BinfmtRule;
+use crate::kernel::syscall::sysnum_of_name;
 
 pub const DEFAULT_ROOTFS: &'static str = "/";
 pub const DEFAULT_CWD: &'static str = "/";
 
+/// Maximum length of a hostname, see sethostname(2).
+pub const HOST_NAME_MAX: usize = 64;
+
+fn hostname_validator(hostname: String) -> Result<(), String> {
+    if hostname.is_empty() || hostname.len() > HOST_NAME_MAX {
+        return Err(format!(
+            "The hostname must have 1 to {} characters",
+            HOST_NAME_MAX
+        ));
+    }
+    Ok(())
+}
+
+fn domainname_validator(domainname: String) -> Result<(), String> {
+    // the maximum length of a domain name is the one of a hostname, see
+    // setdomainname(2)
+    if domainname.len() > HOST_NAME_MAX {
+        return Err(format!(
+            "The domain name must have at most {} characters",
+            HOST_NAME_MAX
+        ));
+    }
+    Ok(())
+}
+
+fn mq_prefix_validator(prefix: String) -> Result<(), String> {
+    if prefix.is_empty() || prefix.contains('/') {
+        return Err(format!(
+            "{:?} must be a non-empty message queue name without '/'",
+            prefix
+        ));
+    }
+    Ok(())
+}
+
+fn binfmt_validator(rule: String) -> Result<(), String> {
+    BinfmtRule::parse(&rule).map(|_| ())
+}
+
+fn binding_flags_validator(value: String) -> Result<(), String> {
+    match value.rfind(':') {
+        Some(index) if value.starts_with('/') => {
+            BindingFlags::parse(&value[index + 1..]).map(|_| ())
+        }
+        _ => Err(format!(
+            "{:?} must be of the form guest_path:flag,flag...",
+            value
+        )),
+    }
+}
+
+fn init_validator(path: String) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err(format!(
+            "The init program {:?} must be an absolute path",
+            path
+        ));
+    }
+    Ok(())
+}
+
+fn umask_validator(umask: String) -> Result<(), String> {
+    match u32::from_str_radix(&umask, 8) {
+        Ok(umask) if umask <= 0o777 => Ok(()),
+        _ => Err(format!("{:?} is not an octal umask", umask)),
+    }
+}
+
+fn syscall_validator(name: String) -> Result<(), String> {
+    match sysnum_of_name(&name) {
+        Some(_) => Ok(()),
+        None => Err(format!("{:?} is not a known syscall", name)),
+    }
+}
+
+fn env_var_validator(name: String) -> Result<(), String> {
+    if name.is_empty() || name.contains('=') {
+        return Err(format!("{:?} is not an environment variable name", name));
+    }
+    Ok(())
+}
+
+fn number_validator(number: String) -> Result<(), String> {
+    number
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("{:?} is not a positive integer", number))
+}
+
 pub fn get_args_parser() -> App<'static, 'static> {
     App::new("proot-rs")
         .arg(Arg::with_name("rootfs")
@@ -16,6 +110,25 @@ pub fn get_args_parser() -> App<'static, 'static> {
             .takes_value(true)
             .default_value(DEFAULT_ROOTFS)
             .validator(path_validator))
+        .arg(Arg::with_name("layer")
+            .long("layer")
+            .help("Search *path* (read-only) for the guest paths missing from the guest rootfs. Can be repeated, the first layer wins.")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true)
+            .validator(path_validator))
+        .arg(Arg::with_name("copy-up")
+            .long("copy-up")
+            .help("Copy a file of a --layer into the guest rootfs before modifying it, instead of failing with EROFS."))
+        .arg(Arg::with_name("proc-shim")
+            .long("proc-shim")
+            .help("Make the host /proc accessible in the guest rootfs, while still virtualizing the links of the tracees (e.g. /proc/self/exe)."))
+        .arg(Arg::with_name("dev-shim")
+            .long("dev-shim")
+            .help("Make the usual host devices (/dev/null, /dev/zero, /dev/full, /dev/random, /dev/urandom and /dev/tty) accessible in the guest rootfs."))
+        .arg(Arg::with_name("virtual-mounts")
+            .long("virtual-mounts")
+            .help("Show the guest rootfs and bindings in /proc/mounts and /proc/self/mountinfo, instead of the host mounts."))
         .arg(Arg::with_name("bind")
             .short("b")
             .long("bind")
@@ -23,49 +136,418 @@ pub fn get_args_parser() -> App<'static, 'static> {
             .multiple(true)
             .takes_value(true)
             .validator(binding_validator))
+        .arg(Arg::with_name("bind-ro")
+            .long("bind-ro")
+            .help("Same as --bind, but the files of the binding can't be opened for writing (EROFS). Format: host_path:guest_path")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true)
+            .validator(binding_validator))
+        .arg(Arg::with_name("bind-flags")
+            .long("bind-flags")
+            .help("Set mount flags (noexec, nosuid, nodev) on the binding of *guest_path*, emulated by proot-rs. Format: guest_path:flag,flag...")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true)
+            .validator(binding_flags_validator))
+        .arg(Arg::with_name("bind-create")
+            .long("bind-create")
+            .help("Create the missing host paths of the bindings (as directories), instead of failing at startup."))
+        .arg(Arg::with_name("bind-synthetic")
+            .long("bind-synthetic")
+            .help("Allow binding a directory onto a guest path missing from the rootfs, which is stated as the bound target."))
         .arg(Arg::with_name("cwd")
             .short("w")
             .long("cwd")
             .help("Set the initial working directory to *path*.")
             .takes_value(true)
             .default_value(DEFAULT_CWD))
+        .arg(Arg::with_name("init")
+            .long("init")
+            .help("Execute the guest *path* as the first program, with the command as its arguments, like the init= of the kernel command line.")
+            .takes_value(true)
+            .validator(init_validator))
+        .arg(Arg::with_name("init-argv0")
+            .long("init-argv0")
+            .help("Use *name* as the argv[0] of the init program, instead of its path.")
+            .takes_value(true)
+            .requires("init"))
+        .arg(Arg::with_name("preserve-argv0")
+            .long("preserve-argv0")
+            .help("Keep the original argv[0] when a script is executed through its interpreter."))
+        .arg(Arg::with_name("aslr")
+            .long("aslr")
+            .help("Load the position independent executables at random addresses, instead of fixed ones."))
+        .arg(Arg::with_name("no-randomize")
+            .long("no-randomize")
+            .help("Disable the address space randomization of the kernel, even if the programs ask for it with personality().")
+            .conflicts_with("aslr"))
+        .arg(Arg::with_name("deny-swap")
+            .long("deny-swap")
+            .help("Make swapon() and swapoff() fail with EPERM, without asking the host."))
+        .arg(Arg::with_name("emulate-pivot-root")
+            .long("emulate-pivot-root")
+            .help("Emulate pivot_root() by changing the guest root, instead of asking the host."))
+        .arg(Arg::with_name("emulate-chroot")
+            .long("emulate-chroot")
+            .help("Emulate chroot() by changing the guest root, instead of asking the host."))
+        .arg(Arg::with_name("emulate-move-mount")
+            .long("emulate-move-mount")
+            .help("Emulate open_tree() and move_mount() by adding bindings, instead of asking the host."))
+        .arg(Arg::with_name("audit-escapes")
+            .long("audit-escapes")
+            .help("Log the paths which try to escape from the guest rootfs with \"..\"."))
+        .arg(Arg::with_name("fallback-policy")
+            .long("fallback-policy")
+            .help("Set what happens when a guest path doesn't exist: fail with ENOENT (*not-found*), fail with EACCES (*deny*), or let the kernel handle the path under the rootfs (*permissive*).")
+            .takes_value(true)
+            .possible_values(&["not-found", "deny", "permissive"])
+            .default_value("not-found"))
+        .arg(Arg::with_name("case-insensitive")
+            .long("case-insensitive")
+            .help("Retry the translation of the missing guest paths case-insensitively, for a rootfs extracted on a case-insensitive filesystem (slow)."))
+        .arg(Arg::with_name("hostname")
+            .long("hostname")
+            .help("Report *name* as the hostname to the guest, instead of the host one.")
+            .takes_value(true)
+            .validator(hostname_validator))
+        .arg(Arg::with_name("domainname")
+            .long("domainname")
+            .help("Report *name* as the domain name to the guest, instead of the host one.")
+            .takes_value(true)
+            .validator(domainname_validator))
+        .arg(Arg::with_name("sysinfo-totalram")
+            .long("sysinfo-totalram")
+            .help("Report at most *bytes* of total memory to sysinfo().")
+            .takes_value(true)
+            .validator(number_validator))
+        .arg(Arg::with_name("sysinfo-freeram")
+            .long("sysinfo-freeram")
+            .help("Report at most *bytes* of free memory to sysinfo().")
+            .takes_value(true)
+            .validator(number_validator))
+        .arg(Arg::with_name("sysinfo-uptime")
+            .long("sysinfo-uptime")
+            .help("Report an uptime of at most *seconds* to sysinfo().")
+            .takes_value(true)
+            .validator(number_validator))
+        .arg(Arg::with_name("timeout")
+            .long("timeout")
+            .help("Kill all the tracees after *seconds*, and exit with the code 124.")
+            .takes_value(true)
+            .validator(number_validator))
+        .arg(Arg::with_name("bypass-syscall")
+            .long("bypass-syscall")
+            .help("Pass the syscall *name* through untouched, with the guest paths given as-is to the host. Can be repeated.")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true)
+            .validator(syscall_validator))
+        .arg(Arg::with_name("sanitize-ld-library-path")
+            .long("sanitize-ld-library-path")
+            .help("Rewrite the host paths under the guest rootfs found in LD_LIBRARY_PATH as guest paths, when a program is executed."))
+        .arg(Arg::with_name("strip-env")
+            .long("strip-env")
+            .help("Remove the environment variable *name* from the environment of the programs executed. Can be repeated.")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true)
+            .validator(env_var_validator))
+        .arg(Arg::with_name("replay-log")
+            .long("replay-log")
+            .help("Log each translated syscall to *path*, one per line. Two logs can be compared with scripts/diff-replay-logs.sh.")
+            .takes_value(true))
+        .arg(Arg::with_name("strict-io-uring")
+            .long("strict-io-uring")
+            .help("Make io_uring_setup() fail with EOPNOTSUPP, since the paths submitted to io_uring can't be translated."))
+        .arg(Arg::with_name("strict-mount-setattr")
+            .long("strict-mount-setattr")
+            .help("Make mount_setattr() fail with EPERM, since the bindings aren't mounts."))
+        .arg(Arg::with_name("mq-prefix")
+            .long("mq-prefix")
+            .help("Add *prefix* to the names of the POSIX message queues of the guest, to keep them apart from the host ones.")
+            .takes_value(true)
+            .validator(mq_prefix_validator))
+        .arg(Arg::with_name("random-seed")
+            .long("random-seed")
+            .help("Fill the buffers of getrandom() from a pseudo-random generator seeded with *seed*, instead of the host entropy.")
+            .takes_value(true)
+            .validator(number_validator))
+        .arg(Arg::with_name("umask")
+            .long("umask")
+            .help("Use the octal *umask* for the files created by the guest, and report it to umask(), instead of the host one.")
+            .takes_value(true)
+            .validator(umask_validator))
+        .arg(Arg::with_name("fake-ownership")
+            .long("fake-ownership")
+            .help("Fake the changes of the owners of the files (and of their modes, when the host refuses them), reported by stat() without changing the host files."))
+        .arg(Arg::with_name("max-tracees")
+            .long("max-tracees")
+            .help("Trace at most *number* processes at the same time.")
+            .takes_value(true)
+            .validator(number_validator))
+        .arg(Arg::with_name("tracee-limit-policy")
+            .long("tracee-limit-policy")
+            .help("Set what happens to a new process beyond --max-tracees: its creation fails with EAGAIN (*fail*), or it runs untraced (*untrace*).")
+            .takes_value(true)
+            .possible_values(&["fail", "untrace"])
+            .default_value("fail"))
+        .arg(Arg::with_name("strip-shebang-cr")
+            .long("strip-shebang-cr")
+            .help("Ignore the \\r ending the #! line of the scripts with CRLF line endings, instead of taking it as part of the interpreter path like Linux."))
+        .arg(Arg::with_name("binfmt")
+            .long("binfmt")
+            .help("Run the files which are neither ELF files nor scripts with an interpreter of the guest, registered with the syntax of binfmt_misc: *:name:type:offset:magic:mask:interpreter:flags*. Can be repeated.")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true)
+            .validator(binfmt_validator))
+        .arg(Arg::with_name("save-fs-state")
+            .long("save-fs-state")
+            .help("Save the root, bindings and options of the guest filesystem to *path*, to set up the same session again with --load-fs-state.")
+            .takes_value(true))
+        .arg(Arg::with_name("load-fs-state")
+            .long("load-fs-state")
+            .help("Set up the guest filesystem from *path*, saved by --save-fs-state. Can't be used with the other filesystem options.")
+            .takes_value(true)
+            .conflicts_with_all(&[
+                "save-fs-state", "rootfs", "layer", "copy-up", "proc-shim",
+                "dev-shim", "bind", "bind-ro", "bind-flags", "bind-create",
+                "bind-synthetic", "cwd", "audit-escapes", "fallback-policy",
+                "case-insensitive", "umask",
+            ])
+            .validator(path_validator))
+        .arg(Arg::with_name("path-max")
+            .long("path-max")
+            .help("Fail with ENAMETOOLONG on the paths of the tracees longer than *number* bytes (null terminator included), instead of PATH_MAX.")
+            .takes_value(true)
+            .validator(number_validator))
         .arg(Arg::with_name("command")
             .multiple(true))
 }
 
-pub fn parse_config() -> Result<(FileSystem, Vec<String>)> {
+pub fn parse_config() -> (ProotConfig, Vec<String>) {
     let app = get_args_parser();
 
-    let mut fs: FileSystem = FileSystem::new();
-
     let matches = app.get_matches();
 
     debug!("proot-rs startup with args:\n{:#?}", matches);
 
     // option -r
     let rootfs: &str = matches.value_of("rootfs").unwrap();
-    // -r *path* is equivalent to -b *path*:/
-    fs.set_root(rootfs)?;
+    let mut builder = ProotConfig::builder().rootfs(rootfs);
+
+    // option(s) --layer
+    if let Some(layers) = matches.values_of("layer") {
+        for layer in layers {
+            builder = builder.layer(layer);
+        }
+    }
+
+    // option --copy-up
+    builder = builder.copy_up(matches.is_present("copy-up"));
+
+    // option --proc-shim
+    builder = builder.proc_shim(matches.is_present("proc-shim"));
+
+    // option --dev-shim
+    builder = builder.dev_shim(matches.is_present("dev-shim"));
+
+    // option --virtual-mounts
+    builder = builder.virtual_mounts(matches.is_present("virtual-mounts"));
 
     // option(s) -b
     if let Some(bindings) = matches.values_of("bind") {
-        let raw_bindings_str: Vec<&str> = bindings.collect::<Vec<&str>>();
+        for raw_binding_str in bindings {
+            let parts: Vec<&str> = raw_binding_str.split_terminator(':').collect();
+            builder = builder.binding(parts[0], parts[1]);
+        }
+    }
 
-        for raw_binding_str in &raw_bindings_str {
+    // option(s) --bind-ro
+    if let Some(bindings) = matches.values_of("bind-ro") {
+        for raw_binding_str in bindings {
             let parts: Vec<&str> = raw_binding_str.split_terminator(':').collect();
-            fs.add_binding(parts[0], parts[1])?;
+            builder = builder.read_only_binding(parts[0], parts[1]);
         }
     }
 
+    // option(s) --bind-flags
+    if let Some(values) = matches.values_of("bind-flags") {
+        for value in values {
+            let (guest_path, flags) = value.split_at(value.rfind(':').unwrap());
+            builder = builder.binding_flags(guest_path, BindingFlags::parse(&flags[1..]).unwrap());
+        }
+    }
+
+    // option --bind-create
+    builder = builder.create_missing_bindings(matches.is_present("bind-create"));
+
+    // option --bind-synthetic
+    builder = builder.synthetic_mountpoints(matches.is_present("bind-synthetic"));
+
     // option -w
     let cwd: &str = matches.value_of("cwd").unwrap();
-    fs.set_cwd(cwd)?;
+    builder = builder.cwd(cwd);
+
+    // option --init
+    if let Some(init) = matches.value_of("init") {
+        builder = builder.init(init);
+    }
+
+    // option --init-argv0
+    if let Some(init_argv0) = matches.value_of("init-argv0") {
+        builder = builder.init_argv0(init_argv0);
+    }
+
+    // option --preserve-argv0
+    builder = builder.preserve_argv0(matches.is_present("preserve-argv0"));
+
+    // option --aslr
+    builder = builder.randomize_load_addresses(matches.is_present("aslr"));
+
+    // option --no-randomize
+    builder = builder.no_randomize(matches.is_present("no-randomize"));
+
+    // option --deny-swap
+    builder = builder.deny_swap(matches.is_present("deny-swap"));
+
+    // option --emulate-pivot-root
+    builder = builder.emulate_pivot_root(matches.is_present("emulate-pivot-root"));
+
+    // option --emulate-chroot
+    builder = builder.emulate_chroot(matches.is_present("emulate-chroot"));
+
+    // option --emulate-move-mount
+    builder = builder.emulate_move_mount(matches.is_present("emulate-move-mount"));
+
+    // option --audit-escapes
+    builder = builder.audit_escapes(matches.is_present("audit-escapes"));
+
+    // option --fallback-policy
+    builder = builder.fallback_policy(match matches.value_of("fallback-policy").unwrap() {
+        "deny" => FallbackPolicy::Deny,
+        "permissive" => FallbackPolicy::Permissive,
+        _ => FallbackPolicy::NotFound,
+    });
+
+    // option --case-insensitive
+    builder = builder.case_insensitive_paths(matches.is_present("case-insensitive"));
+
+    // option --hostname
+    if let Some(hostname) = matches.value_of("hostname") {
+        builder = builder.hostname(hostname);
+    }
+
+    // option --domainname
+    if let Some(domainname) = matches.value_of("domainname") {
+        builder = builder.domainname(domainname);
+    }
+
+    // options --sysinfo-*
+    let parse_number = |name| matches.value_of(name).map(|value| value.parse().unwrap());
+    builder = builder.sysinfo_overlay(SysinfoOverlay {
+        total_ram: parse_number("sysinfo-totalram"),
+        free_ram: parse_number("sysinfo-freeram"),
+        uptime: parse_number("sysinfo-uptime"),
+    });
+
+    // option --timeout
+    if let Some(seconds) = parse_number("timeout") {
+        builder = builder.timeout(Duration::from_secs(seconds));
+    }
+
+    // option(s) --bypass-syscall
+    if let Some(names) = matches.values_of("bypass-syscall") {
+        for name in names {
+            builder = builder.bypass_syscall(name);
+        }
+    }
+
+    // option --sanitize-ld-library-path
+    builder = builder.sanitize_ld_library_path(matches.is_present("sanitize-ld-library-path"));
+
+    // option(s) --strip-env
+    if let Some(names) = matches.values_of("strip-env") {
+        for name in names {
+            builder = builder.strip_env_var(name);
+        }
+    }
+
+    // option --replay-log
+    if let Some(path) = matches.value_of("replay-log") {
+        builder = builder.replay_log(path);
+    }
+
+    // option --strict-io-uring
+    builder = builder.strict_io_uring(matches.is_present("strict-io-uring"));
+
+    // option --strict-mount-setattr
+    builder = builder.strict_mount_setattr(matches.is_present("strict-mount-setattr"));
+
+    // option --mq-prefix
+    if let Some(mq_prefix) = matches.value_of("mq-prefix") {
+        builder = builder.mq_prefix(mq_prefix);
+    }
+
+    // option --random-seed
+    if let Some(seed) = parse_number("random-seed") {
+        builder = builder.random_seed(seed);
+    }
+
+    // option --umask
+    if let Some(umask) = matches.value_of("umask") {
+        builder = builder.umask(u32::from_str_radix(umask, 8).unwrap());
+    }
+
+    // option --fake-ownership
+    builder = builder.fake_ownership(matches.is_present("fake-ownership"));
+
+    // option --max-tracees
+    if let Some(max_tracees) = matches.value_of("max-tracees") {
+        builder = builder.max_tracees(max_tracees.parse().unwrap());
+    }
+
+    // option --tracee-limit-policy
+    builder = builder.tracee_limit_policy(match matches.value_of("tracee-limit-policy").unwrap() {
+        "untrace" => TraceeLimitPolicy::Untrace,
+        _ => TraceeLimitPolicy::Fail,
+    });
+
+    // option --strip-shebang-cr
+    builder = builder.strip_shebang_cr(matches.is_present("strip-shebang-cr"));
+
+    // option(s) --binfmt
+    if let Some(rules) = matches.values_of("binfmt") {
+        for rule in rules {
+            builder = builder.binfmt_rule(BinfmtRule::parse(rule).unwrap());
+        }
+    }
+
+    // option --save-fs-state
+    if let Some(path) = matches.value_of("save-fs-state") {
+        builder = builder.save_fs_state(path);
+    }
+
+    // option --load-fs-state
+    if let Some(path) = matches.value_of("load-fs-state") {
+        builder = builder.load_fs_state(path);
+    }
+
+    // option --path-max
+    if let Some(path_max) = matches.value_of("path-max") {
+        builder = builder.path_max(path_max.parse().unwrap());
+    }
 
-    // command
-    let command: Vec<String> = match matches.values_of("command") {
-        Some(values) => values.map(|s| s.into()).collect(),
-        None => ["/bin/sh".into()].into(),
+    // command, or the arguments of the init program
+    let config = builder.build();
+    let args: Option<Vec<String>> = matches
+        .values_of("command")
+        .map(|values| values.map(|s| s.into()).collect());
+    let command: Vec<String> = match config.init_command(args.clone().unwrap_or_default()) {
+        Some(command) => command,
+        None => args.unwrap_or_else(|| ["/bin/sh".into()].into()),
     };
 
-    Ok((fs, command))
+    (config, command)
 }
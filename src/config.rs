@@ -0,0 +1,798 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use nix::sys::stat::Mode;
+
+use crate::cli::{DEFAULT_CWD, DEFAULT_ROOTFS};
+use crate::errors::*;
+use crate::filesystem::binding::BindingFlags;
+use crate::filesystem::{FallbackPolicy, FileSystem, PathResolver};
+use crate::kernel::execve::binfmt::BinfmtRule;
+use crate::kernel::syscall::name_of_syscall;
+
+/// Host devices bound into the guest by `dev_shim`, when they exist.
+pub const DEV_SHIM_DEVICES: &[&str] = &[
+    "/dev/null",
+    "/dev/zero",
+    "/dev/full",
+    "/dev/random",
+    "/dev/urandom",
+    "/dev/tty",
+];
+
+/// Caps of the values reported to the guest by sysinfo(), to reproduce a
+/// resource-constrained environment. The unset ones are left untouched.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SysinfoOverlay {
+    /// Maximum total memory, in bytes.
+    pub total_ram: Option<u64>,
+    /// Maximum free memory, in bytes.
+    pub free_ram: Option<u64>,
+    /// Maximum uptime, in seconds.
+    pub uptime: Option<u64>,
+}
+
+/// What happens to a process created by a tracee when there are already
+/// `max_tracees` tracees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceeLimitPolicy {
+    /// Fail the fork(), vfork() or clone() with `EAGAIN`, like the kernel
+    /// does when `RLIMIT_NPROC` is reached.
+    Fail,
+    /// Let the new process run, but untraced: its paths aren't translated
+    /// anymore.
+    Untrace,
+}
+
+impl Default for TraceeLimitPolicy {
+    fn default() -> TraceeLimitPolicy {
+        TraceeLimitPolicy::Fail
+    }
+}
+
+/// Gathers all the options of a proot-rs session in one place.
+///
+/// It's built with `ProotConfig::builder()`, and then consumed to create the
+/// initial `FileSystem` (see `create_filesystem`) and the `InfoBag` shared by
+/// all the tracees, from which the syscall handlers read the options.
+/// The default values match the ones of the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProotConfig {
+    /// Host path of the guest root file-system.
+    pub rootfs: PathBuf,
+    /// Host paths of read-only lower layers, searched in order below the guest
+    /// root for the guest paths missing from it.
+    pub layers: Vec<PathBuf>,
+    /// Copy a file of a lower layer into the guest root before it's modified.
+    pub copy_up: bool,
+    /// Bind the host `/proc` to the guest `/proc`, where the links of the
+    /// tracees (e.g. `/proc/self/exe`) are still virtualized.
+    pub proc_shim: bool,
+    /// Bind the host devices of `DEV_SHIM_DEVICES` to the same paths in the
+    /// guest, which are opened and stated as the host ones.
+    pub dev_shim: bool,
+    /// Replace the mount tables of `/proc` (e.g. `/proc/self/mountinfo`) by
+    /// the ones of the guest: its rootfs and its bindings.
+    pub virtual_mounts: bool,
+    /// `(host_path, guest_path)` bindings, in the order they were given.
+    pub bindings: Vec<(PathBuf, PathBuf)>,
+    /// `(host_path, guest_path)` read-only bindings, added after the other
+    /// ones: their files can't be opened for writing.
+    pub read_only_bindings: Vec<(PathBuf, PathBuf)>,
+    /// Mount flags of the bindings, by guest path: the most recent binding
+    /// of the guest path gets them.
+    pub binding_flags: Vec<(PathBuf, BindingFlags)>,
+    /// Create the missing host paths of the bindings (as directories),
+    /// instead of failing at startup.
+    pub create_missing_bindings: bool,
+    /// Allow binding a directory onto a guest path missing from the rootfs,
+    /// which is then stated as the bound target.
+    pub synthetic_mountpoints: bool,
+    /// Initial working directory, as seen by the guest.
+    pub cwd: PathBuf,
+    /// Guest path of the program executed as the first tracee, like the
+    /// `init=` of the kernel command line. The command is then made of its
+    /// arguments, see `init_command`.
+    pub init: Option<PathBuf>,
+    /// argv[0] of `init`, instead of its path (e.g. "init", for the
+    /// multi-call binaries dispatching on their name).
+    pub init_argv0: Option<String>,
+    /// Keep the original argv[0] when a script is executed through the
+    /// interpreter of its shebang, instead of replacing it by the path of the
+    /// interpreter.
+    pub preserve_argv0: bool,
+    /// Load the position independent executables and their interpreter at
+    /// random addresses, instead of fixed ones (reproducible).
+    pub randomize_load_addresses: bool,
+    /// Start the tracees with the `ADDR_NO_RANDOMIZE` persona, and keep it
+    /// through personality(), so that the kernel maps the stack, the heap and
+    /// the libraries at the same addresses on each run.
+    pub no_randomize: bool,
+    /// Fail swapon() and swapoff() with `EPERM`, without asking the host.
+    pub deny_swap: bool,
+    /// Emulate pivot_root() by remapping the bindings, instead of asking the
+    /// host.
+    pub emulate_pivot_root: bool,
+    /// Emulate chroot() by changing the guest root, instead of asking the
+    /// host.
+    pub emulate_chroot: bool,
+    /// Emulate move_mount() by adding a binding, instead of asking the host.
+    pub emulate_move_mount: bool,
+    /// Log the guest paths which try to escape from the guest root.
+    pub audit_escapes: bool,
+    /// What to do with the guest paths which don't exist.
+    pub fallback_policy: FallbackPolicy,
+    /// Retry the translation of the missing guest paths case-insensitively,
+    /// for the rootfs extracted on case-insensitive filesystems.
+    pub case_insensitive_paths: bool,
+    /// Backend of the path resolution, instead of the `DefaultResolver`.
+    pub path_resolver: Option<Rc<dyn PathResolver>>,
+    /// Hostname reported to the guest by uname(), instead of the host one.
+    /// sethostname() then changes it, without changing the host one.
+    pub hostname: Option<String>,
+    /// Domain name reported to the guest by uname(), like `hostname`.
+    pub domainname: Option<String>,
+    /// Caps of the values reported by sysinfo().
+    pub sysinfo_overlay: SysinfoOverlay,
+    /// Wall-clock time after which all the tracees are killed, and proot-rs
+    /// exits with `TIMEOUT_EXIT_CODE`. The time spent by proot-rs itself
+    /// (e.g. translating a long syscall) counts as well.
+    pub timeout: Option<Duration>,
+    /// Names of the syscalls passed through untouched, on both stages, as if
+    /// proot-rs didn't handle them: their paths are the host ones. Meant for
+    /// bisecting the translation bugs.
+    pub bypassed_syscalls: HashSet<String>,
+    /// Rewrite the entries of `LD_LIBRARY_PATH` under the host path of the
+    /// guest root as guest paths, when a program is executed.
+    pub sanitize_ld_library_path: bool,
+    /// Names of the environment variables removed from the envp[] of
+    /// execve(), before it is otherwise rewritten.
+    pub stripped_env_vars: HashSet<String>,
+    /// File where each translated syscall is logged, see `ReplayLog`.
+    pub replay_log: Option<PathBuf>,
+    /// Fail io_uring_setup() with `EOPNOTSUPP`, since the path operations
+    /// submitted to a ring (e.g. `IORING_OP_OPENAT`) can't be translated.
+    pub strict_io_uring: bool,
+    /// Fail mount_setattr() with `EPERM`, since the bindings aren't mounts
+    /// whose attributes could be changed.
+    pub strict_mount_setattr: bool,
+    /// Prefix added to the names of the POSIX message queues of the guest,
+    /// so that they don't collide with the ones of the host.
+    pub mq_prefix: Option<String>,
+    /// Seed of the pseudo-random generator filling the buffers of
+    /// getrandom(), instead of the host entropy, for reproducible runs.
+    pub random_seed: Option<u64>,
+    /// Virtual umask of the guest, applied to the modes of the created files
+    /// and reported by umask(), instead of the host one.
+    pub umask: Option<u32>,
+    /// Fake the changes of the owners of the files, and of their modes when
+    /// the host refuses them: the guest sees them in stat(), but the host
+    /// files are left untouched. See `FakeMetadata`.
+    pub fake_ownership: bool,
+    /// Maximum number of processes traced at the same time.
+    pub max_tracees: Option<usize>,
+    /// What happens to the processes created beyond `max_tracees`.
+    pub tracee_limit_policy: TraceeLimitPolicy,
+    /// Panic when a syscall returns a host path under the guest root to the
+    /// guest, see `leak_check`. Meant for the developers, it's enabled in
+    /// the tests.
+    pub check_host_leaks: bool,
+    /// Ignore the `\r` ending the shebang line of a script with CRLF line
+    /// endings, which Linux takes as part of the interpreter path.
+    pub strip_shebang_cr: bool,
+    /// Interpreters of the executed files which are neither ELF files nor
+    /// scripts, like the ones registered in binfmt_misc.
+    pub binfmt_rules: Vec<BinfmtRule>,
+    /// File where the configuration of the initial `FileSystem` is saved,
+    /// see `FileSystem::save_state`.
+    pub save_fs_state: Option<PathBuf>,
+    /// File from which the initial `FileSystem` is loaded, instead of being
+    /// set up from the root, layers and bindings of this configuration (the
+    /// command line rejects these options with `--load-fs-state`).
+    pub load_fs_state: Option<PathBuf>,
+    /// Maximum length (null terminator included) of the paths read from the
+    /// memory of the tracees, instead of `PATH_MAX`. Longer paths fail with
+    /// `ENAMETOOLONG`.
+    pub path_max: Option<usize>,
+}
+
+impl Default for ProotConfig {
+    fn default() -> ProotConfig {
+        ProotConfig {
+            rootfs: PathBuf::from(DEFAULT_ROOTFS),
+            layers: vec![],
+            copy_up: false,
+            proc_shim: false,
+            dev_shim: false,
+            virtual_mounts: false,
+            bindings: vec![],
+            read_only_bindings: vec![],
+            binding_flags: vec![],
+            create_missing_bindings: false,
+            synthetic_mountpoints: false,
+            cwd: PathBuf::from(DEFAULT_CWD),
+            init: None,
+            init_argv0: None,
+            preserve_argv0: false,
+            randomize_load_addresses: false,
+            no_randomize: false,
+            deny_swap: false,
+            emulate_pivot_root: false,
+            emulate_chroot: false,
+            emulate_move_mount: false,
+            audit_escapes: false,
+            fallback_policy: FallbackPolicy::default(),
+            case_insensitive_paths: false,
+            path_resolver: None,
+            hostname: None,
+            domainname: None,
+            sysinfo_overlay: SysinfoOverlay::default(),
+            timeout: None,
+            bypassed_syscalls: HashSet::new(),
+            sanitize_ld_library_path: false,
+            stripped_env_vars: HashSet::new(),
+            replay_log: None,
+            strict_io_uring: false,
+            strict_mount_setattr: false,
+            mq_prefix: None,
+            random_seed: None,
+            umask: None,
+            fake_ownership: false,
+            max_tracees: None,
+            tracee_limit_policy: TraceeLimitPolicy::default(),
+            check_host_leaks: cfg!(test),
+            strip_shebang_cr: false,
+            binfmt_rules: vec![],
+            save_fs_state: None,
+            load_fs_state: None,
+            path_max: None,
+        }
+    }
+}
+
+impl ProotConfig {
+    pub fn builder() -> ProotConfigBuilder {
+        ProotConfigBuilder::default()
+    }
+
+    /// Returns `true` if the syscall numbered `sysnum` mustn't be translated
+    /// (see `bypassed_syscalls`).
+    pub fn is_syscall_bypassed(&self, sysnum: usize) -> bool {
+        !self.bypassed_syscalls.is_empty()
+            && name_of_syscall(sysnum).map_or(false, |name| self.bypassed_syscalls.contains(name))
+    }
+
+    /// Returns the argv of `init` followed by `args`, or `None` if no init
+    /// program is configured. Its argv[0] is `init_argv0`, or its path like
+    /// the kernel does.
+    pub fn init_command(&self, args: Vec<String>) -> Option<Vec<String>> {
+        let init = self.init.as_ref()?;
+        let argv0 = match &self.init_argv0 {
+            Some(argv0) => argv0.clone(),
+            None => init.to_string_lossy().into_owned(),
+        };
+        Some(std::iter::once(argv0).chain(args).collect())
+    }
+
+    /// Creates the initial filesystem of the tracees: the root, the bindings
+    /// and the working directory are checked here, so that a bad option fails
+    /// before the first tracee is launched.
+    pub fn create_filesystem(&self) -> Result<FileSystem> {
+        if let Some(path) = &self.load_fs_state {
+            let mut fs = FileSystem::load_state(path)?;
+            fs.set_record_translations(self.replay_log.is_some());
+            if let Some(resolver) = &self.path_resolver {
+                fs.set_resolver(resolver.clone());
+            }
+            return Ok(fs);
+        }
+
+        // -r *path* is equivalent to -b *path*:/
+        let mut fs = FileSystem::with_root(&self.rootfs)?;
+        fs.set_fallback_policy(self.fallback_policy);
+        fs.set_case_insensitive(self.case_insensitive_paths);
+        if let Some(resolver) = &self.path_resolver {
+            fs.set_resolver(resolver.clone());
+        }
+        fs.set_audit_escapes(self.audit_escapes);
+        fs.set_record_translations(self.replay_log.is_some());
+        for host_path in &self.layers {
+            fs.add_layer(host_path)?;
+        }
+        fs.set_copy_up(self.copy_up);
+        fs.set_synthetic_mountpoints(self.synthetic_mountpoints);
+        fs.set_umask(self.umask.map(Mode::from_bits_truncate));
+
+        // The bindings of the command line come later, so they win.
+        if self.proc_shim {
+            // a /proc missing from the rootfs is a synthetic mountpoint, the
+            // rootfs isn't modified
+            fs.set_synthetic_mountpoints(true);
+            fs.add_binding("/proc", "/proc")?;
+            fs.set_synthetic_mountpoints(self.synthetic_mountpoints);
+        }
+        if self.dev_shim {
+            // single files can be bound even if the guest /dev is missing
+            for device in DEV_SHIM_DEVICES.iter().map(Path::new) {
+                if device.exists() {
+                    fs.add_binding(device, device)?;
+                }
+            }
+        }
+        let bindings = self.bindings.iter().map(|binding| (binding, false));
+        let read_only_bindings = self
+            .read_only_bindings
+            .iter()
+            .map(|binding| (binding, true));
+        for ((host_path, guest_path), read_only) in bindings.chain(read_only_bindings) {
+            if host_path.symlink_metadata().is_err() {
+                if !self.create_missing_bindings {
+                    return Err(Error::errno_with_msg(
+                        Errno::ENOENT,
+                        format!(
+                            "Host path {:?} of binding {:?}:{:?} doesn't exist",
+                            host_path, host_path, guest_path
+                        ),
+                    ));
+                }
+                std::fs::create_dir_all(host_path)?;
+            }
+            if read_only {
+                fs.add_read_only_binding(host_path, guest_path)?;
+            } else {
+                fs.add_binding(host_path, guest_path)?;
+            }
+        }
+        for (guest_path, flags) in &self.binding_flags {
+            fs.set_binding_flags(guest_path, *flags)?;
+        }
+        fs.set_cwd(&self.cwd)?;
+        if let Some(path) = &self.save_fs_state {
+            fs.save_state(path)?;
+        }
+        Ok(fs)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ProotConfigBuilder {
+    config: ProotConfig,
+}
+
+impl ProotConfigBuilder {
+    pub fn rootfs<P: AsRef<Path>>(mut self, host_path: P) -> Self {
+        self.config.rootfs = host_path.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn layer<P: AsRef<Path>>(mut self, host_path: P) -> Self {
+        self.config.layers.push(host_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn copy_up(mut self, copy_up: bool) -> Self {
+        self.config.copy_up = copy_up;
+        self
+    }
+
+    pub fn proc_shim(mut self, proc_shim: bool) -> Self {
+        self.config.proc_shim = proc_shim;
+        self
+    }
+
+    pub fn dev_shim(mut self, dev_shim: bool) -> Self {
+        self.config.dev_shim = dev_shim;
+        self
+    }
+
+    pub fn virtual_mounts(mut self, virtual_mounts: bool) -> Self {
+        self.config.virtual_mounts = virtual_mounts;
+        self
+    }
+
+    pub fn binding<P1: AsRef<Path>, P2: AsRef<Path>>(
+        mut self,
+        host_path: P1,
+        guest_path: P2,
+    ) -> Self {
+        self.config.bindings.push((
+            host_path.as_ref().to_path_buf(),
+            guest_path.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    pub fn read_only_binding<P1: AsRef<Path>, P2: AsRef<Path>>(
+        mut self,
+        host_path: P1,
+        guest_path: P2,
+    ) -> Self {
+        self.config.read_only_bindings.push((
+            host_path.as_ref().to_path_buf(),
+            guest_path.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    pub fn binding_flags<P: AsRef<Path>>(mut self, guest_path: P, flags: BindingFlags) -> Self {
+        self.config
+            .binding_flags
+            .push((guest_path.as_ref().to_path_buf(), flags));
+        self
+    }
+
+    pub fn create_missing_bindings(mut self, create_missing_bindings: bool) -> Self {
+        self.config.create_missing_bindings = create_missing_bindings;
+        self
+    }
+
+    pub fn synthetic_mountpoints(mut self, synthetic_mountpoints: bool) -> Self {
+        self.config.synthetic_mountpoints = synthetic_mountpoints;
+        self
+    }
+
+    pub fn cwd<P: AsRef<Path>>(mut self, guest_path: P) -> Self {
+        self.config.cwd = guest_path.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn init<P: AsRef<Path>>(mut self, guest_path: P) -> Self {
+        self.config.init = Some(guest_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn init_argv0<S: Into<String>>(mut self, init_argv0: S) -> Self {
+        self.config.init_argv0 = Some(init_argv0.into());
+        self
+    }
+
+    pub fn preserve_argv0(mut self, preserve_argv0: bool) -> Self {
+        self.config.preserve_argv0 = preserve_argv0;
+        self
+    }
+
+    pub fn randomize_load_addresses(mut self, randomize_load_addresses: bool) -> Self {
+        self.config.randomize_load_addresses = randomize_load_addresses;
+        self
+    }
+
+    pub fn no_randomize(mut self, no_randomize: bool) -> Self {
+        self.config.no_randomize = no_randomize;
+        self
+    }
+
+    pub fn deny_swap(mut self, deny_swap: bool) -> Self {
+        self.config.deny_swap = deny_swap;
+        self
+    }
+
+    pub fn emulate_pivot_root(mut self, emulate_pivot_root: bool) -> Self {
+        self.config.emulate_pivot_root = emulate_pivot_root;
+        self
+    }
+
+    pub fn emulate_chroot(mut self, emulate_chroot: bool) -> Self {
+        self.config.emulate_chroot = emulate_chroot;
+        self
+    }
+
+    pub fn emulate_move_mount(mut self, emulate_move_mount: bool) -> Self {
+        self.config.emulate_move_mount = emulate_move_mount;
+        self
+    }
+
+    pub fn audit_escapes(mut self, audit_escapes: bool) -> Self {
+        self.config.audit_escapes = audit_escapes;
+        self
+    }
+
+    pub fn fallback_policy(mut self, fallback_policy: FallbackPolicy) -> Self {
+        self.config.fallback_policy = fallback_policy;
+        self
+    }
+
+    pub fn case_insensitive_paths(mut self, case_insensitive_paths: bool) -> Self {
+        self.config.case_insensitive_paths = case_insensitive_paths;
+        self
+    }
+
+    pub fn path_resolver(mut self, resolver: Rc<dyn PathResolver>) -> Self {
+        self.config.path_resolver = Some(resolver);
+        self
+    }
+
+    pub fn hostname<S: Into<String>>(mut self, hostname: S) -> Self {
+        self.config.hostname = Some(hostname.into());
+        self
+    }
+
+    pub fn domainname<S: Into<String>>(mut self, domainname: S) -> Self {
+        self.config.domainname = Some(domainname.into());
+        self
+    }
+
+    pub fn sysinfo_overlay(mut self, sysinfo_overlay: SysinfoOverlay) -> Self {
+        self.config.sysinfo_overlay = sysinfo_overlay;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    pub fn bypass_syscall<S: Into<String>>(mut self, name: S) -> Self {
+        self.config.bypassed_syscalls.insert(name.into());
+        self
+    }
+
+    pub fn sanitize_ld_library_path(mut self, sanitize_ld_library_path: bool) -> Self {
+        self.config.sanitize_ld_library_path = sanitize_ld_library_path;
+        self
+    }
+
+    pub fn strip_env_var<S: Into<String>>(mut self, name: S) -> Self {
+        self.config.stripped_env_vars.insert(name.into());
+        self
+    }
+
+    pub fn replay_log<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config.replay_log = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn strict_io_uring(mut self, strict_io_uring: bool) -> Self {
+        self.config.strict_io_uring = strict_io_uring;
+        self
+    }
+
+    pub fn strict_mount_setattr(mut self, strict_mount_setattr: bool) -> Self {
+        self.config.strict_mount_setattr = strict_mount_setattr;
+        self
+    }
+
+    pub fn mq_prefix<S: Into<String>>(mut self, mq_prefix: S) -> Self {
+        self.config.mq_prefix = Some(mq_prefix.into());
+        self
+    }
+
+    pub fn random_seed(mut self, random_seed: u64) -> Self {
+        self.config.random_seed = Some(random_seed);
+        self
+    }
+
+    pub fn umask(mut self, umask: u32) -> Self {
+        self.config.umask = Some(umask);
+        self
+    }
+
+    pub fn fake_ownership(mut self, fake_ownership: bool) -> Self {
+        self.config.fake_ownership = fake_ownership;
+        self
+    }
+
+    pub fn max_tracees(mut self, max_tracees: usize) -> Self {
+        self.config.max_tracees = Some(max_tracees);
+        self
+    }
+
+    pub fn tracee_limit_policy(mut self, tracee_limit_policy: TraceeLimitPolicy) -> Self {
+        self.config.tracee_limit_policy = tracee_limit_policy;
+        self
+    }
+
+    pub fn check_host_leaks(mut self, check_host_leaks: bool) -> Self {
+        self.config.check_host_leaks = check_host_leaks;
+        self
+    }
+
+    pub fn strip_shebang_cr(mut self, strip_shebang_cr: bool) -> Self {
+        self.config.strip_shebang_cr = strip_shebang_cr;
+        self
+    }
+
+    pub fn binfmt_rule(mut self, rule: BinfmtRule) -> Self {
+        self.config.binfmt_rules.push(rule);
+        self
+    }
+
+    pub fn save_fs_state<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config.save_fs_state = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn load_fs_state<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config.load_fs_state = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn path_max(mut self, path_max: usize) -> Self {
+        self.config.path_max = Some(path_max);
+        self
+    }
+
+    pub fn build(self) -> ProotConfig {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::binding::Side::{Guest, Host};
+    use crate::filesystem::Translator;
+    use crate::utils::tests::{get_test_rootfs_path, MinimalRootfs};
+
+    #[test]
+    fn test_config_builder() {
+        // the defaults match the ones of the command line
+        let config = ProotConfig::builder().build();
+        assert_eq!(config, ProotConfig::default());
+        assert_eq!(config.rootfs, Path::new("/"));
+        assert_eq!(config.cwd, Path::new("/"));
+        assert!(config.layers.is_empty());
+        assert!(!config.copy_up);
+        assert!(!config.proc_shim);
+        assert!(!config.dev_shim);
+        assert!(!config.virtual_mounts);
+        assert!(config.bindings.is_empty());
+        assert!(config.read_only_bindings.is_empty());
+        assert!(config.binding_flags.is_empty());
+        assert!(!config.create_missing_bindings);
+        assert!(!config.synthetic_mountpoints);
+        assert_eq!(config.init, None);
+        assert_eq!(config.init_argv0, None);
+        assert_eq!(config.init_command(vec!["single".into()]), None);
+        assert!(!config.preserve_argv0);
+        assert!(!config.randomize_load_addresses);
+        assert!(!config.no_randomize);
+        assert!(!config.deny_swap);
+        assert!(!config.emulate_pivot_root);
+        assert!(!config.emulate_chroot);
+        assert!(!config.emulate_move_mount);
+        assert!(!config.audit_escapes);
+        assert_eq!(config.fallback_policy, FallbackPolicy::NotFound);
+        assert!(!config.case_insensitive_paths);
+        assert!(config.path_resolver.is_none());
+        assert_eq!(config.hostname, None);
+        assert_eq!(config.domainname, None);
+        assert_eq!(config.sysinfo_overlay, SysinfoOverlay::default());
+        assert_eq!(config.timeout, None);
+        assert!(config.bypassed_syscalls.is_empty());
+        assert!(!config.sanitize_ld_library_path);
+        assert!(config.stripped_env_vars.is_empty());
+        assert_eq!(config.replay_log, None);
+        assert!(!config.strict_io_uring);
+        assert!(!config.strict_mount_setattr);
+        assert_eq!(config.mq_prefix, None);
+        assert_eq!(config.random_seed, None);
+        assert_eq!(config.umask, None);
+        assert!(!config.fake_ownership);
+        assert_eq!(config.max_tracees, None);
+        assert_eq!(config.tracee_limit_policy, TraceeLimitPolicy::Fail);
+        // except this one, only enabled in the tests
+        assert!(config.check_host_leaks);
+        assert!(!config.strip_shebang_cr);
+        assert!(config.binfmt_rules.is_empty());
+        assert_eq!(config.save_fs_state, None);
+        assert_eq!(config.load_fs_state, None);
+        assert_eq!(config.path_max, None);
+
+        let config = ProotConfig::builder()
+            .rootfs("/tmp")
+            .binding("/etc", "/etc")
+            .binding("/home", "/usr")
+            .cwd("/usr")
+            .preserve_argv0(true)
+            .build();
+        assert_eq!(config.rootfs, Path::new("/tmp"));
+        assert_eq!(
+            config.bindings,
+            vec![
+                (PathBuf::from("/etc"), PathBuf::from("/etc")),
+                (PathBuf::from("/home"), PathBuf::from("/usr")),
+            ]
+        );
+        assert_eq!(config.cwd, Path::new("/usr"));
+        assert!(config.preserve_argv0);
+    }
+
+    #[test]
+    fn test_config_bypassed_syscalls() {
+        let config = ProotConfig::builder().bypass_syscall("open").build();
+
+        assert!(config.is_syscall_bypassed(sc::nr::OPEN));
+        assert!(!config.is_syscall_bypassed(sc::nr::OPENAT));
+        assert!(!ProotConfig::default().is_syscall_bypassed(sc::nr::OPEN));
+    }
+
+    #[test]
+    fn test_config_create_filesystem() {
+        let root_path = get_test_rootfs_path();
+        let fs = ProotConfig::builder()
+            .rootfs(&root_path)
+            .binding("/etc", "/home")
+            .cwd("/home")
+            .fallback_policy(FallbackPolicy::Deny)
+            .build()
+            .create_filesystem()
+            .unwrap();
+
+        assert_eq!(fs.get_root(), root_path);
+        assert_eq!(fs.get_cwd(), Path::new("/home"));
+        assert_eq!(fs.get_fallback_policy(), FallbackPolicy::Deny);
+        assert!(fs.bindings().iter().any(|binding| {
+            binding.get_path(Host) == Path::new("/etc")
+                && binding.get_path(Guest) == Path::new("/home")
+        }));
+
+        // the paths are checked when the filesystem is created
+        assert!(ProotConfig::builder()
+            .rootfs(&root_path)
+            .cwd("/impossible_path")
+            .build()
+            .create_filesystem()
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_create_filesystem_missing_binding() {
+        let root_path = get_test_rootfs_path();
+        let host_path = std::env::temp_dir().join("proot_rs_test_config_missing_binding");
+        let _ = std::fs::remove_dir_all(&host_path);
+
+        // startup fails fast on a missing host path
+        let result = ProotConfig::builder()
+            .rootfs(&root_path)
+            .binding(&host_path, "/tmp")
+            .build()
+            .create_filesystem();
+        assert_eq!(result.unwrap_err().get_errno(), Errno::ENOENT);
+        assert!(!host_path.exists());
+
+        // unless it's asked to create it
+        let fs = ProotConfig::builder()
+            .rootfs(&root_path)
+            .binding(&host_path, "/tmp")
+            .create_missing_bindings(true)
+            .build()
+            .create_filesystem()
+            .unwrap();
+        assert!(host_path.is_dir());
+        assert!(fs.bindings().iter().any(|binding| {
+            binding.get_path(Host) == host_path && binding.get_path(Guest) == Path::new("/tmp")
+        }));
+
+        std::fs::remove_dir_all(&host_path).unwrap();
+    }
+
+    #[test]
+    fn test_config_create_filesystem_proc_shim() {
+        let rootfs = MinimalRootfs::new();
+        std::fs::remove_dir(rootfs.path().join("proc")).unwrap();
+
+        let fs = ProotConfig::builder()
+            .rootfs(rootfs.path())
+            .proc_shim(true)
+            .build()
+            .create_filesystem()
+            .unwrap();
+
+        // the host /proc is bound without creating the mountpoint
+        assert!(!rootfs.path().join("proc").exists());
+        assert_eq!(
+            fs.translate_path("/proc/cpuinfo", true),
+            Ok(PathBuf::from("/proc/cpuinfo"))
+        );
+        // the option isn't enabled for the other bindings
+        assert!(ProotConfig::builder()
+            .rootfs(rootfs.path())
+            .proc_shim(true)
+            .binding("/etc", "/missing")
+            .build()
+            .create_filesystem()
+            .is_err());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::errors::*;
 use libc::PATH_MAX;
 use nix::NixPath;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -18,6 +20,60 @@ impl Side {
     }
 }
 
+/// Mount flags of a binding. The bindings aren't mounts, so proot-rs
+/// emulates them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BindingFlags {
+    /// The files of the binding can't be executed (`EACCES`), see
+    /// `FileSystem::check_executable`.
+    pub noexec: bool,
+    /// The set-user-ID and set-group-ID bits of the files of the binding are
+    /// ignored. There's nothing to emulate, since a traced process never
+    /// gains privileges through execve(), so it's only reported in the mount
+    /// table.
+    pub nosuid: bool,
+    /// The device files of the binding can't be opened (`EACCES`), see
+    /// `FileSystem::check_device_allowed`.
+    pub nodev: bool,
+}
+
+impl BindingFlags {
+    /// Parses a comma-separated list of flags, e.g. "noexec,nodev".
+    pub fn parse(flags: &str) -> std::result::Result<BindingFlags, String> {
+        let mut binding_flags = BindingFlags::default();
+        for flag in flags.split(',') {
+            match flag {
+                "noexec" => binding_flags.noexec = true,
+                "nosuid" => binding_flags.nosuid = true,
+                "nodev" => binding_flags.nodev = true,
+                _ => {
+                    return Err(format!(
+                        "{:?} must be a binding flag: noexec, nosuid or nodev",
+                        flag
+                    ))
+                }
+            }
+        }
+        Ok(binding_flags)
+    }
+
+    /// Returns the names of the flags which are set, in the order of the
+    /// mount options.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.nosuid {
+            names.push("nosuid");
+        }
+        if self.nodev {
+            names.push("nodev");
+        }
+        if self.noexec {
+            names.push("noexec");
+        }
+        names
+    }
+}
+
 // TODO: Maybe we should canonicalize guest path during initialization
 #[derive(Debug, Clone)]
 pub struct Binding {
@@ -29,6 +85,10 @@ pub struct Binding {
     /// which means that the paths under this binding do not require path
     /// substitution.
     need_substitution: bool,
+    /// Whether the files of this binding can't be opened for writing, see
+    /// `FileSystem::check_writable`.
+    read_only: bool,
+    flags: BindingFlags,
     _must_exist: bool,
 }
 
@@ -47,10 +107,39 @@ impl Binding {
             host: host,
             guest: guest,
             need_substitution: need_substitution,
+            read_only: false,
+            flags: BindingFlags::default(),
             _must_exist: must_exist,
         }
     }
 
+    #[inline]
+    pub fn with_read_only(mut self, read_only: bool) -> Binding {
+        self.read_only = read_only;
+        self
+    }
+
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    #[inline]
+    pub fn with_flags(mut self, flags: BindingFlags) -> Binding {
+        self.flags = flags;
+        self
+    }
+
+    #[inline]
+    pub fn get_flags(&self) -> BindingFlags {
+        self.flags
+    }
+
+    #[inline]
+    pub fn set_flags(&mut self, flags: BindingFlags) {
+        self.flags = flags;
+    }
+
     #[inline]
     pub fn get_path(&self, side: Side) -> &PathBuf {
         match side {
@@ -95,6 +184,19 @@ impl Binding {
     }
 }
 
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.host.display(), self.guest.display())?;
+        if self.read_only {
+            write!(f, " (read-only)")?;
+        }
+        for name in self.flags.names() {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Side::{Guest, Host};
@@ -110,6 +212,24 @@ mod tests {
         assert_eq!(binding.get_path(Side::Guest), &PathBuf::from("/media"));
     }
 
+    #[test]
+    fn test_binding_flags_parse() {
+        assert_eq!(
+            BindingFlags::parse("noexec,nodev"),
+            Ok(BindingFlags {
+                noexec: true,
+                nosuid: false,
+                nodev: true,
+            })
+        );
+        assert_eq!(
+            BindingFlags::parse("nosuid").unwrap().names(),
+            vec!["nosuid"]
+        );
+        assert!(BindingFlags::parse("noexec,ro").is_err());
+        assert!(BindingFlags::parse("").is_err());
+    }
+
     #[test]
     fn test_substitute_path_prefix_root() {
         // "/etc" on host = "/media" on guest
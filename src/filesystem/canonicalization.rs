@@ -67,6 +67,7 @@ impl Canonicalizer for FileSystem {
         // We need the `next` component to know if the current one is the last one
         let mut it = guest_path.components();
         let mut next_comp = it.next();
+        let mut is_escape_recorded = false;
         while let Some(component) = next_comp {
             next_comp = it.next();
             let is_last_component = next_comp.is_none();
@@ -81,7 +82,11 @@ impl Canonicalizer for FileSystem {
                     continue;
                 }
                 Component::ParentDir => {
-                    guest_path_new.pop();
+                    // ".." is clamped at the guest root
+                    if !guest_path_new.pop() && !is_escape_recorded {
+                        self.record_escape_attempt(guest_path);
+                        is_escape_recorded = true;
+                    }
                     continue;
                 }
                 Component::Normal(path_part) => {
@@ -103,6 +108,12 @@ impl Canonicalizer for FileSystem {
                     if is_last_component && !deref_final {
                         continue;
                     }
+                    // A missing intermediate directory can still lead to a
+                    // binding (e.g. a single file bound onto "/foo/bar" when
+                    // "/foo" doesn't exist in the rootfs).
+                    if metadata.is_err() && self.is_binding_ancestor(&guest_path_new) {
+                        continue;
+                    }
 
                     let file_type = metadata?.file_type();
 
@@ -112,11 +123,11 @@ impl Canonicalizer for FileSystem {
                     }
                     if file_type.is_symlink() {
                         // we need to deref
-                        // TODO: add test for this
                         let link_value = host_path.read_link()?;
                         let mut new_user_path = if link_value.is_absolute() {
-                            // link_value is a absolute path, so we need to replace user_path
-                            // with link_value first.
+                            // link_value is a absolute guest path, even if the link is in a
+                            // binding: it's resolved again from the guest root, through the
+                            // bindings, never as a host path.
                             link_value
                         } else {
                             // link_value is a relative path, so we need to append link_value to
@@ -157,6 +168,27 @@ mod tests {
     use nix::sys::stat::Mode;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_canonicalize_records_escape_attempts() {
+        let mut fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+
+        // the audit is opt-in
+        assert_eq!(fs.canonicalize("/../etc", true), Ok("/etc".into()));
+        assert!(fs.take_escape_attempts().is_empty());
+
+        fs.set_audit_escapes(true);
+        assert_eq!(
+            fs.canonicalize("/etc/../../../etc", true),
+            Ok("/etc".into())
+        );
+        assert_eq!(fs.canonicalize("/etc/../bin", true), Ok("/bin".into()));
+        assert_eq!(
+            fs.take_escape_attempts(),
+            vec![PathBuf::from("/etc/../../../etc")]
+        );
+        assert!(fs.take_escape_attempts().is_empty());
+    }
+
     #[test]
     fn test_canonicalize_invalid_path() {
         let fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
@@ -264,4 +296,35 @@ mod tests {
             PathBuf::from("/lib")
         );
     }
+
+    #[test]
+    fn test_canonicalize_absolute_symlink_across_bindings() {
+        let rootfs_path = get_test_rootfs_path();
+        let host_dir = std::env::temp_dir().join("proot_rs_test_canonicalize_across_bindings");
+        let _ = std::fs::remove_dir_all(&host_dir);
+        std::fs::create_dir_all(host_dir.join("a")).unwrap();
+        std::fs::create_dir_all(host_dir.join("b/target")).unwrap();
+        // the target is a guest path, which doesn't exist on the host
+        std::os::unix::fs::symlink("/mnt/b/target", host_dir.join("a/link")).unwrap();
+
+        let mut fs = FileSystem::with_root(&rootfs_path).unwrap();
+        fs.set_synthetic_mountpoints(true);
+        fs.add_binding(host_dir.join("a"), "/mnt/a").unwrap();
+        fs.add_binding(host_dir.join("b"), "/mnt/b").unwrap();
+
+        assert_eq!(
+            fs.canonicalize("/mnt/a/link/file", true),
+            Ok("/mnt/b/target/file".into())
+        );
+        assert_eq!(
+            fs.canonicalize("/mnt/a/link", true),
+            Ok("/mnt/b/target".into())
+        );
+        assert_eq!(
+            fs.canonicalize("/mnt/a/link", false),
+            Ok("/mnt/a/link".into())
+        );
+
+        std::fs::remove_dir_all(&host_dir).unwrap();
+    }
 }
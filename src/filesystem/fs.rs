@@ -1,14 +1,49 @@
-use std::path::{Path, PathBuf};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
 
-use nix::sys::{self, stat::Mode};
+use nix::sys;
+use nix::sys::stat::{Mode, UtimensatFlags};
+use nix::sys::time::{TimeSpec, TimeValLike};
 use nix::unistd::{self, AccessFlags};
+use serde::{Deserialize, Serialize};
 
 use crate::errors::*;
 use crate::filesystem::binding::Side::Host;
-use crate::filesystem::binding::{Binding, Side};
+use crate::filesystem::binding::{Binding, BindingFlags, Side};
+use crate::filesystem::resolver::{DefaultResolver, PathResolver};
+use crate::filesystem::state::{BindingState, FileSystemState};
 
 use super::{Canonicalizer, Substitutor};
 
+/// Maximum number of entries of the detranslation cache, which is simply
+/// cleared when it's full.
+const DETRANSLATION_CACHE_CAPACITY: usize = 1024;
+
+/// What to do when a guest path can't be translated because one of its
+/// components doesn't exist in the guest file-system.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FallbackPolicy {
+    /// Fail with `ENOENT`, like the kernel would.
+    NotFound,
+    /// Fail with `EACCES`, without revealing whether the path exists.
+    Deny,
+    /// Append the missing components to their canonical existing prefix
+    /// under the guest root (or the matching binding), and let the kernel
+    /// handle the resulting path. A dangling symlink is never followed.
+    Permissive,
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> FallbackPolicy {
+        FallbackPolicy::NotFound
+    }
+}
+
 /// The file-system information associated with one or more tracee, which
 /// corresponds to the [`fs_struct`] structure in the kernel. If clone() is
 /// called with `CLONE_FS` set, then both parent tracee and child tracee will
@@ -19,6 +54,8 @@ use super::{Canonicalizer, Substitutor};
 pub struct FileSystem {
     /// List of bindings used to replicate `mount` and `bind`.
     /// It will also contain the root binding (to replicate `chroot`).
+    /// It's sorted from the longest guest path to the shortest one, and from
+    /// the most recent binding to the oldest one for a same guest path.
     ///
     /// FIXME: Actually, bindings should not be part of the `fs_struct`, it
     /// should be shared globally
@@ -28,8 +65,43 @@ pub struct FileSystem {
     cwd: PathBuf,
     /// Guest root (the binding associated to `/`)
     root: PathBuf,
+    /// Path of the guest root in the initial guest root, composed by the
+    /// emulated chroot() and pivot_root() calls, see `chroot`.
+    chroot_path: PathBuf,
+    /// Read-only lower layers of the guest root, searched in order when a
+    /// path doesn't exist in the root itself, which is the writable (upper)
+    /// layer. See `add_layer`.
+    layers: Vec<PathBuf>,
+    /// Whether a file of a lower layer is copied into the guest root before
+    /// being written, like overlayfs does, see `redirect_to_upper_layer`.
+    copy_up: bool,
+    /// Whether a directory can be bound onto a guest path which doesn't
+    /// exist, see `add_binding`.
+    synthetic_mountpoints: bool,
+    /// Virtual umask of the tracees, applied by proot-rs to the modes of the
+    /// created files instead of the host one, see `kernel::standard::umask`.
+    umask: Option<Mode>,
     /// Use for glue (//TODO: explain when implemented)
     glue_type: Mode,
+    /// Policy applied when a guest path doesn't exist, see `translate_path`.
+    fallback_policy: FallbackPolicy,
+    /// Whether a missing guest path is translated again with the case of its
+    /// components fixed, see `fix_path_case`.
+    case_insensitive: bool,
+    /// Backend of `translate_path` and `detranslate_path`, see
+    /// `PathResolver`.
+    resolver: Rc<dyn PathResolver>,
+    /// Whether the guest paths clamped at the guest root by `..` components
+    /// are recorded, see `take_escape_attempts`.
+    audit_escapes: bool,
+    escape_attempts: RefCell<Vec<PathBuf>>,
+    /// Whether the `(guest_path, host_path)` pairs of
+    /// `translate_absolute_path()` are recorded, see `take_translations`.
+    record_translations: bool,
+    translations: RefCell<Vec<(PathBuf, PathBuf)>>,
+    /// Results of `detranslate_path()` without referrer, by host path. See
+    /// `invalidate_path_caches`.
+    detranslation_cache: RefCell<HashMap<PathBuf, Option<PathBuf>>>,
 }
 
 impl FileSystem {
@@ -38,11 +110,23 @@ impl FileSystem {
             bindings: vec![],
             cwd: PathBuf::from("/"),
             root: PathBuf::from("/"),
+            chroot_path: PathBuf::from("/"),
+            layers: vec![],
+            copy_up: false,
+            synthetic_mountpoints: false,
+            umask: None,
             glue_type: Mode::empty(),
+            fallback_policy: FallbackPolicy::default(),
+            case_insensitive: false,
+            resolver: Rc::new(DefaultResolver),
+            audit_escapes: false,
+            escape_attempts: RefCell::new(vec![]),
+            record_translations: false,
+            translations: RefCell::new(vec![]),
+            detranslation_cache: RefCell::new(HashMap::new()),
         }
     }
 
-    #[cfg(test)]
     pub fn with_root<P: AsRef<Path>>(root: P) -> Result<FileSystem> {
         let mut file_system = FileSystem::new();
 
@@ -50,52 +134,547 @@ impl FileSystem {
         Ok(file_system)
     }
 
+    /// Returns the configuration of this `FileSystem`, see `FileSystemState`.
+    pub fn to_state(&self) -> FileSystemState {
+        FileSystemState {
+            root: self.root.clone(),
+            cwd: self.cwd.clone(),
+            chroot_path: self.chroot_path.clone(),
+            layers: self.layers.clone(),
+            bindings: self
+                .bindings
+                .iter()
+                .map(|binding| BindingState {
+                    host: binding.get_path(Side::Host).clone(),
+                    guest: binding.get_path(Side::Guest).clone(),
+                    read_only: binding.is_read_only(),
+                    flags: binding.get_flags(),
+                })
+                .collect(),
+            copy_up: self.copy_up,
+            synthetic_mountpoints: self.synthetic_mountpoints,
+            umask: self.umask.map(|umask| umask.bits()),
+            fallback_policy: self.fallback_policy,
+            case_insensitive: self.case_insensitive,
+            audit_escapes: self.audit_escapes,
+        }
+    }
+
+    /// Creates a `FileSystem` from the configuration `state`, as is: the
+    /// bindings aren't checked again, since the paths may have been created
+    /// by the guest (e.g. with `synthetic_mountpoints`).
+    pub fn from_state(state: FileSystemState) -> Result<FileSystem> {
+        state.check_absolute_paths()?;
+        let mut file_system = FileSystem::new();
+        // the oldest and shortest binding first, so that the order is kept
+        for binding in state.bindings.into_iter().rev() {
+            file_system.insert_binding(
+                Binding::new(binding.host, binding.guest, true)
+                    .with_read_only(binding.read_only)
+                    .with_flags(binding.flags),
+            );
+        }
+        file_system.root = state.root;
+        file_system.cwd = state.cwd;
+        file_system.chroot_path = state.chroot_path;
+        file_system.layers = state.layers;
+        file_system.copy_up = state.copy_up;
+        file_system.synthetic_mountpoints = state.synthetic_mountpoints;
+        file_system.umask = state.umask.map(Mode::from_bits_truncate);
+        file_system.fallback_policy = state.fallback_policy;
+        file_system.case_insensitive = state.case_insensitive;
+        file_system.audit_escapes = state.audit_escapes;
+        Ok(file_system)
+    }
+
     /// Add a `host_path` to `guest_path` binding.
-    /// `guest_path` must exist and be an absolute path.
-    //TODO: sort bindings to make substitution of nested bindings deterministic
+    /// `guest_path` must be an absolute path. It must exist when `host_path`
+    /// is a directory, unless `synthetic_mountpoints` is enabled, whereas a
+    /// single file can be bound anywhere, even if the parent directories of
+    /// `guest_path` don't exist. A mountpoint missing from the rootfs is then
+    /// stated as the bound target, like a mount over a missing directory.
     #[inline]
     pub fn add_binding<P1, P2>(&mut self, host_path: P1, guest_path: P2) -> Result<()>
     where
         P1: AsRef<Path>,
         P2: AsRef<Path>,
     {
+        self.add_binding_with(host_path.as_ref(), guest_path.as_ref(), false)
+    }
+
+    /// Same as `add_binding`, but the files of the binding can't be opened
+    /// for writing (`EROFS`), like a read-only bind mount.
+    #[inline]
+    pub fn add_read_only_binding<P1, P2>(&mut self, host_path: P1, guest_path: P2) -> Result<()>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        self.add_binding_with(host_path.as_ref(), guest_path.as_ref(), true)
+    }
+
+    fn add_binding_with(
+        &mut self,
+        host_path: &Path,
+        guest_path: &Path,
+        read_only: bool,
+    ) -> Result<()> {
         let canonical_host_path = std::fs::canonicalize(host_path)?;
+        let is_dir_binding = canonical_host_path.is_dir();
         // TODO: allow path not existed when glue is implemented
-        let canonical_guest_path = self.canonicalize(guest_path.as_ref(), true)?;
+        let canonical_guest_path = match self.canonicalize(guest_path.as_ref(), true) {
+            Err(error)
+                if (!is_dir_binding || self.synthetic_mountpoints)
+                    && error.get_errno() == ENOENT =>
+            {
+                normalize_path(guest_path.as_ref())?
+            }
+            result => result?,
+        };
         // We need to ensure that the target path for the binding exists.
         // Skip the check for "/" because "/" always exists.
-        if canonical_guest_path != Path::new("/") {
+        if is_dir_binding && !self.synthetic_mountpoints && canonical_guest_path != Path::new("/") {
             self.substitute(&canonical_guest_path, Side::Guest)?
                 .metadata()?;
         }
 
-        // Add a binding at the beginning of the list, so that we get the most recent
-        // one when going through them in the `get_binding` method.
-        self.bindings.insert(
-            0,
-            Binding::new(canonical_host_path, canonical_guest_path, true),
+        self.insert_binding(
+            Binding::new(canonical_host_path, canonical_guest_path, true).with_read_only(read_only),
         );
         Ok(())
     }
 
+    /// Fails with `EROFS` if `guest_path` (relative to the cwd, if relative)
+    /// is in a read-only binding. It's checked for all the syscalls creating
+    /// or modifying a file, see `kernel::groups::modifies_paths()`.
+    ///
+    /// The binding is looked up from the guest side, since a same host path
+    /// can be bound both read-only and writable. A missing path is looked up
+    /// lexically, its error being left to the translation.
+    pub fn check_writable(&self, guest_path: &Path, deref_final: bool) -> Result<()> {
+        if !self.bindings.iter().any(Binding::is_read_only) {
+            return Ok(());
+        }
+        let guest_path = self.cwd.join(guest_path);
+        let guest_path = match self.canonicalize(&guest_path, deref_final) {
+            Ok(canonical_guest_path) => canonical_guest_path,
+            Err(_) => normalize_path(&guest_path)?,
+        };
+        match self.get_first_appropriate_binding(&guest_path, Side::Guest) {
+            Some(binding) if binding.is_read_only() => Err(Error::errno_with_msg(
+                EROFS,
+                format!("{:?} is in the read-only binding {}", guest_path, binding),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the mount `flags` of the most recent binding of `guest_path`.
+    pub fn set_binding_flags<P: AsRef<Path>>(
+        &mut self,
+        guest_path: P,
+        flags: BindingFlags,
+    ) -> Result<()> {
+        let guest_path = normalize_path(guest_path.as_ref())?;
+        let binding = self
+            .bindings
+            .iter_mut()
+            .find(|binding| binding.get_path(Side::Guest) == &guest_path)
+            .ok_or_else(|| {
+                Error::errno_with_msg(ENOENT, format!("no binding of {:?}", guest_path))
+            })?;
+        binding.set_flags(flags);
+        Ok(())
+    }
+
+    /// Fails with `EACCES` if the executable `guest_path` (relative to the
+    /// cwd, if relative) is in a `noexec` binding, like execve() on a
+    /// `noexec` mount.
+    pub fn check_executable(&self, guest_path: &Path) -> Result<()> {
+        if !self
+            .bindings
+            .iter()
+            .any(|binding| binding.get_flags().noexec)
+        {
+            return Ok(());
+        }
+        let guest_path = self.canonicalize(self.cwd.join(guest_path), true)?;
+        match self.get_first_appropriate_binding(&guest_path, Side::Guest) {
+            Some(binding) if binding.get_flags().noexec => Err(Error::errno_with_msg(
+                EACCES,
+                format!("{:?} is in the noexec binding {}", guest_path, binding),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `true` if a binding has the `nodev` flag, i.e. if
+    /// `check_device_allowed()` may fail.
+    #[inline]
+    pub fn has_nodev_bindings(&self) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.get_flags().nodev)
+    }
+
+    /// Fails with `EACCES` if `host_path`, the translation of `guest_path`,
+    /// is a device file of a `nodev` binding, like open() on a `nodev` mount.
+    pub fn check_device_allowed(
+        &self,
+        guest_path: &Path,
+        host_path: &Path,
+        deref_final: bool,
+    ) -> Result<()> {
+        if !self.has_nodev_bindings() {
+            return Ok(());
+        }
+        let is_device = match host_path.metadata() {
+            Ok(metadata) => {
+                let file_type = metadata.file_type();
+                file_type.is_char_device() || file_type.is_block_device()
+            }
+            // let the kernel report the error
+            Err(_) => false,
+        };
+        if !is_device {
+            return Ok(());
+        }
+        let guest_path = self.canonicalize(self.cwd.join(guest_path), deref_final)?;
+        match self.get_first_appropriate_binding(&guest_path, Side::Guest) {
+            Some(binding) if binding.get_flags().nodev => Err(Error::errno_with_msg(
+                EACCES,
+                format!(
+                    "{:?} is a device of the nodev binding {}",
+                    guest_path, binding
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Inserts `binding` before the ones with a shorter or equal guest path, so
+    /// that nested bindings are resolved deterministically and the most recent
+    /// one wins for a same guest path.
+    fn insert_binding(&mut self, binding: Binding) {
+        let length = binding.get_path(Side::Guest).components().count();
+        let position = self
+            .bindings
+            .iter()
+            .position(|binding| binding.get_path(Side::Guest).components().count() <= length)
+            .unwrap_or(self.bindings.len());
+        self.bindings.insert(position, binding);
+        self.invalidate_path_caches();
+    }
+
+    /// Removes the most recent binding of `guest_path`, which uncovers the
+    /// previous binding of the same guest path, if any, or the content of the
+    /// guest root. The root binding itself can't be removed.
+    ///
+    /// Like `add_binding`, it can be called by an embedder at runtime, but
+    /// only between two syscalls: the `FileSystem` of a tracee is owned by
+    /// the event loop (through an `Rc<RefCell<_>>` shared by the tracees
+    /// created with `CLONE_FS`), which is single-threaded, so it must be done
+    /// from the thread of `PRoot::event_loop()`, e.g. in a syscall hook once
+    /// the translation is over. Only this `FileSystem` is changed, not the
+    /// copies of the tracees created without `CLONE_FS`.
+    pub fn remove_binding<P: AsRef<Path>>(&mut self, guest_path: P) -> Result<()> {
+        let guest_path = normalize_path(guest_path.as_ref())?;
+        if guest_path == Path::new("/") {
+            return Err(Error::errno_with_msg(
+                EINVAL,
+                "the binding of the guest root can't be removed",
+            ));
+        }
+
+        let position = self
+            .bindings
+            .iter()
+            .position(|binding| binding.get_path(Side::Guest) == &guest_path)
+            .ok_or_else(|| {
+                Error::errno_with_msg(ENOENT, format!("no binding of {:?}", guest_path))
+            })?;
+        self.bindings.remove(position);
+        self.invalidate_path_caches();
+        Ok(())
+    }
+
+    /// Drops the cached path translations. It must be called whenever the
+    /// bindings, the root or the layers change.
+    fn invalidate_path_caches(&mut self) {
+        self.detranslation_cache.get_mut().clear();
+    }
+
+    /// Returns the cached guest path of `host_path`, if any.
+    pub fn get_cached_detranslation(&self, host_path: &Path) -> Option<Option<PathBuf>> {
+        self.detranslation_cache.borrow().get(host_path).cloned()
+    }
+
+    pub fn cache_detranslation(&self, host_path: &Path, guest_path: Option<PathBuf>) {
+        let mut cache = self.detranslation_cache.borrow_mut();
+        if cache.len() >= DETRANSLATION_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(host_path.to_path_buf(), guest_path);
+    }
+
+    /// Emulates `pivot_root(new_root, put_old)`: `new_root` becomes the guest
+    /// root, and the previous root is moved to `put_old`, so the bindings, the
+    /// lower layers and the cwd are remapped accordingly.
+    ///
+    /// Both paths must be canonical guest paths, and `put_old` must be
+    /// `new_root` or one of its subdirectories, see pivot_root(2).
+    pub fn pivot_root(&mut self, new_root: &Path, put_old: &Path) -> Result<()> {
+        if new_root == Path::new("/") {
+            return Err(Error::errno_with_msg(
+                Errno::EBUSY,
+                "new_root is already the root",
+            ));
+        }
+        for path in &[new_root, put_old] {
+            if !self.substitute(path, Side::Guest)?.metadata()?.is_dir() {
+                return Err(Error::errno(Errno::ENOTDIR));
+            }
+        }
+        let put_old = match put_old.strip_prefix(new_root) {
+            Ok(relative_path) => Path::new("/").join(relative_path),
+            Err(_) => {
+                return Err(Error::errno_with_msg(
+                    Errno::EINVAL,
+                    format!("{:?} isn't underneath {:?}", put_old, new_root),
+                ))
+            }
+        };
+        let remap = |guest_path: &Path| match guest_path.strip_prefix(new_root) {
+            Ok(relative_path) => Path::new("/").join(relative_path),
+            Err(_) => match guest_path.strip_prefix("/") {
+                Ok(relative_path) if relative_path != Path::new("") => put_old.join(relative_path),
+                _ => put_old.clone(),
+            },
+        };
+
+        let new_root_host = self.substitute(new_root, Side::Guest)?;
+        let old_bindings = std::mem::replace(&mut self.bindings, vec![]);
+        self.insert_binding(Binding::new(new_root_host.clone(), "/", true));
+        // The previous mounts are on top of the new root, so they are inserted
+        // from the oldest one to keep their precedence.
+        for binding in old_bindings.iter().rev() {
+            self.insert_binding(
+                Binding::new(
+                    binding.get_path(Side::Host),
+                    remap(binding.get_path(Side::Guest)),
+                    true,
+                )
+                .with_read_only(binding.is_read_only())
+                .with_flags(binding.get_flags()),
+            );
+        }
+        // like with chroot(), the lower layers are only relevant if the new
+        // root is in the current one
+        self.layers = match new_root_host.strip_prefix(&self.root) {
+            Ok(relative_path) => self
+                .layers
+                .iter()
+                .map(|layer| layer.join(relative_path))
+                .collect(),
+            Err(_) => vec![],
+        };
+        self.cwd = remap(&self.cwd);
+        self.root = new_root_host;
+        self.chroot_path = self
+            .chroot_path
+            .join(new_root.strip_prefix("/").unwrap_or(new_root));
+        self.invalidate_path_caches();
+        Ok(())
+    }
+
+    /// Emulates `chroot(new_root)`: `new_root` becomes the guest root, and
+    /// the bindings outside of it are dropped since they can't be reached
+    /// anymore. Like the kernel, the cwd is kept if it's under `new_root`;
+    /// otherwise it's set to the new root, since a cwd outside of the root
+    /// can't be represented by a guest path.
+    ///
+    /// `new_root` must be a canonical guest path, so it's always under the
+    /// current root: each chroot narrows the view further, and `..` can't be
+    /// used to get back to a previous root. The successive roots are composed
+    /// in `chroot_path`.
+    pub fn chroot(&mut self, new_root: &Path) -> Result<()> {
+        if !self.substitute(new_root, Side::Guest)?.metadata()?.is_dir() {
+            return Err(Error::errno(Errno::ENOTDIR));
+        }
+        if new_root == Path::new("/") {
+            return Ok(());
+        }
+        let remap = |guest_path: &Path| {
+            guest_path
+                .strip_prefix(new_root)
+                .ok()
+                .map(|relative_path| Path::new("/").join(relative_path))
+        };
+
+        let new_root_host = self.substitute(new_root, Side::Guest)?;
+        let old_bindings = std::mem::replace(&mut self.bindings, vec![]);
+        self.insert_binding(Binding::new(new_root_host.clone(), "/", true));
+        for binding in old_bindings.iter().rev() {
+            if let Some(guest_path) = remap(binding.get_path(Side::Guest)) {
+                if guest_path != Path::new("/") {
+                    self.insert_binding(
+                        Binding::new(binding.get_path(Side::Host), guest_path, true)
+                            .with_read_only(binding.is_read_only())
+                            .with_flags(binding.get_flags()),
+                    );
+                }
+            }
+        }
+        // the lower layers are only relevant if the new root is in the
+        // current one, rather than in a binding
+        self.layers = match new_root_host.strip_prefix(&self.root) {
+            Ok(relative_path) => self
+                .layers
+                .iter()
+                .map(|layer| layer.join(relative_path))
+                .collect(),
+            Err(_) => vec![],
+        };
+        self.cwd = remap(&self.cwd).unwrap_or_else(|| PathBuf::from("/"));
+        self.root = new_root_host;
+        self.chroot_path = self
+            .chroot_path
+            .join(new_root.strip_prefix("/").unwrap_or(new_root));
+        self.invalidate_path_caches();
+        Ok(())
+    }
+
+    /// Returns the path of the guest root in the initial guest root, as
+    /// composed by the emulated chroot() and pivot_root() calls, e.g. the
+    /// target of `/proc/self/root`.
+    #[inline]
+    pub fn get_chroot_path(&self) -> &Path {
+        &self.chroot_path
+    }
+
+    /// Returns the effective bindings, root binding included, in the order
+    /// they are tried when translating a guest path (see
+    /// `get_first_appropriate_binding`).
+    #[inline]
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
     #[inline]
     /// Checks if the translated `host_path` belongs to the guest rootfs,
     /// that is, if it isn't from a binding.
     pub fn belongs_to_guestfs(&self, host_path: &Path) -> bool {
-        host_path.starts_with(&self.root)
+        self.strip_rootfs_prefix(host_path).is_some()
+    }
+
+    /// Adds a lower layer below the guest root and the previous layers: the
+    /// guest paths which don't exist in the upper layers are searched in it,
+    /// the first hit wins. Writes go to the guest root (see
+    /// `translate_path_for_write`).
+    pub fn add_layer<P: AsRef<Path>>(&mut self, host_path: P) -> Result<()> {
+        let canonical_host_path = std::fs::canonicalize(host_path)?;
+        if !canonical_host_path.is_dir() {
+            return Err(Error::errno(Errno::ENOTDIR));
+        }
+        self.layers.push(canonical_host_path);
+        self.invalidate_path_caches();
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_layers(&self) -> &[PathBuf] {
+        &self.layers
+    }
+
+    /// If `host_path` is in the guest root or one of its lower layers,
+    /// returns it relatively to this root or layer.
+    pub fn strip_rootfs_prefix<'a>(&self, host_path: &'a Path) -> Option<&'a Path> {
+        std::iter::once(&self.root)
+            .chain(self.layers.iter())
+            .find_map(|layer| host_path.strip_prefix(layer).ok())
+    }
+
+    #[inline]
+    pub fn set_copy_up(&mut self, copy_up: bool) {
+        self.copy_up = copy_up;
+    }
+
+    #[inline]
+    pub fn set_synthetic_mountpoints(&mut self, synthetic_mountpoints: bool) {
+        self.synthetic_mountpoints = synthetic_mountpoints;
+    }
+
+    /// Redirects the translated `host_path` of a path which is about to be
+    /// created or modified into the guest root (the upper layer):
+    /// - a missing path is created in the upper layer, so its missing parent
+    ///   directories are created there;
+    /// - a regular file of a lower layer is copied up first when `copy_up` is
+    ///   enabled (keeping its mode and timestamps);
+    /// - the other entries of the lower layers, which are read-only, can't be
+    ///   modified (`EROFS`).
+    pub fn redirect_to_upper_layer(&self, host_path: PathBuf) -> Result<PathBuf> {
+        if self.layers.is_empty() {
+            return Ok(host_path);
+        }
+
+        if let Some(relative_path) = self
+            .layers
+            .iter()
+            .find_map(|layer| host_path.strip_prefix(layer).ok())
+        {
+            let metadata = host_path.symlink_metadata()?;
+            if !self.copy_up || !metadata.is_file() {
+                return Err(Error::errno_with_msg(
+                    EROFS,
+                    format!("{:?} is in a read-only lower layer", host_path),
+                ));
+            }
+            let upper_host_path = self.root.join(relative_path);
+            if let Some(parent) = upper_host_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            copy_file_up(&host_path, &upper_host_path, &metadata)?;
+            Ok(upper_host_path)
+        } else {
+            // the path is either in the upper layer or in a binding
+            if host_path.starts_with(&self.root) && host_path.symlink_metadata().is_err() {
+                if let Some(parent) = host_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+            }
+            Ok(host_path)
+        }
+    }
+
+    /// Finds in the layers the host path of the canonical `guest_path`, whose
+    /// host path in the guest root (upper layer) is `upper_host_path`. The
+    /// latter is returned if it exists, or if no lower layer contains the path.
+    pub fn lookup_layers(&self, guest_path: &Path, upper_host_path: PathBuf) -> PathBuf {
+        if self.layers.is_empty() || upper_host_path.symlink_metadata().is_ok() {
+            return upper_host_path;
+        }
+        let relative_path = guest_path.strip_prefix("/").unwrap_or(guest_path);
+
+        self.layers
+            .iter()
+            .map(|layer| layer.join(relative_path))
+            .find(|host_path| host_path.symlink_metadata().is_ok())
+            .unwrap_or(upper_host_path)
     }
 
     /// Retrieves the first appropriate binding for a path translation.
     ///
+    /// The binding with the longest matching path is used (so an exact match,
+    /// typically a single-file binding, always wins), and the most recent one
+    /// among bindings with the same path.
+    ///
     /// * `path` is the path which content will be tested on each binding
     /// * `from_side` indicates the starting side of the translation (ie. guest
     ///   for guest -> host)
     pub fn get_first_appropriate_binding(&self, path: &Path, from_side: Side) -> Option<&Binding> {
-        for binding in self.bindings.iter() {
-            let binding_path = binding.get_path(from_side);
-
-            if !path.starts_with(binding_path) {
-                continue;
+        let is_appropriate = |binding: &&Binding| {
+            if !path.starts_with(binding.get_path(from_side)) {
+                return false;
             }
 
             // TODO: Do we really need to find binding from host to guest?
@@ -108,13 +687,25 @@ impl FileSystem {
                 //
                 //     proot -m /usr:/location -r /usr/local/slackware
                 //
-                continue;
+                return false;
             }
 
-            return Some(&binding);
-        }
+            true
+        };
 
-        None
+        self.bindings
+            .iter()
+            .filter(is_appropriate)
+            .min_by_key(|binding| Reverse(binding.get_path(from_side).components().count()))
+    }
+
+    /// Checks if the canonical `guest_path` is a strict ancestor of the guest
+    /// path of a binding.
+    pub fn is_binding_ancestor(&self, guest_path: &Path) -> bool {
+        self.bindings.iter().any(|binding| {
+            let binding_path = binding.get_path(Side::Guest);
+            binding_path != guest_path && binding_path.starts_with(guest_path)
+        })
     }
 
     #[inline]
@@ -192,6 +783,89 @@ impl FileSystem {
         &self.root
     }
 
+    #[inline]
+    pub fn get_fallback_policy(&self) -> FallbackPolicy {
+        self.fallback_policy
+    }
+
+    #[inline]
+    pub fn set_fallback_policy(&mut self, fallback_policy: FallbackPolicy) {
+        self.fallback_policy = fallback_policy;
+    }
+
+    #[inline]
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    #[inline]
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+    }
+
+    #[inline]
+    pub fn get_resolver(&self) -> &dyn PathResolver {
+        self.resolver.as_ref()
+    }
+
+    /// Replaces the backend of the path resolution, which invalidates the
+    /// cached detranslations.
+    pub fn set_resolver(&mut self, resolver: Rc<dyn PathResolver>) {
+        self.resolver = resolver;
+        self.invalidate_path_caches();
+    }
+
+    #[inline]
+    pub fn set_audit_escapes(&mut self, audit_escapes: bool) {
+        self.audit_escapes = audit_escapes;
+    }
+
+    /// Records a guest path which tried to escape from the guest root, if the
+    /// audit is enabled. The escape itself is already prevented by the
+    /// canonicalization.
+    pub fn record_escape_attempt(&self, guest_path: &Path) {
+        if self.audit_escapes {
+            self.escape_attempts
+                .borrow_mut()
+                .push(guest_path.to_path_buf());
+        }
+    }
+
+    /// Returns the escape attempts recorded since the last call.
+    pub fn take_escape_attempts(&self) -> Vec<PathBuf> {
+        self.escape_attempts.replace(vec![])
+    }
+
+    #[inline]
+    pub fn set_record_translations(&mut self, record_translations: bool) {
+        self.record_translations = record_translations;
+    }
+
+    /// Records a successful translation, if enabled (see the `--replay-log`
+    /// option).
+    pub fn record_translation(&self, guest_path: &Path, host_path: &Path) {
+        if self.record_translations {
+            self.translations
+                .borrow_mut()
+                .push((guest_path.to_path_buf(), host_path.to_path_buf()));
+        }
+    }
+
+    /// Returns the translations recorded since the last call.
+    pub fn take_translations(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.translations.replace(vec![])
+    }
+
+    #[inline]
+    pub fn get_umask(&self) -> Option<Mode> {
+        self.umask
+    }
+
+    #[inline]
+    pub fn set_umask(&mut self, umask: Option<Mode>) {
+        self.umask = umask;
+    }
+
     #[inline]
     pub fn get_glue_type(&self) -> &Mode {
         &self.glue_type
@@ -238,10 +912,68 @@ impl FileSystem {
     }
 }
 
+impl fmt::Display for FileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "root: {}", self.root.display())?;
+        writeln!(f, "cwd: {}", self.cwd.display())?;
+        writeln!(f, "bindings (host:guest):")?;
+        for binding in self.bindings() {
+            writeln!(f, "  {}", binding)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copies the regular file `lower_host_path` to `upper_host_path`, with the
+/// same mode and timestamps.
+fn copy_file_up(
+    lower_host_path: &Path,
+    upper_host_path: &Path,
+    metadata: &std::fs::Metadata,
+) -> Result<()> {
+    // std::fs::copy() also copies the permission bits
+    std::fs::copy(lower_host_path, upper_host_path)?;
+
+    let atime = TimeSpec::nanoseconds(metadata.atime() * 1_000_000_000 + metadata.atime_nsec());
+    let mtime = TimeSpec::nanoseconds(metadata.mtime() * 1_000_000_000 + metadata.mtime_nsec());
+    sys::stat::utimensat(
+        None,
+        upper_host_path,
+        &atime,
+        &mtime,
+        UtimensatFlags::NoFollowSymlink,
+    )?;
+    Ok(())
+}
+
+/// Lexically normalizes the absolute `path`, i.e. removes its "." and ".."
+/// components without accessing the file-system.
+pub fn normalize_path(path: &Path) -> Result<PathBuf> {
+    if path.is_relative() {
+        return Err(Error::errno_with_msg(
+            Errno::EINVAL,
+            format!("Cannot normalize a relative path: {:?}", path),
+        ));
+    }
+
+    let mut normalized_path = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized_path.pop();
+            }
+            Component::CurDir | Component::Prefix(_) => {}
+            component => normalized_path.push(component),
+        }
+    }
+    Ok(normalized_path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::filesystem::binding::Side::{Guest, Host};
+    use crate::filesystem::Translator;
     use crate::utils::tests::get_test_rootfs_path;
     use std::path::{Path, PathBuf};
 
@@ -383,6 +1115,15 @@ mod tests {
         fs.add_binding("/non_existing_path", "/bin").unwrap_err();
         fs.add_binding("/etc", "/usr")?;
         fs.add_binding("/etc/../tmp/", "/home/../home")?;
+        // unless the mountpoints are synthetic
+        fs.set_synthetic_mountpoints(true);
+        fs.add_binding("/etc", "/bin/non_existing_path")?;
+        fs.add_binding("/etc", "/non_existing_dir/non_existing_path")?;
+        assert_eq!(
+            fs.translate_path("/non_existing_dir/non_existing_path", true)?,
+            PathBuf::from("/etc")
+        );
+        fs.set_synthetic_mountpoints(false);
         fs.add_binding("home", "/home").unwrap_err();
         // should be failed since `guest_path` is not absolute path
         fs.add_binding("/dev", "tmp").unwrap_err();
@@ -407,4 +1148,394 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_fs_single_file_binding() -> Result<()> {
+        let root_path = get_test_rootfs_path();
+        let host_file = std::env::temp_dir().join("proot_rs_test_fs_single_file_binding");
+        std::fs::write(&host_file, "single file binding")?;
+        let host_file = std::fs::canonicalize(&host_file)?;
+
+        let mut fs = FileSystem::with_root(&root_path)?;
+        // bind the file onto an existing file, and onto a path whose parent
+        // directory doesn't exist in the rootfs
+        fs.add_binding(&host_file, "/etc/passwd")?;
+        fs.add_binding(&host_file, "/non_existing_dir/./file")?;
+
+        for guest_path in &["/etc/passwd", "/non_existing_dir/file"] {
+            let host_path = fs.translate_path(guest_path, true)?;
+            assert_eq!(host_path, host_file);
+            assert_eq!(std::fs::read_to_string(&host_path)?, "single file binding");
+        }
+        // the most recent binding is used for the detranslation
+        assert_eq!(
+            fs.detranslate_path(&host_file, None),
+            Ok(Some(PathBuf::from("/non_existing_dir/file")))
+        );
+
+        // the other files of the directory are not affected
+        assert_eq!(
+            fs.translate_path("/etc/group", true)?,
+            root_path.join("etc/group")
+        );
+        // a single file can't be traversed
+        assert_eq!(
+            fs.translate_path("/etc/passwd/file", true),
+            Err(Error::errno(Errno::ENOTDIR))
+        );
+
+        // an exact match takes precedence over a more recent directory binding
+        fs.add_binding("/etc", "/etc")?;
+        assert_eq!(fs.translate_path("/etc/passwd", true)?, host_file);
+        assert_eq!(
+            fs.translate_path("/etc/group", true)?,
+            PathBuf::from("/etc/group")
+        );
+
+        std::fs::remove_file(&host_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_bindings_precedence() -> Result<()> {
+        let root_path = get_test_rootfs_path();
+        let host_dir = std::env::temp_dir().join("proot_rs_test_fs_bindings_precedence");
+        std::fs::create_dir_all(host_dir.join("nested"))?;
+        let host_dir = std::fs::canonicalize(&host_dir)?;
+
+        let mut fs = FileSystem::with_root(&root_path)?;
+        fs.add_binding(&host_dir, "/home")?;
+        fs.add_binding("/etc", "/home/nested")?;
+        // a more recent binding with a shorter guest path
+        fs.add_binding("/var", "/home/../home")?;
+
+        let bindings: Vec<(&Path, &Path)> = fs
+            .bindings()
+            .iter()
+            .map(|binding| {
+                (
+                    binding.get_path(Host).as_path(),
+                    binding.get_path(Guest).as_path(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                (Path::new("/etc"), Path::new("/home/nested")),
+                (Path::new("/var"), Path::new("/home")),
+                (host_dir.as_path(), Path::new("/home")),
+                (root_path.as_path(), Path::new("/")),
+            ]
+        );
+
+        // the translation follows the same order
+        assert_eq!(
+            fs.translate_path("/home/nested/passwd", true)?,
+            PathBuf::from("/etc/passwd")
+        );
+        assert_eq!(
+            fs.translate_path("/home/other", true)?,
+            PathBuf::from("/var/other")
+        );
+
+        let display = fs.to_string();
+        let lines: Vec<&str> = display.lines().collect();
+        assert_eq!(lines[0], format!("root: {}", root_path.display()));
+        assert_eq!(lines[3], "  /etc:/home/nested");
+        assert_eq!(lines[4], "  /var:/home");
+        assert_eq!(lines.len(), 7);
+
+        std::fs::remove_dir_all(&host_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_remove_binding() -> Result<()> {
+        let root_path = get_test_rootfs_path();
+        let mut fs = FileSystem::with_root(&root_path)?;
+
+        fs.add_binding("/etc", "/home")?;
+        assert_eq!(
+            fs.translate_path("/home/passwd", true)?,
+            PathBuf::from("/etc/passwd")
+        );
+        assert_eq!(
+            fs.detranslate_path("/etc/passwd", None)?,
+            Some(PathBuf::from("/home/passwd"))
+        );
+
+        fs.remove_binding("/home/")?;
+        assert_eq!(fs.bindings().len(), 1);
+        // the binding doesn't resolve anymore, even the cached detranslations
+        assert_eq!(fs.translate_path("/home", true)?, root_path.join("home"));
+        assert_ne!(
+            fs.detranslate_path("/etc/passwd", None)?,
+            Some(PathBuf::from("/home/passwd"))
+        );
+
+        assert_eq!(fs.remove_binding("/home").unwrap_err().get_errno(), ENOENT);
+        assert_eq!(fs.remove_binding("/").unwrap_err().get_errno(), EINVAL);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_binding_flags() -> Result<()> {
+        let root_path = get_test_rootfs_path();
+        let mut fs = FileSystem::with_root(&root_path)?;
+        fs.set_synthetic_mountpoints(true);
+        fs.add_binding("/dev/null", "/mnt/dev/null")?;
+        fs.add_binding("/etc", "/mnt/etc")?;
+        fs.add_binding("/etc", "/mnt/etc_exec")?;
+
+        // no flags by default
+        assert!(!fs.has_nodev_bindings());
+        assert_eq!(fs.check_executable(Path::new("/mnt/etc/passwd")), Ok(()));
+        assert_eq!(
+            fs.check_device_allowed(Path::new("/mnt/dev/null"), Path::new("/dev/null"), true),
+            Ok(())
+        );
+
+        fs.set_binding_flags("/mnt/etc/", BindingFlags::parse("noexec").unwrap())?;
+        fs.set_binding_flags("/mnt/dev/null", BindingFlags::parse("nodev").unwrap())?;
+        assert!(fs.has_nodev_bindings());
+        assert_eq!(
+            fs.check_executable(Path::new("/mnt/etc/passwd"))
+                .unwrap_err()
+                .get_errno(),
+            EACCES
+        );
+        // the same host directory through another binding
+        assert_eq!(
+            fs.check_executable(Path::new("/mnt/etc_exec/passwd")),
+            Ok(())
+        );
+        assert_eq!(
+            fs.check_device_allowed(Path::new("/mnt/dev/null"), Path::new("/dev/null"), true)
+                .unwrap_err()
+                .get_errno(),
+            EACCES
+        );
+        // only the devices are denied
+        assert_eq!(
+            fs.check_device_allowed(Path::new("/mnt/etc/passwd"), Path::new("/etc/passwd"), true),
+            Ok(())
+        );
+
+        assert_eq!(
+            fs.set_binding_flags("/mnt", BindingFlags::default())
+                .unwrap_err()
+                .get_errno(),
+            ENOENT
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_copy_up() -> Result<()> {
+        use std::io::Write;
+
+        let layers_path = std::env::temp_dir().join("proot_rs_test_fs_copy_up");
+        let upper_path = layers_path.join("upper");
+        let lower_path = layers_path.join("lower");
+        std::fs::create_dir_all(&upper_path)?;
+        std::fs::create_dir_all(lower_path.join("etc"))?;
+        std::fs::write(lower_path.join("etc/file"), "lower\n")?;
+        let lower_metadata = std::fs::metadata(lower_path.join("etc/file"))?;
+        let upper_path = std::fs::canonicalize(&upper_path)?;
+        let lower_path = std::fs::canonicalize(&lower_path)?;
+
+        let mut fs = FileSystem::with_root(&upper_path)?;
+        fs.add_layer(&lower_path)?;
+
+        // without copy-up, the lower file can't be modified
+        assert_eq!(
+            fs.translate_path_for_write("/etc/file", true)
+                .unwrap_err()
+                .get_errno(),
+            EROFS
+        );
+        assert!(!upper_path.join("etc").exists());
+
+        fs.set_copy_up(true);
+        let host_path = fs.translate_path_for_write("/etc/file", true)?;
+        assert_eq!(host_path, upper_path.join("etc/file"));
+        let upper_metadata = std::fs::metadata(&host_path)?;
+        assert_eq!(upper_metadata.mode(), lower_metadata.mode());
+        assert_eq!(upper_metadata.mtime(), lower_metadata.mtime());
+        assert_eq!(upper_metadata.mtime_nsec(), lower_metadata.mtime_nsec());
+
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&host_path)?
+            .write_all(b"upper\n")?;
+        assert_eq!(std::fs::read_to_string(&host_path)?, "lower\nupper\n");
+        assert_eq!(
+            std::fs::read_to_string(lower_path.join("etc/file"))?,
+            "lower\n"
+        );
+        // the copy now hides the lower file
+        assert_eq!(fs.translate_path("/etc/file", true)?, host_path);
+
+        std::fs::remove_dir_all(&layers_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_pivot_root_layers() -> Result<()> {
+        let layers_path = std::env::temp_dir().join("proot_rs_test_fs_pivot_root_layers");
+        let upper_path = layers_path.join("upper");
+        let lower_path = layers_path.join("lower");
+        let _ = std::fs::remove_dir_all(&layers_path);
+        std::fs::create_dir_all(upper_path.join("new_root/old"))?;
+        std::fs::create_dir_all(lower_path.join("new_root/etc"))?;
+        std::fs::write(lower_path.join("new_root/etc/file"), "lower\n")?;
+        std::fs::write(lower_path.join("etc_file"), "lower\n")?;
+        let upper_path = std::fs::canonicalize(&upper_path)?;
+        let lower_path = std::fs::canonicalize(&lower_path)?;
+
+        let mut fs = FileSystem::with_root(&upper_path)?;
+        fs.add_layer(&lower_path)?;
+        fs.pivot_root(Path::new("/new_root"), Path::new("/new_root/old"))?;
+
+        // the lower layers are searched under the new root
+        assert_eq!(fs.get_layers(), &[lower_path.join("new_root")]);
+        assert_eq!(
+            fs.translate_path("/etc/file", true)?,
+            lower_path.join("new_root/etc/file")
+        );
+        assert_ne!(
+            fs.translate_path("/etc_file", true)?,
+            lower_path.join("etc_file")
+        );
+
+        std::fs::remove_dir_all(&layers_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_layers_modifying_syscalls() {
+        use crate::config::ProotConfig;
+        use crate::utils::tests::test_with_proot_config;
+
+        let layers_path = std::env::temp_dir().join("proot_rs_test_fs_layers_modifying_syscalls");
+        let upper_path = layers_path.join("upper");
+        let lower_path = layers_path.join("lower");
+        let _ = std::fs::remove_dir_all(&layers_path);
+        std::fs::create_dir_all(&upper_path).unwrap();
+        std::fs::create_dir_all(lower_path.join("etc/dir")).unwrap();
+        std::fs::write(lower_path.join("etc/file"), "lower").unwrap();
+        std::fs::write(lower_path.join("etc/truncated"), "lower").unwrap();
+        let lower_mode = std::fs::metadata(lower_path.join("etc/file"))
+            .unwrap()
+            .mode();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(&upper_path)
+                .layer(&lower_path)
+                .copy_up(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // the new files are created in the upper layer
+                assert_eq!(nc::mkdir("/etc/dir/new_dir", 0o755), Ok(()));
+                assert_eq!(
+                    nc::mknod("/etc/new_file", nc::S_IFREG | nc::S_IRUSR, 0),
+                    Ok(())
+                );
+                assert_eq!(nc::symlink("../file", "/etc/dir/link"), Ok(()));
+                assert_eq!(nc::link("/etc/new_file", "/etc/dir/linked"), Ok(()));
+
+                // the lower files are copied up before being modified
+                assert_eq!(nc::chmod("/etc/file", 0o600), Ok(()));
+                assert_eq!(nc::truncate("/etc/truncated", 0), Ok(()));
+                assert_eq!(nc::rename("/etc/truncated", "/etc/renamed"), Ok(()));
+                assert_eq!(std::fs::read_to_string("/etc/dir/link").unwrap(), "lower");
+            },
+        );
+
+        assert!(upper_path.join("etc/dir/new_dir").is_dir());
+        assert!(upper_path.join("etc/new_file").is_file());
+        assert!(upper_path.join("etc/dir/linked").is_file());
+        assert_eq!(
+            std::fs::read_link(upper_path.join("etc/dir/link")).unwrap(),
+            Path::new("../file")
+        );
+        let upper_metadata = std::fs::metadata(upper_path.join("etc/file")).unwrap();
+        assert_eq!(upper_metadata.mode() & 0o777, 0o600);
+        assert_eq!(
+            std::fs::read_to_string(upper_path.join("etc/renamed")).unwrap(),
+            ""
+        );
+
+        // the lower layer is left intact
+        assert_eq!(
+            std::fs::read_dir(lower_path.join("etc/dir"))
+                .unwrap()
+                .count(),
+            0
+        );
+        let lower_metadata = std::fs::metadata(lower_path.join("etc/file")).unwrap();
+        assert_eq!(lower_metadata.mode(), lower_mode);
+        assert_eq!(
+            std::fs::read_to_string(lower_path.join("etc/truncated")).unwrap(),
+            "lower"
+        );
+        assert!(!lower_path.join("etc/new_file").exists());
+
+        std::fs::remove_dir_all(&layers_path).unwrap();
+    }
+
+    #[test]
+    fn test_fs_layers_read_only_without_copy_up() {
+        use crate::config::ProotConfig;
+        use crate::utils::tests::test_with_proot_config;
+
+        let layers_path = std::env::temp_dir().join("proot_rs_test_fs_layers_read_only");
+        let upper_path = layers_path.join("upper");
+        let lower_path = layers_path.join("lower");
+        let _ = std::fs::remove_dir_all(&layers_path);
+        std::fs::create_dir_all(&upper_path).unwrap();
+        std::fs::create_dir_all(lower_path.join("etc/dir")).unwrap();
+        std::fs::write(lower_path.join("etc/file"), "lower").unwrap();
+        let lower_mode = std::fs::metadata(lower_path.join("etc/file"))
+            .unwrap()
+            .mode();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(&upper_path)
+                .layer(&lower_path)
+                .build(),
+            |_, _, _| {},
+            || {
+                // the existing lower entries can't be modified
+                assert_eq!(nc::chmod("/etc/file", 0o600), Err(nc::EROFS));
+                assert_eq!(nc::truncate("/etc/file", 0), Err(nc::EROFS));
+                assert_eq!(nc::unlink("/etc/file"), Err(nc::EROFS));
+                assert_eq!(nc::chmod("/etc/dir", 0o700), Err(nc::EROFS));
+                assert_eq!(std::fs::read_to_string("/etc/file").unwrap(), "lower");
+
+                // the new files are still created in the upper layer
+                assert_eq!(nc::mkdir("/etc/dir/new_dir", 0o755), Ok(()));
+            },
+        );
+
+        assert!(upper_path.join("etc/dir/new_dir").is_dir());
+        let lower_metadata = std::fs::metadata(lower_path.join("etc/file")).unwrap();
+        assert_eq!(lower_metadata.mode(), lower_mode);
+        assert_eq!(
+            std::fs::read_to_string(lower_path.join("etc/file")).unwrap(),
+            "lower"
+        );
+        assert_eq!(
+            std::fs::read_dir(lower_path.join("etc/dir"))
+                .unwrap()
+                .count(),
+            0
+        );
+
+        std::fs::remove_dir_all(&layers_path).unwrap();
+    }
 }
@@ -1,14 +1,18 @@
 pub mod binding;
 pub mod canonicalization;
 mod fs;
+pub mod mounts;
 pub mod readers;
+pub mod resolver;
+pub mod state;
 pub mod substitution;
 pub mod temp;
 mod translation;
 pub mod validation;
 
 pub use self::canonicalization::Canonicalizer;
-pub use self::fs::FileSystem;
+pub use self::fs::{FallbackPolicy, FileSystem};
 pub use self::readers::ExtraReader;
+pub use self::resolver::PathResolver;
 pub use self::substitution::Substitutor;
 pub use self::translation::Translator;
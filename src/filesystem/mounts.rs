@@ -0,0 +1,175 @@
+use std::fmt::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::filesystem::binding::{BindingFlags, Side};
+use crate::filesystem::FileSystem;
+
+/// The mount tables of `/proc` which can be virtualized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MountsFormat {
+    /// `/proc/mounts` and `/proc/<pid>/mounts`, see fstab(5).
+    Mounts,
+    /// `/proc/<pid>/mountinfo`, see proc(5).
+    MountInfo,
+}
+
+impl MountsFormat {
+    /// Returns the format of the mount table at `guest_path`, if it's one.
+    /// Only the absolute paths are recognized.
+    pub fn from_guest_path(guest_path: &Path) -> Option<MountsFormat> {
+        let relative_path = guest_path.to_str()?.strip_prefix("/proc/")?;
+        let (process, file) = match relative_path.find('/') {
+            Some(index) => (Some(&relative_path[..index]), &relative_path[index + 1..]),
+            None => (None, relative_path),
+        };
+        let is_process = |process: &str| {
+            process == "self"
+                || process == "thread-self"
+                || (!process.is_empty() && process.bytes().all(|byte| byte.is_ascii_digit()))
+        };
+
+        match (process, file) {
+            (None, "mounts") => Some(MountsFormat::Mounts),
+            (Some(process), "mounts") if is_process(process) => Some(MountsFormat::Mounts),
+            (Some(process), "mountinfo") if is_process(process) => Some(MountsFormat::MountInfo),
+            _ => None,
+        }
+    }
+}
+
+/// Escapes the characters which are special in a mount table, like the
+/// kernel does.
+fn escape_mount_path(path: &Path) -> String {
+    let mut escaped = vec![];
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\\' => escaped.extend(format!("\\{:03o}", byte).bytes()),
+            _ => escaped.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&escaped).into_owned()
+}
+
+impl FileSystem {
+    /// Generates the mount table of the guest, in the given `format`: the
+    /// guest rootfs is mounted on `/`, and each binding is mounted on its
+    /// guest path, with the flags of the binding. Nothing is said about the
+    /// host mounts.
+    pub fn generate_mounts(&self, format: MountsFormat) -> String {
+        let root_flags = self
+            .bindings()
+            .iter()
+            .find(|binding| binding.get_path(Side::Guest) == Path::new("/"))
+            .map(|binding| binding.get_flags())
+            .unwrap_or_default();
+        // the parent mounts come first
+        let mut mount_points: Vec<(&Path, BindingFlags)> = vec![(Path::new("/"), root_flags)];
+        mount_points.extend(
+            self.bindings()
+                .iter()
+                .rev()
+                .map(|binding| (binding.get_path(Side::Guest).as_path(), binding.get_flags()))
+                .filter(|(guest_path, _)| *guest_path != Path::new("/")),
+        );
+
+        let mut content = String::new();
+        for (index, (mount_point, flags)) in mount_points.iter().enumerate() {
+            let is_root = index == 0;
+            let (source, fs_type) = if is_root {
+                ("rootfs", "rootfs")
+            } else {
+                ("none", "none")
+            };
+            let mut mount_options = vec!["rw"];
+            mount_options.extend(flags.names());
+            let mount_options = mount_options.join(",");
+            let mount_point = escape_mount_path(mount_point);
+            match format {
+                MountsFormat::Mounts => writeln!(
+                    content,
+                    "{} {} {} {}{} 0 0",
+                    source,
+                    mount_point,
+                    fs_type,
+                    mount_options,
+                    if is_root { "" } else { ",bind" }
+                ),
+                MountsFormat::MountInfo => writeln!(
+                    content,
+                    "{} 1 0:0 / {} {} - {} {} rw",
+                    index + 1,
+                    mount_point,
+                    mount_options,
+                    fs_type,
+                    source
+                ),
+            }
+            .unwrap();
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::get_test_rootfs_path;
+
+    #[test]
+    fn test_mounts_format_from_guest_path() {
+        for path in &["/proc/mounts", "/proc/self/mounts", "/proc/42/mounts"] {
+            assert_eq!(
+                MountsFormat::from_guest_path(Path::new(path)),
+                Some(MountsFormat::Mounts)
+            );
+        }
+        for path in &["/proc/self/mountinfo", "/proc/thread-self/mountinfo"] {
+            assert_eq!(
+                MountsFormat::from_guest_path(Path::new(path)),
+                Some(MountsFormat::MountInfo)
+            );
+        }
+        for path in &[
+            "proc/mounts",
+            "/proc/mountinfo",
+            "/proc/self/maps",
+            "/proc/foo/mounts",
+            "/tmp/proc/mounts",
+        ] {
+            assert_eq!(MountsFormat::from_guest_path(Path::new(path)), None);
+        }
+    }
+
+    #[test]
+    fn test_fs_generate_mounts() {
+        let mut fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+        fs.add_binding("/etc", "/home").unwrap();
+        fs.add_binding(std::env::current_exe().unwrap(), "/home/a file")
+            .unwrap();
+        // the bindings of a same depth are listed from the oldest one
+        fs.add_binding("/etc", "/tmp").unwrap();
+        fs.set_binding_flags("/tmp", BindingFlags::parse("noexec,nosuid").unwrap())
+            .unwrap();
+
+        assert_eq!(
+            fs.generate_mounts(MountsFormat::Mounts),
+            "rootfs / rootfs rw 0 0\n\
+             none /home none rw,bind 0 0\n\
+             none /tmp none rw,nosuid,noexec,bind 0 0\n\
+             none /home/a\\040file none rw,bind 0 0\n"
+        );
+        assert_eq!(
+            fs.generate_mounts(MountsFormat::MountInfo),
+            "1 1 0:0 / / rw - rootfs rootfs rw\n\
+             2 1 0:0 / /home rw - none none rw\n\
+             3 1 0:0 / /tmp rw,nosuid,noexec - none none rw\n\
+             4 1 0:0 / /home/a\\040file rw - none none rw\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_mount_path() {
+        assert_eq!(escape_mount_path(Path::new("/a b\\c")), "/a\\040b\\134c");
+    }
+}
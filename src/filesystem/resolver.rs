@@ -0,0 +1,181 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::errors::*;
+use crate::filesystem::FileSystem;
+
+/// Backend of the path resolution of a `FileSystem`, which the variants of
+/// the resolution (e.g. with a cache, or for another layout of the rootfs)
+/// implement instead of branching in `Translator`.
+///
+/// The `Translator` methods keep the parts common to every resolver: the
+/// relative paths, the trailing slashes, the recorded translations and the
+/// detranslation cache.
+pub trait PathResolver: fmt::Debug {
+    /// Name of the resolver, for the logs. The resolvers are compared by
+    /// name.
+    fn name(&self) -> &str;
+
+    /// Translates the absolute `guest_path` into a host path, see
+    /// `Translator::translate_absolute_path()`.
+    fn translate(&self, fs: &FileSystem, guest_path: &Path, deref_final: bool) -> Result<PathBuf>;
+
+    /// Translates the absolute `host_path` into a guest path, `referrer`
+    /// being the symlink it was read from, if any. See
+    /// `Translator::detranslate_path()`.
+    fn detranslate(
+        &self,
+        fs: &FileSystem,
+        host_path: &Path,
+        referrer: Option<&Path>,
+    ) -> Result<Option<PathBuf>>;
+}
+
+impl PartialEq for dyn PathResolver {
+    fn eq(&self, other: &dyn PathResolver) -> bool {
+        self.name() == other.name()
+    }
+}
+
+/// The resolver of the `FileSystem`s, unless another one is set: the guest
+/// paths are canonicalized and substituted through the bindings and the
+/// lower layers, and retried case-insensitively when they're missing and
+/// the file-system is case-insensitive.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DefaultResolver;
+
+impl PathResolver for DefaultResolver {
+    fn name(&self) -> &str {
+        "default"
+    }
+
+    fn translate(&self, fs: &FileSystem, guest_path: &Path, deref_final: bool) -> Result<PathBuf> {
+        let result = fs.translate_exact_path(guest_path, deref_final);
+        if !fs.is_case_insensitive() {
+            return result;
+        }
+        let is_missing = result
+            .as_ref()
+            .map_or(true, |host_path| host_path.symlink_metadata().is_err());
+        if !is_missing {
+            return result;
+        }
+        // a missing path is translated again with the case of its components
+        // fixed
+        match fs
+            .fix_path_case(guest_path)
+            .filter(|fixed_path| fixed_path.as_path() != guest_path)
+        {
+            Some(fixed_path) => fs.translate_exact_path(&fixed_path, deref_final),
+            None => result,
+        }
+    }
+
+    fn detranslate(
+        &self,
+        fs: &FileSystem,
+        host_path: &Path,
+        referrer: Option<&Path>,
+    ) -> Result<Option<PathBuf>> {
+        fs.detranslate_path_uncached(host_path, referrer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::filesystem::Translator;
+    use crate::utils::tests::get_test_rootfs_path;
+
+    /// Records the paths it's consulted with, and redirects "/magic" to the
+    /// host "/etc".
+    #[derive(Debug, Default)]
+    struct RecordingResolver {
+        translated: RefCell<Vec<PathBuf>>,
+        detranslated: RefCell<Vec<PathBuf>>,
+    }
+
+    impl PathResolver for RecordingResolver {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn translate(
+            &self,
+            fs: &FileSystem,
+            guest_path: &Path,
+            deref_final: bool,
+        ) -> Result<PathBuf> {
+            self.translated.borrow_mut().push(guest_path.to_path_buf());
+            match guest_path.strip_prefix("/magic") {
+                Ok(relative_path) => Ok(Path::new("/etc").join(relative_path)),
+                Err(_) => DefaultResolver.translate(fs, guest_path, deref_final),
+            }
+        }
+
+        fn detranslate(
+            &self,
+            fs: &FileSystem,
+            host_path: &Path,
+            referrer: Option<&Path>,
+        ) -> Result<Option<PathBuf>> {
+            self.detranslated.borrow_mut().push(host_path.to_path_buf());
+            DefaultResolver.detranslate(fs, host_path, referrer)
+        }
+    }
+
+    #[test]
+    fn test_path_resolver_consulted() {
+        let rootfs_path = get_test_rootfs_path();
+        let mut fs = FileSystem::with_root(&rootfs_path).unwrap();
+        assert_eq!(fs.get_resolver().name(), "default");
+
+        let resolver = Rc::new(RecordingResolver::default());
+        fs.set_resolver(resolver.clone());
+        assert_eq!(
+            fs.translate_path("/magic/passwd", true),
+            Ok(PathBuf::from("/etc/passwd"))
+        );
+        assert_eq!(
+            fs.translate_path("/etc/passwd", true),
+            Ok(rootfs_path.join("etc/passwd"))
+        );
+        assert_eq!(
+            fs.detranslate_path(rootfs_path.join("etc/passwd"), None),
+            Ok(Some(PathBuf::from("/etc/passwd")))
+        );
+        assert_eq!(
+            *resolver.translated.borrow(),
+            vec![PathBuf::from("/magic/passwd"), PathBuf::from("/etc/passwd")]
+        );
+        assert_eq!(
+            *resolver.detranslated.borrow(),
+            vec![rootfs_path.join("etc/passwd")]
+        );
+    }
+
+    #[test]
+    fn test_path_resolver_from_config() {
+        let resolver = Rc::new(RecordingResolver::default());
+        let fs = ProotConfig::builder()
+            .rootfs(get_test_rootfs_path())
+            .path_resolver(resolver.clone())
+            .build()
+            .create_filesystem()
+            .unwrap();
+
+        assert_eq!(fs.get_resolver().name(), "recording");
+        assert_eq!(
+            fs.translate_path("/magic/passwd", true),
+            Ok(PathBuf::from("/etc/passwd"))
+        );
+        assert!(resolver
+            .translated
+            .borrow()
+            .contains(&PathBuf::from("/magic/passwd")));
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::*;
+use crate::filesystem::binding::BindingFlags;
+use crate::filesystem::{FallbackPolicy, FileSystem};
+
+/// A binding of a `FileSystemState`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BindingState {
+    pub host: PathBuf,
+    pub guest: PathBuf,
+    pub read_only: bool,
+    #[serde(default)]
+    pub flags: BindingFlags,
+}
+
+/// The configuration of a `FileSystem` (its root, bindings and options),
+/// without the state of the tracees (e.g. the recorded translations), so that
+/// a session can be set up again from a file.
+///
+/// All the paths are absolute: the host ones are canonical, and the guest
+/// ones are canonical in the guest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSystemState {
+    pub root: PathBuf,
+    pub cwd: PathBuf,
+    pub chroot_path: PathBuf,
+    pub layers: Vec<PathBuf>,
+    /// In the order of `FileSystem::bindings()`, root binding included.
+    pub bindings: Vec<BindingState>,
+    pub copy_up: bool,
+    pub synthetic_mountpoints: bool,
+    pub umask: Option<u32>,
+    pub fallback_policy: FallbackPolicy,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    pub audit_escapes: bool,
+}
+
+impl FileSystemState {
+    /// Fails with `EINVAL` if a path of the state is relative.
+    pub fn check_absolute_paths(&self) -> Result<()> {
+        let bindings = self
+            .bindings
+            .iter()
+            .flat_map(|binding| vec![&binding.host, &binding.guest]);
+        let paths = vec![&self.root, &self.cwd, &self.chroot_path]
+            .into_iter()
+            .chain(self.layers.iter())
+            .chain(bindings);
+        for path in paths {
+            if path.is_relative() {
+                return Err(Error::errno_with_msg(
+                    EINVAL,
+                    format!("the path {:?} of the filesystem state isn't absolute", path),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FileSystem {
+    /// Saves the configuration of this `FileSystem` in the file `path`, as
+    /// JSON. See `load_state`.
+    pub fn save_state<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path.as_ref()).with_context(|| {
+            format!("Failed to create the filesystem state {:?}", path.as_ref())
+        })?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.to_state())
+            .map_err(|error| Error::errno_with_msg(EIO, error.to_string()))
+    }
+
+    /// Creates a `FileSystem` from the configuration saved in the file `path`
+    /// by `save_state`.
+    pub fn load_state<P: AsRef<Path>>(path: P) -> Result<FileSystem> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("Failed to open the filesystem state {:?}", path.as_ref()))?;
+        let state: FileSystemState = serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| Error::errno_with_msg(EINVAL, error.to_string()))?;
+        FileSystem::from_state(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::sys::stat::Mode;
+
+    use super::*;
+    use crate::filesystem::Translator;
+    use crate::utils::tests::get_test_rootfs_path;
+
+    #[test]
+    fn test_filesystem_state_round_trip() {
+        let rootfs_path = get_test_rootfs_path();
+        let mut fs = FileSystem::with_root(&rootfs_path).unwrap();
+        fs.set_synthetic_mountpoints(true);
+        fs.set_umask(Some(Mode::from_bits_truncate(0o027)));
+        fs.set_fallback_policy(FallbackPolicy::Deny);
+        fs.set_case_insensitive(true);
+        fs.add_binding("/etc", "/mnt/etc").unwrap();
+        fs.add_read_only_binding("/etc", "/mnt/etc_ro").unwrap();
+        fs.add_binding(rootfs_path.join("bin"), "/opt/bin").unwrap();
+        fs.set_binding_flags("/opt/bin", BindingFlags::parse("noexec").unwrap())
+            .unwrap();
+        fs.set_cwd("/tmp").unwrap();
+
+        let state_path = std::env::temp_dir().join("proot_rs_test_filesystem_state.json");
+        fs.save_state(&state_path).unwrap();
+        let loaded_fs = FileSystem::load_state(&state_path).unwrap();
+        std::fs::remove_file(&state_path).unwrap();
+
+        assert_eq!(loaded_fs.to_state(), fs.to_state());
+        assert!(loaded_fs.to_state().check_absolute_paths().is_ok());
+        assert_eq!(loaded_fs.get_cwd(), Path::new("/tmp"));
+        for guest_path in &[
+            "/mnt/etc/passwd",
+            "/mnt/etc_ro/passwd",
+            "/opt/bin/sh",
+            "/bin/sh",
+            "/tmp",
+        ] {
+            assert_eq!(
+                loaded_fs.translate_path(guest_path, true),
+                fs.translate_path(guest_path, true)
+            );
+        }
+        assert_eq!(
+            loaded_fs.check_writable(Path::new("/mnt/etc_ro/passwd"), true),
+            Err(Error::errno(EROFS))
+        );
+    }
+
+    #[test]
+    fn test_filesystem_state_relative_path() {
+        let mut state = FileSystem::with_root(get_test_rootfs_path())
+            .unwrap()
+            .to_state();
+        state.bindings.push(BindingState {
+            host: "etc".into(),
+            guest: "/mnt".into(),
+            read_only: false,
+            flags: BindingFlags::default(),
+        });
+        assert_eq!(
+            FileSystem::from_state(state).unwrap_err().get_errno(),
+            EINVAL
+        );
+    }
+}
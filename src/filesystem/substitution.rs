@@ -25,6 +25,15 @@ impl Substitutor for FileSystem {
     #[inline]
     fn substitute<P: AsRef<Path>>(&self, path: P, from_side: Side) -> Result<PathBuf> {
         let path = path.as_ref();
+        // The lower layers of the guest root aren't bindings.
+        if from_side == Side::Host
+            && !self.get_layers().is_empty()
+            && !path.starts_with(self.get_root())
+        {
+            if let Some(relative_path) = self.strip_rootfs_prefix(path) {
+                return Ok(Path::new("/").join(relative_path));
+            }
+        }
         let maybe_binding = self.get_first_appropriate_binding(path, from_side);
         // TODO: should we substitute with root?
         if maybe_binding.is_none() {
@@ -39,11 +48,17 @@ impl Substitutor for FileSystem {
         let binding = maybe_binding.unwrap();
 
         // Is it a "symmetric" binding?
-        if !binding.needs_substitution() {
-            return Ok(path.to_path_buf());
+        let substituted_path = if binding.needs_substitution() {
+            binding.substitute_path_prefix(path, from_side)?
+        } else {
+            path.to_path_buf()
+        };
+
+        // The paths of the root binding may be in its lower layers.
+        if from_side == Side::Guest && binding.get_path(Side::Guest) == Path::new("/") {
+            return Ok(self.lookup_layers(path, substituted_path));
         }
-
-        binding.substitute_path_prefix(path, from_side)
+        Ok(substituted_path)
     }
 
     /// Substitute a binding of a canonicalized path, from `Guest` to `Host`,
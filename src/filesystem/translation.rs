@@ -1,13 +1,20 @@
-use crate::errors::Result;
+use crate::errors::{Errno, Error, Result};
 
 use crate::filesystem::binding::Side::{Guest, Host};
 use crate::filesystem::canonicalization::Canonicalizer;
+use crate::filesystem::fs::normalize_path;
 use crate::filesystem::substitution::Substitutor;
-use crate::filesystem::FileSystem;
-use std::path::{Path, PathBuf};
+use crate::filesystem::{FallbackPolicy, FileSystem};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
 
 pub trait Translator {
     fn translate_path<P: AsRef<Path>>(&self, guest_path: P, deref_final: bool) -> Result<PathBuf>;
+    fn translate_path_for_write<P: AsRef<Path>>(
+        &self,
+        guest_path: P,
+        deref_final: bool,
+    ) -> Result<PathBuf>;
     fn translate_absolute_path<P: AsRef<Path>>(
         &self,
         guest_path: P,
@@ -23,26 +30,73 @@ pub trait Translator {
 impl Translator for FileSystem {
     /// Translates a path from `guest` to `host`. Relative guest path is also
     /// accepted.
+    ///
+    /// With the `tracing` feature, the paths are recorded in the span of the
+    /// syscall being translated.
     fn translate_path<P: AsRef<Path>>(&self, guest_path: P, deref_final: bool) -> Result<PathBuf> {
-        if guest_path.as_ref().is_relative() {
+        let guest_path = guest_path.as_ref();
+        let result = if guest_path.is_relative() {
             // It is relative to the current working directory.
             let mut absolute_guest_path = PathBuf::from(self.get_cwd());
             absolute_guest_path.push(guest_path);
             self.translate_absolute_path(&absolute_guest_path, deref_final)
         } else {
             self.translate_absolute_path(guest_path, deref_final)
+        };
+
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            span.record("guest_path", &tracing::field::debug(guest_path));
+            if let Ok(host_path) = &result {
+                span.record("host_path", &tracing::field::debug(host_path));
+            }
         }
+        result
+    }
+
+    /// Same as `translate_path()`, for a path which is about to be created or
+    /// modified: when the guest root has lower layers, the resulting host path
+    /// is redirected to the guest root (the writable layer) if needed, see
+    /// `FileSystem::redirect_to_upper_layer()`. It fails with `EROFS` in a
+    /// read-only binding.
+    fn translate_path_for_write<P: AsRef<Path>>(
+        &self,
+        guest_path: P,
+        deref_final: bool,
+    ) -> Result<PathBuf> {
+        self.check_writable(guest_path.as_ref(), deref_final)?;
+        let host_path = self.translate_path(guest_path, deref_final)?;
+        self.redirect_to_upper_layer(host_path)
     }
 
     /// Translates a path from `guest` to `host`. Only absolute guest path is
     /// accepted.
+    ///
+    /// If a component of the path doesn't exist, the `FallbackPolicy` of the
+    /// file-system decides of the result.
+    ///
+    /// A trailing slash requires the final component to be a directory: like
+    /// the kernel, the final component is then dereferenced, and the trailing
+    /// slash is kept in the host path so that the kernel reports `ENOTDIR`
+    /// for the other files.
+    ///
+    /// The path itself is resolved by the `PathResolver` of the file-system.
     fn translate_absolute_path<P: AsRef<Path>>(
         &self,
         guest_path: P,
         deref_final: bool,
     ) -> Result<PathBuf> {
-        let canonical_guest_path = self.canonicalize(&guest_path, deref_final)?;
-        let host_path = self.substitute(&canonical_guest_path, Guest)?;
+        let guest_path = guest_path.as_ref();
+        let must_be_dir = has_trailing_slash(guest_path);
+        let deref_final = deref_final || must_be_dir;
+        let mut host_path = self
+            .get_resolver()
+            .translate(self, guest_path, deref_final)?;
+        if must_be_dir {
+            add_trailing_slash(&mut host_path);
+        }
+        self.record_translation(guest_path, &host_path);
         Ok(host_path)
     }
 
@@ -67,6 +121,166 @@ impl Translator for FileSystem {
             return Ok(None);
         }
 
+        // The result only depends on the bindings when there is no referrer.
+        if referrer.is_none() {
+            if let Some(maybe_path) = self.get_cached_detranslation(host_path) {
+                return Ok(maybe_path);
+            }
+            let maybe_path = self.get_resolver().detranslate(self, host_path, None)?;
+            self.cache_detranslation(host_path, maybe_path.clone());
+            return Ok(maybe_path);
+        }
+        self.get_resolver().detranslate(self, host_path, referrer)
+    }
+}
+
+impl FileSystem {
+    /// Fast path of `translate_absolute_path()`, for the common case of a
+    /// guest root without other bindings nor lower layers: the host path is
+    /// then the guest path under the root, as long as none of its components
+    /// is `.`, `..`, or a symlink to dereference (checked with lstat()).
+    ///
+    /// Returns `None` when the path has to be canonicalized, which also
+    /// produces the errors (e.g. a missing intermediate directory).
+    fn translate_without_bindings(&self, guest_path: &Path, deref_final: bool) -> Option<PathBuf> {
+        if self.bindings().len() != 1 || !self.get_layers().is_empty() {
+            return None;
+        }
+
+        let mut components = guest_path.components().peekable();
+        if components.next() != Some(Component::RootDir) {
+            return None;
+        }
+        let mut host_path = self.get_root().to_path_buf();
+        while let Some(component) = components.next() {
+            let is_last_component = components.peek().is_none();
+            match component {
+                Component::Normal(name) => host_path.push(name),
+                _ => return None,
+            }
+
+            match host_path.symlink_metadata() {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    if deref_final || !is_last_component {
+                        return None;
+                    }
+                }
+                Ok(metadata) if !is_last_component && !metadata.is_dir() => return None,
+                Ok(_) => {}
+                // the final component may not exist (e.g. mkdir())
+                Err(_) if is_last_component => {}
+                Err(_) => return None,
+            }
+        }
+        Some(host_path)
+    }
+
+    /// Translates `guest_path` as is, with the fast path when possible.
+    pub(super) fn translate_exact_path(
+        &self,
+        guest_path: &Path,
+        deref_final: bool,
+    ) -> Result<PathBuf> {
+        match self.translate_without_bindings(guest_path, deref_final) {
+            Some(host_path) => Ok(host_path),
+            None => self.canonicalize_and_substitute(guest_path, deref_final),
+        }
+    }
+
+    /// Returns the normalized `guest_path`, where each component missing from
+    /// its host directory is replaced by an entry of this directory with the
+    /// same name ignoring the case, if any. Each directory is listed, so it's
+    /// only a fallback of `DefaultResolver` for the missing paths, when the
+    /// file-system is case-insensitive.
+    ///
+    /// Returns `None` if a directory can't be listed.
+    pub(super) fn fix_path_case(&self, guest_path: &Path) -> Option<PathBuf> {
+        let mut fixed_path = PathBuf::from("/");
+        for component in normalize_path(guest_path).ok()?.components() {
+            let name = match component {
+                Component::Normal(name) => name,
+                _ => continue,
+            };
+            let dir_host_path = self
+                .substitute(&self.canonicalize(&fixed_path, true).ok()?, Guest)
+                .ok()?;
+            if dir_host_path.join(name).symlink_metadata().is_err() {
+                let lowercase_name = name.to_string_lossy().to_lowercase();
+                let entry = std::fs::read_dir(&dir_host_path)
+                    .ok()?
+                    .filter_map(|entry| entry.ok())
+                    .find(|entry| {
+                        entry.file_name().to_string_lossy().to_lowercase() == lowercase_name
+                    });
+                if let Some(entry) = entry {
+                    fixed_path.push(entry.file_name());
+                    continue;
+                }
+            }
+            fixed_path.push(name);
+        }
+        Some(fixed_path)
+    }
+
+    /// Slow path of `translate_absolute_path()`, see `Canonicalizer`.
+    fn canonicalize_and_substitute(&self, guest_path: &Path, deref_final: bool) -> Result<PathBuf> {
+        let canonical_guest_path = match self.canonicalize(guest_path, deref_final) {
+            Err(error) if error.get_errno() == Errno::ENOENT => match self.get_fallback_policy() {
+                FallbackPolicy::NotFound => return Err(error),
+                FallbackPolicy::Deny => return Err(Error::errno(Errno::EACCES)),
+                FallbackPolicy::Permissive => {
+                    return self.translate_missing_path(guest_path, error)
+                }
+            },
+            result => result?,
+        };
+        self.substitute(&canonical_guest_path, Guest)
+    }
+
+    /// Translates the missing `guest_path` with `FallbackPolicy::Permissive`:
+    /// its longest existing prefix is canonicalized, and only the missing
+    /// components which follow are appended as-is.
+    ///
+    /// Fails with `error` if the first missing component is a host symlink
+    /// (its target being missing in the guest), since the host kernel would
+    /// follow it out of the guest.
+    fn translate_missing_path(&self, guest_path: &Path, error: Error) -> Result<PathBuf> {
+        let components: Vec<Component> = guest_path.components().collect();
+        let mut prefix = PathBuf::new();
+
+        for (index, component) in components.iter().enumerate() {
+            prefix.push(component);
+            let canonical_path = self.canonicalize(&prefix, true)?;
+            let host_path = self.substitute(&canonical_path, Guest)?;
+            match host_path.symlink_metadata() {
+                // the existing symlinks are dereferenced by canonicalize()
+                Ok(metadata) if metadata.file_type().is_symlink() => return Err(error),
+                Ok(_) => continue,
+                Err(_) => {}
+            }
+
+            let suffix = &components[index + 1..];
+            if suffix.contains(&Component::ParentDir) {
+                // ".." cancels a missing component, the path is resolved
+                // again from its canonical prefix
+                let path =
+                    normalize_path(&canonical_path.join(suffix.iter().collect::<PathBuf>()))?;
+                return self.translate_missing_path(&path, error);
+            }
+            let missing_path: PathBuf = canonical_path
+                .components()
+                .chain(suffix.iter().cloned())
+                .collect();
+            return self.substitute(&missing_path, Guest);
+        }
+        Err(error)
+    }
+
+    pub(super) fn detranslate_path_uncached(
+        &self,
+        host_path: &Path,
+        referrer: Option<&Path>,
+    ) -> Result<Option<PathBuf>> {
         let mut follow_binding = true;
 
         // Is it a symlink?
@@ -75,10 +289,11 @@ impl Translator for FileSystem {
 
             // In some cases bindings have to be resolved.
             if referrer_path.starts_with("/proc") {
-                // Some links in "/proc" are generated dynamically by the kernel.
-                // PRoot has to emulate some of them.
+                // Some links in "/proc" are generated dynamically by the kernel,
+                // and point to host paths: they are detranslated like any
+                // other host path.
                 //TODO: readlink_proc2
-                unimplemented!(" /proc/.. referrer paths not supported!");
+                follow_binding = true;
             } else if !self.belongs_to_guestfs(referrer_path) {
                 let maybe_binding_referree = self.get_first_appropriate_binding(host_path, Host);
                 let binding_referrer = self
@@ -108,8 +323,8 @@ impl Translator for FileSystem {
             }
         }
 
-        // otherwise, we simply try to strip the (guest) root
-        if let Ok(stripped_path) = host_path.strip_prefix(&self.get_root()) {
+        // otherwise, we simply try to strip the (guest) root or its layer
+        if let Some(stripped_path) = self.strip_rootfs_prefix(host_path) {
             return Ok(Some(PathBuf::from("/").join(stripped_path)));
         }
 
@@ -117,6 +332,21 @@ impl Translator for FileSystem {
     }
 }
 
+/// Returns whether `path` ends with a slash, which isn't visible in its
+/// components. The root "/" doesn't count.
+fn has_trailing_slash(path: &Path) -> bool {
+    let bytes = path.as_os_str().as_bytes();
+    bytes.len() > 1 && bytes.ends_with(b"/")
+}
+
+fn add_trailing_slash(path: &mut PathBuf) {
+    if !path.as_os_str().as_bytes().ends_with(b"/") {
+        let mut os_string = std::mem::take(path).into_os_string();
+        os_string.push("/");
+        *path = PathBuf::from(os_string);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,6 +403,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_translate_path_trailing_slash() {
+        let rootfs_path = get_test_rootfs_path();
+        let fs = FileSystem::with_root(&rootfs_path).unwrap();
+
+        // the trailing slash is kept for the kernel, which checks the directory
+        for (guest_path, host_path) in &[
+            ("/etc/", "etc/"),
+            ("/etc/passwd/", "etc/passwd/"),
+            ("/tmp/../etc//", "etc/"),
+        ] {
+            assert_eq!(
+                fs.translate_path(guest_path, false).unwrap().as_os_str(),
+                rootfs_path.join(host_path).as_os_str()
+            );
+        }
+        assert_eq!(
+            fs.translate_path("/", false).unwrap().as_os_str(),
+            rootfs_path.as_os_str()
+        );
+        assert!(!has_trailing_slash(Path::new("/")));
+        assert!(has_trailing_slash(Path::new("etc/")));
+    }
+
+    #[test]
+    fn test_translate_path_case_insensitive() {
+        let rootfs_path = get_test_rootfs_path();
+        let hostname_path = rootfs_path.join("etc/hostname");
+        let is_created = hostname_path.symlink_metadata().is_err();
+        if is_created {
+            std::fs::write(&hostname_path, "proot\n").unwrap();
+        }
+
+        let mut fs = FileSystem::with_root(&rootfs_path).unwrap();
+        assert_eq!(
+            fs.translate_path("/ETC/Hostname", true)
+                .unwrap_err()
+                .get_errno(),
+            Errno::ENOENT
+        );
+
+        fs.set_case_insensitive(true);
+        assert_eq!(
+            fs.translate_path("/ETC/Hostname", true),
+            Ok(hostname_path.clone())
+        );
+        assert_eq!(
+            fs.translate_path("/etc/hostname", true),
+            Ok(hostname_path.clone())
+        );
+        // only the existing components are fixed
+        assert_eq!(
+            fs.translate_path("/Etc/missing_file", false),
+            Ok(rootfs_path.join("etc/missing_file"))
+        );
+        assert_eq!(
+            fs.translate_path("/missing_dir/Hostname", false)
+                .unwrap_err()
+                .get_errno(),
+            Errno::ENOENT
+        );
+
+        if is_created {
+            std::fs::remove_file(&hostname_path).unwrap();
+        }
+    }
+
     #[test]
     fn test_detranslate_path_root() {
         let rootfs_path = PathBuf::from(get_test_rootfs_path());
@@ -228,4 +525,266 @@ mod tests {
 
         //TODO: detranslate symlink tests
     }
+
+    #[test]
+    fn test_detranslate_path_cache() {
+        let mut fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+
+        // "/etc/passwd" isn't in the guest file-system yet
+        assert_eq!(fs.detranslate_path("/etc/passwd", None), Ok(None));
+        assert_eq!(
+            fs.get_cached_detranslation(Path::new("/etc/passwd")),
+            Some(None)
+        );
+
+        // a new binding invalidates the cached results
+        fs.add_binding("/etc", "/tmp").unwrap();
+        assert_eq!(fs.get_cached_detranslation(Path::new("/etc/passwd")), None);
+        assert_eq!(
+            fs.detranslate_path("/etc/passwd", None),
+            Ok(Some(PathBuf::from("/tmp/passwd")))
+        );
+        assert_eq!(
+            fs.detranslate_path("/etc/passwd", None),
+            Ok(Some(PathBuf::from("/tmp/passwd")))
+        );
+    }
+
+    /// Rough benchmark of the detranslation cache, run it with
+    /// `cargo test --release -- --ignored bench_detranslate_path --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_detranslate_path() {
+        use std::time::Instant;
+
+        let mut fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+        for (host_path, guest_path) in &[("/etc", "/etc"), ("/usr", "/usr"), ("/tmp", "/tmp")] {
+            fs.add_binding(host_path, guest_path).unwrap();
+        }
+        let host_paths: Vec<PathBuf> = (0..100)
+            .map(|i| PathBuf::from(format!("/usr/lib/file_{}", i)))
+            .collect();
+
+        let start = Instant::now();
+        for _ in 0..1000 {
+            for host_path in &host_paths {
+                fs.detranslate_path_uncached(host_path, None).unwrap();
+            }
+        }
+        let uncached = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..1000 {
+            for host_path in &host_paths {
+                fs.detranslate_path(host_path, None).unwrap();
+            }
+        }
+        let cached = start.elapsed();
+
+        println!("uncached: {:?}, cached: {:?}", uncached, cached);
+    }
+
+    #[test]
+    fn test_translate_path_fast_path() {
+        let rootfs_path = get_test_rootfs_path();
+        let dir_path = rootfs_path.join("tmp/dir_for_test_translate_path_fast_path");
+        std::fs::create_dir_all(dir_path.join("real")).unwrap();
+        std::fs::create_dir_all(dir_path.join("component")).unwrap();
+        let fs = FileSystem::with_root(&rootfs_path).unwrap();
+        let guest_path = "/tmp/dir_for_test_translate_path_fast_path/component/file";
+
+        assert_eq!(
+            fs.translate_without_bindings(Path::new(guest_path), true),
+            Some(dir_path.join("component/file"))
+        );
+        assert_eq!(
+            fs.translate_path(guest_path, true),
+            Ok(dir_path.join("component/file"))
+        );
+
+        // the component is replaced by a symlink, which has to be dereferenced
+        std::fs::remove_dir(dir_path.join("component")).unwrap();
+        std::os::unix::fs::symlink("real", dir_path.join("component")).unwrap();
+        assert_eq!(
+            fs.translate_without_bindings(Path::new(guest_path), true),
+            None
+        );
+        assert_eq!(
+            fs.translate_path(guest_path, true),
+            Ok(dir_path.join("real/file"))
+        );
+        // but a final symlink is kept if it's not dereferenced
+        assert_eq!(
+            fs.translate_path(
+                "/tmp/dir_for_test_translate_path_fast_path/component",
+                false
+            ),
+            Ok(dir_path.join("component"))
+        );
+        // ".." is never handled by the fast path
+        assert_eq!(
+            fs.translate_without_bindings(Path::new("/tmp/../etc"), true),
+            None
+        );
+
+        std::fs::remove_dir_all(&dir_path).unwrap();
+    }
+
+    /// Rough benchmark of the fast path of the translation, run it with
+    /// `cargo test --release -- --ignored bench_translate_path --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_translate_path() {
+        use std::time::Instant;
+
+        let fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+        let guest_paths: Vec<PathBuf> = (0..100)
+            .map(|i| PathBuf::from(format!("/etc/file_{}", i)))
+            .collect();
+
+        let start = Instant::now();
+        for _ in 0..1000 {
+            for guest_path in &guest_paths {
+                fs.canonicalize_and_substitute(guest_path, true).unwrap();
+            }
+        }
+        let canonicalized = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..1000 {
+            for guest_path in &guest_paths {
+                fs.translate_path(guest_path, true).unwrap();
+            }
+        }
+        let fast_path = start.elapsed();
+
+        println!(
+            "canonicalized: {:?}, fast path: {:?}",
+            canonicalized, fast_path
+        );
+    }
+
+    #[test]
+    fn test_translate_path_fallback_policy() {
+        let rootfs_path = get_test_rootfs_path();
+        let mut fs = FileSystem::with_root(&rootfs_path).unwrap();
+        let missing_path = "/impossible_dir/../impossible_dir/file";
+
+        // the default policy is the one of the kernel
+        assert_eq!(fs.get_fallback_policy(), FallbackPolicy::NotFound);
+        assert_eq!(
+            fs.translate_path(missing_path, true),
+            Err(Error::errno(Errno::ENOENT))
+        );
+
+        fs.set_fallback_policy(FallbackPolicy::Deny);
+        assert_eq!(
+            fs.translate_path(missing_path, true),
+            Err(Error::errno(Errno::EACCES))
+        );
+
+        // the missing components are appended to the canonical existing
+        // prefix, ".." being resolved lexically once a component is missing
+        fs.set_fallback_policy(FallbackPolicy::Permissive);
+        assert_eq!(
+            fs.translate_path(missing_path, true),
+            Ok(rootfs_path.join("impossible_dir/file"))
+        );
+        assert_eq!(
+            fs.translate_path("/impossible_dir/../../../etc/file", true),
+            Ok(rootfs_path.join("etc/file"))
+        );
+
+        // a symlink whose target is missing in the guest isn't followed on
+        // the host, where its target may exist
+        let host_target_path = std::env::temp_dir().join("proot_rs_test_fallback_target");
+        let link_path = rootfs_path.join("tmp/proot_rs_test_fallback_link");
+        std::fs::create_dir_all(&host_target_path).unwrap();
+        let _ = std::fs::remove_file(&link_path);
+        std::os::unix::fs::symlink(&host_target_path, &link_path).unwrap();
+        let guest_link_path = Path::new("/tmp/proot_rs_test_fallback_link");
+        let is_missing_in_guest = !rootfs_path
+            .join(host_target_path.strip_prefix("/").unwrap())
+            .exists();
+        if is_missing_in_guest {
+            assert_eq!(
+                fs.translate_path(guest_link_path.join("file"), true),
+                Err(Error::errno(Errno::ENOENT))
+            );
+            assert_eq!(
+                fs.translate_path(guest_link_path, true),
+                Err(Error::errno(Errno::ENOENT))
+            );
+        }
+        // it's still a path of its own
+        assert_eq!(
+            fs.translate_path(guest_link_path, false),
+            Ok(link_path.clone())
+        );
+        std::fs::remove_file(&link_path).unwrap();
+
+        // existing paths aren't affected by the policy
+        for &policy in [FallbackPolicy::NotFound, FallbackPolicy::Deny].iter() {
+            fs.set_fallback_policy(policy);
+            assert_eq!(
+                fs.translate_path("/etc/impossible_file", true),
+                Ok(rootfs_path.join("etc/impossible_file"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_translate_path_with_layers() {
+        let layers_path = std::env::temp_dir().join("proot_rs_test_translate_path_with_layers");
+        let upper_path = layers_path.join("upper");
+        let lower_path = layers_path.join("lower");
+        let lowest_path = layers_path.join("lowest");
+        std::fs::create_dir_all(&upper_path).unwrap();
+        std::fs::create_dir_all(lower_path.join("etc/lower_only")).unwrap();
+        std::fs::create_dir_all(lowest_path.join("etc")).unwrap();
+        std::fs::write(lower_path.join("etc/file"), "lower").unwrap();
+        std::fs::write(lowest_path.join("etc/file"), "lowest").unwrap();
+        std::fs::write(lowest_path.join("etc/lowest_file"), "lowest").unwrap();
+        let upper_path = std::fs::canonicalize(&upper_path).unwrap();
+        let lower_path = std::fs::canonicalize(&lower_path).unwrap();
+        let lowest_path = std::fs::canonicalize(&lowest_path).unwrap();
+
+        let mut fs = FileSystem::with_root(&upper_path).unwrap();
+        fs.add_layer(&lower_path).unwrap();
+        fs.add_layer(&lowest_path).unwrap();
+
+        // reads: the first layer containing the path wins
+        assert_eq!(
+            fs.translate_path("/etc/file", true),
+            Ok(lower_path.join("etc/file"))
+        );
+        assert_eq!(
+            fs.translate_path("/etc/lowest_file", true),
+            Ok(lowest_path.join("etc/lowest_file"))
+        );
+        assert_eq!(
+            fs.detranslate_path(lowest_path.join("etc/lowest_file"), None),
+            Ok(Some(PathBuf::from("/etc/lowest_file")))
+        );
+        // a missing path is in the upper layer
+        assert_eq!(
+            fs.translate_path("/etc/new_file", true),
+            Ok(upper_path.join("etc/new_file"))
+        );
+
+        // writes: the path lands in the upper layer, with its parents
+        let host_path = fs
+            .translate_path_for_write("/etc/lower_only/new_file", true)
+            .unwrap();
+        assert_eq!(host_path, upper_path.join("etc/lower_only/new_file"));
+        assert!(upper_path.join("etc/lower_only").is_dir());
+        std::fs::write(&host_path, "upper").unwrap();
+        assert_eq!(
+            fs.translate_path("/etc/lower_only/new_file", true),
+            Ok(host_path)
+        );
+        assert!(!lower_path.join("etc/lower_only/new_file").exists());
+
+        std::fs::remove_dir_all(&layers_path).unwrap();
+    }
 }
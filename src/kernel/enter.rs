@@ -1,48 +1,168 @@
 use crate::errors::Result;
-use crate::kernel::execve;
-use crate::kernel::groups::syscall_group_from_sysnum;
-use crate::kernel::groups::SyscallGroup::*;
-use crate::kernel::heap::*;
-use crate::kernel::ptrace::*;
-use crate::kernel::socket::*;
-use crate::kernel::standard::*;
+use crate::kernel::registry;
+use crate::kernel::standard::umask;
 use crate::process::proot::InfoBag;
 use crate::process::tracee::Tracee;
 use crate::register::Original;
 
+/// Translates the enter stage of the syscall of `tracee`.
+///
+/// The syscalls without handlers (e.g. the ones unknown to proot-rs) and the
+/// bypassed ones are passed through: none of their registers is modified.
 pub fn translate(info_bag: &InfoBag, tracee: &mut Tracee) -> Result<()> {
     let sys_num = tracee.regs.get_sys_num(Original);
-    let sys_type = syscall_group_from_sysnum(sys_num);
-
-    match sys_type {
-        Accept => accept::enter(),
-        BindConnect => bind_connect::enter(),
-        Brk => brk::enter(),
-        Chdir => chdir::enter(tracee),
-        ChmodAccessMkNodAt => chmod_access_mknod_at::enter(tracee),
-        DirLinkAttr => dir_link_attr::enter(tracee),
-        Execve => execve::enter(tracee, &info_bag.loader),
-        GetCwd => getcwd::enter(tracee),
-        GetSockOrPeerName => get_sockorpeer_name::enter(),
-        InotifyAddWatch => inotify_add_watch::enter(),
-        Link => link_rename::enter(tracee),
-        LinkAt => link_at::enter(tracee),
-        Mount => mount::enter(),
-        Open => open::enter(tracee),
-        OpenAt => open_at::enter(tracee),
-        PivotRoot => pivot_root::enter(),
-        Ptrace => ptrace::enter(),
-        ReadLink => dir_link_attr::enter(tracee),
-        ReadLinkAt => unlink_mkdir_at::enter(tracee),
-        Rename => link_rename::enter(tracee),
-        RenameAt => rename_at::enter(tracee),
-        SocketCall => socketcall::enter(),
-        StandardSyscall => standard_syscall::enter(tracee),
-        StatAt => stat_at::enter(tracee),
-        SymLink => sym_link::enter(tracee),
-        SymLinkAt => sym_link_at::enter(tracee),
-        Wait => wait::enter(),
-        UnlinkMkdirAt => unlink_mkdir_at::enter(tracee),
-        _ => Ok(()),
+    let handlers = registry::handlers_of_sysnum(sys_num);
+    if handlers.is_passthrough() || info_bag.config.is_syscall_bypassed(sys_num) {
+        return Ok(());
+    }
+    umask::apply(tracee)?;
+
+    match handlers.enter {
+        Some(enter) => enter(info_bag, tracee),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use libc::{c_long, SYS_futex, SYS_open, FUTEX_PRIVATE_FLAG, FUTEX_WAKE, O_RDONLY};
+    use sc::nr::{FUTEX, OPEN};
+
+    use crate::config::ProotConfig;
+    use crate::register::{Current, Original, SysArg, SysArg1, SysArg2, SysArg3};
+    use crate::register::{SysArg4, SysArg5, SysArg6};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot, test_with_proot_config};
+
+    #[test]
+    fn test_enter_futex_arguments_untouched() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation {
+                    return;
+                }
+                if tracee.regs.get_sys_num(Original) == FUTEX {
+                    assert_eq!(tracee.regs.get_sys_num(Current), FUTEX);
+                    for &index in [SysArg1, SysArg2, SysArg3, SysArg4, SysArg5, SysArg6].iter() {
+                        assert_eq!(
+                            tracee.regs.get(Current, SysArg(index)),
+                            tracee.regs.get(Original, SysArg(index))
+                        );
+                    }
+                }
+            },
+            || {
+                // The first argument is a pointer to a word which could be read
+                // as a (short) path.
+                let futex_word: u32 = u32::from_ne_bytes(*b"/et\0");
+                let woken = unsafe {
+                    libc::syscall(
+                        SYS_futex,
+                        &futex_word as *const u32,
+                        FUTEX_WAKE | FUTEX_PRIVATE_FLAG,
+                        1 as c_long,
+                    )
+                };
+                assert_eq!(woken, 0);
+                assert_eq!(futex_word, u32::from_ne_bytes(*b"/et\0"));
+            },
+        )
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    fn test_enter_rseq_arguments_untouched() {
+        use crate::kernel::groups::nr::RSEQ;
+
+        /// `struct rseq` of `include/uapi/linux/rseq.h`.
+        #[repr(C, align(32))]
+        struct Rseq {
+            cpu_id_start: u32,
+            cpu_id: u32,
+            rseq_cs: u64,
+            flags: u32,
+        }
+        const RSEQ_SIG: u32 = 0x5305_3053;
+
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation || tracee.regs.get_sys_num(Original) != RSEQ {
+                    return;
+                }
+                assert_eq!(tracee.regs.get_sys_num(Current), RSEQ);
+                for &index in [SysArg1, SysArg2, SysArg3, SysArg4].iter() {
+                    assert_eq!(
+                        tracee.regs.get(Current, SysArg(index)),
+                        tracee.regs.get(Original, SysArg(index))
+                    );
+                }
+            },
+            || {
+                // `cpu_id` is set by the kernel on registration
+                let rseq = Box::new(Rseq {
+                    cpu_id_start: 0,
+                    cpu_id: u32::MAX - 1,
+                    rseq_cs: 0,
+                    flags: 0,
+                });
+                let result = unsafe {
+                    libc::syscall(
+                        RSEQ as c_long,
+                        &*rseq as *const Rseq,
+                        std::mem::size_of::<Rseq>() as u32,
+                        0,
+                        RSEQ_SIG,
+                    )
+                };
+                if result == 0 {
+                    assert_ne!(
+                        unsafe { std::ptr::read_volatile(&rseq.cpu_id) },
+                        u32::MAX - 1
+                    );
+                    // unregister it before it's freed
+                    let result = unsafe {
+                        libc::syscall(
+                            RSEQ as c_long,
+                            &*rseq as *const Rseq,
+                            std::mem::size_of::<Rseq>() as u32,
+                            1,
+                            RSEQ_SIG,
+                        )
+                    };
+                    assert_eq!(result, 0);
+                } else {
+                    // the thread is already registered by a recent glibc
+                    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::EBUSY);
+                    std::mem::forget(rseq);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_enter_bypassed_syscall() {
+        // the test program is only on the host
+        let host_path = CString::new(std::env::current_exe().unwrap().to_str().unwrap()).unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .bypass_syscall("open")
+                .build(),
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter && !before_translation && tracee.regs.get_sys_num(Original) == OPEN {
+                    assert_eq!(
+                        tracee.regs.get(Current, SysArg(SysArg1)),
+                        tracee.regs.get(Original, SysArg(SysArg1))
+                    );
+                }
+            },
+            || {
+                let fd = unsafe { libc::syscall(SYS_open, host_path.as_ptr(), O_RDONLY) };
+                assert!(fd >= 0);
+                unsafe { libc::close(fd as i32) };
+            },
+        )
     }
 }
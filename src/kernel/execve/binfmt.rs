@@ -0,0 +1,294 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::errors::Result;
+use crate::kernel::execve::shebang::BINPRM_BUF_SIZE;
+
+/// Magic number of the ELF files, which are run natively.
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// How a `BinfmtRule` recognizes the files it handles.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinfmtMatcher {
+    /// The bytes at `offset` in the file are `magic`, only the bits set in
+    /// `mask` being compared, if any.
+    Magic {
+        offset: usize,
+        magic: Vec<u8>,
+        mask: Option<Vec<u8>>,
+    },
+    /// The name of the file ends with "." followed by this extension.
+    Extension(String),
+}
+
+/// An interpreter of the files which are neither ELF files nor scripts, like
+/// the ones registered in binfmt_misc on the host. The interpreter is a guest
+/// path, and receives the file like the interpreter of a script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinfmtRule {
+    pub name: String,
+    pub matcher: BinfmtMatcher,
+    pub interpreter: PathBuf,
+    /// Keep the original argv[0], after the path of the file (flag `P`),
+    /// instead of replacing it by the path of the file.
+    pub preserve_argv0: bool,
+}
+
+impl BinfmtRule {
+    /// Parses a rule with the syntax of the registrations of binfmt_misc,
+    /// `:name:type:offset:magic:mask:interpreter:flags`, where the first
+    /// character is the separator, `type` is `M` (magic) or `E`
+    /// (extension), and the bytes of `magic` and `mask` can be escaped as
+    /// `\xHH`. Only the flag `P` is supported.
+    pub fn parse(registration: &str) -> std::result::Result<BinfmtRule, String> {
+        let separator = registration
+            .chars()
+            .next()
+            .ok_or_else(|| "empty binfmt rule".to_string())?;
+        let fields: Vec<&str> = registration[separator.len_utf8()..]
+            .split(separator)
+            .collect();
+        if fields.len() != 7 {
+            return Err(format!(
+                "{:?} must be a binfmt rule :name:type:offset:magic:mask:interpreter:flags",
+                registration
+            ));
+        }
+        let (name, kind, offset, magic, mask, interpreter, flags) = (
+            fields[0], fields[1], fields[2], fields[3], fields[4], fields[5], fields[6],
+        );
+        if name.is_empty() || name.contains('/') {
+            return Err(format!("{:?} must be a non-empty name without '/'", name));
+        }
+        if !interpreter.starts_with('/') {
+            return Err(format!("{:?} must be an absolute guest path", interpreter));
+        }
+
+        let matcher = match kind {
+            "M" => {
+                let offset = match offset {
+                    "" => 0,
+                    offset => offset
+                        .parse()
+                        .map_err(|_| format!("{:?} must be a number", offset))?,
+                };
+                let magic = unescape(magic)?;
+                if magic.is_empty() {
+                    return Err("the magic of a binfmt rule can't be empty".into());
+                }
+                let mask_bytes = match mask {
+                    "" => None,
+                    mask => Some(unescape(mask)?),
+                };
+                if mask_bytes
+                    .as_ref()
+                    .map_or(false, |bytes| bytes.len() != magic.len())
+                {
+                    return Err(format!("{:?} must be as long as the magic", mask));
+                }
+                BinfmtMatcher::Magic {
+                    offset,
+                    magic,
+                    mask: mask_bytes,
+                }
+            }
+            "E" => {
+                if magic.is_empty() || magic.contains('/') {
+                    return Err(format!("{:?} must be a non-empty extension", magic));
+                }
+                BinfmtMatcher::Extension(magic.into())
+            }
+            _ => return Err(format!("{:?} must be the type M or E", kind)),
+        };
+
+        let mut preserve_argv0 = false;
+        for flag in flags.chars() {
+            match flag {
+                'P' => preserve_argv0 = true,
+                _ => return Err(format!("the binfmt flag {:?} isn't supported", flag)),
+            }
+        }
+
+        Ok(BinfmtRule {
+            name: name.into(),
+            matcher,
+            interpreter: interpreter.into(),
+            preserve_argv0,
+        })
+    }
+
+    fn matches(&self, path: &Path, header: &[u8]) -> bool {
+        match &self.matcher {
+            BinfmtMatcher::Magic {
+                offset,
+                magic,
+                mask,
+            } => match header.get(*offset..*offset + magic.len()) {
+                Some(bytes) => bytes.iter().enumerate().all(|(i, byte)| {
+                    let mask = mask.as_ref().map_or(0xff, |mask| mask[i]);
+                    (byte ^ magic[i]) & mask == 0
+                }),
+                None => false,
+            },
+            BinfmtMatcher::Extension(extension) => path
+                .extension()
+                .map_or(false, |path_extension| path_extension == extension.as_str()),
+        }
+    }
+}
+
+/// Unescapes the `\xHH` sequences of `field`, and `\\` as a backslash.
+fn unescape(field: &str) -> std::result::Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut rest = field.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+        } else if rest.first() == Some(&b'\\') {
+            bytes.push(b'\\');
+            rest = &rest[1..];
+        } else if rest.first() == Some(&b'x') && rest.len() >= 3 {
+            let hex = std::str::from_utf8(&rest[1..3]).unwrap_or("");
+            let value = u8::from_str_radix(hex, 16)
+                .map_err(|_| format!("{:?} holds an invalid escape sequence", field))?;
+            bytes.push(value);
+            rest = &rest[3..];
+        } else {
+            return Err(format!("{:?} holds an invalid escape sequence", field));
+        }
+    }
+    Ok(bytes)
+}
+
+/// Returns the first of `rules` handling the file executed as `user_path`,
+/// whose host path is `host_path`. The ELF files are run natively, so they
+/// aren't handled by any rule.
+pub(super) fn find_rule<'a>(
+    rules: &'a [BinfmtRule],
+    user_path: &Path,
+    host_path: &Path,
+) -> Result<Option<&'a BinfmtRule>> {
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let mut header = Vec::with_capacity(BINPRM_BUF_SIZE);
+    File::open(host_path)?
+        .take(BINPRM_BUF_SIZE as u64)
+        .read_to_end(&mut header)?;
+    if header.starts_with(ELF_MAGIC) {
+        return Ok(None);
+    }
+    Ok(rules.iter().find(|rule| rule.matches(user_path, &header)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::PermissionsExt;
+
+    use nix::unistd::execv;
+
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config_and_exit_code};
+
+    #[test]
+    fn test_binfmt_rule_parse() {
+        assert_eq!(
+            BinfmtRule::parse(r":test:M:2:\x00ab\\:\xff\x0f\xff\xff:/bin/sh:P"),
+            Ok(BinfmtRule {
+                name: "test".into(),
+                matcher: BinfmtMatcher::Magic {
+                    offset: 2,
+                    magic: b"\x00ab\\".to_vec(),
+                    mask: Some(b"\xff\x0f\xff\xff".to_vec()),
+                },
+                interpreter: "/bin/sh".into(),
+                preserve_argv0: true,
+            })
+        );
+        assert_eq!(
+            BinfmtRule::parse("|jar|E||jar||/usr/bin/java-runner|"),
+            Ok(BinfmtRule {
+                name: "jar".into(),
+                matcher: BinfmtMatcher::Extension("jar".into()),
+                interpreter: "/usr/bin/java-runner".into(),
+                preserve_argv0: false,
+            })
+        );
+
+        assert!(BinfmtRule::parse(":test:M::abc::/bin/sh").is_err());
+        assert!(BinfmtRule::parse(":test:X::abc::/bin/sh:").is_err());
+        assert!(BinfmtRule::parse(":test:M::::/bin/sh:").is_err());
+        assert!(BinfmtRule::parse(":test:M::abc:\\xff:/bin/sh:").is_err());
+        assert!(BinfmtRule::parse(":test:M::\\xzz::/bin/sh:").is_err());
+        assert!(BinfmtRule::parse(":test:M::abc::bin/sh:").is_err());
+        assert!(BinfmtRule::parse(":test:M::abc::/bin/sh:C").is_err());
+    }
+
+    #[test]
+    fn test_binfmt_rule_matches() {
+        let rule = BinfmtRule::parse(r":test:M:1:\x10b::/bin/sh:").unwrap();
+        assert!(rule.matches(Path::new("/file"), b"a\x10bc"));
+        assert!(!rule.matches(Path::new("/file"), b"\x10bc"));
+        assert!(!rule.matches(Path::new("/file"), b"a\x10"));
+
+        let rule = BinfmtRule::parse(r":test:M::\x10b:\xf0\xff:/bin/sh:").unwrap();
+        assert!(rule.matches(Path::new("/file"), b"\x1fb"));
+        assert!(!rule.matches(Path::new("/file"), b"\x2fb"));
+
+        let rule = BinfmtRule::parse(":test:E::wasm::/bin/sh:").unwrap();
+        assert!(rule.matches(Path::new("/dir/file.wasm"), b""));
+        assert!(!rule.matches(Path::new("/dir/file.wasm.txt"), b""));
+        assert!(!rule.matches(Path::new("/dir/wasm"), b""));
+    }
+
+    #[test]
+    fn test_binfmt_find_rule_skips_elf() {
+        // a rule matching any file
+        let rules = [BinfmtRule::parse(r":any:M::\x7f:\x00:/bin/sh:").unwrap()];
+        let sh_path = get_test_rootfs_path().join("bin/sh");
+        assert_eq!(find_rule(&rules, Path::new("/bin/sh"), &sh_path), Ok(None));
+    }
+
+    #[test]
+    /// Runs a file matching the magic of a rule, whose interpreter is a shell
+    /// checking its arguments.
+    fn test_binfmt_exec_magic_rule() {
+        let name = "proot_rs_test_binfmt_magic";
+        let host_path = get_test_rootfs_path().join("tmp").join(name);
+        // the magic is a comment for the shell
+        fs::write(
+            &host_path,
+            "#PROOT_RS_TEST\n[ \"$0\" = /tmp/proot_rs_test_binfmt_magic ] && [ \"$1\" = my_file ] && [ \"$2\" = hello ] && exit 42\n",
+        )
+        .unwrap();
+        fs::set_permissions(&host_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        test_with_proot_config_and_exit_code(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .binfmt_rule(BinfmtRule::parse(":test:M::#PROOT_RS_TEST::/bin/sh:P").unwrap())
+                .build(),
+            42,
+            |_, _, _| {},
+            || {
+                let path = Path::new("/tmp").join(name);
+                let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+                execv(
+                    &path,
+                    &[
+                        CString::new("my_file").unwrap(),
+                        CString::new("hello").unwrap(),
+                    ],
+                )
+                .expect("failed execv file");
+            },
+        );
+        fs::remove_file(&host_path).unwrap();
+    }
+}
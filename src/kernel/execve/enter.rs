@@ -3,14 +3,22 @@ use std::rc::Rc;
 
 use crate::errors::*;
 use crate::filesystem::Translator;
+use crate::kernel::execve::elf::ExecutableClass;
+use crate::kernel::execve::environ;
 use crate::kernel::execve::load_info::LoadInfo;
 use crate::kernel::execve::loader::LoaderFile;
 use crate::kernel::execve::shebang;
+use crate::process::proot::InfoBag;
 use crate::process::tracee::Tracee;
 use crate::register::PtraceWriter;
 use crate::register::{PtraceReader, SysArg1};
 
-pub fn translate(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
+#[cfg(target_pointer_width = "64")]
+const HOST_CLASS: ExecutableClass = ExecutableClass::Class64;
+#[cfg(target_pointer_width = "32")]
+const HOST_CLASS: ExecutableClass = ExecutableClass::Class32;
+
+pub fn translate(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
     //TODO: implement this part for ptrace translation
     //	if (IS_NOTIFICATION_PTRACED_LOAD_DONE(tracee)) {
     //		/* Syscalls can now be reported to its ptracer.  */
@@ -24,14 +32,26 @@ pub fn translate(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
 
     let raw_path = tracee.regs.get_sysarg_path(SysArg1)?;
     debug!("execve({:?})", raw_path);
-    //TODO: return user path
-    let host_path = match shebang::expand(&tracee.fs.borrow(), &raw_path) {
-        Ok(path) => path,
+    let (host_path, user_path) = match shebang::expand(
+        tracee,
+        &raw_path,
+        info_bag.config.preserve_argv0,
+        info_bag.config.strip_shebang_cr,
+        &info_bag.config.binfmt_rules,
+    ) {
+        Ok(paths) => paths,
         // The Linux kernel actually returns -EACCES when trying to execute a directory.
         Err(error) if error.get_errno() == Errno::EISDIR => return Err(Error::from(Errno::EACCES)),
         Err(error) => return Err(error),
     };
 
+    if !info_bag.config.stripped_env_vars.is_empty() {
+        environ::strip_variables(tracee, &info_bag.config.stripped_env_vars)?;
+    }
+    if info_bag.config.sanitize_ld_library_path {
+        environ::sanitize_ld_library_path(tracee)?;
+    }
+
     //TODO: clear this when raw_path and user_path's implementations are done
     //	/* user_path is modified only if there's an interpreter
     // 	 * (ie. for a script or with qemu).  */
@@ -60,10 +80,19 @@ pub fn translate(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
     let mut load_info = LoadInfo::from(&tracee.fs.borrow(), &host_path)
         .with_context(|| format!("Failed to parse LoadInfo for {:?}", host_path))?;
 
-    load_info.raw_path = Some(raw_path.clone());
-    //TODO: use user_path when implemented
-    load_info.user_path = Some(raw_path);
-    load_info.host_path = Some(host_path);
+    load_info.raw_path = Some(raw_path);
+    load_info.user_path = Some(user_path);
+    load_info.host_path = Some(host_path.clone());
+
+    if !needs_loader(&load_info) {
+        // Let the kernel execute the program from its host path.
+        tracee.regs.set_sysarg_path(
+            SysArg1,
+            &host_path,
+            "during enter execve translation, setting host path",
+        )?;
+        return Ok(());
+    }
 
     if load_info.interp.is_none() {
         return Err(Error::errno_with_msg(
@@ -80,7 +109,10 @@ pub fn translate(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
         }
     }
 
-    load_info.compute_load_addresses(false)?;
+    load_info.compute_load_addresses(
+        false,
+        info_bag.config.randomize_load_addresses && !info_bag.config.no_randomize,
+    )?;
 
     tracee.load_info = Some(load_info);
 
@@ -88,7 +120,7 @@ pub fn translate(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
     // instead. TODO: uncomment this when execve::exit is ready
     tracee.regs.set_sysarg_path(
         SysArg1,
-        loader.get_loader_path(),
+        info_bag.loader.get_loader_path(),
         "during enter execve translation, setting new loader path",
     )?;
 
@@ -101,17 +133,41 @@ pub fn translate(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
     Ok(())
 }
 
+/// Checks if the program must be executed through the loader, rather than by
+/// the kernel from its translated host path. The kernel would load the ELF
+/// interpreter from the guest path found in the program, so it's only
+/// possible when this path leads to the same file on both sides (e.g. without
+/// a guest rootfs), or when there is no interpreter at all. Programs of another class
+/// than proot-rs (e.g. 32-bit programs) always go through the loader.
+///
+/// TODO: programs run with QEMU will need the loader too.
+fn needs_loader(load_info: &LoadInfo) -> bool {
+    if load_info.elf_header.get_class() != HOST_CLASS {
+        return true;
+    }
+    match load_info.interp {
+        Some(ref interp) => match interp.user_path {
+            Some(ref user_path) => std::fs::canonicalize(user_path).ok() != interp.host_path,
+            None => true,
+        },
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::tests::fork_test;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{fork_test, test_with_proot, test_with_proot_config};
     use crate::{
         register::{Current, Original, PtraceReader},
         utils::tests::get_test_rootfs_path,
     };
-    use nix::unistd::execvp;
+    use nix::unistd::{execv, execve, execvp};
     use sc::nr::{CLOCK_NANOSLEEP, EXECVE, NANOSLEEP};
     use std::ffi::CString;
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
 
     #[test]
     fn test_execve_translate_enter() {
@@ -131,7 +187,7 @@ mod tests {
 
                     // if the file executed by execve exists, we expect the translation to go well.
                     if file_exists {
-                        assert_eq!(Ok(()), translate(tracee, &info_bag.loader));
+                        assert_eq!(Ok(()), translate(tracee, info_bag));
                         at_least_one_translation_occured = true;
                     }
                     false
@@ -158,4 +214,182 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    /// Busybox selects the applet to run according to its argv[0], so it will
+    /// only exit successfully if argv[0] wasn't replaced by the loader path.
+    fn test_execve_preserve_argv0() {
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                execv(
+                    &CString::new("/bin/busybox").unwrap(),
+                    &[CString::new("true").unwrap()],
+                )
+                .expect("failed execv busybox");
+            },
+        );
+    }
+
+    #[test]
+    /// The option is read by the handler from the configuration: busybox runs
+    /// the applet named after the script ("false") when argv[0] is replaced by
+    /// the interpreter path, and the "true" applet when it's preserved.
+    fn test_execve_config_preserve_argv0() {
+        let script_dir = get_test_rootfs_path().join("tmp/proot_rs_test_config_preserve_argv0");
+        std::fs::create_dir_all(&script_dir).unwrap();
+        std::fs::write(script_dir.join("false"), "#!/bin/busybox\n").unwrap();
+        std::fs::set_permissions(script_dir.join("false"), Permissions::from_mode(0o755)).unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .preserve_argv0(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                execv(
+                    &CString::new("/tmp/proot_rs_test_config_preserve_argv0/false").unwrap(),
+                    &[CString::new("true").unwrap()],
+                )
+                .expect("failed execv script");
+            },
+        );
+    }
+
+    #[test]
+    fn test_execve_sanitize_ld_library_path() {
+        let ld_library_path = format!(
+            "LD_LIBRARY_PATH=/usr/lib:{}:/etc/ssl",
+            get_test_rootfs_path().join("lib").display()
+        );
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .binding("/etc", "/home")
+                .sanitize_ld_library_path(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // the guest entry is kept, the host ones are made guest paths
+                execve(
+                    &CString::new("/bin/sh").unwrap(),
+                    &[
+                        CString::new("sh").unwrap(),
+                        CString::new("-c").unwrap(),
+                        CString::new("[ \"$LD_LIBRARY_PATH\" = /usr/lib:/lib:/home/ssl ]").unwrap(),
+                    ],
+                    &[CString::new(ld_library_path).unwrap()],
+                )
+                .expect("failed execve sh");
+            },
+        );
+    }
+
+    #[test]
+    fn test_execve_strip_env_vars() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .strip_env_var("LD_PRELOAD")
+                .strip_env_var("PROOT_RS_TEST_SECRET")
+                .build(),
+            |_, _, _| {},
+            || {
+                // the stripped variables are unset, the other ones are kept
+                execve(
+                    &CString::new("/bin/sh").unwrap(),
+                    &[
+                        CString::new("sh").unwrap(),
+                        CString::new("-c").unwrap(),
+                        CString::new(
+                            "[ -z \"${LD_PRELOAD+x}${PROOT_RS_TEST_SECRET+x}\" ] && [ \"$KEPT\" = 1 ]",
+                        )
+                        .unwrap(),
+                    ],
+                    &[
+                        CString::new("PROOT_RS_TEST_SECRET=1").unwrap(),
+                        CString::new("KEPT=1").unwrap(),
+                        CString::new("LD_PRELOAD=").unwrap(),
+                    ],
+                )
+                .expect("failed execve sh");
+            },
+        );
+    }
+
+    #[test]
+    fn test_execve_randomized_load_addresses() {
+        // the interpreter is position independent, and busybox may be too
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .randomize_load_addresses(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                execv(
+                    &CString::new("/bin/sleep").unwrap(),
+                    &[CString::new("sleep").unwrap(), CString::new("0").unwrap()],
+                )
+                .expect("failed execv sleep");
+            },
+        );
+    }
+
+    /// Checks on the sysenter stage of execve() whether the loader is used.
+    fn check_loader_used(tracee: &Tracee, is_sysenter: bool, before_translation: bool, used: bool) {
+        if is_sysenter
+            && !before_translation
+            && tracee.regs.get_sys_num(Current) == EXECVE
+            && !tracee.regs.is_syscall_cancelled()
+        {
+            assert_eq!(tracee.load_info.is_some(), used);
+        }
+    }
+
+    #[test]
+    fn test_execve_needs_loader() {
+        // the ELF interpreter of the guest programs is in the guest rootfs
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                check_loader_used(tracee, is_sysenter, before_translation, true)
+            },
+            || {
+                execv(
+                    &CString::new("/bin/true").unwrap(),
+                    &[CString::new("true").unwrap()],
+                )
+                .expect("failed execv true");
+            },
+        );
+    }
+
+    #[test]
+    fn test_execve_simple_native_exec() {
+        // without a guest rootfs, the kernel can load the ELF interpreter
+        let test_program = CString::new(
+            std::env::current_exe()
+                .unwrap()
+                .into_os_string()
+                .into_string()
+                .unwrap(),
+        )
+        .unwrap();
+        test_with_proot_config(
+            ProotConfig::builder().rootfs("/").build(),
+            |tracee, is_sysenter, before_translation| {
+                check_loader_used(tracee, is_sysenter, before_translation, false)
+            },
+            || {
+                // the test harness only lists the tests and exits
+                execv(
+                    &test_program,
+                    &[test_program.clone(), CString::new("--list").unwrap()],
+                )
+                .expect("failed execv the test program");
+            },
+        );
+    }
 }
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+
+use libc::c_void;
+
+use crate::errors::Result;
+use crate::filesystem::{FileSystem, Translator};
+use crate::kernel::execve::shebang::{read_array, write_array, Argument};
+use crate::process::tracee::Tracee;
+use crate::register::{PtraceReader, SysArg3};
+
+/// Maximum length of a string of envp[], null terminator included
+/// (`MAX_ARG_STRLEN` in Linux).
+const MAX_ARG_STRLEN: usize = 32 * 4096;
+
+const LD_LIBRARY_PATH: &[u8] = b"LD_LIBRARY_PATH=";
+
+/// Rewrites the value of `LD_LIBRARY_PATH` in the envp[] of execve(), so that
+/// its entries under the host path of the guest root or of a binding (e.g.
+/// leaked by a tool which saw the host side) become guest paths again. The
+/// other entries, notably the guest ones, are kept as-is. envp[] is only
+/// rewritten on the tracee's stack if an entry was changed.
+pub fn sanitize_ld_library_path(tracee: &mut Tracee) -> Result<()> {
+    let fs = tracee.fs.clone();
+    let pointers = read_array(&tracee.regs, SysArg3)?;
+    let mut envp: Vec<Argument> = Vec::with_capacity(pointers.len());
+    let mut changed = false;
+
+    for pointer in pointers {
+        let variable = tracee
+            .regs
+            .read_string(pointer as *const c_void, MAX_ARG_STRLEN)?;
+        let sanitized = variable
+            .strip_prefix(LD_LIBRARY_PATH)
+            .and_then(|value| sanitize(value, &fs.borrow()));
+
+        match sanitized {
+            Some(value) => {
+                envp.push(Argument::New([LD_LIBRARY_PATH, &value].concat()));
+                changed = true;
+            }
+            None => envp.push(Argument::Tracee(pointer)),
+        }
+    }
+
+    if changed {
+        write_array(&mut tracee.regs, SysArg3, &envp)?;
+    }
+    Ok(())
+}
+
+/// Removes the variables named in `names` from the envp[] of execve(). It
+/// runs before the other rewrites of envp[], and envp[] is only rewritten on
+/// the tracee's stack if a variable was removed.
+pub fn strip_variables(tracee: &mut Tracee, names: &HashSet<String>) -> Result<()> {
+    let pointers = read_array(&tracee.regs, SysArg3)?;
+    let mut envp: Vec<Argument> = Vec::with_capacity(pointers.len());
+    let mut changed = false;
+
+    for pointer in pointers {
+        let variable = tracee
+            .regs
+            .read_string(pointer as *const c_void, MAX_ARG_STRLEN)?;
+        if is_named(&variable, names) {
+            changed = true;
+        } else {
+            envp.push(Argument::Tracee(pointer));
+        }
+    }
+
+    if changed {
+        write_array(&mut tracee.regs, SysArg3, &envp)?;
+    }
+    Ok(())
+}
+
+/// Tells whether the name of `variable` (`NAME=value`, or a bare `NAME`) is
+/// one of `names`.
+fn is_named(variable: &[u8], names: &HashSet<String>) -> bool {
+    let name = variable.split(|&c| c == b'=').next().unwrap_or_default();
+    std::str::from_utf8(name).map_or(false, |name| names.contains(name))
+}
+
+/// Returns `value` (a list of paths separated by ':') with the host paths
+/// replaced by their guest paths (see `Translator::detranslate_path`), or
+/// `None` if there is none.
+fn sanitize(value: &[u8], fs: &FileSystem) -> Option<Vec<u8>> {
+    let mut changed = false;
+    let entries: Vec<Vec<u8>> = value
+        .split(|&c| c == b':')
+        .map(|entry| {
+            let path = Path::new(OsStr::from_bytes(entry));
+            match fs.detranslate_path(path, None) {
+                Ok(Some(guest_path)) if guest_path != path => {
+                    changed = true;
+                    guest_path.into_os_string().into_vec()
+                }
+                _ => entry.to_vec(),
+            }
+        })
+        .collect();
+
+    if changed {
+        Some(entries.join(&b':'))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::get_test_rootfs_path;
+
+    #[test]
+    fn test_environ_sanitize() {
+        let root = get_test_rootfs_path();
+        let mut fs = FileSystem::with_root(&root).unwrap();
+        fs.add_binding("/etc", "/home").unwrap();
+        let root = root.to_str().unwrap();
+
+        // the guest paths are preserved
+        assert_eq!(sanitize(b"/usr/lib:/opt/lib:lib", &fs), None);
+        assert_eq!(sanitize(format!("{}2/lib", root).as_bytes(), &fs), None);
+
+        // the host ones are made guest paths, the ones of the bindings too
+        assert_eq!(
+            sanitize(
+                format!("/usr/lib:{}/opt/lib::{}:/etc/ssl", root, root).as_bytes(),
+                &fs
+            ),
+            Some(b"/usr/lib:/opt/lib::/:/home/ssl".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_environ_is_named() {
+        let names: HashSet<String> = vec!["LD_PRELOAD".to_string()].into_iter().collect();
+
+        assert!(is_named(b"LD_PRELOAD=/lib/libfoo.so", &names));
+        assert!(is_named(b"LD_PRELOAD=", &names));
+        assert!(is_named(b"LD_PRELOAD", &names));
+        assert!(!is_named(b"LD_PRELOAD_X=1", &names));
+        assert!(!is_named(b"X=LD_PRELOAD", &names));
+        assert!(!is_named(b"", &names));
+    }
+}
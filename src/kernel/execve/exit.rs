@@ -23,15 +23,18 @@ pub fn translate(tracee: &mut Tracee) -> Result<()> {
         return Ok(());
     }
 
-    if tracee.new_exe.is_some() {
-        // Execve happened; commit the new "/proc/self/exe".
-        tracee.exe = tracee.new_exe.take();
-    }
+    // Usually already done on PTRACE_EVENT_EXEC.
+    tracee.commit_exec();
 
     //TODO: implement heap
     // New processes have no heap.
     //bzero(tracee->heap, sizeof(Heap));
 
+    // The program was executed by the kernel directly, see `needs_loader()`.
+    if tracee.load_info.is_none() {
+        return Ok(());
+    }
+
     let res = transfert_load_script(tracee);
     tracee.load_info = None;
     res
@@ -218,3 +221,161 @@ pub fn transfert_load_script(tracee: &mut Tracee) -> Result<()> {
     tracee.regs.set_restore_original_regs(false);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::ffi::CString;
+    use std::path::{Path, PathBuf};
+
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+    use nix::unistd::execv;
+    use sc::nr::{EXECVE, OPEN, OPENAT};
+
+    use crate::config::ProotConfig;
+    use crate::filesystem::FileSystem;
+    use crate::kernel::execve::elf::{PF_X, PT_GNU_STACK};
+    use crate::kernel::execve::load_info::LoadInfo;
+    use crate::register::{Current, Original, PtraceReader, SysArg1, SysArg2, SysResult};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot, test_with_proot_config};
+
+    #[test]
+    fn test_execve_exit_prunes_cloexec_fds() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != EXECVE
+                    || tracee.regs.get(Current, SysResult) != 0
+                {
+                    return;
+                }
+                let fd_table = tracee.fd_table.borrow();
+                let entries: Vec<_> = (0..64).filter_map(|fd| fd_table.get(fd)).collect();
+                assert!(entries.iter().all(|entry| !entry.cloexec));
+                // inherited file descriptors are still tracked
+                assert!(entries
+                    .iter()
+                    .any(|entry| entry.path == Path::new("/etc/group")));
+                assert!(entries
+                    .iter()
+                    .all(|entry| entry.path != Path::new("/etc/passwd")));
+            },
+            || {
+                fcntl::open(
+                    "/etc/passwd",
+                    OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+                    Mode::empty(),
+                )
+                .unwrap();
+                fcntl::open("/etc/group", OFlag::O_RDONLY, Mode::empty()).unwrap();
+                execv(
+                    &CString::new("/bin/busybox").unwrap(),
+                    &[CString::new("true").unwrap()],
+                )
+                .expect("failed execv busybox");
+            },
+        );
+    }
+
+    #[test]
+    fn test_execve_event_then_open() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation {
+                    return;
+                }
+                // the "/proc/self/exe" was committed on the exec event
+                let is_after_exec = tracee
+                    .exe
+                    .as_ref()
+                    .map_or(false, |exe| *exe.borrow() == Path::new("/bin/busybox"));
+                let path_sysarg = match tracee.regs.get_sys_num(Current) {
+                    OPEN => SysArg1,
+                    OPENAT => SysArg2,
+                    _ => return,
+                };
+                let host_path = tracee.regs.get_sysarg_path(path_sysarg).unwrap();
+                if is_after_exec && host_path.ends_with("etc/passwd") {
+                    assert_eq!(host_path, get_test_rootfs_path().join("etc/passwd"));
+                }
+                // the registers are not stale
+                assert_ne!(host_path, PathBuf::from("/etc/passwd"));
+            },
+            || {
+                execv(
+                    &CString::new("/bin/busybox").unwrap(),
+                    &[
+                        CString::new("cat").unwrap(),
+                        CString::new("/etc/passwd").unwrap(),
+                    ],
+                )
+                .expect("failed execv busybox");
+            },
+        );
+    }
+
+    /// Sets the executable flag of the `PT_GNU_STACK` segment of the 64-bit
+    /// ELF `content`, like `execstack -s` does.
+    fn set_elf64_exec_stack(content: &mut [u8]) {
+        let read_u16 =
+            |offset: usize| u16::from_ne_bytes(content[offset..offset + 2].try_into().unwrap());
+        let phoff = u64::from_ne_bytes(content[0x20..0x28].try_into().unwrap()) as usize;
+        let phentsize = read_u16(0x36) as usize;
+        let phnum = read_u16(0x38) as usize;
+
+        for header in (0..phnum).map(|i| phoff + i * phentsize) {
+            let p_type = u32::from_ne_bytes(content[header..header + 4].try_into().unwrap());
+            if p_type == PT_GNU_STACK {
+                let p_flags = &mut content[header + 4..header + 8];
+                let flags = u32::from_ne_bytes((&*p_flags).try_into().unwrap()) | PF_X;
+                p_flags.copy_from_slice(&flags.to_ne_bytes());
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    fn test_execve_exit_executable_stack() {
+        let rootfs_path = get_test_rootfs_path();
+        let test_dir = rootfs_path.join("tmp/proot_rs_test_execve_exit_executable_stack");
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let mut content = std::fs::read(rootfs_path.join("bin/busybox")).unwrap();
+        set_elf64_exec_stack(&mut content);
+        std::fs::write(test_dir.join("busybox"), content).unwrap();
+        std::fs::set_permissions(
+            test_dir.join("busybox"),
+            std::fs::metadata(rootfs_path.join("bin/busybox"))
+                .unwrap()
+                .permissions(),
+        )
+        .unwrap();
+
+        let fs = FileSystem::with_root(&rootfs_path).unwrap();
+        let load_info = LoadInfo::from(&fs, &test_dir.join("busybox")).unwrap();
+        assert!(load_info.needs_executable_stack);
+
+        // the stack of the program is executable, as reported by the kernel
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(&rootfs_path)
+                .proc_shim(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                execv(
+                    &CString::new("/tmp/proot_rs_test_execve_exit_executable_stack/busybox")
+                        .unwrap(),
+                    &[
+                        CString::new("grep").unwrap(),
+                        CString::new("-q").unwrap(),
+                        CString::new("rwxp.*\\[stack\\]").unwrap(),
+                        CString::new("/proc/self/maps").unwrap(),
+                    ],
+                )
+                .expect("failed execv busybox");
+            },
+        );
+    }
+}
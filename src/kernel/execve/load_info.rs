@@ -9,7 +9,7 @@ use nix::sys::mman::MapFlags;
 use nix::sys::mman::ProtFlags;
 use nix::unistd::{sysconf, SysconfVar};
 use std::fs::File;
-use std::io::{Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, PartialEq)]
@@ -49,6 +49,11 @@ const EXEC_PIC_ADDRESS: Word = 0x500000000000;
 const INTERP_PIC_ADDRESS: Word = 0x6f0000000000;
 const EXEC_PIC_ADDRESS_32: Word = 0x0f000000;
 const INTERP_PIC_ADDRESS_32: Word = 0xaf000000;
+/// Upper bound of the random offset added to the addresses above when the
+/// load addresses are randomized, so that the executable stays below the
+/// interpreter, and the interpreter below the stack.
+const PIC_RANDOM_RANGE: Word = 0x10000000000;
+const PIC_RANDOM_RANGE_32: Word = 0x1000000;
 
 impl LoadInfo {
     fn new(elf_header: ElfHeader) -> Self {
@@ -239,9 +244,25 @@ impl LoadInfo {
         )
     }
 
+    /// Returns the lowest and highest (exclusive) addresses of the mappings.
+    fn get_address_range(&self) -> Option<(Word, Word)> {
+        let start = self.mappings.iter().map(|mapping| mapping.addr).min()?;
+        let end = self
+            .mappings
+            .iter()
+            .map(|mapping| mapping.addr + mapping.length)
+            .max()?;
+        Some((start, end))
+    }
+
     /// Compute the final load address for each position independent objects of
     /// @tracee.
-    pub fn compute_load_addresses(&mut self, is_interp: bool) -> Result<()> {
+    ///
+    /// Position independent objects (`ET_DYN`) are loaded at a fixed base,
+    /// plus a random page-aligned offset if `randomize` is set, whereas the
+    /// other ones (`ET_EXEC`) are loaded at the addresses of their segments.
+    /// The executable and its interpreter must not overlap.
+    pub fn compute_load_addresses(&mut self, is_interp: bool, randomize: bool) -> Result<()> {
         let is_pos_indep = apply!(self.elf_header, |header| header.is_position_independent())?;
         let (load_base_32, load_base) = match is_interp {
             false => (EXEC_PIC_ADDRESS_32, EXEC_PIC_ADDRESS), // exec
@@ -249,22 +270,52 @@ impl LoadInfo {
         };
 
         if is_pos_indep && self.mappings.get(0).unwrap().addr == 0 {
-            if HAS_LOADER_32BIT && self.elf_header.get_class() == ExecutableClass::Class32 {
-                self.add_load_base(load_base_32)?;
-            } else {
-                self.add_load_base(load_base)?;
-            }
+            let (load_base, random_range) =
+                if HAS_LOADER_32BIT && self.elf_header.get_class() == ExecutableClass::Class32 {
+                    (load_base_32, PIC_RANDOM_RANGE_32)
+                } else {
+                    (load_base, PIC_RANDOM_RANGE)
+                };
+            let load_bias = match randomize {
+                true => get_random_load_bias(random_range)?,
+                false => 0,
+            };
+            self.add_load_base(load_base + load_bias)?;
         }
 
         if !is_interp {
             if let Some(ref mut interp_load_info) = self.interp {
-                interp_load_info.compute_load_addresses(true)?;
+                interp_load_info.compute_load_addresses(true, randomize)?;
+            }
+            let interp_range = self
+                .interp
+                .as_ref()
+                .and_then(|interp_load_info| interp_load_info.get_address_range());
+            if let (Some((start, end)), Some((interp_start, interp_end))) =
+                (self.get_address_range(), interp_range)
+            {
+                if start < interp_end && interp_start < end {
+                    return Err(Error::errno_with_msg(
+                        ENOMEM,
+                        format!(
+                            "The executable ({:#x}-{:#x}) overlaps its interpreter ({:#x}-{:#x})",
+                            start, end, interp_start, interp_end
+                        ),
+                    ));
+                }
             }
         }
         Ok(())
     }
 }
 
+/// Returns a random page-aligned offset lower than `range`.
+fn get_random_load_bias(range: Word) -> Result<Word> {
+    let mut bytes = [0u8; std::mem::size_of::<Word>()];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok((Word::from_ne_bytes(bytes) % range) & *PAGE_MASK)
+}
+
 // TODO: change size of enum tags
 #[repr(C, u64)]
 #[derive(Debug)]
@@ -359,7 +410,9 @@ fn process_prot_flags(flags: u32) -> ProtFlags {
 mod tests {
     use super::*;
     use crate::errors::Error;
+    use crate::filesystem::temp::TempFile;
     use crate::filesystem::FileSystem;
+    use crate::kernel::execve::loader::LoaderFile;
     use crate::register::Word;
     use crate::utils::tests::get_test_rootfs_path;
     use std::path::PathBuf;
@@ -430,10 +483,71 @@ mod tests {
 
         let before_e_entry = get!(interp.elf_header, e_entry, Word).unwrap();
 
-        interp.compute_load_addresses(true).unwrap();
+        interp.compute_load_addresses(true, false).unwrap();
 
         let after_e_entry = get!(interp.elf_header, e_entry, Word).unwrap();
 
         assert!(after_e_entry > before_e_entry);
     }
+
+    #[test]
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64")))]
+    fn test_load_info_compute_load_addresses_pie() {
+        let fs = FileSystem::with_root("/").unwrap();
+        let is_pie = |load_info: &LoadInfo| {
+            apply!(load_info.elf_header, |header| header
+                .is_position_independent())
+            .unwrap()
+        };
+
+        // the loader is a static non-PIE executable, which keeps its addresses
+        let loader = TempFile::new("proot_rs_test_load_addresses");
+        loader.prepare_loader().unwrap();
+        let mut load_info = LoadInfo::from(&fs, loader.get_loader_path()).unwrap();
+        assert!(!is_pie(&load_info));
+        let addresses: Vec<Word> = load_info.mappings.iter().map(|m| m.addr).collect();
+        assert_ne!(addresses[0], 0);
+        load_info.compute_load_addresses(false, false).unwrap();
+        assert_eq!(
+            load_info
+                .mappings
+                .iter()
+                .map(|m| m.addr)
+                .collect::<Vec<_>>(),
+            addresses
+        );
+
+        // the test binary is a PIE executable (the default of rustc), which is
+        // loaded at a fixed base, below its interpreter
+        let exe = std::env::current_exe().unwrap();
+        let mut load_info = LoadInfo::from(&fs, &exe).unwrap();
+        assert!(is_pie(&load_info));
+        assert_eq!(load_info.mappings[0].addr, 0);
+        load_info.compute_load_addresses(false, false).unwrap();
+        let (start, end) = load_info.get_address_range().unwrap();
+        assert_eq!(start, EXEC_PIC_ADDRESS);
+        let (interp_start, _) = load_info
+            .interp
+            .as_ref()
+            .unwrap()
+            .get_address_range()
+            .unwrap();
+        assert_eq!(interp_start, INTERP_PIC_ADDRESS);
+        assert!(end <= interp_start);
+
+        // or at a random page-aligned base
+        let mut load_info = LoadInfo::from(&fs, &exe).unwrap();
+        load_info.compute_load_addresses(false, true).unwrap();
+        let (start, end) = load_info.get_address_range().unwrap();
+        assert!(start >= EXEC_PIC_ADDRESS && start < EXEC_PIC_ADDRESS + PIC_RANDOM_RANGE);
+        assert_eq!(start & !*PAGE_MASK, 0);
+        let (interp_start, _) = load_info
+            .interp
+            .as_ref()
+            .unwrap()
+            .get_address_range()
+            .unwrap();
+        assert!(interp_start >= INTERP_PIC_ADDRESS);
+        assert!(end <= interp_start);
+    }
 }
@@ -5,6 +5,9 @@ use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 
+/// The loader is a tiny static and freestanding program (see `loader/`),
+/// built for the host architecture by `build_loader.rs`. It's embedded in
+/// proot-rs, and materialized in a temporary file by `prepare_loader()`.
 const LOADER_EXE: &'static [u8] = include_bytes!("loader/binary_loader_exe");
 
 pub trait LoaderFile {
@@ -35,6 +38,22 @@ impl LoaderFile for TempFile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kernel::execve::elf::{ElfHeader, ExecutableClass};
+    use nix::fcntl::OFlag;
+    use nix::sys::wait::waitpid;
+    use nix::unistd::{close, execv, fork, pipe2, read, write, ForkResult};
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[cfg(target_arch = "x86_64")]
+    const HOST_MACHINE: (ExecutableClass, u16) = (ExecutableClass::Class64, 62); // EM_X86_64
+    #[cfg(target_arch = "x86")]
+    const HOST_MACHINE: (ExecutableClass, u16) = (ExecutableClass::Class32, 3); // EM_386
+    #[cfg(target_arch = "arm")]
+    const HOST_MACHINE: (ExecutableClass, u16) = (ExecutableClass::Class32, 40); // EM_ARM
+    #[cfg(target_arch = "aarch64")]
+    const HOST_MACHINE: (ExecutableClass, u16) = (ExecutableClass::Class64, 183); // EM_AARCH64
 
     #[test]
     fn test_loader_is_loaded_and_deleted() {
@@ -56,4 +75,56 @@ mod tests {
         // the loader must have been deleted
         assert!(!loader_path.exists());
     }
+
+    #[test]
+    fn test_loader_is_valid_host_elf() {
+        let loader = TempFile::new("prefix_test_loader_is_valid_host_elf");
+        loader.prepare_loader().unwrap();
+
+        let mut file = File::open(loader.get_loader_path()).unwrap();
+        let (elf_header, _) = ElfHeader::extract_from(&mut file).unwrap();
+        assert_eq!(elf_header.get_class(), HOST_MACHINE.0);
+        let e_machine = elf_header
+            .apply(|header| Ok(header.e_machine), |header| Ok(header.e_machine))
+            .unwrap();
+        assert_eq!(e_machine, HOST_MACHINE.1);
+        elf_header
+            .apply(
+                |header| header.is_exec_or_dyn(),
+                |header| header.is_exec_or_dyn(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_loader_is_launchable() {
+        let loader = TempFile::new("prefix_test_loader_is_launchable");
+        loader.prepare_loader().unwrap();
+        let loader_path = CString::new(loader.get_loader_path().as_os_str().as_bytes()).unwrap();
+
+        // The write end of the pipe is closed by a successful execve(), whereas
+        // the child writes the errno if it fails.
+        let (read_fd, write_fd) = pipe2(OFlag::O_CLOEXEC).unwrap();
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                let errno = execv(&loader_path, &[loader_path.clone()]).unwrap_err();
+                let _ = write(write_fd, format!("{}", errno).as_bytes());
+                std::process::exit(1);
+            }
+            ForkResult::Parent { child } => {
+                close(write_fd).unwrap();
+                let mut buffer = [0u8; 64];
+                let size = read(read_fd, &mut buffer).unwrap();
+                assert_eq!(
+                    size,
+                    0,
+                    "failed to execute the loader: {}",
+                    String::from_utf8_lossy(&buffer[..size])
+                );
+                // Without a load script, the loader gives up (or crashes).
+                waitpid(child, None).unwrap();
+                close(read_fd).unwrap();
+            }
+        }
+    }
 }
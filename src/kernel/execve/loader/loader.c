@@ -170,9 +170,11 @@ void _start(void *cursor)
 			break;
 
 		case LOAD_ACTION_MAKE_STACK_EXEC:
-			SYSCALL(MPROTECT, 3,
-				stmt->make_stack_exec.start, 1,
-				PROT_READ | PROT_WRITE | PROT_EXEC | PROT_GROWSDOWN);
+			status = SYSCALL(MPROTECT, 3,
+					stmt->make_stack_exec.start, 1,
+					PROT_READ | PROT_WRITE | PROT_EXEC | PROT_GROWSDOWN);
+			if (unlikely((int) status < 0))
+				FATAL();
 
 			cursor += LOAD_STATEMENT_SIZE(*stmt, make_stack_exec);
 			break;
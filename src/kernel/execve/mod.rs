@@ -1,19 +1,20 @@
 #[macro_use]
 mod macros;
+pub mod binfmt;
 mod elf;
 pub mod enter;
+mod environ;
 pub mod exit;
 pub mod load_info;
 pub mod loader;
 mod shebang;
 
 use crate::errors::Result;
-use crate::kernel::execve::loader::LoaderFile;
-
+use crate::process::proot::InfoBag;
 use crate::process::tracee::Tracee;
 
-pub fn enter(tracee: &mut Tracee, loader: &dyn LoaderFile) -> Result<()> {
-    enter::translate(tracee, loader)
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    enter::translate(tracee, info_bag)
 }
 
 pub fn exit(tracee: &mut Tracee) -> Result<()> {
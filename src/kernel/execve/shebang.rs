@@ -4,26 +4,59 @@ use self::bstr::ByteSlice;
 use crate::errors::*;
 use crate::errors::{Error, Result};
 use crate::filesystem::{FileSystem, Translator};
+use crate::kernel::execve::binfmt::{self, BinfmtRule};
+use crate::process::tracee::Tracee;
+use crate::register::{
+    Current, PtraceMemoryAllocator, PtraceWriter, Registers, SysArg, SysArg2, SysArgIndex, Word,
+};
+use libc::c_void;
+use nix::sys::ptrace;
 use std::io::BufReader;
+use std::mem::size_of;
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::{fs::File, io::Read};
 
-/// Expand in argv[] the shebang of `user_path`, if any.  This function
-/// returns -errno if an error occurred, 1 if a shebang was found and
-/// extracted, otherwise 0.  On success, both `host_path` and `user_path`
-/// point to the program to execute (respectively from host
-/// point-of-view and as-is), and @tracee's argv[] (pointed to by
-/// `SYSARG_2`) is correctly updated.
+/// Size of the buffer where Linux reads the shebang line, "#!" included: the
+/// longer lines are truncated. The magic numbers of the binfmt rules are
+/// matched in the same buffer.
+pub(super) const BINPRM_BUF_SIZE: usize = 128;
+
+/// An element of the argv[] (or envp[]) array of the tracee, as rebuilt
+/// during the expansion of a shebang.
+pub(super) enum Argument {
+    /// An argument already present in the tracee's memory, identified by its
+    /// address.
+    Tracee(Word),
+    /// A new argument, which has to be written in the tracee's memory.
+    New(Vec<u8>),
+}
+
+/// Expand in argv[] the shebang of `user_path`, if any.
+///
+/// On success, returns the program to execute respectively from the host
+/// point-of-view and as-is (i.e. `host_path` and `user_path`). If a shebang
+/// was found, the tracee's argv[] (pointed to by `SysArg2`) is updated on
+/// the stack so that the interpreter receives the script as argument.
+///
+/// If `preserve_argv0` is enabled, the original argv[0] given by the guest
+/// is kept as argv[0] of the interpreter, instead of the path of the
+/// interpreter itself.
+///
+/// If `strip_shebang_cr` is enabled, a `\r` ending the shebang line is
+/// ignored, see `extract()`.
+///
+/// The files which are neither ELF files nor scripts are expanded the same
+/// way, with the interpreter of the first of `binfmt_rules` matching them.
 // int expand_shebang(Tracee *tracee, char host_path[PATH_MAX], char
 // user_path[PATH_MAX])
-pub fn expand(fs: &FileSystem, user_path: &Path) -> Result<PathBuf> {
-    //  ArrayOfXPointers *argv = NULL;
-    //	bool has_shebang = false;
-    //
-    //	char argument[BINPRM_BUF_SIZE];
-    //	int status;
-    //	size_t i;
-
+pub fn expand(
+    tracee: &mut Tracee,
+    user_path: &Path,
+    preserve_argv0: bool,
+    strip_shebang_cr: bool,
+    binfmt_rules: &[BinfmtRule],
+) -> Result<(PathBuf, PathBuf)> {
     // "The interpreter must be a valid pathname for an executable
     //  which is not itself a script [1].  If the filename
     //  argument of execve() specifies an interpreter script, then
@@ -38,101 +71,178 @@ pub fn expand(fs: &FileSystem, user_path: &Path) -> Result<PathBuf> {
     //      ELF interpreter; ie. a script can use a script as
     //      interpreter.
 
-    let mut result_host_path: Option<PathBuf> = None;
+    let fs = tracee.fs.clone();
+    let fs = fs.borrow();
+    let mut user_path = user_path.to_path_buf();
+    let mut argv: Option<Vec<Argument>> = None;
+    let mut original_argv0: Option<Word> = None;
     let mut loop_iterations = 0;
-    let mut has_shebang = false;
     let max_sym_links = 50; //TODO: found this constant in libc
 
-    while loop_iterations < max_sym_links {
+    loop {
+        if loop_iterations == max_sym_links {
+            return Err(Error::errno_with_msg(ELOOP, "when expanding shebang"));
+        }
         loop_iterations += 1;
 
         // Translate this path (user -> host), then check it is executable.
         let host_path = fs.translate_path(&user_path, true)?;
+        fs.check_executable(&user_path)?;
         FileSystem::check_host_path_executable(&host_path)?;
 
-        let expanded_user_path = extract(&host_path)?;
+        let (interpreter, argument, keep_argv0) = match extract(&host_path, strip_shebang_cr)? {
+            Some((interpreter, argument)) => (interpreter, argument, false),
+            None => match binfmt::find_rule(binfmt_rules, &user_path, &host_path)? {
+                Some(rule) => (rule.interpreter.clone(), None, rule.preserve_argv0),
+                None => {
+                    // Push argv[] only on demand.
+                    if let Some(mut argv) = argv {
+                        if preserve_argv0 {
+                            if let Some(original_argv0) = original_argv0 {
+                                argv[0] = Argument::Tracee(original_argv0);
+                            }
+                        }
+                        write_argv(&mut tracee.regs, &argv)?;
+                    }
+                    return Ok((host_path, user_path));
+                }
+            },
+        };
 
-        if expanded_user_path.is_none() {
-            result_host_path = Some(host_path);
-            break;
+        // Fetch argv[] only on demand.
+        if argv.is_none() {
+            let pointers = read_argv(&tracee.regs)?;
+            original_argv0 = pointers.first().copied();
+            argv = Some(pointers.into_iter().map(Argument::Tracee).collect());
         }
-        has_shebang = true;
+        let argv = argv.as_mut().unwrap();
 
-        // Translate new path (user -> host), then check it is executable.
-        let new_host_path = fs.translate_path(&expanded_user_path.unwrap(), true)?;
-        FileSystem::check_host_path_executable(&new_host_path)?;
+        // Assuming the shebang of "script" is "#!/bin/sh -x",
+        // a call to:
+        //
+        //     execve("./script", { "script.sh", NULL }, ...)
+        //
+        // becomes:
+        //
+        //     execve("/bin/sh", { "/bin/sh", "-x", "./script", NULL }, ...)
+        //
+        // Note that argv[0] is appended instead of replaced if argv[] is empty,
+        // or if the binfmt rule of the file preserves it.
+        //
+        // The script is given to the interpreter with its guest path, and not
+        // with `host_path`: the interpreter runs under proot-rs too, so its
+        // open() of the script is translated like any other guest access
+        // (a host path would be translated a second time). This is also what
+        // the script sees in "$0".
+        let script = Argument::New(user_path.as_os_str().as_bytes().to_vec());
+        if argv.is_empty() {
+            argv.push(script);
+        } else if keep_argv0 {
+            argv.insert(0, script);
+        } else {
+            argv[0] = script;
+        }
+        let mut prefix = vec![Argument::New(interpreter.as_os_str().as_bytes().to_vec())];
+        if let Some(argument) = argument {
+            prefix.push(Argument::New(argument));
+        }
+        prefix.extend(argv.drain(..));
+        *argv = prefix;
 
-        println!("new host path: {:?}", new_host_path);
+        user_path = interpreter;
     }
+}
 
-    //TODO: implement argument extraction for scripts
-    //
-    //		/* Fetch argv[] only on demand.  */
-    //		if (argv == NULL) {
-    //			status = fetch_array_of_xpointers(tracee, &argv, SYSARG_2, 0);
-    //			if (status < 0)
-    //				return status;
-    //		}
-    //
-    //		/* Assuming the shebang of "script" is "#!/bin/sh -x",
-    // 		 * a call to:
-    //		 *
-    // 		 * execve("./script", { "script.sh", NULL }, ...)
-    //		 *
-    // 		 * becomes:
-    //		 *
-    // 		 * execve("/bin/sh", { "/bin/sh", "-x", "./script", NULL }, ...)
-    //		 *
-    // 		 * See commit 8c8fbe85 about "argv->length == 1".  */
-    //		if (argument[0] != '\0') {
-    //			status = resize_array_of_xpointers(argv, 0, 2 + (argv->length == 1));
-    //			if (status < 0)
-    //				return status;
-    //
-    //			status = write_xpointees(argv, 0, 3, user_path, argument, old_user_path);
-    //			if (status < 0)
-    //				return status;
-    //		}
-    //		else {
-    //			status = resize_array_of_xpointers(argv, 0, 1 + (argv->length == 1));
-    //			if (status < 0)
-    //				return status;
-    //
-    //			status = write_xpointees(argv, 0, 2, user_path, old_user_path);
-    //			if (status < 0)
-    //				return status;
-    //		}
-    //	}
-    //
+/// Reads the array of pointers argv[] (pointed to by `SysArg2`) from the
+/// tracee's memory, without its terminating null pointer.
+fn read_argv(regs: &Registers) -> Result<Vec<Word>> {
+    read_array(regs, SysArg2)
+}
+
+/// Reads a null-terminated array of pointers (argv[] or envp[], pointed to by
+/// `sys_arg`) from the tracee's memory, without its terminating null pointer.
+pub(super) fn read_array(regs: &Registers, sys_arg: SysArgIndex) -> Result<Vec<Word>> {
+    let mut pointers: Vec<Word> = vec![];
+    let src_argv = regs.get(Current, SysArg(sys_arg)) as *mut Word;
 
-    if loop_iterations == max_sym_links {
-        return Err(Error::errno_with_msg(ELOOP, "when expanding shebang"));
+    // A null argv[] is considered as an empty one by Linux.
+    if src_argv.is_null() {
+        return Ok(pointers);
     }
 
-    //	/* Push argv[] only on demand.  */
-    //	if (argv != NULL) {
-    //		status = push_array_of_xpointers(argv, SYSARG_2);
-    //		if (status < 0)
-    //			return status;
-    //	}
-    //
-    //	return (has_shebang ? 1 : 0);
+    loop {
+        let src_addr = unsafe { src_argv.add(pointers.len()) as *mut c_void };
+        let pointer = ptrace::read(regs.get_pid(), src_addr)? as Word;
 
-    Ok(result_host_path.unwrap())
+        if pointer == 0 {
+            return Ok(pointers);
+        }
+        pointers.push(pointer);
+    }
 }
 
-/// Extract into `user_path` and `argument` the shebang from @host_path.
-/// This function returns -errno if an error occured, 1 if a shebang
-/// was found and extracted, otherwise 0.
+/// Writes the new strings of `argv` and then the new array of pointers
+/// (null-terminated) on the tracee's stack, and makes `SysArg2` point to it.
+fn write_argv(regs: &mut Registers, argv: &[Argument]) -> Result<()> {
+    write_array(regs, SysArg2, argv)
+}
+
+/// Same as `write_argv()`, for any array of strings (argv[] or envp[])
+/// pointed to by `sys_arg`.
+pub(super) fn write_array(
+    regs: &mut Registers,
+    sys_arg: SysArgIndex,
+    argv: &[Argument],
+) -> Result<()> {
+    let mut pointers: Vec<Word> = Vec::with_capacity(argv.len() + 1);
+
+    for argument in argv {
+        let pointer = match argument {
+            Argument::Tracee(pointer) => *pointer,
+            Argument::New(bytes) => {
+                let pointer = regs.alloc_mem_on_stack(bytes.len() as isize + 1)?;
+                regs.write_data(pointer as *mut c_void, bytes, true)?;
+                pointer
+            }
+        };
+        pointers.push(pointer);
+    }
+    pointers.push(0);
+
+    let mut data: Vec<u8> = Vec::with_capacity(pointers.len() * size_of::<Word>());
+    for pointer in pointers {
+        data.extend_from_slice(&pointer.to_ne_bytes());
+    }
+
+    regs.set_sysarg_data(
+        sys_arg,
+        &data,
+        "during enter execve translation, setting new argv or envp",
+        false,
+    )?;
+
+    Ok(())
+}
+
+/// Extract from `host_path` the shebang, if any: the path of the interpreter
+/// and its optional argument. Returns `None` if `host_path` isn't a script.
 ///
 /// Extract from "man 2 execve":
 ///
 ///     On Linux, the entire string following the interpreter name is
 ///     passed as a *single* argument to the interpreter, and this
 ///     string can include white space.
+///
+/// Like Linux, only the spaces and tabs are white spaces: the `\r` of the
+/// scripts with CRLF line endings is part of the interpreter path (or of its
+/// argument), unless `strip_cr` is enabled.
+///
+/// A shebang without interpreter (i.e. an empty line, or only made of white
+/// spaces) is rejected with `ENOEXEC`, and a line longer than
+/// `BINPRM_BUF_SIZE` is truncated.
 //const char *host_path, char user_path[PATH_MAX], char
 // argument[BINPRM_BUF_SIZE]
-fn extract(host_path: &Path) -> Result<Option<PathBuf>> {
+fn extract(host_path: &Path, strip_cr: bool) -> Result<Option<(PathBuf, Option<Vec<u8>>)>> {
     let mut bytes = BufReader::new(File::open(host_path)?).bytes();
     match (bytes.next(), bytes.next()) {
         (Some(Err(err)), _) | (_, Some(Err(err))) => return Err(Error::from(err)),
@@ -140,16 +250,18 @@ fn extract(host_path: &Path) -> Result<Option<PathBuf>> {
         _ => return Ok(None),
     }
     let first_line = bytes
-        .take_while(|c| match c {
-            Ok(b'\n') => true,
-            _ => false,
-        })
+        .take(BINPRM_BUF_SIZE - 2)
+        .take_while(|c| !matches!(c, Ok(b'\n')))
         .collect::<std::result::Result<Vec<u8>, _>>()?;
-    let first_line = first_line.trim();
+    let first_line = match first_line.split_last() {
+        Some((&b'\r', line)) if strip_cr => line,
+        _ => &first_line,
+    };
+    let first_line = trim_spaces_and_tabs(first_line);
 
     let path = &first_line[..first_line
         .iter()
-        .position(|c| c.is_ascii_whitespace())
+        .position(|&c| is_space_or_tab(c))
         .unwrap_or(first_line.len())];
 
     if path.is_empty() {
@@ -160,11 +272,15 @@ fn extract(host_path: &Path) -> Result<Option<PathBuf>> {
     }
     // NOTE: this unwrap may fail on non-UNIX systems (a.k.a Windows)
     // where paths may not be arbitrary bytes
-    let arg = first_line[path.len()..].trim();
+    let interpreter = PathBuf::from(path.as_bstr().to_path().unwrap());
+    let arg = trim_spaces_and_tabs(&first_line[path.len()..]);
+    let argument = if arg.is_empty() {
+        None
+    } else {
+        Some(arg.to_vec())
+    };
 
-    let mut argv = PathBuf::from(path.as_bstr().to_path().unwrap());
-    argv.push(arg.as_bstr().to_path().unwrap()); // FIXME: why append arg here?
-    Ok(Some(argv))
+    Ok(Some((interpreter, argument)))
     //
     //	/* Skip leading spaces. */
     //	do {
@@ -276,18 +392,127 @@ fn extract(host_path: &Path) -> Result<Option<PathBuf>> {
     //	return 1;
 }
 
+fn is_space_or_tab(c: u8) -> bool {
+    c == b' ' || c == b'\t'
+}
+
+fn trim_spaces_and_tabs(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|&c| !is_space_or_tab(c))
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|&c| !is_space_or_tab(c))
+        .map_or(start, |end| end + 1);
+    &bytes[start..end]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{filesystem::FileSystem, utils::tests::get_test_rootfs_path};
+    use crate::config::ProotConfig;
+    use crate::filesystem::binding::BindingFlags;
+    use crate::register::{Original, StackPointer};
+    use crate::utils::tests::{
+        fork_test, get_test_rootfs_path, test_with_proot, test_with_proot_config,
+    };
+    use nix::unistd::{execv, getpid};
+    use sc::nr::EXECVE;
+    use std::cell::RefCell;
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use std::path::PathBuf;
+    use std::rc::Rc;
+
+    /// Writes an executable script named `name` in the `/tmp` directory of the
+    /// test rootfs, and returns its guest path.
+    fn create_test_script(name: &str, content: &str) -> PathBuf {
+        let host_path = get_test_rootfs_path().join("tmp").join(name);
+        fs::write(&host_path, content).unwrap();
+        fs::set_permissions(&host_path, fs::Permissions::from_mode(0o755)).unwrap();
+        PathBuf::from("/tmp").join(name)
+    }
 
     #[test]
     fn test_extract_shebang_not_script() {
         let rootfs_path = get_test_rootfs_path();
 
         // it should detect that `/bin/sleep` is not a script
-        assert_eq!(Ok(None), extract(&rootfs_path.join("bin/sleep")));
+        assert_eq!(Ok(None), extract(&rootfs_path.join("bin/sleep"), false));
+    }
+
+    #[test]
+    fn test_extract_shebang_with_argument() {
+        let guest_path = create_test_script(
+            "proot_rs_test_extract_shebang.sh",
+            "#!/bin/sh -x -e  \nexit 0\n",
+        );
+        let host_path = get_test_rootfs_path().join(guest_path.strip_prefix("/").unwrap());
+
+        // the whole string following the interpreter is a single argument
+        assert_eq!(
+            Ok(Some((PathBuf::from("/bin/sh"), Some(b"-x -e".to_vec())))),
+            extract(&host_path, false)
+        );
+    }
+
+    #[test]
+    fn test_extract_shebang_without_interpreter() {
+        for (name, content) in &[
+            ("proot_rs_test_extract_shebang_empty.sh", "#!\nexit 0\n"),
+            ("proot_rs_test_extract_shebang_eof.sh", "#!"),
+            (
+                "proot_rs_test_extract_shebang_spaces.sh",
+                "#!  \t \nexit 0\n",
+            ),
+        ] {
+            let guest_path = create_test_script(name, content);
+            let host_path = get_test_rootfs_path().join(guest_path.strip_prefix("/").unwrap());
+            assert_eq!(
+                extract(&host_path, false).map_err(|error| error.get_errno()),
+                Err(Errno::ENOEXEC)
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_shebang_too_long() {
+        let guest_path = create_test_script(
+            "proot_rs_test_extract_shebang_too_long.sh",
+            &format!("#!/bin/sh -{}\nexit 0\n", "x".repeat(2 * BINPRM_BUF_SIZE)),
+        );
+        let host_path = get_test_rootfs_path().join(guest_path.strip_prefix("/").unwrap());
+
+        // the argument is truncated, so that the whole line fits in the buffer
+        let argument = format!("-{}", "x".repeat(BINPRM_BUF_SIZE - "#!/bin/sh -".len()));
+        assert_eq!(
+            Ok(Some((
+                PathBuf::from("/bin/sh"),
+                Some(argument.into_bytes())
+            ))),
+            extract(&host_path, false)
+        );
+    }
+
+    #[test]
+    fn test_extract_shebang_crlf() {
+        let guest_path = create_test_script(
+            "proot_rs_test_extract_shebang_crlf.sh",
+            "#!/bin/sh -e\r\nexit 0\r\n",
+        );
+        let host_path = get_test_rootfs_path().join(guest_path.strip_prefix("/").unwrap());
+
+        // the \r is part of the argument, like for Linux
+        assert_eq!(
+            Ok(Some((PathBuf::from("/bin/sh"), Some(b"-e\r".to_vec())))),
+            extract(&host_path, false)
+        );
+        assert_eq!(
+            Ok(Some((PathBuf::from("/bin/sh"), Some(b"-e".to_vec())))),
+            extract(&host_path, true)
+        );
     }
 
     // TODO: test shebang expand not contains shebang
@@ -296,11 +521,228 @@ mod tests {
         let rootfs_path = get_test_rootfs_path();
 
         let fs = FileSystem::with_root(&rootfs_path).unwrap();
+        let mut tracee = Tracee::new(getpid(), Rc::new(RefCell::new(fs)));
 
         // it should detect that `/etc/hostname` is not executable
         assert_eq!(
             Err(Error::errno(Errno::EACCES)),
-            expand(&fs, &PathBuf::from("/etc/passwd"))
+            expand(
+                &mut tracee,
+                &PathBuf::from("/etc/passwd"),
+                false,
+                false,
+                &[]
+            )
+        );
+    }
+
+    #[test]
+    /// Runs a script checking its arguments: the interpreter must receive the
+    /// guest path of the script, followed by the original arguments.
+    fn test_expand_shebang_script_arguments() {
+        let script_path = create_test_script(
+            "proot_rs_test_shebang_arguments.sh",
+            "#!/bin/sh\n[ \"$0\" = \"/tmp/proot_rs_test_shebang_arguments.sh\" ] && [ \"$1\" = \"hello\" ]\n",
+        );
+
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                let script_path = CString::new(script_path.as_os_str().as_bytes()).unwrap();
+                execv(
+                    &script_path,
+                    &[
+                        CString::new("my_script").unwrap(),
+                        CString::new("hello").unwrap(),
+                    ],
+                )
+                .expect("failed execv script");
+            },
+        );
+    }
+
+    #[test]
+    /// The interpreter must open the script of the guest rootfs, and not the
+    /// file found at the same path on the host, which would make it fail.
+    /// Each interpreter is only checked if the guest rootfs provides it.
+    fn test_expand_shebang_interpreter_opens_guest_script() {
+        let rootfs_path = get_test_rootfs_path();
+        if rootfs_path == Path::new("/") {
+            eprintln!("skipped: the guest script is its own decoy with a \"/\" rootfs");
+            return;
+        }
+
+        for (interpreter, name, body) in &[
+            (
+                "/bin/sh",
+                "proot_rs_test_shebang_opens_guest_script.sh",
+                "exit 0",
+            ),
+            (
+                "/bin/bash",
+                "proot_rs_test_shebang_opens_guest_script.bash",
+                "exit 0",
+            ),
+            (
+                "/usr/bin/python3",
+                "proot_rs_test_shebang_opens_guest_script.py",
+                "import sys; sys.exit(0)",
+            ),
+        ] {
+            if rootfs_path
+                .join(interpreter.trim_start_matches('/'))
+                .symlink_metadata()
+                .is_err()
+            {
+                eprintln!("skipped {}: not found in the guest rootfs", interpreter);
+                continue;
+            }
+            // the decoy's `exit 1` fails either as a status or as a python
+            // syntax error
+            let script_path = create_test_script(name, &format!("#!{}\n{}\n", interpreter, body));
+            let decoy_path = PathBuf::from("/tmp").join(name);
+            fs::write(&decoy_path, "exit 1\n").unwrap();
+
+            test_with_proot(
+                |_, _, _| {},
+                || {
+                    let script_path = CString::new(script_path.as_os_str().as_bytes()).unwrap();
+                    execv(&script_path, &[script_path.clone()]).expect("failed execv script");
+                },
+            );
+            fs::remove_file(&decoy_path).unwrap();
+        }
+    }
+
+    #[test]
+    /// The `\r` of a CRLF shebang line is part of the interpreter path, which
+    /// doesn't exist, unless `strip_shebang_cr` is enabled.
+    fn test_expand_shebang_crlf() {
+        let script_path =
+            create_test_script("proot_rs_test_shebang_crlf.sh", "#!/bin/sh\r\nexit 0\r\n");
+        let exec_script = move || {
+            execv(
+                &CString::new(script_path.as_os_str().as_bytes()).unwrap(),
+                &[CString::new("my_script").unwrap()],
+            )
+        };
+
+        let child_exec_script = exec_script.clone();
+        test_with_proot(
+            |_, _, _| {},
+            move || assert_eq!(child_exec_script(), Err(nix::Error::Sys(Errno::ENOENT))),
+        );
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .strip_shebang_cr(true)
+                .build(),
+            |_, _, _| {},
+            move || {
+                exec_script().expect("failed execv script");
+            },
+        );
+    }
+
+    #[test]
+    /// The programs of a `noexec` binding, and the scripts whose interpreter
+    /// is in one, can't be executed.
+    fn test_expand_noexec_binding() {
+        let script_path = create_test_script(
+            "proot_rs_test_shebang_noexec.sh",
+            "#!/mnt/noexec/sh\nexit 0\n",
+        );
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .synthetic_mountpoints(true)
+                .binding(get_test_rootfs_path().join("bin"), "/mnt/noexec")
+                .binding_flags("/mnt/noexec", BindingFlags::parse("noexec").unwrap())
+                .build(),
+            |_, _, _| {},
+            move || {
+                let sh_path = CString::new("/mnt/noexec/sh").unwrap();
+                assert_eq!(
+                    execv(&sh_path, &[sh_path.clone()]),
+                    Err(nix::Error::Sys(Errno::EACCES))
+                );
+                let script_path = CString::new(script_path.as_os_str().as_bytes()).unwrap();
+                assert_eq!(
+                    execv(&script_path, &[script_path.clone()]),
+                    Err(nix::Error::Sys(Errno::EACCES))
+                );
+            },
+        );
+    }
+
+    #[test]
+    /// Checks that argv[0] is kept as-is when `preserve_argv0` is enabled, and
+    /// replaced by the interpreter otherwise.
+    fn test_expand_shebang_preserve_argv0() {
+        let script_path = create_test_script(
+            "proot_rs_test_shebang_preserve_argv0.sh",
+            "#!/bin/sh\nexit 0\n",
+        );
+        let child_script_path = script_path.clone();
+
+        fork_test(
+            get_test_rootfs_path(),
+            // expecting an error (because the script is only in the guest rootfs)
+            1,
+            // parent
+            |tracee, _| {
+                if tracee.regs.get_sys_num(Current) == EXECVE {
+                    tracee.regs.set_restore_original_regs(false);
+                    tracee.regs.save_current_regs(Original);
+                    let original_argv = read_argv(&tracee.regs).unwrap();
+                    assert_eq!(original_argv.len(), 2);
+
+                    let (host_path, user_path) =
+                        expand(tracee, &script_path, true, false, &[]).unwrap();
+                    assert_eq!(user_path, PathBuf::from("/bin/sh"));
+                    assert!(host_path.starts_with(get_test_rootfs_path()));
+
+                    // { "my_script", "/tmp/script.sh", "hello" }
+                    let argv = read_argv(&tracee.regs).unwrap();
+                    assert_eq!(argv.len(), 3);
+                    assert_eq!(argv[0], original_argv[0]);
+                    assert_ne!(argv[1], original_argv[0]);
+                    assert_eq!(argv[2], original_argv[1]);
+
+                    tracee
+                        .regs
+                        .restore_original(StackPointer, "restoring for test");
+                    tracee
+                        .regs
+                        .restore_original(SysArg(SysArg2), "restoring for test");
+
+                    // { "/bin/sh", "/tmp/script.sh", "hello" }
+                    expand(tracee, &script_path, false, false, &[]).unwrap();
+                    let argv = read_argv(&tracee.regs).unwrap();
+                    assert_eq!(argv.len(), 3);
+                    assert_ne!(argv[0], original_argv[0]);
+                    assert_eq!(argv[2], original_argv[1]);
+
+                    // we don't push the regs, we stop here
+                    true
+                } else {
+                    false
+                }
+            },
+            // child
+            || {
+                let script_path = CString::new(child_script_path.as_os_str().as_bytes()).unwrap();
+                execv(
+                    &script_path,
+                    &[
+                        CString::new("my_script").unwrap(),
+                        CString::new("hello").unwrap(),
+                    ],
+                )
+                .expect("failed execv script");
+            },
         );
     }
 }
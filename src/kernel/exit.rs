@@ -1,41 +1,126 @@
-use crate::kernel::execve;
-use crate::kernel::groups::{syscall_group_from_sysnum, SyscallGroup};
-use crate::kernel::heap::*;
-use crate::kernel::ptrace::*;
-use crate::kernel::socket::*;
-use crate::kernel::standard::*;
+use crate::errors::Result;
+use crate::kernel::registry;
+use crate::process::proot::InfoBag;
 use crate::process::tracee::Tracee;
 use crate::register::{Original, SysResult, Word};
 
-pub fn translate(tracee: &mut Tracee) {
-    let syscall_number = tracee.regs.get_sys_num(Original);
-    let syscall_group = syscall_group_from_sysnum(syscall_number);
-
-    let result = match syscall_group {
-        SyscallGroup::Brk => brk::exit(),
-        SyscallGroup::GetCwd => getcwd::exit(tracee),
-        SyscallGroup::Accept => accept::exit(),
-        SyscallGroup::GetSockOrPeerName => get_sockorpeer_name::exit(),
-        SyscallGroup::SocketCall => socketcall::exit(),
-        SyscallGroup::Chdir => chdir::exit(tracee),
-        SyscallGroup::Rename => link_rename::exit(tracee),
-        SyscallGroup::RenameAt => rename_at::exit(tracee),
-        SyscallGroup::ReadLinkAt => readlink_at::exit(),
-        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-        SyscallGroup::Uname => uname::exit(),
-        SyscallGroup::Execve => execve::exit(tracee),
-        SyscallGroup::Ptrace => ptrace::exit(),
-        SyscallGroup::Wait => wait::exit(),
-        _ => Ok(()),
-    };
+/// Routes the exit stage to the handler of the syscall. Like on the enter
+/// stage, the syscalls without handlers and the bypassed ones are passed
+/// through.
+///
+/// The syscall number is the one saved in the `Original` registers, since it
+/// may have been changed (or the syscall cancelled) during the enter stage.
+fn dispatch(info_bag: &InfoBag, tracee: &mut Tracee) -> Result<()> {
+    let sys_num = tracee.regs.get_sys_num(Original);
+    let handlers = registry::handlers_of_sysnum(sys_num);
+    if handlers.is_passthrough() || info_bag.config.is_syscall_bypassed(sys_num) {
+        return Ok(());
+    }
+
+    match handlers.exit {
+        Some(exit) => exit(info_bag, tracee),
+        None => Ok(()),
+    }
+}
 
-    if let Err(error) = result {
+pub fn translate(info_bag: &InfoBag, tracee: &mut Tracee) {
+    if let Err(error) = dispatch(info_bag, tracee) {
         debug!("syscall translate raised an error: {:?}", error);
         tracee.regs.set(
             SysResult,
             // errno is negative
-            error.get_errno() as Word,
+            (-(error.get_errno() as i32)) as Word,
             "following error during exit translation, setting errno",
         );
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use nix::errno::Errno;
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+    use nix::unistd;
+    use sc::nr::{READLINK, READLINKAT};
+
+    use std::cell::Cell;
+
+    use crate::register::{Current, Original, SysArg, SysArg1, SysArg2, SysArg3, SysResult};
+    use crate::utils::tests::test_with_proot;
+
+    /// A syscall number unknown to proot-rs (and to the kernel).
+    const UNKNOWN_SYSNUM: usize = 1000;
+
+    #[test]
+    fn test_exit_dispatch_uses_original_sysnum() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                // the syscall number is changed when the enter stage cancels a
+                // syscall, but the exit handler must be the original one
+                if !is_sysenter && before_translation {
+                    let sys_num = tracee.regs.get_sys_num(Original);
+                    if tracee.regs.get_sys_num(Current) != sys_num {
+                        assert!(sys_num == READLINK || sys_num == READLINKAT);
+                    }
+                }
+            },
+            || {
+                let fd = fcntl::open("/etc/passwd", OFlag::O_RDONLY, Mode::empty()).unwrap();
+                let path = CString::new(format!("/proc/self/fd/{}", fd)).unwrap();
+
+                // readlink() is cancelled and emulated by its exit handler
+                let mut buf = [0u8; 64];
+                let size =
+                    unsafe { libc::readlink(path.as_ptr(), buf.as_mut_ptr() as *mut _, buf.len()) };
+                assert_eq!(&buf[..size as usize], b"/etc/passwd");
+
+                // errors raised by an exit handler are reported as negative errno
+                let result =
+                    unsafe { libc::readlink(path.as_ptr(), buf.as_mut_ptr() as *mut _, 0) };
+                assert_eq!(result, -1);
+                assert_eq!(Errno::last(), Errno::EINVAL);
+
+                unistd::close(fd).unwrap();
+            },
+        )
+    }
+
+    #[test]
+    fn test_exit_unknown_syscall_passed_through() {
+        let result_before_translation = Cell::new(None);
+        test_with_proot(
+            move |tracee, is_sysenter, before_translation| {
+                if tracee.regs.get_sys_num(Original) != UNKNOWN_SYSNUM {
+                    return;
+                }
+                if is_sysenter {
+                    assert_eq!(tracee.regs.get_sys_num(Current), UNKNOWN_SYSNUM);
+                    for &index in [SysArg1, SysArg2, SysArg3].iter() {
+                        assert_eq!(
+                            tracee.regs.get(Current, SysArg(index)),
+                            tracee.regs.get(Original, SysArg(index))
+                        );
+                    }
+                } else if before_translation {
+                    result_before_translation.set(Some(tracee.regs.get(Current, SysResult)));
+                } else {
+                    // no exit handler changed the result of the kernel
+                    assert_eq!(
+                        result_before_translation.take(),
+                        Some(tracee.regs.get(Current, SysResult))
+                    );
+                }
+            },
+            || {
+                // the first argument looks like a path, but is never translated
+                let path = CString::new("/etc").unwrap();
+                let result =
+                    unsafe { libc::syscall(UNKNOWN_SYSNUM as libc::c_long, path.as_ptr(), 0, 0) };
+                assert_eq!(result, -1);
+                assert_eq!(Errno::last(), Errno::ENOSYS);
+            },
+        )
+    }
+}
@@ -1,10 +1,14 @@
 use sc::nr::*;
 
+use crate::kernel::standard::declared;
+
 /// Used to organise system call numbers into an easily-matchable enumeration.
-/// It's easier and cleaner to use cfg conditions here rather than in the huge
-/// match in `translate_syscall_enter` and `translate_syscall_exit`.
-#[derive(Debug, PartialEq)]
+/// It's easier and cleaner to use cfg conditions here rather than in the
+/// registry of the handlers (see `registry::handlers_of`).
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SyscallGroup {
+    /// Syscalls passed through untouched, e.g. the ones without any path
+    /// argument.
     Ignored = 0,
     Execve,
     Ptrace,
@@ -12,32 +16,93 @@ pub enum SyscallGroup {
     Brk,
     GetCwd,
     Chdir,
+    Chroot,
+    Clone,
+    Close,
+    Dup,
+    Declared,
+    Fcntl,
+    Pipe,
+    AnonInode,
     BindConnect,
+    Bpf,
     Accept,
     GetSockOrPeerName,
+    GetRandom,
+    Ioctl,
+    RecvMsg,
+    IoUringSetup,
     #[allow(dead_code)]
     SocketCall,
     StandardSyscall, // syscalls that only require their path arguments to be translated
     Open,
+    OpenTree,
+    Ownership,
     StatAt,
+    Unlink,
     ChmodAccessMkNodAt,
     InotifyAddWatch,
+    Landlock,
+    NewMountApi,
+    FanotifyMark,
     DirLinkAttr,
     PivotRoot,
+    Quotactl,
+    Read,
     LinkAt,
     Mount,
+    MoveMount,
+    MountSetattr,
+    MqOpen,
     OpenAt,
+    OpenAt2,
+    Personality,
+    Pidfd,
     Link,
     ReadLink,
     ReadLinkAt,
     Rename,
     RenameAt,
+    SetHostname,
+    Swap,
     SymLink,
+    Sysinfo,
     SymLinkAt,
+    Umask,
     Uname,
     UnlinkMkdirAt,
 }
 
+/// Numbers of the syscalls which are too recent for the `sc` crate.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub mod nr {
+    pub const RSEQ: usize = 334;
+    pub const PIDFD_SEND_SIGNAL: usize = 424;
+    pub const IO_URING_SETUP: usize = 425;
+    pub const IO_URING_ENTER: usize = 426;
+    pub const IO_URING_REGISTER: usize = 427;
+    pub const OPEN_TREE: usize = 428;
+    pub const MOVE_MOUNT: usize = 429;
+    pub const FSOPEN: usize = 430;
+    pub const FSCONFIG: usize = 431;
+    pub const FSMOUNT: usize = 432;
+    pub const FSPICK: usize = 433;
+    pub const PIDFD_OPEN: usize = 434;
+    pub const CLONE3: usize = 435;
+    pub const CLOSE_RANGE: usize = 436;
+    pub const OPENAT2: usize = 437;
+    pub const PIDFD_GETFD: usize = 438;
+    pub const FACCESSAT2: usize = 439;
+    pub const PROCESS_MADVISE: usize = 440;
+    pub const MOUNT_SETATTR: usize = 442;
+    pub const QUOTACTL_FD: usize = 443;
+    pub const LANDLOCK_CREATE_RULESET: usize = 444;
+    pub const LANDLOCK_ADD_RULE: usize = 445;
+    pub const LANDLOCK_RESTRICT_SELF: usize = 446;
+    pub const CACHESTAT: usize = 451;
+    pub const FCHMODAT2: usize = 452;
+}
+
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
 pub fn syscall_group_from_sysnum(sysnum: usize) -> SyscallGroup {
     match sysnum {
@@ -47,43 +112,139 @@ pub fn syscall_group_from_sysnum(sysnum: usize) -> SyscallGroup {
         BRK                                         => SyscallGroup::Brk,
         GETCWD                                      => SyscallGroup::GetCwd,
         FCHDIR | CHDIR                              => SyscallGroup::Chdir,
+        CHROOT                                      => SyscallGroup::Chroot,
+        CLONE | nr::CLONE3 | FORK | VFORK           => SyscallGroup::Clone,
+        CLOSE | nr::CLOSE_RANGE                     => SyscallGroup::Close,
+        DUP | DUP2 | DUP3                           => SyscallGroup::Dup,
+        FCNTL                                       => SyscallGroup::Fcntl,
+        IOCTL                                       => SyscallGroup::Ioctl,
+        PIPE | PIPE2                                => SyscallGroup::Pipe,
+        EVENTFD2 | SIGNALFD4 | TIMERFD_CREATE
+            | EPOLL_CREATE1                         => SyscallGroup::AnonInode,
         BIND | CONNECT                              => SyscallGroup::BindConnect,
+        BPF                                         => SyscallGroup::Bpf,
         ACCEPT | ACCEPT4                            => SyscallGroup::Accept,
         GETSOCKNAME | GETPEERNAME                   => SyscallGroup::GetSockOrPeerName,
+        RECVMSG                                     => SyscallGroup::RecvMsg,
         /* SOCKETCALL => SyscallGroup::SocketCall, */
         // int syscall(const char *pathname, ...) follow symlink
-        ACCESS | ACCT | CHMOD | CHOWN /*| CHOWN32*/
-            | CHROOT | GETXATTR | LISTXATTR | MKNOD
-            | /*OLDSTAT |*/ CREAT | REMOVEXATTR
-            | SETXATTR | STAT /*| STAT64*/ /*| STATSFS64*/
-            | SWAPOFF | SWAPON | TRUNCATE /*| TRUNCATE64*/ /*| UMOUNT*/
+        ACCESS | ACCT
+            | GETXATTR | LISTXATTR | MKNOD
+            | /*OLDSTAT |*/ REMOVEXATTR
+            | SETXATTR /*| STAT64*/ /*| STATSFS64*/
+            | TRUNCATE /*| TRUNCATE64*/ /*| UMOUNT*/
             | UMOUNT2 | USELIB | UTIME | UTIMES     => SyscallGroup::StandardSyscall,
-        // int syscall(const char *pathname, int flags, ...)
-        OPEN                                        => SyscallGroup::Open,
+        // int syscall(const char *pathname, int flags, ...), creat() being
+        // open(pathname, O_CREAT | O_WRONLY | O_TRUNC, mode)
+        OPEN | CREAT                                => SyscallGroup::Open,
         // int syscall(int dirfd, const char *pathname, ... , int flags, ...)
-        FCHOWNAT /*| FSTATAT64*/ | NEWFSTATAT
-            | UTIMENSAT | NAME_TO_HANDLE_AT | STATX => SyscallGroup::StatAt,
+        UTIMENSAT /*| FSTATAT64*/
+            | NAME_TO_HANDLE_AT                     => SyscallGroup::StatAt,
         // int syscall(int dirfd, const char *pathname, ...)
-        FCHMODAT | FACCESSAT | FUTIMESAT | MKNODAT  => SyscallGroup::ChmodAccessMkNodAt,
+        FACCESSAT | FUTIMESAT | MKNODAT             => SyscallGroup::ChmodAccessMkNodAt,
+        // syscalls changing or reading the owners and the mode of a file,
+        // faked with `fake_ownership`
+        CHOWN /*| CHOWN32*/ | LCHOWN /*| LCHOWN32*/
+            | FCHOWN | FCHOWNAT | CHMOD | FCHMOD
+            | FCHMODAT | STAT | LSTAT | FSTAT
+            | NEWFSTATAT | STATX                    => SyscallGroup::Ownership,
         INOTIFY_ADD_WATCH                           => SyscallGroup::InotifyAddWatch,
+        FANOTIFY_MARK                               => SyscallGroup::FanotifyMark,
+        nr::LANDLOCK_ADD_RULE                       => SyscallGroup::Landlock,
         // int syscall(const char *pathname, ...) not follow symlink
-        LCHOWN /*| LCHOWN32*/ | LGETXATTR
-            | LLISTXATTR | LREMOVEXATTR | LSETXATTR
-            | LSTAT /*| LSTATE64*/ /*| OLDLSTAT*/
-            | UNLINK | RMDIR | MKDIR                => SyscallGroup::DirLinkAttr,
+        LGETXATTR | LLISTXATTR | LREMOVEXATTR
+            | LSETXATTR /*| LSTATE64*/ /*| OLDLSTAT*/
+            | MKDIR                                 => SyscallGroup::DirLinkAttr,
+        // the fake metadata of a file is forgotten with its last link
+        UNLINK | UNLINKAT | RMDIR                   => SyscallGroup::Unlink,
         PIVOT_ROOT                                  => SyscallGroup::PivotRoot,
+        SWAPON | SWAPOFF                            => SyscallGroup::Swap,
         LINKAT                                      => SyscallGroup::LinkAt,
+        QUOTACTL                                    => SyscallGroup::Quotactl,
+        READ | PREAD64 | READV | PREADV | PREADV2
+            | LSEEK                                 => SyscallGroup::Read,
         MOUNT                                       => SyscallGroup::Mount,
+        nr::OPEN_TREE                               => SyscallGroup::OpenTree,
+        nr::MOVE_MOUNT                              => SyscallGroup::MoveMount,
+        nr::MOUNT_SETATTR                           => SyscallGroup::MountSetattr,
+        nr::FSOPEN | nr::FSCONFIG | nr::FSMOUNT
+            | nr::FSPICK                            => SyscallGroup::NewMountApi,
+        // their name isn't a path of the guest filesystem
+        MQ_OPEN | MQ_UNLINK                         => SyscallGroup::MqOpen,
         OPENAT                                      => SyscallGroup::OpenAt,
+        // only the mode of its `struct open_how` is translated, with the
+        // virtual umask
+        nr::OPENAT2                                 => SyscallGroup::OpenAt2,
+        PERSONALITY                                 => SyscallGroup::Personality,
+        // the pids aren't virtualized, so the ones of pidfd_open() and the
+        // pidfds of pidfd_send_signal() and process_madvise() are passed
+        // through, only the new file descriptors are recorded
+        nr::PIDFD_OPEN | nr::PIDFD_GETFD            => SyscallGroup::Pidfd,
         READLINK                                    => SyscallGroup::ReadLink,
         READLINKAT                                  => SyscallGroup::ReadLinkAt,
-        UNLINKAT | MKDIRAT                          => SyscallGroup::UnlinkMkdirAt,
+        MKDIRAT                                     => SyscallGroup::UnlinkMkdirAt,
         LINK                                        => SyscallGroup::Link,
         RENAME                                      => SyscallGroup::Rename,
         RENAMEAT                                    => SyscallGroup::RenameAt,
         SYMLINK                                     => SyscallGroup::SymLink,
         SYMLINKAT                                   => SyscallGroup::SymLinkAt,
+        UMASK                                       => SyscallGroup::Umask,
         UNAME                                       => SyscallGroup::Uname,
+        SETHOSTNAME | SETDOMAINNAME                 => SyscallGroup::SetHostname,
+        SYSINFO                                     => SyscallGroup::Sysinfo,
+        nr::IO_URING_SETUP                          => SyscallGroup::IoUringSetup,
+        GETRANDOM                                   => SyscallGroup::GetRandom,
+        // syscalls operating on file descriptors, whose paths were already
+        // translated when they were opened
+        nr::QUOTACTL_FD | FSTATFS | FTRUNCATE
+            | FALLOCATE | FSYNC | FDATASYNC | SYNCFS
+            | FGETXATTR | FSETXATTR | FLISTXATTR
+            | FREMOVEXATTR                          => SyscallGroup::Ignored,
+        // syscalls translated from the kinds of their arguments
+        sysnum if declared::get_declared_args(sysnum).is_some()
+                                                    => SyscallGroup::Declared,
+        // the other syscalls are passed through, none of their arguments
+        // is ever read as a path (e.g. the pointers of futex() or rseq())
         _                                           => SyscallGroup::Ignored,
     }
 }
+
+/// Tells whether the syscall numbered `sysnum` creates or modifies the files
+/// at its path arguments (at its new path, for link() and symlink()). These
+/// paths are translated with `Translator::translate_path_for_write()`, so
+/// that the read-only bindings fail with `EROFS` and the lower layers aren't
+/// modified in place.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub fn modifies_paths(sysnum: usize) -> bool {
+    matches!(
+        sysnum,
+        MKDIR
+            | MKDIRAT
+            | MKNOD
+            | MKNODAT
+            | RMDIR
+            | UNLINK
+            | UNLINKAT
+            | RENAME
+            | RENAMEAT
+            | LINK
+            | LINKAT
+            | SYMLINK
+            | SYMLINKAT
+            | CHMOD
+            | FCHMODAT
+            | nr::FCHMODAT2
+            | CHOWN
+            | LCHOWN
+            | FCHOWNAT
+            | TRUNCATE
+            | UTIME
+            | UTIMES
+            | UTIMENSAT
+            | FUTIMESAT
+            | SETXATTR
+            | LSETXATTR
+            | REMOVEXATTR
+            | LREMOVEXATTR
+    )
+}
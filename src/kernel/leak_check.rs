@@ -0,0 +1,154 @@
+use std::ffi::OsStr;
+use std::mem::size_of;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use libc::{c_void, sa_family_t, socklen_t, AF_UNIX};
+use sc::nr::{ACCEPT, ACCEPT4, GETCWD, GETPEERNAME, GETSOCKNAME, READLINK, READLINKAT};
+
+use crate::kernel::syscall;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Modified, Original, PtraceReader};
+use crate::register::{SysArg, SysArg1, SysArg2, SysArg3, SysArg6, SysArgIndex, SysResult};
+
+/// Returns the path written by a successful syscall in the memory of the
+/// tracee, if it returns one. Only the syscalls whose paths are detranslated
+/// are checked: readlink() (and so the links of `/proc`), getcwd(), and the
+/// ones writing the address of a socket.
+fn get_returned_path(tracee: &Tracee) -> Option<PathBuf> {
+    let result = tracee.regs.get(Current, SysResult) as isize;
+    if result < 0 {
+        return None;
+    }
+
+    let sys_num = tracee.regs.get_sys_num(Original);
+    let bytes = match sys_num {
+        READLINK => read_buffer(tracee, SysArg2, result as usize)?,
+        READLINKAT => read_buffer(tracee, SysArg3, result as usize)?,
+        // the size includes the terminating null byte
+        GETCWD => read_buffer(tracee, SysArg1, (result as usize).saturating_sub(1))?,
+        GETSOCKNAME | GETPEERNAME | ACCEPT | ACCEPT4 => read_unix_socket_path(tracee)?,
+        _ => return None,
+    };
+    Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+}
+
+fn read_buffer(tracee: &Tracee, buf: SysArgIndex, size: usize) -> Option<Vec<u8>> {
+    let address = tracee.regs.get(Original, SysArg(buf)) as *const c_void;
+    if address.is_null() || size == 0 {
+        return None;
+    }
+    tracee.regs.read_data(address, size).ok()
+}
+
+/// Reads the path of the unix socket address written in `addr`, its size
+/// being the one of `addrlen` capped by the max. size saved on the enter
+/// stage (see `get_sockorpeer_name`).
+fn read_unix_socket_path(tracee: &Tracee) -> Option<Vec<u8>> {
+    let size_address = tracee.regs.get(Original, SysArg(SysArg3)) as *const c_void;
+    if size_address.is_null() {
+        return None;
+    }
+    let size_bytes = tracee
+        .regs
+        .read_data(size_address, size_of::<socklen_t>())
+        .ok()?;
+    let mut bytes = [0u8; size_of::<socklen_t>()];
+    bytes.copy_from_slice(&size_bytes[..size_of::<socklen_t>()]);
+    let max_size = tracee.regs.get(Modified, SysArg(SysArg6)) as usize;
+    let size = (socklen_t::from_ne_bytes(bytes) as usize).min(max_size);
+
+    let family_size = size_of::<sa_family_t>();
+    let address = read_buffer(tracee, SysArg2, size)?;
+    if address.len() <= family_size {
+        return None;
+    }
+    let mut family_bytes = [0u8; size_of::<sa_family_t>()];
+    family_bytes.copy_from_slice(&address[..family_size]);
+    if sa_family_t::from_ne_bytes(family_bytes) != AF_UNIX as sa_family_t {
+        return None;
+    }
+    let path_bytes = &address[family_size..];
+    let end = path_bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or_else(|| path_bytes.len());
+    Some(path_bytes[..end].to_vec())
+}
+
+/// Returns the path returned to the guest by the current syscall if it's a
+/// host path under the guest root, i.e. if its detranslation is missing.
+pub fn find_host_leak(tracee: &Tracee) -> Option<PathBuf> {
+    let root = tracee.fs.borrow().get_root().to_path_buf();
+    // every path is under the host root
+    if root == Path::new("/") {
+        return None;
+    }
+    get_returned_path(tracee).filter(|path| path.starts_with(&root))
+}
+
+/// Panics if the current syscall returned a host path to the guest. Checked
+/// on the exit stage when `check_host_leaks` is set, which is meant for the
+/// developers: it catches the missing detranslations.
+pub fn check(tracee: &Tracee) {
+    if let Some(path) = find_host_leak(tracee) {
+        let sys_num = tracee.regs.get_sys_num(Original);
+        panic!(
+            "host path {:?} leaked to the guest by {} (syscall {})",
+            path,
+            syscall::name_of_syscall(sys_num).unwrap_or("unknown"),
+            sys_num
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::os::unix::io::AsRawFd;
+    use std::panic::AssertUnwindSafe;
+
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    fn test_leak_check_fires_without_detranslation() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .bypass_syscall("readlink")
+                // checked in the hook instead, where the panic is caught
+                .check_host_leaks(false)
+                .build(),
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != READLINK
+                {
+                    return;
+                }
+                // readlink() isn't detranslated anymore
+                assert_eq!(
+                    find_host_leak(tracee),
+                    Some(get_test_rootfs_path().join("etc/passwd"))
+                );
+                assert!(std::panic::catch_unwind(AssertUnwindSafe(|| check(tracee))).is_err());
+            },
+            || {
+                let file = std::fs::File::open("/etc/passwd").unwrap();
+                let path = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+                let mut buf = [0u8; 4096];
+                let result = unsafe {
+                    libc::syscall(
+                        READLINK as libc::c_long,
+                        path.as_ptr(),
+                        buf.as_mut_ptr(),
+                        buf.len(),
+                    )
+                };
+                assert!(result > 0);
+            },
+        )
+    }
+}
@@ -1,9 +1,11 @@
 pub mod enter;
 pub mod execve;
 pub mod exit;
-mod groups;
+pub mod groups;
 mod heap;
+pub mod leak_check;
 mod ptrace;
+pub mod registry;
 mod socket;
 mod standard;
 pub mod syscall;
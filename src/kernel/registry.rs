@@ -0,0 +1,314 @@
+use crate::errors::Result;
+use crate::kernel::execve;
+use crate::kernel::groups::{syscall_group_from_sysnum, SyscallGroup};
+use crate::kernel::heap::*;
+use crate::kernel::ptrace::*;
+use crate::kernel::socket::*;
+use crate::kernel::standard::*;
+use crate::kernel::syscall;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+
+/// Translation of one stage (enter or exit) of the syscalls of a group.
+pub type Handler = fn(&InfoBag, &mut Tracee) -> Result<()>;
+
+/// The handlers of the syscalls of a `SyscallGroup`.
+#[derive(Clone, Copy)]
+pub struct SyscallHandlers {
+    pub enter: Option<Handler>,
+    pub exit: Option<Handler>,
+    /// Whether the tracee has to be stopped on the exit stage of the
+    /// syscall. When it doesn't (only possible under seccomp), the registers
+    /// modified by the enter stage are not given back to the tracee.
+    pub needs_exit_stage: bool,
+}
+
+impl SyscallHandlers {
+    const NONE: SyscallHandlers = SyscallHandlers {
+        enter: None,
+        exit: None,
+        needs_exit_stage: false,
+    };
+
+    fn enter(enter: Handler) -> Self {
+        SyscallHandlers {
+            enter: Some(enter),
+            exit: None,
+            needs_exit_stage: false,
+        }
+    }
+
+    fn exit(exit: Handler) -> Self {
+        SyscallHandlers {
+            enter: None,
+            exit: Some(exit),
+            needs_exit_stage: true,
+        }
+    }
+
+    fn enter_exit(enter: Handler, exit: Handler) -> Self {
+        SyscallHandlers {
+            enter: Some(enter),
+            exit: Some(exit),
+            needs_exit_stage: true,
+        }
+    }
+
+    /// Whether the syscalls are passed through untouched, on both stages.
+    pub fn is_passthrough(&self) -> bool {
+        self.enter.is_none() && self.exit.is_none()
+    }
+}
+
+/// Returns the handlers of the syscalls of `group`.
+pub fn handlers_of(group: SyscallGroup) -> SyscallHandlers {
+    use SyscallGroup::*;
+
+    match group {
+        Ignored => SyscallHandlers::NONE,
+        Accept => SyscallHandlers::enter_exit(
+            |_, tracee| accept::enter(tracee),
+            |_, tracee| accept::exit(tracee),
+        ),
+        AnonInode => SyscallHandlers::exit(|_, tracee| anon_inode::exit(tracee)),
+        BindConnect => SyscallHandlers::enter_exit(
+            |_, _| bind_connect::enter(),
+            |_, tracee| bind_connect::exit(tracee),
+        ),
+        Bpf => SyscallHandlers::enter(|_, tracee| bpf::enter(tracee)),
+        Brk => SyscallHandlers::enter_exit(|_, _| brk::enter(), |_, _| brk::exit()),
+        Chdir => SyscallHandlers::enter_exit(
+            |_, tracee| chdir::enter(tracee),
+            |_, tracee| chdir::exit(tracee),
+        ),
+        Chroot => SyscallHandlers::enter_exit(
+            |info_bag, tracee| chroot::enter(tracee, info_bag),
+            |_, tracee| chroot::exit(tracee),
+        ),
+        Clone => SyscallHandlers::enter(|info_bag, _| clone::enter(info_bag)),
+        ChmodAccessMkNodAt => {
+            SyscallHandlers::enter(|_, tracee| chmod_access_mknod_at::enter(tracee))
+        }
+        Close => SyscallHandlers::exit(|_, tracee| close::exit(tracee)),
+        DirLinkAttr => SyscallHandlers::enter(|_, tracee| dir_link_attr::enter(tracee)),
+        Declared => SyscallHandlers::enter(|_, tracee| declared::enter(tracee)),
+        Dup => SyscallHandlers::exit(|_, tracee| dup::exit(tracee)),
+        Execve => SyscallHandlers::enter_exit(
+            |info_bag, tracee| execve::enter(tracee, info_bag),
+            |_, tracee| execve::exit(tracee),
+        ),
+        FanotifyMark => SyscallHandlers::enter(|_, tracee| fanotify_mark::enter(tracee)),
+        Fcntl => SyscallHandlers::exit(|_, tracee| fcntl::exit(tracee)),
+        GetCwd => SyscallHandlers::enter_exit(
+            |_, tracee| getcwd::enter(tracee),
+            |_, tracee| getcwd::exit(tracee),
+        ),
+        GetRandom => SyscallHandlers::enter_exit(
+            |info_bag, tracee| getrandom::enter(tracee, info_bag),
+            |info_bag, tracee| getrandom::exit(tracee, info_bag),
+        ),
+        GetSockOrPeerName => SyscallHandlers::enter_exit(
+            |_, tracee| get_sockorpeer_name::enter(tracee),
+            |_, tracee| get_sockorpeer_name::exit(tracee),
+        ),
+        Ioctl => SyscallHandlers::enter(|_, tracee| ioctl::enter(tracee)),
+        IoUringSetup => {
+            SyscallHandlers::enter(|info_bag, tracee| io_uring::enter(tracee, info_bag))
+        }
+        InotifyAddWatch => SyscallHandlers::enter(|_, _| inotify_add_watch::enter()),
+        Landlock => SyscallHandlers::enter(|_, tracee| landlock::enter(tracee)),
+        Link => SyscallHandlers::enter(|_, tracee| link_rename::enter(tracee)),
+        LinkAt => SyscallHandlers::enter(|_, tracee| link_at::enter(tracee)),
+        Mount => SyscallHandlers::enter(|_, _| mount::enter()),
+        MqOpen => SyscallHandlers::enter(|info_bag, tracee| mq_open::enter(tracee, info_bag)),
+        MoveMount => SyscallHandlers::enter_exit(
+            |info_bag, tracee| mount_api::move_mount_enter(tracee, info_bag),
+            |_, tracee| mount_api::move_mount_exit(tracee),
+        ),
+        MountSetattr => SyscallHandlers::enter(|info_bag, tracee| {
+            mount_api::mount_setattr_enter(tracee, info_bag)
+        }),
+        NewMountApi => SyscallHandlers::enter(|_, tracee| mount_api::reject_enter(tracee)),
+        OpenTree => SyscallHandlers::enter_exit(
+            |info_bag, tracee| mount_api::open_tree_enter(tracee, info_bag),
+            |_, tracee| mount_api::open_tree_exit(tracee),
+        ),
+        Open => SyscallHandlers::enter_exit(
+            |info_bag, tracee| open::enter(tracee, info_bag),
+            |_, tracee| open::exit(tracee),
+        ),
+        OpenAt => SyscallHandlers::enter_exit(
+            |info_bag, tracee| open_at::enter(tracee, info_bag),
+            |_, tracee| open_at::exit(tracee),
+        ),
+        OpenAt2 => SyscallHandlers::enter(|_, tracee| umask::apply_to_open_how(tracee)),
+        Ownership => SyscallHandlers::enter_exit(
+            |_, tracee| ownership::enter(tracee),
+            |info_bag, tracee| ownership::exit(tracee, info_bag),
+        ),
+        Personality => {
+            SyscallHandlers::enter(|info_bag, tracee| personality::enter(tracee, info_bag))
+        }
+        Pidfd => SyscallHandlers::exit(|_, tracee| pidfd::exit(tracee)),
+        Pipe => SyscallHandlers::exit(|_, tracee| pipe::exit(tracee)),
+        PivotRoot => SyscallHandlers::enter_exit(
+            |info_bag, tracee| pivot_root::enter(tracee, info_bag),
+            |_, tracee| pivot_root::exit(tracee),
+        ),
+        Ptrace => SyscallHandlers::enter_exit(|_, _| ptrace::enter(), |_, _| ptrace::exit()),
+        Quotactl => SyscallHandlers::enter(|_, tracee| quotactl::enter(tracee)),
+        RecvMsg => SyscallHandlers::exit(|_, tracee| recvmsg::exit(tracee)),
+        Read => SyscallHandlers::enter_exit(
+            |_, tracee| read::enter(tracee),
+            |_, tracee| read::exit(tracee),
+        ),
+        ReadLink | ReadLinkAt => SyscallHandlers::enter_exit(
+            |_, tracee| readlink_at::enter(tracee),
+            |_, tracee| readlink_at::exit(tracee),
+        ),
+        Rename => SyscallHandlers::enter_exit(
+            |_, tracee| link_rename::enter(tracee),
+            |_, tracee| link_rename::exit(tracee),
+        ),
+        RenameAt => SyscallHandlers::enter_exit(
+            |_, tracee| rename_at::enter(tracee),
+            |_, tracee| rename_at::exit(tracee),
+        ),
+        SetHostname => SyscallHandlers::enter_exit(
+            |info_bag, tracee| sethostname::enter(tracee, info_bag),
+            |_, tracee| sethostname::exit(tracee),
+        ),
+        SocketCall => {
+            SyscallHandlers::enter_exit(|_, _| socketcall::enter(), |_, _| socketcall::exit())
+        }
+        StandardSyscall => SyscallHandlers::enter(|_, tracee| standard_syscall::enter(tracee)),
+        StatAt => SyscallHandlers::enter(|_, tracee| stat_at::enter(tracee)),
+        Swap => SyscallHandlers::enter(|info_bag, tracee| swap::enter(tracee, info_bag)),
+        SymLink => SyscallHandlers::enter(|_, tracee| sym_link::enter(tracee)),
+        SymLinkAt => SyscallHandlers::enter(|_, tracee| sym_link_at::enter(tracee)),
+        Sysinfo => SyscallHandlers::exit(|info_bag, tracee| sysinfo::exit(tracee, info_bag)),
+        Umask => SyscallHandlers::enter_exit(
+            |_, tracee| umask::enter(tracee),
+            |_, tracee| umask::exit(tracee),
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        Uname => SyscallHandlers::exit(|info_bag, tracee| uname::exit(tracee, info_bag)),
+        #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+        Uname => SyscallHandlers::NONE,
+        Unlink => SyscallHandlers::enter_exit(
+            |info_bag, tracee| ownership::unlink_enter(tracee, info_bag),
+            |info_bag, tracee| ownership::unlink_exit(tracee, info_bag),
+        ),
+        UnlinkMkdirAt => SyscallHandlers::enter(|_, tracee| unlink_mkdir_at::enter(tracee)),
+        Wait => SyscallHandlers::enter_exit(|_, _| wait::enter(), |_, _| wait::exit()),
+    }
+}
+
+/// Returns the handlers of the syscall numbered `sysnum`.
+pub fn handlers_of_sysnum(sysnum: usize) -> SyscallHandlers {
+    handlers_of(syscall_group_from_sysnum(sysnum))
+}
+
+/// Returns the numbers of the syscalls translated by proot-rs on this
+/// architecture, in increasing order.
+#[allow(dead_code)]
+pub fn handled_syscalls() -> Vec<usize> {
+    let mut sysnums: Vec<usize> = syscall::known_sysnums()
+        .filter(|&sysnum| syscall_group_from_sysnum(sysnum) != SyscallGroup::Ignored)
+        .collect();
+    sysnums.sort_unstable();
+    sysnums
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+    use nix::unistd;
+    use sc::nr::{EXECVE, FUTEX, OPENAT, UNAME};
+
+    use super::*;
+    use crate::register::{Current, Original, SysResult};
+    use crate::utils::tests::test_with_proot;
+
+    #[test]
+    fn test_registry_handled_syscalls() {
+        let sysnums = handled_syscalls();
+
+        for &sysnum in [EXECVE, OPENAT, UNAME].iter() {
+            assert!(sysnums.contains(&sysnum));
+        }
+        // its pointer arguments are never paths
+        assert!(!sysnums.contains(&FUTEX));
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        assert!(!sysnums.contains(&crate::kernel::groups::nr::RSEQ));
+        // nor are the ones of the unknown syscalls
+        assert!(handlers_of_sysnum(1000).enter.is_none());
+        assert!(handlers_of_sysnum(1000).exit.is_none());
+    }
+
+    #[test]
+    fn test_registry_consistent_metadata() {
+        assert!(handlers_of(SyscallGroup::Ignored).enter.is_none());
+        assert!(handlers_of(SyscallGroup::Ignored).exit.is_none());
+        assert!(!handlers_of(SyscallGroup::Ignored).needs_exit_stage);
+
+        for sysnum in handled_syscalls() {
+            let name = syscall::name_of_syscall(sysnum).unwrap();
+            let handlers = handlers_of_sysnum(sysnum);
+
+            // a handled syscall is translated on at least one of the stages
+            assert!(
+                handlers.enter.is_some() || handlers.exit.is_some(),
+                "{} has no handler",
+                name
+            );
+        }
+    }
+
+    #[test]
+    /// Under seccomp, the exit stage of the syscalls without
+    /// `needs_exit_stage` is skipped (see `translate_syscall_enter`): it must
+    /// then never change the result of one of them.
+    fn test_registry_needs_exit_stage_on_sysexit() {
+        let result_before_exit = Cell::new(0);
+
+        test_with_proot(
+            move |tracee, is_sysenter, before_translation| {
+                if is_sysenter || !tracee.status.is_ok() {
+                    return;
+                }
+                if before_translation {
+                    result_before_exit.set(tracee.regs.get(Current, SysResult));
+                    return;
+                }
+                let sys_num = tracee.regs.get_sys_num(Original);
+                if tracee.regs.get(Current, SysResult) != result_before_exit.get() {
+                    assert!(
+                        handlers_of_sysnum(sys_num).needs_exit_stage,
+                        "the exit stage of {:?} can't be skipped",
+                        syscall::name_of_syscall(sys_num)
+                    );
+                }
+            },
+            || {
+                // getcwd() and the emulated readlink() are both translated on
+                // the exit stage
+                env::current_dir().unwrap();
+                let fd = fcntl::open("/etc/passwd", OFlag::O_RDONLY, Mode::empty()).unwrap();
+                assert_eq!(
+                    fs::read_link(format!("/proc/self/fd/{}", fd)).unwrap(),
+                    Path::new("/etc/passwd")
+                );
+                unistd::close(fd).unwrap();
+                fs::metadata("/etc/passwd").unwrap();
+                nix::sys::utsname::uname();
+            },
+        );
+    }
+}
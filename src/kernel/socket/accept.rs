@@ -1,22 +1,14 @@
 use crate::errors::Result;
 
 use crate::kernel::socket::get_sockorpeer_name;
+use crate::process::tracee::Tracee;
 
-pub fn enter() -> Result<()> {
-    get_sockorpeer_name::enter()
-
-    /* Nothing special to do if no sockaddr was specified. */
-    // if (peek_reg(tracee, ORIGINAL, SYSARG_2) == 0) {
-    //     status = 0;
-    //     break;
-    // }
-    // special = true;
+/// accept(fd, addr, addrlen) and accept4() write the address of the peer
+/// like getpeername().
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    get_sockorpeer_name::enter(tracee)
 }
 
-pub fn exit() -> Result<()> {
-    /* Nothing special to do if no sockaddr was specified. */
-    // if (peek_reg(tracee, ORIGINAL, SYSARG_2) == 0)
-    //      return SyscallExitResult::None;
-
-    get_sockorpeer_name::exit()
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    get_sockorpeer_name::exit(tracee)
 }
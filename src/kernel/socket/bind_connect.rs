@@ -1,7 +1,15 @@
 use crate::errors::Result;
+use crate::kernel::standard::umask;
+use crate::process::tracee::Tracee;
 
 pub fn enter() -> Result<()> {
     Ok(())
 
     //TODO: bind/connect => socketcall
 }
+
+/// The socket file created by bind() gets the virtual umask, see
+/// `umask::apply_to_bound_socket()`.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    umask::apply_to_bound_socket(tracee)
+}
@@ -1,40 +1,142 @@
-use crate::errors::Result;
+use std::ffi::OsStr;
+use std::mem::size_of;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 
-pub fn enter() -> Result<()> {
+use libc::{c_void, sa_family_t, socklen_t, AF_UNIX};
+
+use crate::errors::*;
+use crate::filesystem::Translator;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Modified, Original, PtraceReader, PtraceWriter};
+use crate::register::{SysArg, SysArg2, SysArg3, SysArg6, SysResult, Word};
+
+/// getsockname(fd, addr, addrlen), getpeername(), accept() and accept4()
+/// write the address of a socket in `addr`, which is the host path of a unix
+/// socket bound in the guest rootfs.
+///
+/// The `addrlen` argument is both used as an input parameter (max. size) and
+/// as an output parameter (actual size). The exit stage needs to know the
+/// max. size to not overwrite anything, that's why it is copied in the 6th
+/// argument (unused) before the kernel updates it.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    let size_address = tracee.regs.get(Original, SysArg(SysArg3)) as *const c_void;
+    // nothing special to do if no sockaddr was specified
+    if tracee.regs.get(Original, SysArg(SysArg2)) == 0 || size_address.is_null() {
+        return Ok(());
+    }
+
+    let max_size = read_socklen(tracee, size_address)?;
+    tracee.regs.set(
+        SysArg(SysArg6),
+        max_size as Word,
+        "during enter sockaddr translation, saving the max. size of the sockaddr",
+    );
     Ok(())
+}
 
-    //    /* Remember: PEEK_WORD puts -errno in status and breaks if an
-    //     * error occured.  */
-    //    // size = (int) PEEK_WORD(peek_reg(tracee, ORIGINAL, SYSARG_3),
-    // special ? -EINVAL : 0);
-    //
-    //    /* The "size" argument is both used as an input parameter
-    //     * (max. size) and as an output parameter (actual size).  The
-    //     * exit stage needs to know the max. size to not overwrite
-    //     * anything, that's why it is copied in the 6th argument
-    //     * (unused) before the kernel updates it.  */
-    //    // poke_reg(tracee, SYSARG_6, size);
-    //
-    //    // status = 0;
+fn read_socklen(tracee: &Tracee, address: *const c_void) -> Result<socklen_t> {
+    let data = tracee.regs.read_data(address, size_of::<socklen_t>())?;
+    let mut bytes = [0u8; size_of::<socklen_t>()];
+    bytes.copy_from_slice(&data[..size_of::<socklen_t>()]);
+    Ok(socklen_t::from_ne_bytes(bytes))
 }
 
-pub fn exit() -> Result<()> {
-    //    word_t sock_addr;
-    //    word_t size_addr;
-    //    word_t max_size;
-    //
-    //    /* Error reported by the kernel.  */
-    //    if ((int) syscall_result < 0)
-    //        return SyscallExitResult::None;
-    //
-    //    sock_addr = peek_reg(tracee, ORIGINAL, SYSARG_2);
-    //    size_addr = peek_reg(tracee, MODIFIED, SYSARG_3);
-    //    max_size  = peek_reg(tracee, MODIFIED, SYSARG_6);
-    //
-    //    status = translate_socketcall_exit(tracee, sock_addr, size_addr,
-    // max_size);    if (status < 0)
-    //        return SyscallExitResult::Value(status);
-    //
-    // Don't overwrite the syscall result.
+/// Detranslates the path of the unix socket address written by the kernel,
+/// so that no host path leaks into the guest. Like the kernel, the address is
+/// truncated to the max. size, and `addrlen` holds its actual size.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    // error reported by the kernel
+    if (tracee.regs.get(Current, SysResult) as isize) < 0 {
+        return Ok(());
+    }
+    let sock_address = tracee.regs.get(Original, SysArg(SysArg2)) as *mut c_void;
+    let size_address = tracee.regs.get(Original, SysArg(SysArg3)) as *mut c_void;
+    if sock_address.is_null() || size_address.is_null() {
+        return Ok(());
+    }
+    let max_size = tracee.regs.get(Modified, SysArg(SysArg6)) as usize;
+    let size = (read_socklen(tracee, size_address)? as usize).min(max_size);
+
+    let family_size = size_of::<sa_family_t>();
+    if size <= family_size {
+        return Ok(());
+    }
+    let address = tracee.regs.read_data(sock_address, size)?;
+    let mut family_bytes = [0u8; size_of::<sa_family_t>()];
+    family_bytes.copy_from_slice(&address[..family_size]);
+    // only the paths of the unix sockets are translated, the abstract ones
+    // start with a null byte
+    if sa_family_t::from_ne_bytes(family_bytes) != AF_UNIX as sa_family_t
+        || address[family_size] != b'/'
+    {
+        return Ok(());
+    }
+
+    let path_bytes = &address[family_size..];
+    let path_bytes = match path_bytes.iter().position(|&byte| byte == 0) {
+        Some(end) => &path_bytes[..end],
+        None => path_bytes,
+    };
+    let guest_path = match tracee
+        .fs
+        .borrow()
+        .detranslate_path(Path::new(OsStr::from_bytes(path_bytes)), None)?
+    {
+        Some(guest_path) => guest_path,
+        None => return Ok(()),
+    };
+
+    let mut new_address = family_bytes.to_vec();
+    new_address.extend_from_slice(guest_path.as_os_str().as_bytes());
+    new_address.push(0);
+    let new_size = new_address.len();
+    new_address.truncate(max_size);
+    tracee.regs.write_data(sock_address, &new_address, false)?;
+    tracee
+        .regs
+        .write_data(size_address, &(new_size as socklen_t).to_ne_bytes(), false)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot};
+
+    #[test]
+    fn test_getsockname_unix_path_detranslated() {
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                // bind() isn't translated yet, so a socket bound with the
+                // host path of a guest file was reported with that host path
+                let host_path = get_test_rootfs_path().join("tmp/proot_rs_test_getsockname");
+                let _ = std::fs::remove_file(&host_path);
+                let listener = UnixListener::bind(&host_path).unwrap();
+                let result = std::panic::catch_unwind(|| {
+                    let address = listener.local_addr().unwrap();
+                    assert_eq!(
+                        address.as_pathname(),
+                        Some(Path::new("/tmp/proot_rs_test_getsockname"))
+                    );
+
+                    let stream = UnixStream::connect(&host_path).unwrap();
+                    assert_eq!(
+                        stream.peer_addr().unwrap().as_pathname(),
+                        Some(Path::new("/tmp/proot_rs_test_getsockname"))
+                    );
+                    let (accepted, _) = listener.accept().unwrap();
+                    assert!(accepted.as_raw_fd() >= 0);
+                });
+                std::fs::remove_file("/tmp/proot_rs_test_getsockname").unwrap();
+                if let Err(err) = result {
+                    std::panic::resume_unwind(err);
+                }
+            },
+        )
+    }
+}
@@ -1,4 +1,5 @@
 pub mod accept;
 pub mod bind_connect;
 pub mod get_sockorpeer_name;
+pub mod recvmsg;
 pub mod socketcall;
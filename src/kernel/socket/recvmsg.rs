@@ -0,0 +1,163 @@
+use std::mem::size_of;
+use std::os::unix::prelude::RawFd;
+use std::ptr;
+
+use libc::{c_int, c_void, cmsghdr, msghdr, MSG_CMSG_CLOEXEC, SCM_RIGHTS, SOL_SOCKET};
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::process::fd_table::RECEIVED_FD_MARKER;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceReader, SysArg, SysArg2, SysArg3, SysResult};
+
+/// Aligns `len` like `CMSG_ALIGN()`.
+#[inline]
+fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+/// Reads the file descriptors received in the `SCM_RIGHTS` control messages
+/// of the `msghdr` given to recvmsg(), once filled in by the kernel.
+fn read_received_fds(tracee: &Tracee) -> Result<Vec<RawFd>> {
+    let address = tracee.regs.get(Original, SysArg(SysArg2)) as *const c_void;
+    let data = tracee.regs.read_data(address, size_of::<msghdr>())?;
+    let message: msghdr = unsafe { ptr::read_unaligned(data.as_ptr() as *const _) };
+
+    // `msg_controllen` was updated with the length of the control messages
+    // actually written.
+    if message.msg_control.is_null() || message.msg_controllen == 0 {
+        return Ok(vec![]);
+    }
+    let control = tracee
+        .regs
+        .read_data(message.msg_control, message.msg_controllen as usize)?;
+
+    let header_len = cmsg_align(size_of::<cmsghdr>());
+    let mut fds = vec![];
+    let mut offset = 0;
+    while offset + size_of::<cmsghdr>() <= control.len() {
+        let header: cmsghdr =
+            unsafe { ptr::read_unaligned(control[offset..].as_ptr() as *const _) };
+        let cmsg_len = header.cmsg_len as usize;
+        if cmsg_len < header_len || offset + cmsg_len > control.len() {
+            break;
+        }
+
+        if header.cmsg_level == SOL_SOCKET && header.cmsg_type == SCM_RIGHTS {
+            let payload = &control[offset + header_len..offset + cmsg_len];
+            for bytes in payload.chunks_exact(size_of::<c_int>()) {
+                let mut raw = [0u8; size_of::<c_int>()];
+                raw.copy_from_slice(bytes);
+                fds.push(c_int::from_ne_bytes(raw));
+            }
+        }
+        offset += cmsg_align(cmsg_len);
+    }
+    Ok(fds)
+}
+
+/// Records the file descriptors passed by another process with
+/// `SCM_RIGHTS` in the fd table, under their guest path if they refer to a
+/// file of the guest filesystem, or `RECEIVED_FD_MARKER` otherwise (e.g. a
+/// socket, or a file outside of the guest root).
+///
+/// The message is already received at this point, so a failure only results
+/// in the file descriptors not being tracked.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let result = tracee.regs.get(Current, SysResult) as isize;
+    if result < 0 {
+        return Ok(());
+    }
+
+    let cloexec = tracee.regs.get(Original, SysArg(SysArg3)) as c_int & MSG_CMSG_CLOEXEC != 0;
+
+    match read_received_fds(tracee) {
+        Ok(fds) => {
+            for fd in fds {
+                let path = tracee
+                    .get_path_from_fd(fd, Side::Guest)
+                    .unwrap_or_else(|_| RECEIVED_FD_MARKER.into());
+                tracee.fd_table.borrow_mut().insert(fd, path, cloexec);
+            }
+        }
+        Err(error) => debug!("failed to track received file descriptors: {}", error),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::RawFd;
+    use std::path::Path;
+
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::socket::{
+        recvmsg, sendmsg, socketpair, AddressFamily, ControlMessage, ControlMessageOwned, MsgFlags,
+        SockFlag, SockType,
+    };
+    use nix::sys::stat::Mode;
+    use nix::sys::uio::IoVec;
+    use nix::unistd;
+    use sc::nr::RECVMSG;
+
+    use crate::register::{Current, Original, SysResult};
+    use crate::utils::tests::test_with_proot;
+
+    use super::read_received_fds;
+
+    #[test]
+    fn test_recvmsg_scm_rights_fd_tracking() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter || before_translation {
+                    return;
+                }
+                if tracee.regs.get_sys_num(Original) == RECVMSG
+                    && (tracee.regs.get(Current, SysResult) as isize) >= 0
+                {
+                    let fds = read_received_fds(tracee).unwrap();
+                    assert_eq!(fds.len(), 1);
+                    let fd_table = tracee.fd_table.borrow();
+                    let entry = fd_table.get(fds[0]).unwrap();
+                    assert_eq!(entry.path, Path::new("/etc/passwd"));
+                    assert!(entry.cloexec);
+                }
+            },
+            || {
+                let (sender, receiver) = socketpair(
+                    AddressFamily::Unix,
+                    SockType::Stream,
+                    None,
+                    SockFlag::empty(),
+                )
+                .unwrap();
+                let fd = fcntl::open("/etc/passwd", OFlag::O_RDONLY, Mode::empty()).unwrap();
+
+                let iov = [IoVec::from_slice(b"x")];
+                let fds = [fd];
+                let cmsg = [ControlMessage::ScmRights(&fds)];
+                sendmsg(sender, &iov, &cmsg, MsgFlags::empty(), None).unwrap();
+                unistd::close(fd).unwrap();
+
+                let mut buf = [0u8; 1];
+                let iov = [IoVec::from_mut_slice(&mut buf)];
+                let mut cmsg_buffer = nix::cmsg_space!([RawFd; 1]);
+                let message = recvmsg(
+                    receiver,
+                    &iov,
+                    Some(&mut cmsg_buffer),
+                    MsgFlags::MSG_CMSG_CLOEXEC,
+                )
+                .unwrap();
+                let received_fd = match message.cmsgs().next() {
+                    Some(ControlMessageOwned::ScmRights(fds)) => fds[0],
+                    _ => panic!("no file descriptor received"),
+                };
+
+                unistd::close(received_fd).unwrap();
+                unistd::close(sender).unwrap();
+                unistd::close(receiver).unwrap();
+            },
+        )
+    }
+}
@@ -0,0 +1,114 @@
+use std::os::unix::prelude::RawFd;
+
+use nix::fcntl::OFlag;
+use sc::nr::{EPOLL_CREATE1, EVENTFD2, SIGNALFD4, TIMERFD_CREATE};
+
+use crate::errors::*;
+use crate::process::fd_table::{EPOLL_MARKER, EVENTFD_MARKER, SIGNALFD_MARKER, TIMERFD_MARKER};
+use crate::process::tracee::Tracee;
+use crate::register::{
+    Current, Original, SysArg, SysArg1, SysArg2, SysArg4, SysArgIndex, SysResult,
+};
+
+/// Returns the synthetic marker of the file descriptor created by the
+/// syscall `sys_num`, and the index of its `flags` argument.
+fn get_marker_and_flags_sysarg(sys_num: usize) -> Option<(&'static str, SysArgIndex)> {
+    match sys_num {
+        EVENTFD2 => Some((EVENTFD_MARKER, SysArg2)),
+        SIGNALFD4 => Some((SIGNALFD_MARKER, SysArg4)),
+        TIMERFD_CREATE => Some((TIMERFD_MARKER, SysArg2)),
+        EPOLL_CREATE1 => Some((EPOLL_MARKER, SysArg1)),
+        _ => None,
+    }
+}
+
+/// Records the file descriptor created by eventfd2(), signalfd4(),
+/// timerfd_create() or epoll_create1() in the fd table with a synthetic
+/// marker. Their `*_CLOEXEC` flags all have the value of `O_CLOEXEC`.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let fd = tracee.regs.get(Current, SysResult) as RawFd;
+    if fd < 0 {
+        return Ok(());
+    }
+
+    let sys_num = tracee.regs.get_sys_num(Original);
+    // signalfd4() with a valid fd only updates the mask of an existing
+    // signalfd, which keeps its entry.
+    if sys_num == SIGNALFD4 && tracee.regs.get(Original, SysArg(SysArg1)) as RawFd != -1 {
+        return Ok(());
+    }
+
+    if let Some((marker, flags_sysarg)) = get_marker_and_flags_sysarg(sys_num) {
+        let cloexec =
+            OFlag::from_bits_truncate(tracee.regs.get(Original, SysArg(flags_sysarg)) as _)
+                .contains(OFlag::O_CLOEXEC);
+        tracee.fd_table.borrow_mut().insert(fd, marker, cloexec);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+    use std::path::Path;
+
+    use nix::fcntl::OFlag;
+    use nix::unistd;
+
+    use crate::register::{Current, Original, SysArg, SysResult};
+    use crate::utils::tests::test_with_proot;
+
+    use super::get_marker_and_flags_sysarg;
+
+    #[test]
+    fn test_anon_inode_fd_tracking() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter || before_translation {
+                    return;
+                }
+                let fd = tracee.regs.get(Current, SysResult) as i32;
+                let sys_num = tracee.regs.get_sys_num(Original);
+                if let Some((marker, flags_sysarg)) = get_marker_and_flags_sysarg(sys_num) {
+                    if fd < 0 {
+                        return;
+                    }
+                    let cloexec = OFlag::from_bits_truncate(
+                        tracee.regs.get(Original, SysArg(flags_sysarg)) as _,
+                    )
+                    .contains(OFlag::O_CLOEXEC);
+                    let fd_table = tracee.fd_table.borrow();
+                    let entry = fd_table.get(fd).unwrap();
+                    assert_eq!(entry.path, Path::new(marker));
+                    assert_eq!(entry.cloexec, cloexec);
+                    assert!(entry.is_anonymous());
+                }
+            },
+            || {
+                let mut fds = vec![];
+                for &cloexec in [false, true].iter() {
+                    let flags = if cloexec { libc::O_CLOEXEC } else { 0 };
+                    unsafe {
+                        let mut mask = MaybeUninit::<libc::sigset_t>::uninit();
+                        libc::sigemptyset(mask.as_mut_ptr());
+                        libc::sigaddset(mask.as_mut_ptr(), libc::SIGUSR1);
+
+                        fds.push(libc::eventfd(0, flags));
+                        fds.push(libc::signalfd(-1, mask.as_ptr(), flags));
+                        fds.push(libc::timerfd_create(libc::CLOCK_MONOTONIC, flags));
+                        fds.push(libc::epoll_create1(flags));
+                    }
+                }
+                for &fd in fds.iter() {
+                    assert!(fd >= 0);
+                    // the kernel applied the close-on-exec flag as well
+                    let fd_flags = nix::fcntl::fcntl(fd, nix::fcntl::FcntlArg::F_GETFD).unwrap();
+                    assert_eq!(fd_flags & libc::FD_CLOEXEC != 0, fds[4..].contains(&fd));
+                }
+                for &fd in fds.iter() {
+                    unistd::close(fd).unwrap();
+                }
+            },
+        )
+    }
+}
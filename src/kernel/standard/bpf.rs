@@ -0,0 +1,155 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::prelude::RawFd;
+use std::path::PathBuf;
+
+use libc::{c_int, c_void};
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::filesystem::Translator;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, PtraceReader, PtraceWriter, SysArg, SysArg1, SysArg2, SysArg3};
+
+/// Commands of bpf() whose `attr` holds a path of the bpf filesystem, see
+/// `include/uapi/linux/bpf.h`.
+const BPF_OBJ_PIN: c_int = 6;
+const BPF_OBJ_GET: c_int = 7;
+/// Flag of `file_flags`, making a relative `pathname` relative to `path_fd`.
+const BPF_F_PATH_FD: u32 = 1 << 14;
+
+/// Layout of the `BPF_OBJ_*` part of `union bpf_attr`: the pointer to the
+/// path, `bpf_fd`, `file_flags` and `path_fd`.
+const PATHNAME_OFFSET: usize = 0;
+const FILE_FLAGS_OFFSET: usize = 12;
+const PATH_FD_OFFSET: usize = 16;
+/// Size of the `BPF_OBJ_*` part of `union bpf_attr`, the shorter ones
+/// (older kernels) having no `path_fd`.
+const OBJ_ATTR_SIZE: usize = 20;
+
+fn read_u32(attr: &[u8], offset: usize) -> Option<u32> {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(attr.get(offset..offset + 4)?);
+    Some(u32::from_ne_bytes(bytes))
+}
+
+/// bpf(cmd, attr, size): with `BPF_OBJ_PIN` and `BPF_OBJ_GET`, `attr` points
+/// to the path of an object pinned in a bpf filesystem, which is translated.
+///
+/// The `attr` of the tracee is left untouched: a copy of it pointing to the
+/// host path is written on the stack, and given to the kernel instead. The
+/// other commands are passed through.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    let cmd = tracee.regs.get(Current, SysArg(SysArg1)) as c_int;
+    if cmd != BPF_OBJ_PIN && cmd != BPF_OBJ_GET {
+        return Ok(());
+    }
+
+    let attr_address = tracee.regs.get(Current, SysArg(SysArg2)) as *const c_void;
+    let size = (tracee.regs.get(Current, SysArg(SysArg3)) as usize).min(OBJ_ATTR_SIZE);
+    if attr_address.is_null() || size < FILE_FLAGS_OFFSET {
+        // let the kernel report the error
+        return Ok(());
+    }
+    let mut attr = tracee.regs.read_data(attr_address, size)?;
+
+    let mut pathname_bytes = [0u8; 8];
+    pathname_bytes.copy_from_slice(&attr[PATHNAME_OFFSET..PATHNAME_OFFSET + 8]);
+    let pathname_address = u64::from_ne_bytes(pathname_bytes) as *const c_void;
+    if pathname_address.is_null() {
+        return Ok(());
+    }
+    let raw_path = tracee
+        .regs
+        .read_string(pathname_address, tracee.regs.get_path_max())?;
+    let mut guest_path = PathBuf::from(OsStr::from_bytes(&raw_path));
+
+    let file_flags = read_u32(&attr, FILE_FLAGS_OFFSET).unwrap_or(0);
+    if guest_path.is_relative() && file_flags & BPF_F_PATH_FD != 0 {
+        if let Some(path_fd) = read_u32(&attr, PATH_FD_OFFSET) {
+            guest_path = tracee
+                .get_path_from_fd(path_fd as RawFd, Side::Guest)?
+                .join(guest_path);
+        }
+    }
+
+    // the final component is created by BPF_OBJ_PIN, so it isn't dereferenced
+    let host_path = tracee
+        .fs
+        .borrow()
+        .translate_path(&guest_path, cmd == BPF_OBJ_GET)?;
+
+    let mut host_path_bytes = host_path.as_os_str().as_bytes().to_vec();
+    host_path_bytes.push(0);
+    let host_path_address = tracee.regs.write_scratch(&host_path_bytes)?;
+    attr[PATHNAME_OFFSET..PATHNAME_OFFSET + 8]
+        .copy_from_slice(&(host_path_address as u64).to_ne_bytes());
+
+    let attr_copy_address = tracee.regs.write_scratch(&attr)?;
+    tracee.regs.set(
+        SysArg(SysArg2),
+        attr_copy_address,
+        "during enter bpf translation, pointing to a copy of attr with the host path",
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::path::Path;
+
+    use sc::nr::BPF;
+
+    use super::*;
+    use crate::register::Original;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot};
+
+    #[test]
+    fn test_bpf_obj_pin_translated() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation || tracee.regs.get_sys_num(Original) != BPF {
+                    return;
+                }
+                let attr_address = tracee.regs.get(Current, SysArg(SysArg2)) as *const c_void;
+                assert_ne!(
+                    attr_address,
+                    tracee.regs.get(Original, SysArg(SysArg2)) as *const c_void
+                );
+                let attr = tracee.regs.read_data(attr_address, OBJ_ATTR_SIZE).unwrap();
+                let mut pathname_bytes = [0u8; 8];
+                pathname_bytes.copy_from_slice(&attr[..8]);
+                let pathname = tracee
+                    .regs
+                    .read_string(u64::from_ne_bytes(pathname_bytes) as *const c_void, 4096)
+                    .unwrap();
+                assert_eq!(
+                    Path::new(OsStr::from_bytes(&pathname)),
+                    get_test_rootfs_path().join("tmp/proot_rs_test_bpf_pin")
+                );
+                // the other fields are copied
+                assert_eq!(read_u32(&attr, 8), Some(u32::MAX));
+            },
+            || {
+                let pathname = CString::new("/tmp/proot_rs_test_bpf_pin").unwrap();
+                let mut attr = [0u8; OBJ_ATTR_SIZE];
+                attr[..8].copy_from_slice(&(pathname.as_ptr() as u64).to_ne_bytes());
+                // a dummy object, the pin fails
+                attr[8..12].copy_from_slice(&u32::MAX.to_ne_bytes());
+
+                let result = unsafe {
+                    libc::syscall(
+                        BPF as libc::c_long,
+                        BPF_OBJ_PIN,
+                        attr.as_ptr(),
+                        attr.len() as libc::c_uint,
+                    )
+                };
+                assert_eq!(result, -1);
+                // the attr of the tracee is untouched
+                assert_eq!(&attr[..8], &(pathname.as_ptr() as u64).to_ne_bytes());
+            },
+        )
+    }
+}
@@ -1,6 +1,7 @@
 use std::os::unix::prelude::RawFd;
 
 use crate::errors::*;
+use crate::kernel::groups::modifies_paths;
 use crate::process::tracee::Tracee;
 use crate::register::PtraceWriter;
 use crate::register::{Current, PtraceReader, SysArg, SysArg1, SysArg2};
@@ -16,7 +17,11 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
         _ => true,
     };
 
-    let host_path = tracee.translate_path_at(dirfd, raw_path, deref_final)?;
+    let host_path = if modifies_paths(sys_num) {
+        tracee.translate_path_at_for_write(dirfd, raw_path, deref_final)?
+    } else {
+        tracee.translate_path_at(dirfd, raw_path, deref_final)?
+    };
 
     tracee.regs.set_sysarg_path(
         SysArg2,
@@ -33,7 +38,7 @@ mod tests {
 
     use nix::{fcntl::OFlag, sys::stat::Mode};
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - FCHMODAT
@@ -123,4 +128,29 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_chmod_access_mknod_at_read_only_binding() {
+        test_with_read_only_binding("chmod_access_mknod_at", || {
+            let fd = nix::fcntl::open("/mnt/ro", OFlag::O_RDONLY, Mode::empty()).unwrap();
+            assert_eq!(nc::fchmodat(fd, "file", 0o600), Err(nc::EROFS));
+            assert_eq!(
+                nc::mknodat(fd, "node", nc::S_IFREG | nc::S_IRUSR, 0),
+                Err(nc::EROFS)
+            );
+            let time = [
+                nc::timeval_t {
+                    tv_sec: 100,
+                    tv_usec: 0,
+                },
+                nc::timeval_t {
+                    tv_sec: 10,
+                    tv_usec: 0,
+                },
+            ];
+            assert_eq!(nc::futimesat(fd, "file", &time), Err(nc::EROFS));
+            // the reads are allowed
+            assert_eq!(nc::faccessat(fd, "file", nc::R_OK), Ok(()));
+        })
+    }
 }
@@ -0,0 +1,94 @@
+use crate::errors::*;
+use crate::kernel::standard::pivot_root::get_canonical_guest_path;
+use crate::kernel::standard::standard_syscall;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{SysArg1, SysResult};
+
+/// chroot(path) requires `CAP_SYS_CHROOT`, so it can be emulated
+/// (`emulate_chroot`) by changing the guest root of the tracee, instead of
+/// being sent to the host with its path translated.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if info_bag.config.emulate_chroot {
+        let new_root = get_canonical_guest_path(tracee, SysArg1)?;
+
+        tracee.fs.borrow_mut().chroot(&new_root)?;
+        tracee
+            .regs
+            .cancel_syscall("chroot() is emulated, avoid syscall");
+        return Ok(());
+    }
+
+    standard_syscall::enter(tracee)
+}
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.is_syscall_cancelled() {
+        tracee
+            .regs
+            .set(SysResult, 0, "chroot() was emulated successfully");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use nix::errno::Errno;
+    use nix::unistd;
+
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    fn test_chroot_emulation_nested() {
+        let outer_root = get_test_rootfs_path().join("tmp/dir_for_test_chroot");
+        fs::create_dir_all(outer_root.join("inner")).unwrap();
+        fs::write(outer_root.join("inner/marker"), "").unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .emulate_chroot(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                assert_eq!(fs::read_link("/proc/self/root").unwrap(), Path::new("/"));
+                assert_eq!(
+                    unistd::chroot("/tmp/dir_for_test_chroot/inner/marker"),
+                    Err(nix::Error::Sys(Errno::ENOTDIR))
+                );
+
+                unistd::chroot("/tmp/dir_for_test_chroot").unwrap();
+                assert_eq!(
+                    fs::read_link("/proc/self/root").unwrap(),
+                    Path::new("/tmp/dir_for_test_chroot")
+                );
+                assert!(Path::new("/inner/marker").exists());
+                assert!(!Path::new("/bin").exists());
+
+                unistd::chdir("/inner").unwrap();
+                unistd::chroot(".").unwrap();
+                assert_eq!(
+                    fs::read_link("/proc/self/root").unwrap(),
+                    Path::new("/tmp/dir_for_test_chroot/inner")
+                );
+                assert_eq!(unistd::getcwd().unwrap(), Path::new("/"));
+                assert!(Path::new("/marker").exists());
+
+                // ".." is clamped at the current root, the previous ones can't
+                // be reached again
+                unistd::chroot("../..").unwrap();
+                assert_eq!(
+                    fs::read_link("/proc/self/root").unwrap(),
+                    Path::new("/tmp/dir_for_test_chroot/inner")
+                );
+                assert!(Path::new("/marker").exists());
+            },
+        );
+
+        fs::remove_dir_all(&outer_root).unwrap();
+    }
+}
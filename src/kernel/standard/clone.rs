@@ -0,0 +1,23 @@
+use crate::config::TraceeLimitPolicy;
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+
+/// fork(), vfork(), clone() and clone3() have no path argument, and are
+/// passed through, unless there are already `max_tracees` tracees with the
+/// `Fail` policy: the new process would be traced, so its creation fails
+/// with `EAGAIN`. With the `Untrace` policy, the new process is detached by
+/// the event loop instead.
+pub fn enter(info_bag: &InfoBag) -> Result<()> {
+    match info_bag.config.max_tracees {
+        Some(max_tracees)
+            if info_bag.tracee_count.get() >= max_tracees
+                && info_bag.config.tracee_limit_policy == TraceeLimitPolicy::Fail =>
+        {
+            Err(Error::errno_with_msg(
+                EAGAIN,
+                format!("the maximum number of tracees ({}) is reached", max_tracees),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
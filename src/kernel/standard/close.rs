@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::os::unix::prelude::RawFd;
+use std::rc::Rc;
+
+use crate::errors::*;
+use crate::kernel::groups::nr::CLOSE_RANGE;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, SysArg, SysArg1, SysArg2, SysArg3, SysResult};
+
+/// Flags of close_range(), see close_range(2).
+const CLOSE_RANGE_UNSHARE: u32 = 1 << 1;
+const CLOSE_RANGE_CLOEXEC: u32 = 1 << 2;
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.get_sys_num(Original) == CLOSE_RANGE {
+        return close_range_exit(tracee);
+    }
+
+    let fd = tracee.regs.get(Original, SysArg(SysArg1)) as RawFd;
+    let result = tracee.regs.get(Current, SysResult) as i32;
+
+    // The file descriptor is released even if close() was interrupted by a
+    // signal, see close(2).
+    if result == 0 || result == -(EINTR as i32) {
+        tracee.fd_table.borrow_mut().remove(fd);
+    }
+    Ok(())
+}
+
+/// close_range(first, last, flags) closes the file descriptors from `first`
+/// to `last` (included), or only marks them close-on-exec with
+/// `CLOSE_RANGE_CLOEXEC`.
+fn close_range_exit(tracee: &mut Tracee) -> Result<()> {
+    let result = tracee.regs.get(Current, SysResult) as i32;
+    if result != 0 {
+        return Ok(());
+    }
+
+    // The arguments are unsigned, e.g. `last` is often `~0U`.
+    let to_fd = |value: u32| value.min(RawFd::MAX as u32) as RawFd;
+    let first = to_fd(tracee.regs.get(Original, SysArg(SysArg1)) as u32);
+    let last = to_fd(tracee.regs.get(Original, SysArg(SysArg2)) as u32);
+    let flags = tracee.regs.get(Original, SysArg(SysArg3)) as u32;
+
+    // The table of the other tracees isn't affected anymore.
+    if flags & CLOSE_RANGE_UNSHARE != 0 {
+        let fd_table = tracee.fd_table.borrow().clone();
+        tracee.fd_table = Rc::new(RefCell::new(fd_table));
+    }
+
+    if flags & CLOSE_RANGE_CLOEXEC != 0 {
+        tracee.fd_table.borrow_mut().set_cloexec_range(first, last);
+    } else {
+        tracee.fd_table.borrow_mut().remove_range(first, last);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use nix::fcntl::{self, FcntlArg, FdFlag, OFlag};
+    use nix::sys::stat::Mode;
+
+    use super::*;
+    use crate::utils::tests::test_with_proot;
+
+    fn close_range(first: u32, last: u32, flags: u32) {
+        let result = unsafe { libc::syscall(CLOSE_RANGE as libc::c_long, first, last, flags) };
+        assert_eq!(result, 0);
+    }
+
+    #[test]
+    fn test_close_range() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != CLOSE_RANGE
+                {
+                    return;
+                }
+                let first = tracee.regs.get(Original, SysArg(SysArg1)) as RawFd;
+                let last = tracee.regs.get(Original, SysArg(SysArg2)) as u32;
+                let flags = tracee.regs.get(Original, SysArg(SysArg3)) as u32;
+                let fd_table = tracee.fd_table.borrow();
+                if flags & CLOSE_RANGE_CLOEXEC != 0 {
+                    // the fds are marked, not closed
+                    assert!(fd_table.get(first).unwrap().cloexec);
+                } else if last != u32::MAX {
+                    assert!((first..=last as RawFd).all(|fd| fd_table.get(fd).is_none()));
+                }
+            },
+            || {
+                let fds: Vec<RawFd> = (0..6)
+                    .map(|_| fcntl::open("/etc/passwd", OFlag::O_RDONLY, Mode::empty()).unwrap())
+                    .collect();
+                let readlink_fd =
+                    |fd: RawFd| fcntl::readlink(format!("/proc/self/fd/{}", fd).as_str());
+
+                // close the 2nd to the 4th fds
+                close_range(fds[1] as u32, fds[3] as u32, 0);
+                for &fd in &fds[1..=3] {
+                    // a stale entry would still be resolved by proot-rs
+                    assert!(readlink_fd(fd).is_err());
+                }
+                for &fd in [fds[0], fds[4], fds[5]].iter() {
+                    assert_eq!(
+                        PathBuf::from(readlink_fd(fd).unwrap()),
+                        PathBuf::from("/etc/passwd")
+                    );
+                }
+
+                // the last fds are only marked close-on-exec
+                close_range(fds[4] as u32, u32::MAX, CLOSE_RANGE_CLOEXEC);
+                for &fd in &fds[4..] {
+                    let fd_flags =
+                        FdFlag::from_bits_truncate(fcntl::fcntl(fd, FcntlArg::F_GETFD).unwrap());
+                    assert!(fd_flags.contains(FdFlag::FD_CLOEXEC));
+                    assert!(readlink_fd(fd).is_ok());
+                }
+
+                close_range(fds[0] as u32, u32::MAX, 0);
+                assert!(readlink_fd(fds[0]).is_err());
+            },
+        )
+    }
+}
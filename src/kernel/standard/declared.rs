@@ -0,0 +1,198 @@
+use std::os::unix::prelude::RawFd;
+
+use libc::{AT_FDCWD, AT_SYMLINK_NOFOLLOW};
+use sc::nr::STATFS;
+
+use crate::errors::*;
+use crate::kernel::groups::{modifies_paths, nr};
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceReader, PtraceWriter, SysArg, SysArgIndex};
+use crate::register::{SysArg1, SysArg2, SysArg4};
+
+/// Kind of an argument of a syscall declared in `DECLARED_SYSCALLS`.
+#[derive(Debug, Clone, Copy)]
+pub enum ArgKind {
+    /// A path, translated relatively to the cwd, or to the directory file
+    /// descriptor in `dirfd`. Its final component is dereferenced if
+    /// `deref_final` is set, unless the flags in `flags` hold
+    /// `AT_SYMLINK_NOFOLLOW`.
+    Path {
+        dirfd: Option<SysArgIndex>,
+        flags: Option<SysArgIndex>,
+        deref_final: bool,
+    },
+    /// A file descriptor, whose path was translated when it was opened, so
+    /// it's passed through.
+    Fd,
+}
+
+/// The syscalls whose arguments are only translated as declared by their
+/// kinds, without a handler of their own. The arguments which are not
+/// declared are passed through.
+///
+/// Only the simple syscalls belong here, the others get their own handlers.
+const DECLARED_SYSCALLS: &[(usize, &[(SysArgIndex, ArgKind)])] = &[
+    // int statfs(const char *path, struct statfs *buf)
+    (
+        STATFS,
+        &[(
+            SysArg1,
+            ArgKind::Path {
+                dirfd: None,
+                flags: None,
+                deref_final: true,
+            },
+        )],
+    ),
+    // int faccessat2(int dirfd, const char *path, int mode, int flags)
+    (
+        nr::FACCESSAT2,
+        &[
+            (SysArg1, ArgKind::Fd),
+            (
+                SysArg2,
+                ArgKind::Path {
+                    dirfd: Some(SysArg1),
+                    flags: Some(SysArg4),
+                    deref_final: true,
+                },
+            ),
+        ],
+    ),
+    // int cachestat(unsigned int fd, struct cachestat_range *range,
+    //               struct cachestat *cstat, unsigned int flags)
+    (nr::CACHESTAT, &[(SysArg1, ArgKind::Fd)]),
+    // int fchmodat2(int dirfd, const char *path, mode_t mode, int flags)
+    (
+        nr::FCHMODAT2,
+        &[
+            (SysArg1, ArgKind::Fd),
+            (
+                SysArg2,
+                ArgKind::Path {
+                    dirfd: Some(SysArg1),
+                    flags: Some(SysArg4),
+                    deref_final: true,
+                },
+            ),
+        ],
+    ),
+];
+
+/// Returns the declared arguments of the syscall numbered `sysnum`, if it's
+/// in `DECLARED_SYSCALLS`.
+pub fn get_declared_args(sysnum: usize) -> Option<&'static [(SysArgIndex, ArgKind)]> {
+    DECLARED_SYSCALLS
+        .iter()
+        .find(|(declared_sysnum, _)| *declared_sysnum == sysnum)
+        .map(|(_, args)| *args)
+}
+
+/// Translates the path arguments of a syscall of `DECLARED_SYSCALLS`.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    let sys_num = tracee.regs.get_sys_num(Original);
+    let args = match get_declared_args(sys_num) {
+        Some(args) => args,
+        None => return Ok(()),
+    };
+
+    for &(index, kind) in args {
+        let (dirfd, flags, deref_final) = match kind {
+            ArgKind::Path {
+                dirfd,
+                flags,
+                deref_final,
+            } => (dirfd, flags, deref_final),
+            ArgKind::Fd => continue,
+        };
+        let dirfd = dirfd.map_or(AT_FDCWD, |dirfd| {
+            tracee.regs.get(Current, SysArg(dirfd)) as RawFd
+        });
+        let nofollow = flags.map_or(false, |flags| {
+            tracee.regs.get(Current, SysArg(flags)) as i32 & AT_SYMLINK_NOFOLLOW != 0
+        });
+
+        let raw_path = tracee.regs.get_sysarg_path(index)?;
+        let deref_final = deref_final && !nofollow;
+        let host_path = if modifies_paths(sys_num) {
+            tracee.translate_path_at_for_write(dirfd, raw_path, deref_final)?
+        } else {
+            tracee.translate_path_at(dirfd, raw_path, deref_final)?
+        };
+        tracee.regs.set_sysarg_path(
+            index,
+            &host_path,
+            "during enter translation of a declared syscall, setting host path",
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+
+    use super::*;
+    use crate::kernel::groups::{syscall_group_from_sysnum, SyscallGroup};
+    use crate::kernel::syscall;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot};
+
+    #[test]
+    fn test_declared_syscalls_consistent() {
+        for &(sysnum, _) in DECLARED_SYSCALLS {
+            assert_eq!(syscall_group_from_sysnum(sysnum), SyscallGroup::Declared);
+            assert!(syscall::name_of_syscall(sysnum).is_some());
+        }
+        assert!(get_declared_args(sc::nr::OPENAT).is_none());
+    }
+
+    #[test]
+    fn test_declared_syscalls_translated() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation {
+                    return;
+                }
+                let sys_num = tracee.regs.get_sys_num(Original);
+                if sys_num == STATFS {
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg1).unwrap(),
+                        get_test_rootfs_path().join("tmp")
+                    );
+                } else if sys_num == nr::FACCESSAT2 {
+                    // relative to the dirfd, which is left untouched
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg2).unwrap(),
+                        get_test_rootfs_path().join("etc/passwd")
+                    );
+                    assert_eq!(
+                        tracee.regs.get(Current, SysArg(SysArg1)),
+                        tracee.regs.get(Original, SysArg(SysArg1))
+                    );
+                }
+            },
+            || {
+                let path = CString::new("/tmp").unwrap();
+                let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+                assert_eq!(unsafe { libc::statfs(path.as_ptr(), &mut buf) }, 0);
+
+                let dirfd = fcntl::open("/etc", OFlag::O_RDONLY, Mode::empty()).unwrap();
+                let path = CString::new("passwd").unwrap();
+                let result = unsafe {
+                    libc::syscall(
+                        nr::FACCESSAT2 as libc::c_long,
+                        dirfd,
+                        path.as_ptr(),
+                        libc::R_OK,
+                        0,
+                    )
+                };
+                // the syscall is recent
+                assert!(result == 0 || nix::errno::Errno::last() == nix::errno::Errno::ENOSYS);
+            },
+        )
+    }
+}
@@ -1,14 +1,22 @@
 use crate::errors::*;
 
 use crate::filesystem::Translator;
+use crate::kernel::groups::modifies_paths;
 use crate::process::tracee::Tracee;
 use crate::register::PtraceWriter;
-use crate::register::{PtraceReader, SysArg1};
+use crate::register::{Current, PtraceReader, SysArg1};
 
 pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let raw_path = tracee.regs.get_sysarg_path(SysArg1)?;
 
-    let host_path = tracee.fs.borrow().translate_path(raw_path, false)?;
+    let host_path = if modifies_paths(tracee.regs.get_sys_num(Current)) {
+        tracee
+            .fs
+            .borrow()
+            .translate_path_for_write(raw_path, false)?
+    } else {
+        tracee.fs.borrow().translate_path(raw_path, false)?
+    };
 
     tracee.regs.set_sysarg_path(
         SysArg1,
@@ -22,7 +30,7 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
 mod tests {
     use std::fs::File;
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - lsetxattr
@@ -152,4 +160,30 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_dir_link_attr_read_only_binding() {
+        test_with_read_only_binding("dir_link_attr", || {
+            assert_eq!(nc::mkdir("/mnt/ro/new_dir", 0o755), Err(nc::EROFS));
+            assert_eq!(nc::rmdir("/mnt/ro/dir"), Err(nc::EROFS));
+            assert_eq!(nc::unlink("/mnt/ro/file"), Err(nc::EROFS));
+            let attr_value = "value";
+            assert_eq!(
+                nc::lsetxattr(
+                    "/mnt/ro/file",
+                    "user.proot-rs-unit-test",
+                    attr_value.as_ptr() as usize,
+                    attr_value.len(),
+                    0,
+                ),
+                Err(nc::EROFS)
+            );
+            assert_eq!(
+                nc::lremovexattr("/mnt/ro/file", "user.proot-rs-unit-test"),
+                Err(nc::EROFS)
+            );
+            // the reads are allowed
+            assert!(std::fs::symlink_metadata("/mnt/ro/file").unwrap().is_file());
+        })
+    }
 }
@@ -0,0 +1,31 @@
+use std::os::unix::prelude::RawFd;
+
+use nix::fcntl::OFlag;
+use sc::nr::DUP3;
+
+use crate::errors::*;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, SysArg, SysArg1, SysArg3, SysResult};
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let new_fd = tracee.regs.get(Current, SysResult) as RawFd;
+    if new_fd < 0 {
+        return Ok(());
+    }
+
+    let old_fd = tracee.regs.get(Original, SysArg(SysArg1)) as RawFd;
+    // dup2(fd, fd) does nothing, not even clearing the close-on-exec flag.
+    if old_fd == new_fd {
+        return Ok(());
+    }
+
+    let cloexec = tracee.regs.get_sys_num(Original) == DUP3
+        && OFlag::from_bits_truncate(tracee.regs.get(Original, SysArg(SysArg3)) as _)
+            .contains(OFlag::O_CLOEXEC);
+
+    tracee
+        .fd_table
+        .borrow_mut()
+        .duplicate(old_fd, new_fd, cloexec);
+    Ok(())
+}
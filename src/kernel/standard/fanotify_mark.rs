@@ -0,0 +1,98 @@
+use std::os::unix::prelude::RawFd;
+
+use libc::c_uint;
+
+use crate::errors::*;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, PtraceReader, PtraceWriter, SysArg, SysArg2, SysArg4, SysArg5};
+
+/// Don't dereference `pathname` if it's a symbolic link, see fanotify_mark(2).
+const FAN_MARK_DONT_FOLLOW: c_uint = 0x0000_0004;
+
+/// fanotify_mark(fanotify_fd, flags, mask, dirfd, pathname): `pathname` is
+/// resolved against `dirfd`, or `dirfd` itself is marked if it's NULL.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.get(Current, SysArg(SysArg5)) == 0 {
+        return Ok(());
+    }
+
+    let flags = tracee.regs.get(Current, SysArg(SysArg2)) as c_uint;
+    let dirfd = tracee.regs.get(Current, SysArg(SysArg4)) as RawFd;
+    let raw_path = tracee.regs.get_sysarg_path(SysArg5)?;
+
+    let deref_final = flags & FAN_MARK_DONT_FOLLOW == 0;
+    let host_path = tracee.translate_path_at(dirfd, raw_path, deref_final)?;
+
+    tracee.regs.set_sysarg_path(
+        SysArg5,
+        &host_path,
+        "during enter fanotify_mark translation, setting host path",
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io::Write;
+
+    use libc::{c_int, c_uint, SYS_fanotify_init, SYS_fanotify_mark, AT_FDCWD, O_RDONLY};
+    use nix::errno::Errno;
+    use nix::unistd;
+
+    use crate::utils::tests::test_with_proot;
+
+    const FAN_CLASS_NOTIF: c_uint = 0x0000_0000;
+    const FAN_MARK_ADD: c_uint = 0x0000_0001;
+    const FAN_MODIFY: u64 = 0x0000_0002;
+
+    /// Unit test for fanotify_mark(), which requires `CAP_SYS_ADMIN` on older
+    /// kernels: the test is skipped if fanotify isn't available.
+    #[test]
+    fn test_fanotify_mark() {
+        test_with_proot(
+            |_tracee, _is_sysenter, _before_translation| {},
+            || {
+                let fanotify_fd =
+                    unsafe { libc::syscall(SYS_fanotify_init, FAN_CLASS_NOTIF, O_RDONLY) } as c_int;
+                if fanotify_fd < 0 {
+                    return;
+                }
+
+                // this file only exists in the guest rootfs
+                let filepath = "/tmp/file_for_test_fanotify_mark";
+                let mut file = File::create(filepath).unwrap();
+
+                let result = std::panic::catch_unwind(|| {
+                    let path = CString::new(filepath).unwrap();
+                    let result = unsafe {
+                        libc::syscall(
+                            SYS_fanotify_mark,
+                            fanotify_fd,
+                            FAN_MARK_ADD,
+                            FAN_MODIFY,
+                            AT_FDCWD,
+                            path.as_ptr(),
+                        )
+                    };
+                    assert_eq!(result, 0, "fanotify_mark: {}", Errno::last());
+
+                    // an event fires when the host file underneath is modified
+                    file.write_all(b"proot").unwrap();
+                    let mut event = [0u8; 256];
+                    let size = unistd::read(fanotify_fd, &mut event).unwrap();
+                    assert!(size >= 24);
+                    let mut mask = [0u8; 8];
+                    mask.copy_from_slice(&event[8..16]);
+                    assert_ne!(u64::from_ne_bytes(mask) & FAN_MODIFY, 0);
+                });
+                std::fs::remove_file(filepath).unwrap();
+                if let Err(err) = result {
+                    std::panic::resume_unwind(err);
+                }
+            },
+        )
+    }
+}
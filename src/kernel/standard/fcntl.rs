@@ -0,0 +1,27 @@
+use std::os::unix::prelude::RawFd;
+
+use libc::{c_int, FD_CLOEXEC, F_DUPFD, F_DUPFD_CLOEXEC, F_SETFD};
+
+use crate::errors::*;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, SysArg, SysArg1, SysArg2, SysArg3, SysResult};
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let result = tracee.regs.get(Current, SysResult) as RawFd;
+    if result < 0 {
+        return Ok(());
+    }
+
+    let fd = tracee.regs.get(Original, SysArg(SysArg1)) as RawFd;
+    let cmd = tracee.regs.get(Original, SysArg(SysArg2)) as c_int;
+    let arg = tracee.regs.get(Original, SysArg(SysArg3)) as c_int;
+
+    let mut fd_table = tracee.fd_table.borrow_mut();
+    match cmd {
+        F_DUPFD => fd_table.duplicate(fd, result, false),
+        F_DUPFD_CLOEXEC => fd_table.duplicate(fd, result, true),
+        F_SETFD => fd_table.set_cloexec(fd, arg & FD_CLOEXEC != 0),
+        _ => {}
+    }
+    Ok(())
+}
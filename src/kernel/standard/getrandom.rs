@@ -0,0 +1,124 @@
+use libc::c_void;
+
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{Original, PtraceWriter, SysArg, SysArg1, SysArg2, SysResult, Word};
+
+/// Maximum number of bytes returned by one seeded getrandom(), which may
+/// return less bytes than requested, like the kernel does for large buffers.
+const MAX_RANDOM_BYTES: usize = 1 << 20;
+
+/// Returns the next 64 bits of the SplitMix64 generator of `state`, which is
+/// advanced.
+fn next_random_word(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Returns the next `count` bytes of the generator of `state`.
+fn random_bytes(state: &mut u64, count: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(count + 8);
+    while bytes.len() < count {
+        bytes.extend_from_slice(&next_random_word(state).to_le_bytes());
+    }
+    bytes.truncate(count);
+    bytes
+}
+
+/// getrandom(buf, count, flags) has no path argument, and is passed through,
+/// unless `random_seed` is set: the syscall is then cancelled, and its
+/// buffer is filled in `exit()`.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if info_bag.config.random_seed.is_some() {
+        tracee
+            .regs
+            .cancel_syscall("getrandom() is seeded, avoid syscall");
+    }
+    Ok(())
+}
+
+/// Fills the buffer of a seeded getrandom() from the generator shared by all
+/// the tracees, so that a same seed gives the same bytes in the same order
+/// of calls.
+pub fn exit(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if !tracee.regs.is_syscall_cancelled() {
+        return Ok(());
+    }
+
+    let buffer = tracee.regs.get(Original, SysArg(SysArg1)) as *mut c_void;
+    let count = (tracee.regs.get(Original, SysArg(SysArg2)) as usize).min(MAX_RANDOM_BYTES);
+
+    let mut state = info_bag.random_state.get();
+    let bytes = random_bytes(&mut state, count);
+    tracee
+        .regs
+        .write_data(buffer, &bytes, false)
+        .map_err(|_| Error::errno(EFAULT))?;
+    info_bag.random_state.set(state);
+
+    tracee.regs.set(
+        SysResult,
+        count as Word,
+        "getrandom() is seeded, simulate its result",
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+
+    use nix::unistd;
+
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    /// Runs a tracee calling getrandom() twice under `config`, and returns
+    /// the bytes it got.
+    fn run_getrandom(config: ProotConfig) -> Vec<u8> {
+        let (reader, writer) = unistd::pipe().unwrap();
+        test_with_proot_config(
+            config,
+            |_, _, _| {},
+            move || {
+                let mut buffer = [0u8; 48];
+                for chunk in buffer.chunks_mut(24) {
+                    let result = unsafe {
+                        libc::syscall(libc::SYS_getrandom, chunk.as_mut_ptr(), chunk.len(), 0)
+                    };
+                    assert_eq!(result, chunk.len() as libc::c_long);
+                }
+                unistd::write(writer, &buffer).unwrap();
+            },
+        );
+        unistd::close(writer).unwrap();
+
+        let mut bytes = vec![];
+        unsafe { File::from_raw_fd(reader) }
+            .read_to_end(&mut bytes)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_getrandom_seeded() {
+        let config = || {
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .random_seed(42)
+                .build()
+        };
+
+        let bytes = run_getrandom(config());
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(bytes, run_getrandom(config()));
+        assert_eq!(bytes, random_bytes(&mut 42, 48));
+    }
+}
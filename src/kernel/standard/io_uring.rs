@@ -0,0 +1,60 @@
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+
+/// io_uring_setup(entries, params) creates a ring, whose submissions are read
+/// by the kernel from memory shared with the tracee: the paths of operations
+/// like `IORING_OP_OPENAT` are never seen by proot-rs, and would be resolved
+/// against the host root. Checking the submissions on io_uring_enter() isn't
+/// enough either, since a ring created with `IORING_SETUP_SQPOLL` is polled
+/// by a kernel thread without any syscall.
+///
+/// So the rings are only detected and logged, unless `strict_io_uring` is
+/// enabled, in which case they can't be created at all. Programs using
+/// io_uring usually fall back to the regular syscalls.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if info_bag.config.strict_io_uring {
+        return Err(Error::errno_with_msg(
+            EOPNOTSUPP,
+            "io_uring_setup() is denied, the path operations of io_uring can't be translated",
+        ));
+    }
+
+    warn!(
+        "-- {}, io_uring_setup(): the paths submitted to this ring won't be translated",
+        tracee.pid
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::errno::Errno;
+
+    use crate::config::ProotConfig;
+    use crate::kernel::groups::nr::IO_URING_SETUP;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    /// Size of `struct io_uring_params`.
+    const IO_URING_PARAMS_SIZE: usize = 120;
+
+    #[test]
+    fn test_io_uring_setup_strict() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .strict_io_uring(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // without a ring, no IORING_OP_OPENAT can be submitted
+                let mut params = [0u8; IO_URING_PARAMS_SIZE];
+                let result = unsafe {
+                    libc::syscall(IO_URING_SETUP as libc::c_long, 8, params.as_mut_ptr())
+                };
+                assert_eq!(result, -1);
+                assert_eq!(Errno::last(), Errno::EOPNOTSUPP);
+            },
+        )
+    }
+}
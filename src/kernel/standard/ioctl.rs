@@ -0,0 +1,224 @@
+use std::os::unix::prelude::RawFd;
+
+use libc::{c_ulong, c_void};
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, PtraceReader, SysArg, SysArg1, SysArg2, SysArg3};
+
+/// Commands of the loop devices giving them a backing file, see
+/// `include/uapi/linux/loop.h`.
+const LOOP_SET_FD: c_ulong = 0x4C00;
+const LOOP_CHANGE_FD: c_ulong = 0x4C06;
+const LOOP_CONFIGURE: c_ulong = 0x4C0A;
+/// Flag of `lo_flags` making the loop device read-only.
+const LO_FLAGS_READ_ONLY: u32 = 1;
+/// Offsets of `fd` and `info.lo_flags` in `struct loop_config`.
+const LOOP_CONFIG_FD_OFFSET: usize = 0;
+const LOOP_CONFIG_FLAGS_OFFSET: usize = 60;
+
+/// Commands sharing the extents of a source file with the file of the ioctl,
+/// see `include/uapi/linux/fs.h`.
+const FICLONE: c_ulong = 0x4004_9409;
+const FICLONERANGE: c_ulong = 0x4020_940d;
+/// Offset of `src_fd` in `struct file_clone_range`.
+const CLONE_RANGE_SRC_FD_OFFSET: usize = 0;
+
+/// Handler of an ioctl() command, called on the enter stage.
+type CommandHandler = fn(&mut Tracee, c_ulong) -> Result<()>;
+
+/// Handlers of the ioctl() commands whose argument holds a path or a file
+/// descriptor. The other commands are passed through.
+const COMMAND_HANDLERS: &[(c_ulong, CommandHandler)] = &[
+    (LOOP_SET_FD, loop_set_fd_enter),
+    (LOOP_CHANGE_FD, loop_set_fd_enter),
+    (LOOP_CONFIGURE, loop_configure_enter),
+    (FICLONE, ficlone_enter),
+    (FICLONERANGE, ficlone_enter),
+];
+
+/// ioctl(fd, cmd, arg) has no path argument, but the `arg` of some commands
+/// holds a path or a file descriptor, which are handled by the handler of
+/// `cmd` in `COMMAND_HANDLERS`.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    let cmd = tracee.regs.get(Current, SysArg(SysArg2)) as c_ulong;
+    match COMMAND_HANDLERS.iter().find(|(command, _)| *command == cmd) {
+        Some((_, handler)) => handler(tracee, cmd),
+        None => Ok(()),
+    }
+}
+
+/// Reads `size` bytes of the structure pointed by `arg`, or returns `None`
+/// if it's null.
+fn read_arg_struct(tracee: &Tracee, size: usize) -> Result<Option<Vec<u8>>> {
+    let address = tracee.regs.get(Current, SysArg(SysArg3)) as *const c_void;
+    if address.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(tracee.regs.read_data(address, size)?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+/// LOOP_SET_FD and LOOP_CHANGE_FD take the backing file as `arg`, which
+/// can't be in a read-only binding. The access mode of the backing fd isn't
+/// tracked, so the loop device is assumed to be writable.
+fn loop_set_fd_enter(tracee: &mut Tracee, _: c_ulong) -> Result<()> {
+    let backing_fd = tracee.regs.get(Current, SysArg(SysArg3)) as RawFd;
+    check_fd_writable(tracee, backing_fd)
+}
+
+/// LOOP_CONFIGURE takes a `struct loop_config`, whose backing file can't be
+/// in a read-only binding, unless the loop device is read-only.
+fn loop_configure_enter(tracee: &mut Tracee, _: c_ulong) -> Result<()> {
+    let config = match read_arg_struct(tracee, LOOP_CONFIG_FLAGS_OFFSET + 4)? {
+        Some(config) => config,
+        // let the kernel report the error
+        None => return Ok(()),
+    };
+    if read_u32(&config, LOOP_CONFIG_FLAGS_OFFSET) & LO_FLAGS_READ_ONLY != 0 {
+        return Ok(());
+    }
+    check_fd_writable(tracee, read_u32(&config, LOOP_CONFIG_FD_OFFSET) as RawFd)
+}
+
+/// FICLONE takes the source fd as `arg`, and FICLONERANGE takes a
+/// `struct file_clone_range` holding it. The file of the ioctl is written,
+/// so it can't be in a read-only binding, and the source has to be a file of
+/// the guest: a source fd which doesn't refer to a guest path is rejected
+/// with `EXDEV`, like a source of another filesystem.
+fn ficlone_enter(tracee: &mut Tracee, cmd: c_ulong) -> Result<()> {
+    let dest_fd = tracee.regs.get(Current, SysArg(SysArg1)) as RawFd;
+    check_fd_writable(tracee, dest_fd)?;
+
+    let src_fd = if cmd == FICLONE {
+        tracee.regs.get(Current, SysArg(SysArg3)) as RawFd
+    } else {
+        match read_arg_struct(tracee, CLONE_RANGE_SRC_FD_OFFSET + 8)? {
+            Some(range) => read_u32(&range, CLONE_RANGE_SRC_FD_OFFSET) as RawFd,
+            None => return Ok(()),
+        }
+    };
+    // the errors of the fds which aren't files are reported by the kernel
+    let is_host_file = tracee.get_path_from_fd(src_fd, Side::Host).is_ok();
+    if is_host_file && tracee.get_guest_path_of_fd(src_fd).is_none() {
+        return Err(Error::errno_with_msg(
+            EXDEV,
+            format!(
+                "the source fd {} of the clone isn't a file of the guest",
+                src_fd
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Fails with `EROFS` if `fd` was opened on a file of a read-only binding.
+fn check_fd_writable(tracee: &Tracee, fd: RawFd) -> Result<()> {
+    match tracee.get_guest_path_of_fd(fd) {
+        Some(guest_path) => tracee.fs.borrow().check_writable(&guest_path, true),
+        // not a file, let the kernel report the error
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    use nix::errno::Errno;
+
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    fn test_ioctl_loop_set_fd_read_only_binding() {
+        let host_dir = std::env::temp_dir().join("proot_rs_test_ioctl_loop_set_fd");
+        std::fs::create_dir_all(&host_dir).unwrap();
+        std::fs::write(host_dir.join("disk.img"), vec![0u8; 4096]).unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .synthetic_mountpoints(true)
+                .binding(&host_dir, "/mnt/rw")
+                .read_only_binding(&host_dir, "/mnt/ro")
+                .build(),
+            |_, _, _| {},
+            || {
+                // the checks are done before the kernel looks at the device,
+                // so any fd can stand for it
+                let device = File::open("/dev/null").unwrap();
+                let loop_set_fd = |path| {
+                    let backing_file = File::open(path).unwrap();
+                    let result = unsafe {
+                        libc::ioctl(
+                            device.as_raw_fd(),
+                            LOOP_SET_FD as _,
+                            backing_file.as_raw_fd(),
+                        )
+                    };
+                    assert_eq!(result, -1);
+                    Errno::last()
+                };
+
+                assert_eq!(loop_set_fd("/mnt/ro/disk.img"), Errno::EROFS);
+                // the same file through the writable binding reaches the kernel
+                assert_ne!(loop_set_fd("/mnt/rw/disk.img"), Errno::EROFS);
+            },
+        );
+
+        std::fs::remove_dir_all(&host_dir).unwrap();
+    }
+
+    #[test]
+    fn test_ioctl_ficlone_bindings() {
+        let host_dir = std::env::temp_dir().join("proot_rs_test_ioctl_ficlone");
+        std::fs::create_dir_all(&host_dir).unwrap();
+        std::fs::write(host_dir.join("src"), vec![1u8; 4096]).unwrap();
+        std::fs::write(host_dir.join("dest"), "").unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .synthetic_mountpoints(true)
+                .binding(&host_dir, "/mnt/rw")
+                .read_only_binding(&host_dir, "/mnt/ro")
+                .build(),
+            |_, _, _| {},
+            || {
+                let source = File::open("/mnt/ro/src").unwrap();
+                let ficlone = |dest: &File| {
+                    let result =
+                        unsafe { libc::ioctl(dest.as_raw_fd(), FICLONE as _, source.as_raw_fd()) };
+                    match result {
+                        0 => None,
+                        _ => Some(Errno::last()),
+                    }
+                };
+
+                // the clone is done by the kernel, if the host filesystem
+                // supports it
+                let dest = std::fs::OpenOptions::new()
+                    .write(true)
+                    .open("/mnt/rw/dest")
+                    .unwrap();
+                let result = ficlone(&dest);
+                assert!(result != Some(Errno::EROFS) && result != Some(Errno::EXDEV));
+
+                // the destination can't be in a read-only binding
+                let dest = File::open("/mnt/ro/dest").unwrap();
+                assert_eq!(ficlone(&dest), Some(Errno::EROFS));
+            },
+        );
+
+        std::fs::remove_dir_all(&host_dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use std::os::unix::prelude::RawFd;
+
+use libc::{c_int, c_void};
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, PtraceReader, SysArg, SysArg2, SysArg3};
+
+/// Type of the rules allowing accesses to a file hierarchy, see
+/// `include/uapi/linux/landlock.h`.
+const LANDLOCK_RULE_PATH_BENEATH: c_int = 1;
+/// Layout of the packed `struct landlock_path_beneath_attr`: the
+/// `allowed_access` mask, and the `parent_fd` of the hierarchy.
+const PARENT_FD_OFFSET: usize = 8;
+const PATH_BENEATH_ATTR_SIZE: usize = 12;
+
+/// landlock_add_rule(ruleset_fd, rule_type, rule_attr, flags) has no path
+/// argument: a path-beneath rule refers to its hierarchy with a file
+/// descriptor, whose path was translated when it was opened, so the syscall
+/// is passed through.
+///
+/// The `parent_fd` of the rule is checked anyway, since the host files
+/// outside of the guest (e.g. opened before proot-rs started) can't be given
+/// to the ruleset: it fails with `EBADF` like an unknown file descriptor.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    let rule_type = tracee.regs.get(Current, SysArg(SysArg2)) as c_int;
+    let rule_attr_address = tracee.regs.get(Current, SysArg(SysArg3)) as *const c_void;
+    if rule_type != LANDLOCK_RULE_PATH_BENEATH || rule_attr_address.is_null() {
+        // let the kernel report the error
+        return Ok(());
+    }
+
+    let rule_attr = tracee
+        .regs
+        .read_data(rule_attr_address, PATH_BENEATH_ATTR_SIZE)?;
+    let mut parent_fd_bytes = [0u8; 4];
+    parent_fd_bytes.copy_from_slice(&rule_attr[PARENT_FD_OFFSET..PARENT_FD_OFFSET + 4]);
+    let parent_fd = i32::from_ne_bytes(parent_fd_bytes) as RawFd;
+
+    if tracee.fd_table.borrow().get(parent_fd).is_some() {
+        return Ok(());
+    }
+    // the fds which aren't files are reported by the kernel
+    if tracee.get_path_from_fd(parent_fd, Side::Host).is_ok()
+        && tracee.get_path_from_fd(parent_fd, Side::Guest).is_err()
+    {
+        return Err(Error::errno_with_msg(
+            EBADF,
+            format!(
+                "the parent fd {} of the landlock rule isn't a file of the guest",
+                parent_fd
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::AsRawFd;
+
+    use nix::errno::Errno;
+
+    use super::*;
+    use crate::kernel::groups::nr::{LANDLOCK_ADD_RULE, LANDLOCK_CREATE_RULESET};
+    use crate::register::Original;
+    use crate::utils::tests::test_with_proot;
+
+    /// Access rights of the files of a hierarchy.
+    const LANDLOCK_ACCESS_FS_READ_FILE: u64 = 1 << 2;
+
+    #[test]
+    fn test_landlock_add_rule_tracked_fd() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != LANDLOCK_ADD_RULE
+                {
+                    return;
+                }
+                // the rule isn't changed
+                assert_eq!(
+                    tracee.regs.get(Current, SysArg(SysArg3)),
+                    tracee.regs.get(Original, SysArg(SysArg3))
+                );
+            },
+            || {
+                let ruleset_attr = LANDLOCK_ACCESS_FS_READ_FILE.to_ne_bytes();
+                let ruleset_fd = unsafe {
+                    libc::syscall(
+                        LANDLOCK_CREATE_RULESET as libc::c_long,
+                        ruleset_attr.as_ptr(),
+                        ruleset_attr.len(),
+                        0,
+                    )
+                };
+                if ruleset_fd < 0 {
+                    // landlock isn't supported or enabled by the host kernel
+                    assert!([Errno::ENOSYS, Errno::EOPNOTSUPP].contains(&Errno::last()));
+                    return;
+                }
+
+                let add_rule = |parent_fd: RawFd| {
+                    let mut rule_attr = [0u8; PATH_BENEATH_ATTR_SIZE];
+                    rule_attr[..8].copy_from_slice(&LANDLOCK_ACCESS_FS_READ_FILE.to_ne_bytes());
+                    rule_attr[PARENT_FD_OFFSET..].copy_from_slice(&parent_fd.to_ne_bytes());
+                    let result = unsafe {
+                        libc::syscall(
+                            LANDLOCK_ADD_RULE as libc::c_long,
+                            ruleset_fd,
+                            LANDLOCK_RULE_PATH_BENEATH,
+                            rule_attr.as_ptr(),
+                            0,
+                        )
+                    };
+                    match result {
+                        0 => Ok(()),
+                        _ => Err(Errno::last()),
+                    }
+                };
+
+                let parent = std::fs::File::open("/etc").unwrap();
+                assert_eq!(add_rule(parent.as_raw_fd()), Ok(()));
+                assert_eq!(add_rule(999), Err(Errno::EBADF));
+                unsafe { libc::close(ruleset_fd as c_int) };
+            },
+        )
+    }
+}
@@ -15,15 +15,20 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
 
     let flags = AtFlags::from_bits_truncate(tracee.regs.get(Current, SysArg(SysArg5)) as _);
     let deref_final = flags.contains(AtFlags::AT_SYMLINK_FOLLOW);
-
-    let old_host_path = tracee.translate_path_at(olddirfd, old_path, deref_final)?;
-    let new_host_path = tracee.translate_path_at(newdirfd, new_path, false)?;
-
-    tracee.regs.set_sysarg_path(
-        SysArg2,
-        &old_host_path,
-        "during enter open translation, setting host path",
-    )?;
+    // the file descriptor `olddirfd` itself is linked with an empty path
+    let empty_path = flags.contains(AtFlags::AT_EMPTY_PATH);
+
+    let old_host_path =
+        tracee.translate_path_at_or_dirfd(olddirfd, old_path, empty_path, deref_final)?;
+    let new_host_path = tracee.translate_path_at_for_write(newdirfd, new_path, false)?;
+
+    if let Some(old_host_path) = old_host_path {
+        tracee.regs.set_sysarg_path(
+            SysArg2,
+            &old_host_path,
+            "during enter open translation, setting host path",
+        )?;
+    }
     tracee.regs.set_sysarg_path(
         SysArg4,
         &new_host_path,
@@ -39,7 +44,7 @@ mod tests {
 
     use nix::{fcntl::OFlag, sys::stat::Mode};
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - linkat
@@ -96,4 +101,16 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_link_at_read_only_binding() {
+        test_with_read_only_binding("link_at", || {
+            let fd = nix::fcntl::open("/mnt/ro", OFlag::O_RDONLY, Mode::empty()).unwrap();
+            assert_eq!(nc::linkat(fd, "file", fd, "linked", 0), Err(nc::EROFS));
+            assert_eq!(
+                nc::linkat(fd, "file", nc::AT_FDCWD, "/mnt/ro/linked", 0),
+                Err(nc::EROFS)
+            );
+        })
+    }
 }
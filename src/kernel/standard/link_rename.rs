@@ -1,16 +1,25 @@
+use sc::nr::RENAME;
+
 use crate::errors::*;
 use crate::filesystem::Translator;
 use crate::process::tracee::Tracee;
 use crate::register::PtraceWriter;
-use crate::register::{PtraceReader, SysArg1, SysArg2};
+use crate::register::{Current, PtraceReader, SysArg1, SysArg2};
 
 /// Translates link and rename kernel
 pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let old_path = tracee.regs.get_sysarg_path(SysArg1)?;
     let new_path = tracee.regs.get_sysarg_path(SysArg2)?;
 
-    let old_host_path = tracee.fs.borrow().translate_path(old_path, false)?;
-    let new_host_path = tracee.fs.borrow().translate_path(new_path, false)?;
+    let fs = tracee.fs.borrow();
+    // link() only creates the new path
+    let old_host_path = if tracee.regs.get_sys_num(Current) == RENAME {
+        fs.translate_path_for_write(old_path, false)?
+    } else {
+        fs.translate_path(old_path, false)?
+    };
+    let new_host_path = fs.translate_path_for_write(new_path, false)?;
+    drop(fs);
 
     tracee.regs.set_sysarg_path(
         SysArg1,
@@ -110,7 +119,7 @@ mod tests {
 
     use nix::{fcntl::OFlag, sys::stat::Mode};
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - link
@@ -189,4 +198,28 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_link_rename_read_only_binding() {
+        test_with_read_only_binding("link_rename", || {
+            let tmp_path = "/tmp/file_for_test_link_rename_read_only_binding";
+            File::create(tmp_path).unwrap();
+
+            // rename() modifies both directories
+            assert_eq!(
+                nc::rename("/mnt/ro/file", "/mnt/ro/renamed"),
+                Err(nc::EROFS)
+            );
+            assert_eq!(nc::rename("/mnt/ro/file", tmp_path), Err(nc::EROFS));
+            assert_eq!(nc::rename(tmp_path, "/mnt/ro/renamed"), Err(nc::EROFS));
+            let fd = nix::fcntl::open("/mnt/ro", OFlag::O_RDONLY, Mode::empty()).unwrap();
+            assert_eq!(nc::renameat(fd, "file", fd, "renamed"), Err(nc::EROFS));
+
+            // link() only modifies the directory of the new path
+            assert_eq!(nc::link("/mnt/ro/file", "/mnt/ro/linked"), Err(nc::EROFS));
+            assert_eq!(nc::link(tmp_path, "/mnt/ro/linked"), Err(nc::EROFS));
+
+            std::fs::remove_file(tmp_path).unwrap();
+        })
+    }
 }
@@ -1,19 +1,45 @@
+pub mod anon_inode;
+pub mod bpf;
 pub mod chdir;
 pub mod chmod_access_mknod_at;
+pub mod chroot;
+pub mod clone;
+pub mod close;
+pub mod declared;
 pub mod dir_link_attr;
+pub mod dup;
+pub mod fanotify_mark;
+pub mod fcntl;
 pub mod getcwd;
+pub mod getrandom;
 pub mod inotify_add_watch;
+pub mod io_uring;
+pub mod ioctl;
+pub mod landlock;
 pub mod link_at;
 pub mod link_rename;
 pub mod mount;
+pub mod mount_api;
+pub mod mq_open;
 pub mod open;
 pub mod open_at;
+pub mod ownership;
+pub mod personality;
+pub mod pidfd;
+pub mod pipe;
 pub mod pivot_root;
+pub mod proc_link;
+pub mod quotactl;
+pub mod read;
 pub mod readlink_at;
 pub mod rename_at;
+pub mod sethostname;
 pub mod standard_syscall;
 pub mod stat_at;
+pub mod swap;
 pub mod sym_link;
 pub mod sym_link_at;
+pub mod sysinfo;
+pub mod umask;
 pub mod uname;
 pub mod unlink_mkdir_at;
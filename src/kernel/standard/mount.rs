@@ -1,4 +1,7 @@
+use std::path::{Path, PathBuf};
+
 use crate::errors::Result;
+use crate::filesystem::{FileSystem, Translator};
 
 pub fn enter() -> Result<()> {
     Ok(())
@@ -16,3 +19,145 @@ pub fn enter() -> Result<()> {
     //
     //                status = translate_sysarg(tracee, SYSARG_2, REGULAR);
 }
+
+/// Options of the `data` string of mount() holding a list of paths, for each
+/// filesystem type whose options are understood, along with the separator of
+/// their paths.
+const PATH_OPTIONS: &[(&str, &[(&str, Option<char>)])] = &[(
+    "overlay",
+    &[
+        ("lowerdir", Some(':')),
+        ("upperdir", None),
+        ("workdir", None),
+    ],
+)];
+
+/// Splits `string` on `separator`, unless it's escaped with a backslash like
+/// the kernel does. The escapes are kept.
+fn split_escaped(string: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, c) in string.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&string[start..index]);
+            start = index + c.len_utf8();
+        }
+    }
+    parts.push(&string[start..]);
+    parts
+}
+
+fn unescape(string: &str) -> String {
+    let mut unescaped = String::with_capacity(string.len());
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        if c == '\\' || c == ':' || c == ',' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Translates the guest paths of the `data` string given to mount() for a
+/// filesystem of type `fstype`, e.g.
+/// `lowerdir=/a:/b,upperdir=/c,workdir=/d` for "overlay".
+///
+/// Returns `None` if the options of `fstype` aren't understood, in which
+/// case `data` has to be passed untouched. The other options are kept as is.
+///
+/// This isn't used yet, since mount() only ever binds paths, whose `data` is
+/// ignored by the kernel.
+#[allow(dead_code)]
+pub fn translate_mount_data(fs: &FileSystem, fstype: &str, data: &str) -> Result<Option<String>> {
+    let path_options = match PATH_OPTIONS.iter().find(|(name, _)| *name == fstype) {
+        Some((_, path_options)) => path_options,
+        None => return Ok(None),
+    };
+
+    let translate = |guest_path: &str| -> Result<String> {
+        let host_path: PathBuf = fs.translate_path(Path::new(&unescape(guest_path)), true)?;
+        Ok(escape(&host_path.to_string_lossy()))
+    };
+
+    let mut options = vec![];
+    for option in split_escaped(data, ',') {
+        let (key, value) = match option.find('=') {
+            Some(index) => (&option[..index], &option[index + 1..]),
+            None => {
+                options.push(option.to_string());
+                continue;
+            }
+        };
+        let value = match path_options.iter().find(|(name, _)| *name == key) {
+            Some((_, Some(separator))) => split_escaped(value, *separator)
+                .into_iter()
+                .map(translate)
+                .collect::<Result<Vec<_>>>()?
+                .join(&separator.to_string()),
+            Some((_, None)) => translate(value)?,
+            None => value.to_string(),
+        };
+        options.push(format!("{}={}", key, value));
+    }
+    Ok(Some(options.join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::get_test_rootfs_path;
+
+    #[test]
+    fn test_translate_mount_data_overlay() {
+        let rootfs_path = get_test_rootfs_path();
+        let fs = FileSystem::with_root(&rootfs_path).unwrap();
+        let host = |path: &str| rootfs_path.join(path).to_string_lossy().into_owned();
+
+        let data = translate_mount_data(
+            &fs,
+            "overlay",
+            "lowerdir=/etc:/bin,upperdir=/tmp,workdir=/usr,index=off,xino",
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            Some(format!(
+                "lowerdir={}:{},upperdir={},workdir={},index=off,xino",
+                host("etc"),
+                host("bin"),
+                host("tmp"),
+                host("usr")
+            ))
+        );
+
+        // the options of the other filesystems are left untouched
+        assert_eq!(
+            translate_mount_data(&fs, "tmpfs", "size=10m,mode=755").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_split_escaped() {
+        assert_eq!(split_escaped("/a:/b\\:c", ':'), vec!["/a", "/b\\:c"]);
+        assert_eq!(unescape("/b\\:c"), "/b:c");
+        assert_eq!(escape("/b:c"), "/b\\:c");
+    }
+}
@@ -0,0 +1,413 @@
+use std::os::unix::prelude::RawFd;
+use std::path::{Path, PathBuf};
+
+use libc::{c_uint, AT_EMPTY_PATH, AT_SYMLINK_NOFOLLOW, O_CLOEXEC};
+use nix::fcntl::OFlag;
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::filesystem::{Canonicalizer, Translator};
+use crate::kernel::standard::open::track_fd;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{
+    Current, Original, PtraceReader, PtraceWriter, SysArg, SysArg1, SysArg2, SysArg3, SysArg4,
+    SysArg5, SysArgIndex, SysResult, Word,
+};
+
+/// Flags of open_tree(), see `include/uapi/linux/mount.h`.
+const OPEN_TREE_CLONE: c_uint = 1;
+const AT_RECURSIVE: c_uint = 0x8000;
+/// Flags of move_mount().
+const MOVE_MOUNT_F_SYMLINKS: c_uint = 0x01;
+const MOVE_MOUNT_F_EMPTY_PATH: c_uint = 0x04;
+const MOVE_MOUNT_T_SYMLINKS: c_uint = 0x10;
+const MOVE_MOUNT_T_EMPTY_PATH: c_uint = 0x40;
+
+/// Returns the guest path of the `(dirfd, path)` pair pointed to by the
+/// arguments `dirfd_sysarg` and `path_sysarg`, or `None` if the path is empty
+/// and refers to `dirfd` itself, see `Tracee::get_guest_path_at()`.
+fn get_guest_path_at(
+    tracee: &Tracee,
+    dirfd_sysarg: SysArgIndex,
+    path_sysarg: SysArgIndex,
+    empty_path: bool,
+) -> Result<Option<PathBuf>> {
+    let dirfd = tracee.regs.get(Current, SysArg(dirfd_sysarg)) as RawFd;
+    let raw_path = tracee.regs.get_sysarg_path(path_sysarg)?;
+    tracee.get_guest_path_at(dirfd, raw_path, empty_path)
+}
+
+/// Translates the path pointed to by `path_sysarg`, unless it's empty and
+/// refers to the file descriptor of `dirfd_sysarg`.
+fn translate_sysarg_at(
+    tracee: &mut Tracee,
+    dirfd_sysarg: SysArgIndex,
+    path_sysarg: SysArgIndex,
+    empty_path: bool,
+    deref_final: bool,
+) -> Result<()> {
+    if let Some(guest_path) = get_guest_path_at(tracee, dirfd_sysarg, path_sysarg, empty_path)? {
+        let host_path = tracee.fs.borrow().translate_path(guest_path, deref_final)?;
+        tracee.regs.set_sysarg_path(
+            path_sysarg,
+            &host_path,
+            "during enter mount API translation, setting host path",
+        )?;
+    }
+    Ok(())
+}
+
+/// open_tree(dirfd, path, flags) opens the mount tree at `path`, whose path
+/// is translated.
+///
+/// With `emulate_move_mount`, `OPEN_TREE_CLONE` is dropped, since a
+/// detached copy of the tree can't be created without `CAP_SYS_ADMIN`: the
+/// result is a mere `O_PATH` file descriptor, which `move_mount_enter()`
+/// turns into a binding.
+pub fn open_tree_enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let flags = tracee.regs.get(Current, SysArg(SysArg3)) as c_uint;
+    let empty_path = flags & AT_EMPTY_PATH as c_uint != 0;
+    let deref_final = flags & AT_SYMLINK_NOFOLLOW as c_uint == 0;
+
+    translate_sysarg_at(tracee, SysArg1, SysArg2, empty_path, deref_final)?;
+
+    if info_bag.config.emulate_move_mount && flags & OPEN_TREE_CLONE != 0 {
+        tracee.regs.set(
+            SysArg(SysArg3),
+            (flags & !(OPEN_TREE_CLONE | AT_RECURSIVE)) as Word,
+            "open_tree() clone is emulated, opening the path instead",
+        );
+    }
+    Ok(())
+}
+
+/// Records the file descriptor of the mount tree in the fd table.
+pub fn open_tree_exit(tracee: &mut Tracee) -> Result<()> {
+    let fd = tracee.regs.get(Current, SysResult) as RawFd;
+    let flags = tracee.regs.get(Original, SysArg(SysArg3)) as c_uint;
+    let cloexec = flags & O_CLOEXEC as c_uint != 0;
+
+    let dirfd = tracee.regs.get(Original, SysArg(SysArg1)) as RawFd;
+    if fd >= 0
+        && flags & AT_EMPTY_PATH as c_uint != 0
+        && tracee.regs.get_sysarg_path(SysArg2)? == Path::new("")
+    {
+        tracee.fd_table.borrow_mut().duplicate(dirfd, fd, cloexec);
+        return Ok(());
+    }
+
+    let flags = if cloexec {
+        OFlag::O_CLOEXEC
+    } else {
+        OFlag::empty()
+    };
+    track_fd(tracee, fd, SysArg2, flags);
+    Ok(())
+}
+
+/// move_mount(from_dirfd, from_path, to_dirfd, to_path, flags) attaches the
+/// mount tree at `from` onto `to`.
+///
+/// With `emulate_move_mount`, it's emulated by binding the host path of
+/// `from` (usually the file descriptor of open_tree()) onto the guest path
+/// `to`, like a bind mount. Otherwise both paths are translated.
+pub fn move_mount_enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let flags = tracee.regs.get(Current, SysArg(SysArg5)) as c_uint;
+    let from_empty_path = flags & MOVE_MOUNT_F_EMPTY_PATH != 0;
+    let from_deref_final = flags & MOVE_MOUNT_F_SYMLINKS != 0;
+    let to_empty_path = flags & MOVE_MOUNT_T_EMPTY_PATH != 0;
+    let to_deref_final = flags & MOVE_MOUNT_T_SYMLINKS != 0;
+
+    if !info_bag.config.emulate_move_mount {
+        translate_sysarg_at(tracee, SysArg1, SysArg2, from_empty_path, from_deref_final)?;
+        return translate_sysarg_at(tracee, SysArg3, SysArg4, to_empty_path, to_deref_final);
+    }
+
+    let from_host_path = match get_guest_path_at(tracee, SysArg1, SysArg2, from_empty_path)? {
+        Some(guest_path) => tracee
+            .fs
+            .borrow()
+            .translate_path(guest_path, from_deref_final)?,
+        None => {
+            // usually the file descriptor of open_tree(), known by the guest
+            // path recorded by `open_tree_exit()`
+            let from_dirfd = tracee.regs.get(Current, SysArg(SysArg1)) as RawFd;
+            match tracee.get_guest_path_of_fd(from_dirfd) {
+                Some(guest_path) => tracee.fs.borrow().translate_path(guest_path, true)?,
+                None => tracee.get_path_from_fd(from_dirfd, Side::Host)?,
+            }
+        }
+    };
+    let to_guest_path = match get_guest_path_at(tracee, SysArg3, SysArg4, to_empty_path)? {
+        Some(guest_path) => tracee
+            .fs
+            .borrow()
+            .canonicalize(guest_path, to_deref_final)?,
+        None => {
+            let to_dirfd = tracee.regs.get(Current, SysArg(SysArg3)) as RawFd;
+            match tracee.get_guest_path_of_fd(to_dirfd) {
+                Some(guest_path) => guest_path,
+                None => tracee.get_path_from_fd(to_dirfd, Side::Guest)?,
+            }
+        }
+    };
+
+    tracee
+        .fs
+        .borrow_mut()
+        .add_binding(from_host_path, to_guest_path)?;
+    tracee
+        .regs
+        .cancel_syscall("move_mount() is emulated, avoid syscall");
+    Ok(())
+}
+
+pub fn move_mount_exit(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.is_syscall_cancelled() {
+        tracee
+            .regs
+            .set(SysResult, 0, "move_mount() was emulated successfully");
+    }
+    Ok(())
+}
+
+/// mount_setattr(dirfd, path, flags, attr, size) changes the attributes of
+/// the mount at `path` (and of the mounts under it with `AT_RECURSIVE`),
+/// whose path is translated so that the errors of the kernel are accurate
+/// (e.g. `ENOENT`).
+///
+/// The bindings aren't mounts, so it's rejected with `EPERM` once the path
+/// is translated if `strict_mount_setattr` is enabled.
+pub fn mount_setattr_enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let flags = tracee.regs.get(Current, SysArg(SysArg3)) as c_uint;
+    let empty_path = flags & AT_EMPTY_PATH as c_uint != 0;
+    let deref_final = flags & AT_SYMLINK_NOFOLLOW as c_uint == 0;
+
+    translate_sysarg_at(tracee, SysArg1, SysArg2, empty_path, deref_final)?;
+
+    if info_bag.config.strict_mount_setattr {
+        return Err(Error::errno_with_msg(
+            EPERM,
+            "mount_setattr() is rejected, the bindings aren't mounts",
+        ));
+    }
+    Ok(())
+}
+
+/// fsopen(), fspick(), fsconfig() and fsmount() create and mount new
+/// filesystems (or reconfigure existing ones), whose paths are given to the
+/// kernel in many forms. They are rejected, so that programs fall back to
+/// mount().
+pub fn reject_enter(_tracee: &mut Tracee) -> Result<()> {
+    Err(Error::errno_with_msg(
+        EOPNOTSUPP,
+        "only open_tree() and move_mount() of the new mount API are supported",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    use libc::AT_FDCWD;
+    use nix::errno::Errno;
+
+    use super::{AT_RECURSIVE, MOVE_MOUNT_F_EMPTY_PATH, OPEN_TREE_CLONE};
+    use crate::config::ProotConfig;
+    use crate::kernel::groups::nr::{FSOPEN, MOUNT_SETATTR, MOVE_MOUNT, OPEN_TREE};
+    use crate::register::{Current, Original, PtraceReader, SysArg, SysArg2, SysArg3, SysResult};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot, test_with_proot_config};
+
+    #[test]
+    fn test_move_mount_emulation() {
+        let test_dir = get_test_rootfs_path().join("tmp/dir_for_test_move_mount");
+        fs::create_dir_all(test_dir.join("source")).unwrap();
+        fs::create_dir_all(test_dir.join("target")).unwrap();
+        fs::write(test_dir.join("source/marker"), "").unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .emulate_move_mount(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                let source = CString::new("/tmp/dir_for_test_move_mount/source").unwrap();
+                let target = CString::new("/tmp/dir_for_test_move_mount/target").unwrap();
+                let empty = CString::new("").unwrap();
+
+                let fd = unsafe {
+                    libc::syscall(
+                        OPEN_TREE as libc::c_long,
+                        AT_FDCWD,
+                        source.as_ptr(),
+                        OPEN_TREE_CLONE | libc::O_CLOEXEC as libc::c_uint,
+                    )
+                };
+                assert!(fd >= 0);
+                let result = unsafe {
+                    libc::syscall(
+                        MOVE_MOUNT as libc::c_long,
+                        fd,
+                        empty.as_ptr(),
+                        AT_FDCWD,
+                        target.as_ptr(),
+                        MOVE_MOUNT_F_EMPTY_PATH,
+                    )
+                };
+                assert_eq!(result, 0);
+
+                // the source is now bound onto the target
+                assert!(Path::new("/tmp/dir_for_test_move_mount/target/marker").exists());
+                nix::unistd::close(fd as i32).unwrap();
+            },
+        );
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    /// Opens the tree relatively to a dirfd: its file descriptor is recorded
+    /// with its guest path, which move_mount() binds.
+    fn test_open_tree_fd_tracked() {
+        let test_dir = get_test_rootfs_path().join("tmp/dir_for_test_open_tree");
+        fs::create_dir_all(test_dir.join("source")).unwrap();
+        fs::create_dir_all(test_dir.join("target")).unwrap();
+        fs::write(test_dir.join("source/marker"), "").unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .emulate_move_mount(true)
+                .build(),
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != OPEN_TREE
+                {
+                    return;
+                }
+                let fd = tracee.regs.get(Current, SysResult) as i32;
+                assert!(fd >= 0);
+                let fd_table = tracee.fd_table.borrow();
+                let entry = fd_table.get(fd).unwrap();
+                assert_eq!(entry.path, Path::new("/tmp/dir_for_test_open_tree/source"));
+                assert!(entry.cloexec);
+            },
+            || {
+                let dir = fs::File::open("/tmp/dir_for_test_open_tree").unwrap();
+                let source = CString::new("source").unwrap();
+                let target = CString::new("/tmp/dir_for_test_open_tree/target").unwrap();
+                let empty = CString::new("").unwrap();
+
+                let fd = unsafe {
+                    libc::syscall(
+                        OPEN_TREE as libc::c_long,
+                        dir.as_raw_fd(),
+                        source.as_ptr(),
+                        OPEN_TREE_CLONE | libc::O_CLOEXEC as libc::c_uint,
+                    )
+                };
+                assert!(fd >= 0);
+                let result = unsafe {
+                    libc::syscall(
+                        MOVE_MOUNT as libc::c_long,
+                        fd,
+                        empty.as_ptr(),
+                        AT_FDCWD,
+                        target.as_ptr(),
+                        MOVE_MOUNT_F_EMPTY_PATH,
+                    )
+                };
+                assert_eq!(result, 0);
+                assert!(Path::new("/tmp/dir_for_test_open_tree/target/marker").exists());
+                nix::unistd::close(fd as i32).unwrap();
+            },
+        );
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    /// Calls mount_setattr() on `path` without changing any attribute.
+    fn mount_setattr(path: &str) -> Result<(), Errno> {
+        let path = CString::new(path).unwrap();
+        // struct mount_attr, with no attribute to set or clear
+        let attr = [0u64; 4];
+        let result = unsafe {
+            libc::syscall(
+                MOUNT_SETATTR as libc::c_long,
+                AT_FDCWD,
+                path.as_ptr(),
+                AT_RECURSIVE,
+                attr.as_ptr(),
+                std::mem::size_of_val(&attr),
+            )
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(Errno::last()),
+        }
+    }
+
+    #[test]
+    fn test_mount_setattr_translated() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    && !before_translation
+                    && tracee.regs.get_sys_num(Original) == MOUNT_SETATTR
+                {
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg2).unwrap(),
+                        get_test_rootfs_path().join("tmp")
+                    );
+                    // the flags are passed through
+                    assert_eq!(
+                        tracee.regs.get(Current, SysArg(SysArg3)),
+                        AT_RECURSIVE as u64
+                    );
+                }
+            },
+            || {
+                // the result depends on the capabilities and on the kernel
+                let _ = mount_setattr("/tmp");
+            },
+        )
+    }
+
+    #[test]
+    fn test_mount_setattr_strict() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .strict_mount_setattr(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                assert_eq!(mount_setattr("/tmp"), Err(Errno::EPERM));
+                // the path is translated first
+                assert_eq!(
+                    mount_setattr("/tmp/proot_rs_test_mount_setattr_missing/mnt"),
+                    Err(Errno::ENOENT)
+                );
+            },
+        )
+    }
+
+    #[test]
+    fn test_fsopen_rejected() {
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                let fs_name = CString::new("tmpfs").unwrap();
+                let result = unsafe { libc::syscall(FSOPEN as libc::c_long, fs_name.as_ptr(), 0) };
+                assert_eq!(result, -1);
+                assert_eq!(Errno::last(), Errno::EOPNOTSUPP);
+            },
+        )
+    }
+}
@@ -0,0 +1,100 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{PtraceReader, PtraceWriter, SysArg1};
+
+/// mq_open(name, ...) and mq_unlink(name) take the name of a POSIX message
+/// queue, which is looked up by the kernel in its own filesystem (glibc
+/// strips the leading '/'), so it's never translated like a path.
+///
+/// With `mq_prefix`, the name is prefixed, so that the message queues of the
+/// guest are kept apart from the ones of the host. Both syscalls are
+/// rewritten the same way, so a queue can be unlinked by its guest name.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let prefix = match info_bag.config.mq_prefix {
+        Some(ref prefix) => prefix,
+        None => return Ok(()),
+    };
+
+    let name = tracee.regs.get_sysarg_path(SysArg1)?;
+    let mut new_name = OsStr::new(prefix).to_os_string();
+    new_name.push(name.as_os_str());
+
+    tracee.regs.set_sysarg_data(
+        SysArg1,
+        new_name.as_bytes(),
+        "during enter mq_open translation, setting prefixed name",
+        true,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::path::PathBuf;
+
+    use nix::mqueue::{mq_close, mq_open, mq_unlink, MQ_OFlag};
+    use nix::sys::stat::Mode;
+    use sc::nr::{MQ_OPEN, MQ_UNLINK};
+
+    use crate::config::ProotConfig;
+    use crate::register::{Current, PtraceReader, SysArg1};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    fn test_mq_open_name(config: ProotConfig, expected_name: &'static str) {
+        test_with_proot_config(
+            config,
+            move |tracee, is_sysenter, before_translation| {
+                let sys_num = tracee.regs.get_sys_num(Current);
+                if is_sysenter
+                    && !before_translation
+                    && (sys_num == MQ_OPEN || sys_num == MQ_UNLINK)
+                {
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg1).unwrap(),
+                        PathBuf::from(expected_name)
+                    );
+                }
+            },
+            || {
+                let name = CString::new("/proot_rs_test_mq").unwrap();
+                // the kernel may lack the support of message queues
+                if let Ok(mqd) = mq_open(
+                    &name,
+                    MQ_OFlag::O_CREAT | MQ_OFlag::O_RDWR,
+                    Mode::S_IRUSR | Mode::S_IWUSR,
+                    None,
+                ) {
+                    mq_close(mqd).unwrap();
+                    // the same queue is found by its guest name
+                    mq_unlink(&name).unwrap();
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_mq_open_untouched() {
+        test_mq_open_name(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .build(),
+            "proot_rs_test_mq",
+        )
+    }
+
+    #[test]
+    fn test_mq_open_prefix() {
+        test_mq_open_name(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .mq_prefix("proot-")
+                .build(),
+            "proot-proot_rs_test_mq",
+        )
+    }
+}
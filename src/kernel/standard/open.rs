@@ -1,20 +1,66 @@
+use std::os::unix::prelude::RawFd;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use libc::AT_FDCWD;
 use nix::fcntl::OFlag;
+use sc::nr::CREAT;
 
 use crate::errors::*;
 
+use crate::filesystem::mounts::MountsFormat;
+use crate::filesystem::temp::TempFile;
 use crate::filesystem::Translator;
-use crate::process::tracee::Tracee;
+use crate::process::fd_table::VirtualContent;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::{Tracee, VirtualFile};
 use crate::register::PtraceWriter;
-use crate::register::{Current, PtraceReader, SysArg, SysArg1, SysArg2};
+use crate::register::{
+    Current, Original, PtraceReader, RegVersion, SysArg, SysArg1, SysArg2, SysArgIndex, SysResult,
+};
+
+/// Returns the flags of open(), or the implicit ones of creat().
+fn get_flags(tracee: &Tracee, version: RegVersion) -> OFlag {
+    if tracee.regs.get_sys_num(version) == CREAT {
+        OFlag::O_CREAT | OFlag::O_WRONLY | OFlag::O_TRUNC
+    } else {
+        OFlag::from_bits_truncate(tracee.regs.get(version, SysArg(SysArg2)) as _)
+    }
+}
 
-pub fn enter(tracee: &mut Tracee) -> Result<()> {
+/// Returns the guest path of open() or creat(), made absolute: having no
+/// dirfd, a relative path is resolved against the guest cwd of the tracee
+/// (and not the one of proot), like with `AT_FDCWD`.
+fn get_guest_path(tracee: &Tracee) -> Result<PathBuf> {
     let raw_path = tracee.regs.get_sysarg_path(SysArg1)?;
+    // `None` is only returned for an empty path with `AT_EMPTY_PATH`
+    Ok(tracee
+        .get_guest_path_at(AT_FDCWD, raw_path, false)?
+        .unwrap_or_default())
+}
+
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let raw_path = get_guest_path(tracee)?;
 
-    let flags = OFlag::from_bits_truncate(tracee.regs.get(Current, SysArg(SysArg2)) as _);
+    let flags = get_flags(tracee, Current);
 
     let deref_final = !(flags.contains(OFlag::O_NOFOLLOW)
         || (flags.contains(OFlag::O_EXCL) && flags.contains(OFlag::O_CREAT)));
-    let host_path = tracee.fs.borrow().translate_path(raw_path, deref_final)?;
+    let host_path =
+        if let Some(host_path) = translate_virtual_file(tracee, info_bag, &raw_path, flags)? {
+            host_path
+        } else if opens_for_write(flags) {
+            tracee
+                .fs
+                .borrow()
+                .translate_path_for_write(&raw_path, deref_final)?
+        } else {
+            tracee.fs.borrow().translate_path(&raw_path, deref_final)?
+        };
+    tracee
+        .fs
+        .borrow()
+        .check_device_allowed(&raw_path, &host_path, deref_final)?;
 
     tracee.regs.set_sysarg_path(
         SysArg1,
@@ -25,9 +71,102 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
     Ok(())
 }
 
+/// Checks if the `flags` of open() may create or modify the file.
+pub fn opens_for_write(flags: OFlag) -> bool {
+    flags.intersects(OFlag::O_CREAT | OFlag::O_TRUNC | OFlag::O_APPEND)
+        || (flags & OFlag::O_ACCMODE) != OFlag::O_RDONLY
+}
+
+/// Returns the host path of an empty temporary file to open instead of the
+/// guest file at `raw_path`, if its content is generated (e.g. the mount
+/// tables of `/proc` with the `virtual_mounts` option). Its content is then
+/// served to the reads of the file descriptor (see `track_fd`).
+pub fn translate_virtual_file(
+    tracee: &mut Tracee,
+    info_bag: &InfoBag,
+    raw_path: &Path,
+    flags: OFlag,
+) -> Result<Option<PathBuf>> {
+    let content = match MountsFormat::from_guest_path(raw_path) {
+        Some(format) if info_bag.config.virtual_mounts => {
+            tracee.fs.borrow().generate_mounts(format)
+        }
+        _ => return Ok(None),
+    };
+    if opens_for_write(flags) {
+        return Err(Error::errno_with_msg(
+            EACCES,
+            format!("{:?} is generated, it can't be modified", raw_path),
+        ));
+    }
+
+    let temp_file = TempFile::new(&format!("proot-virtual-file-{}", tracee.pid));
+    temp_file.create_file()?;
+    let host_path = temp_file.path.clone();
+    tracee.virtual_file = Some(VirtualFile {
+        guest_path: raw_path.to_path_buf(),
+        temp_file: temp_file,
+        content: Rc::new(VirtualContent::new(content.into_bytes())),
+    });
+
+    Ok(Some(host_path))
+}
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let fd = tracee.regs.get(Current, SysResult) as RawFd;
+    let flags = get_flags(tracee, Original);
+
+    track_fd(tracee, fd, SysArg1, flags);
+    Ok(())
+}
+
+/// Returns the guest path of the file opened through the (translated) path
+/// pointed to by `sys_arg`.
+fn get_opened_guest_path(tracee: &Tracee, sys_arg: SysArgIndex) -> Result<PathBuf> {
+    let host_path = tracee.regs.get_sysarg_path(sys_arg)?;
+    let maybe_path = tracee.fs.borrow().detranslate_path(&host_path, None)?;
+
+    Ok(maybe_path.unwrap_or(host_path))
+}
+
+/// Records in the fd table the file descriptor `fd` returned by a syscall
+/// which opened the path pointed to by `sys_arg` (already translated during
+/// the enter stage).
+///
+/// The file is already opened at this point, so a failure only results in the
+/// file descriptor not being tracked.
+pub fn track_fd(tracee: &mut Tracee, fd: RawFd, sys_arg: SysArgIndex, flags: OFlag) {
+    // the temporary file of a virtual file isn't needed anymore
+    let virtual_file = tracee.virtual_file.take();
+    if fd < 0 {
+        return;
+    }
+
+    let cloexec = flags.contains(OFlag::O_CLOEXEC);
+    if let Some(virtual_file) = virtual_file {
+        let mut fd_table = tracee.fd_table.borrow_mut();
+        fd_table.insert(fd, virtual_file.guest_path, cloexec);
+        fd_table.set_virtual_content(fd, virtual_file.content);
+        return;
+    }
+
+    match get_opened_guest_path(tracee, sys_arg) {
+        Ok(guest_path) => tracee.fd_table.borrow_mut().insert(fd, guest_path, cloexec),
+        Err(error) => debug!("failed to track file descriptor {}: {}", fd, error),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::tests::test_with_proot;
+    use std::ffi::CString;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::{Path, PathBuf};
+
+    use sc::nr::CREAT;
+
+    use crate::config::ProotConfig;
+    use crate::register::{Current, Original, SysResult};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot, test_with_proot_config};
 
     /// Unit test for the following syscalls:
     /// - linkat
@@ -76,4 +215,233 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_open_creat() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter || before_translation {
+                    return;
+                }
+                if tracee.regs.get_sys_num(Original) == CREAT {
+                    // the file descriptor is known by its guest path
+                    let fd = tracee.regs.get(Current, SysResult) as i32;
+                    let fd_table = tracee.fd_table.borrow();
+                    let entry = fd_table.get(fd).unwrap();
+                    assert_eq!(entry.path, Path::new("/tmp/file_for_test_open_creat"));
+                    assert!(!entry.cloexec);
+                }
+            },
+            || {
+                let filepath = "/tmp/file_for_test_open_creat";
+
+                let result = std::panic::catch_unwind(|| {
+                    // glibc's creat() is implemented with openat()
+                    let path = CString::new(filepath).unwrap();
+                    let fd = unsafe { libc::syscall(libc::SYS_creat, path.as_ptr(), 0o640) } as i32;
+                    assert!(fd >= 0);
+                    assert_eq!(nix::unistd::write(fd, b"proot").unwrap(), 5);
+                    nix::unistd::close(fd).unwrap();
+
+                    // the file was created through its guest path, and truncated
+                    // by a second creat()
+                    assert_eq!(std::fs::read(filepath).unwrap(), b"proot");
+                    let fd = unsafe { libc::syscall(libc::SYS_creat, path.as_ptr(), 0o640) } as i32;
+                    assert!(fd >= 0);
+                    nix::unistd::close(fd).unwrap();
+                    assert_eq!(std::fs::metadata(filepath).unwrap().len(), 0);
+                });
+                let _ = std::fs::remove_file(filepath);
+                if let Err(err) = result {
+                    std::panic::resume_unwind(err);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_open_dev_shim() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .dev_shim(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                use std::io::{Read, Write};
+                use std::os::unix::fs::FileTypeExt;
+
+                // the host devices are stated and opened through the bindings
+                for device in &["/dev/null", "/dev/urandom"] {
+                    let metadata = std::fs::metadata(device).unwrap();
+                    assert!(metadata.file_type().is_char_device());
+                }
+
+                let mut random = [0u8; 64];
+                std::fs::File::open("/dev/urandom")
+                    .unwrap()
+                    .read_exact(&mut random)
+                    .unwrap();
+                assert!(random.iter().any(|&byte| byte != 0));
+
+                let mut null = std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open("/dev/null")
+                    .unwrap();
+                null.write_all(b"proot").unwrap();
+                let mut content = vec![];
+                assert_eq!(null.read_to_end(&mut content).unwrap(), 0);
+            },
+        )
+    }
+
+    #[test]
+    fn test_open_tracefs_bindings() {
+        let host_dir = std::env::temp_dir().join("proot_rs_test_open_tracefs_bindings");
+        std::fs::create_dir_all(&host_dir).unwrap();
+        std::fs::write(host_dir.join("trace_marker"), "").unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .synthetic_mountpoints(true)
+                .binding(&host_dir, "/sys/kernel/tracing")
+                .read_only_binding(&host_dir, "/sys/kernel/debug/tracing")
+                .build(),
+            |_, _, _| {},
+            || {
+                use std::io::Write;
+
+                let open_for_write = |path| std::fs::OpenOptions::new().append(true).open(path);
+                open_for_write("/sys/kernel/tracing/trace_marker")
+                    .unwrap()
+                    .write_all(b"proot")
+                    .unwrap();
+
+                // the same file is readable through the read-only binding
+                let error = open_for_write("/sys/kernel/debug/tracing/trace_marker").unwrap_err();
+                assert_eq!(error.raw_os_error(), Some(libc::EROFS));
+                assert_eq!(
+                    std::fs::read("/sys/kernel/debug/tracing/trace_marker").unwrap(),
+                    b"proot"
+                );
+            },
+        );
+
+        std::fs::remove_dir_all(&host_dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_virtual_mounts() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .binding("/etc", "/home")
+                .virtual_mounts(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // the guest view, without the host mounts
+                let mounts = std::fs::read_to_string("/proc/mounts").unwrap();
+                assert_eq!(
+                    mounts,
+                    "rootfs / rootfs rw 0 0\nnone /home none rw,bind 0 0\n"
+                );
+                assert_eq!(
+                    std::fs::read_to_string("/proc/self/mounts").unwrap(),
+                    mounts
+                );
+                let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").unwrap();
+                assert!(mountinfo.starts_with("1 1 0:0 / / rw - rootfs rootfs rw\n"));
+
+                // the file descriptor is known by its guest path
+                let file = std::fs::File::open("/proc/mounts").unwrap();
+                let fd_path = format!("/proc/self/fd/{}", file.as_raw_fd());
+                assert_eq!(
+                    PathBuf::from(nix::fcntl::readlink(fd_path.as_str()).unwrap()),
+                    PathBuf::from("/proc/mounts")
+                );
+
+                // but it can't be modified
+                assert_eq!(
+                    std::fs::OpenOptions::new()
+                        .write(true)
+                        .open("/proc/mounts")
+                        .unwrap_err()
+                        .raw_os_error(),
+                    Some(libc::EACCES)
+                );
+            },
+        )
+    }
+
+    #[test]
+    fn test_open_trailing_slash() {
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                // a trailing slash requires a directory
+                assert_eq!(
+                    std::fs::File::open("/etc/passwd/")
+                        .unwrap_err()
+                        .raw_os_error(),
+                    Some(libc::ENOTDIR)
+                );
+                assert!(std::fs::File::open("/etc/passwd").is_ok());
+                let dir = std::fs::File::open("/tmp/").unwrap();
+                assert!(dir.metadata().unwrap().is_dir());
+            },
+        )
+    }
+
+    #[test]
+    fn test_open_relative_path_after_chdir() {
+        let dir_name = "proot_rs_test_open_relative";
+        let host_dir = get_test_rootfs_path().join("tmp").join(dir_name);
+        std::fs::create_dir_all(&host_dir).unwrap();
+        std::fs::write(host_dir.join("file"), "guest").unwrap();
+
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != sc::nr::OPEN
+                {
+                    return;
+                }
+                // the file is recorded with its absolute guest path
+                let fd = tracee.regs.get(Current, SysResult) as i32;
+                if fd >= 0 {
+                    let fd_table = tracee.fd_table.borrow();
+                    assert!(fd_table.get(fd).unwrap().path.is_absolute());
+                }
+            },
+            || {
+                use std::io::Read;
+
+                nix::unistd::chdir(Path::new("/tmp").join(dir_name).as_path()).unwrap();
+
+                // resolved in the guest cwd, not in the one of proot
+                let fd = nc::open("file", nc::O_RDONLY, 0).unwrap();
+                let mut content = String::new();
+                unsafe { std::fs::File::from_raw_fd(fd) }
+                    .read_to_string(&mut content)
+                    .unwrap();
+                assert_eq!(content, "guest");
+
+                let fd = nc::open("../../etc/passwd", nc::O_RDONLY, 0).unwrap();
+                let mut stat = nc::stat_t::default();
+                nc::fstat(fd, &mut stat).unwrap();
+                let mut expected_stat = nc::stat_t::default();
+                nc::stat("/etc/passwd", &mut expected_stat).unwrap();
+                assert_eq!(stat.st_ino, expected_stat.st_ino);
+                nc::close(fd).unwrap();
+
+                assert_eq!(nc::open("missing", nc::O_RDONLY, 0), Err(nc::ENOENT));
+                assert_eq!(nc::open("", nc::O_RDONLY, 0), Err(nc::ENOENT));
+            },
+        );
+        std::fs::remove_dir_all(&host_dir).unwrap();
+    }
 }
@@ -3,11 +3,16 @@ use std::os::unix::prelude::RawFd;
 use nix::fcntl::OFlag;
 
 use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::kernel::standard::open::{opens_for_write, track_fd, translate_virtual_file};
+use crate::process::proot::InfoBag;
 use crate::process::tracee::Tracee;
 use crate::register::PtraceWriter;
-use crate::register::{Current, PtraceReader, SysArg, SysArg1, SysArg2, SysArg3};
+use crate::register::{
+    Current, Original, PtraceReader, SysArg, SysArg1, SysArg2, SysArg3, SysResult,
+};
 
-pub fn enter(tracee: &mut Tracee) -> Result<()> {
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
     let dirfd = tracee.regs.get(Current, SysArg(SysArg1)) as RawFd;
     let raw_path = tracee.regs.get_sysarg_path(SysArg2)?;
     let flags = OFlag::from_bits_truncate(tracee.regs.get(Current, SysArg(SysArg3)) as _);
@@ -15,7 +20,27 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let deref_final = !(flags.contains(OFlag::O_NOFOLLOW)
         || (flags.contains(OFlag::O_EXCL) && flags.contains(OFlag::O_CREAT)));
 
-    let host_path = tracee.translate_path_at(dirfd, raw_path, deref_final)?;
+    let mut host_path = match translate_virtual_file(tracee, info_bag, &raw_path, flags)? {
+        Some(host_path) => host_path,
+        None => tracee.translate_path_at(dirfd, &raw_path, deref_final)?,
+    };
+    // the guest path of a relative path is only needed by the checks
+    let for_write = opens_for_write(flags);
+    if for_write || tracee.fs.borrow().has_nodev_bindings() {
+        let guest_path = if raw_path.is_relative() {
+            tracee.get_path_from_fd(dirfd, Side::Guest)?.join(&raw_path)
+        } else {
+            raw_path
+        };
+        let fs = tracee.fs.borrow();
+        fs.check_device_allowed(&guest_path, &host_path, deref_final)?;
+        if for_write {
+            fs.check_writable(&guest_path, deref_final)?;
+        }
+    }
+    if for_write {
+        host_path = tracee.fs.borrow().redirect_to_upper_layer(host_path)?;
+    }
 
     tracee.regs.set_sysarg_path(
         SysArg2,
@@ -27,11 +52,27 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
     Ok(())
 }
 
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let fd = tracee.regs.get(Current, SysResult) as RawFd;
+    let flags = OFlag::from_bits_truncate(tracee.regs.get(Original, SysArg(SysArg3)) as _);
+
+    track_fd(tracee, fd, SysArg2, flags);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use nix::{fcntl::OFlag, sys::stat::Mode};
+    use std::ffi::OsStr;
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::FromRawFd;
+    use std::path::PathBuf;
 
-    use crate::utils::tests::test_with_proot;
+    use nix::errno::Errno;
+    use nix::{fcntl::OFlag, sys::stat::Mode, unistd};
+
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot};
 
     /// Unit test for the following syscalls:
     /// - linkat
@@ -85,4 +126,47 @@ mod tests {
             },
         )
     }
+
+    /// mkstemp() is an open() with `O_CREAT | O_EXCL` of a template whose
+    /// `XXXXXX` is filled in, retried on `EEXIST`.
+    #[test]
+    fn test_mkstemp() {
+        let (reader, writer) = unistd::pipe().unwrap();
+        test_with_proot(
+            |_, _, _| {},
+            move || {
+                let (fd, path) = unistd::mkstemp("/tmp/file_for_test_mkstemp_XXXXXX").unwrap();
+                unistd::write(fd, b"proot").unwrap();
+                unistd::close(fd).unwrap();
+
+                // the guest sees the filled in template
+                assert!(path.starts_with("/tmp"));
+                assert!(!path.to_string_lossy().ends_with("XXXXXX"));
+                assert_eq!(std::fs::read(&path).unwrap(), b"proot");
+
+                // the error on which mkstemp() retries with another name
+                assert_eq!(
+                    nix::fcntl::open(
+                        &path,
+                        OFlag::O_RDWR | OFlag::O_CREAT | OFlag::O_EXCL,
+                        Mode::from_bits_truncate(0o600)
+                    ),
+                    Err(nix::Error::Sys(Errno::EEXIST))
+                );
+                unistd::write(writer, path.as_os_str().as_bytes()).unwrap();
+            },
+        );
+        unistd::close(writer).unwrap();
+
+        let mut guest_path = vec![];
+        unsafe { File::from_raw_fd(reader) }
+            .read_to_end(&mut guest_path)
+            .unwrap();
+        let guest_path = PathBuf::from(OsStr::from_bytes(&guest_path));
+        // the file was created at the translated location
+        let host_path = get_test_rootfs_path().join(guest_path.strip_prefix("/").unwrap());
+        let content = std::fs::read(&host_path);
+        std::fs::remove_file(&host_path).unwrap();
+        assert_eq!(content.unwrap(), b"proot");
+    }
 }
@@ -0,0 +1,335 @@
+use std::mem;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use libc::{c_void, AT_FDCWD, AT_SYMLINK_NOFOLLOW};
+use sc::nr::{
+    CHMOD, CHOWN, FCHMOD, FCHMODAT, FCHOWN, FCHOWNAT, FSTAT, LCHOWN, LSTAT, NEWFSTATAT, STAT,
+    STATX, UNLINKAT,
+};
+
+use crate::errors::*;
+use crate::kernel::standard::{
+    chmod_access_mknod_at, dir_link_attr, standard_syscall, stat_at, unlink_mkdir_at,
+};
+use crate::process::fake_metadata::{FakeMetadata, FileId};
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{
+    Current, Original, PtraceReader, PtraceWriter, SysArg, SysArg1, SysArg2, SysArg3, SysArg4,
+    SysArg5, SysResult,
+};
+
+/// Translates the paths of the syscalls changing or reading the owners and
+/// the mode of a file, like in the groups of the syscalls with the same
+/// arguments.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    match tracee.regs.get_sys_num(Current) {
+        CHOWN | CHMOD | STAT => standard_syscall::enter(tracee),
+        LCHOWN | LSTAT => dir_link_attr::enter(tracee),
+        FCHOWNAT | NEWFSTATAT | STATX => stat_at::enter(tracee),
+        FCHMODAT => chmod_access_mknod_at::enter(tracee),
+        // fchown(), fchmod() and fstat() operate on a file descriptor
+        _ => Ok(()),
+    }
+}
+
+/// With `fake_ownership`, chown() pretends to succeed when the host refuses
+/// it with `EPERM`, and so does chmod(): the owners and the mode given by the
+/// guest are recorded in the `FakeMetadata` instead, and reported by stat().
+///
+/// The other errors (e.g. a missing file) are reported as is.
+pub fn exit(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if !info_bag.config.fake_ownership {
+        return Ok(());
+    }
+    let sys_num = tracee.regs.get_sys_num(Original);
+    let result = tracee.regs.get(Current, SysResult) as isize;
+
+    match sys_num {
+        STAT | LSTAT | FSTAT | NEWFSTATAT | STATX if result == 0 => {
+            return fake_stat_buffer(tracee, sys_num, &info_bag.fake_metadata.borrow());
+        }
+        CHOWN | LCHOWN | FCHOWN | FCHOWNAT | CHMOD | FCHMOD | FCHMODAT
+            if result == 0 || result == -(EPERM as isize) => {}
+        _ => return Ok(()),
+    }
+
+    let file_id = match get_file_id(tracee, sys_num) {
+        Some(file_id) => file_id,
+        None => return Ok(()),
+    };
+    let arg = |index| tracee.regs.get(Original, SysArg(index));
+    // -1 leaves the owner unchanged
+    let id = |index| Some(arg(index) as u32).filter(|&id| id != u32::MAX);
+    let mut fake_metadata = info_bag.fake_metadata.borrow_mut();
+
+    match sys_num {
+        FCHOWNAT => fake_metadata.set_owners(file_id, id(SysArg3), id(SysArg4)),
+        CHOWN | LCHOWN | FCHOWN => fake_metadata.set_owners(file_id, id(SysArg2), id(SysArg3)),
+        _ => {
+            let mode_arg = if sys_num == FCHMODAT {
+                SysArg3
+            } else {
+                SysArg2
+            };
+            // the mode of the host file is the real one when it's changed
+            let mode = Some(arg(mode_arg) as u32).filter(|_| result != 0);
+            fake_metadata.set_mode(file_id, mode);
+        }
+    }
+
+    tracee
+        .regs
+        .set(SysResult, 0, "the ownership is faked, simulate success");
+    Ok(())
+}
+
+/// Translates the path of unlink(), unlinkat() and rmdir(). With
+/// `fake_ownership`, the file is noted if its last link is being removed and
+/// its metadata is faked, to forget it in `unlink_exit()`: its inode may be
+/// reused by another file.
+pub fn unlink_enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let sys_num = tracee.regs.get_sys_num(Current);
+    if sys_num == UNLINKAT {
+        unlink_mkdir_at::enter(tracee)?;
+    } else {
+        dir_link_attr::enter(tracee)?;
+    }
+
+    tracee.unlinked_file = None;
+    if !info_bag.config.fake_ownership {
+        return Ok(());
+    }
+    let path_arg = if sys_num == UNLINKAT {
+        SysArg2
+    } else {
+        SysArg1
+    };
+    let host_path = tracee.regs.get_sysarg_path(path_arg)?;
+    let metadata = match host_path.symlink_metadata() {
+        Ok(metadata) => metadata,
+        // the kernel reports the error
+        Err(_) => return Ok(()),
+    };
+    // the links of a directory are its entries, it has no other link
+    if metadata.is_dir() || metadata.nlink() == 1 {
+        let file_id = FileId::from(&metadata);
+        if info_bag.fake_metadata.borrow().get(file_id).is_some() {
+            tracee.unlinked_file = Some(file_id);
+        }
+    }
+    Ok(())
+}
+
+pub fn unlink_exit(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if let Some(file_id) = tracee.unlinked_file.take() {
+        if tracee.regs.get(Current, SysResult) == 0 {
+            info_bag.fake_metadata.borrow_mut().remove(file_id);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the identity of the file the current syscall operated on: the one
+/// of its (translated) path argument, or the one of its file descriptor.
+fn get_file_id(tracee: &Tracee, sys_num: usize) -> Option<FileId> {
+    let arg = |index| tracee.regs.get(Original, SysArg(index));
+    let (dirfd, path_arg, flags) = match sys_num {
+        CHOWN | CHMOD => (AT_FDCWD, Some(SysArg1), 0),
+        LCHOWN => (AT_FDCWD, Some(SysArg1), AT_SYMLINK_NOFOLLOW),
+        FCHOWN | FCHMOD => (arg(SysArg1) as RawFd, None, 0),
+        FCHOWNAT => (arg(SysArg1) as RawFd, Some(SysArg2), arg(SysArg5) as i32),
+        FCHMODAT => (arg(SysArg1) as RawFd, Some(SysArg2), 0),
+        _ => return None,
+    };
+
+    let host_path = match path_arg {
+        Some(path_arg) => tracee.regs.get_sysarg_path(path_arg).ok()?,
+        None => Default::default(),
+    };
+    if !host_path.as_os_str().is_empty() {
+        return FileId::of_path(&host_path, flags & AT_SYMLINK_NOFOLLOW == 0);
+    }
+    // the file descriptor itself (e.g. with `AT_EMPTY_PATH`), whose link in
+    // `/proc` reaches the file even if it was moved or removed
+    let fd_path = match dirfd {
+        AT_FDCWD => format!("/proc/{}/cwd", tracee.pid),
+        fd => format!("/proc/{}/fd/{}", tracee.pid, fd),
+    };
+    FileId::of_path(Path::new(&fd_path), true)
+}
+
+/// Returns the device number of `st_dev` from its major and minor numbers,
+/// like `makedev()`.
+fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+/// Replaces the owners and the mode of the `struct stat` (or `struct statx`)
+/// filled by the kernel with the faked ones, if the file it describes has
+/// any.
+fn fake_stat_buffer(tracee: &Tracee, sys_num: usize, fake_metadata: &FakeMetadata) -> Result<()> {
+    let buffer_arg = match sys_num {
+        NEWFSTATAT => SysArg3,
+        STATX => SysArg5,
+        _ => SysArg2,
+    };
+    let address = tracee.regs.get(Original, SysArg(buffer_arg)) as *mut c_void;
+
+    if sys_num == STATX {
+        let size = mem::size_of::<libc::statx>();
+        let data = tracee.regs.read_data(address, size)?;
+        let mut stat: libc::statx = unsafe { ptr::read_unaligned(data.as_ptr() as *const _) };
+        let file_id = FileId {
+            dev: makedev(stat.stx_dev_major, stat.stx_dev_minor),
+            ino: stat.stx_ino,
+        };
+        let attributes = match fake_metadata.get(file_id) {
+            Some(attributes) => attributes,
+            None => return Ok(()),
+        };
+        stat.stx_uid = attributes.uid.unwrap_or(stat.stx_uid);
+        stat.stx_gid = attributes.gid.unwrap_or(stat.stx_gid);
+        stat.stx_mode = attributes.apply_to_mode(stat.stx_mode as u32) as u16;
+        let data = unsafe { slice::from_raw_parts(&stat as *const _ as *const u8, size) };
+        tracee.regs.write_data(address, data, false)
+    } else {
+        let size = mem::size_of::<libc::stat>();
+        let data = tracee.regs.read_data(address, size)?;
+        let mut stat: libc::stat = unsafe { ptr::read_unaligned(data.as_ptr() as *const _) };
+        let file_id = FileId {
+            dev: stat.st_dev,
+            ino: stat.st_ino,
+        };
+        let attributes = match fake_metadata.get(file_id) {
+            Some(attributes) => attributes,
+            None => return Ok(()),
+        };
+        stat.st_uid = attributes.uid.unwrap_or(stat.st_uid);
+        stat.st_gid = attributes.gid.unwrap_or(stat.st_gid);
+        stat.st_mode = attributes.apply_to_mode(stat.st_mode);
+        let data = unsafe { slice::from_raw_parts(&stat as *const _ as *const u8, size) };
+        tracee.regs.write_data(address, data, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    /// Changes the owners of a file of a binding through a symlink, and stats
+    /// it through the binding.
+    fn test_fake_ownership_aliases() {
+        let host_dir = std::env::temp_dir().join("proot_rs_test_fake_ownership");
+        std::fs::create_dir_all(&host_dir).unwrap();
+        std::fs::write(host_dir.join("file"), "").unwrap();
+        let host_file = host_dir.join("file");
+        let link_path = get_test_rootfs_path().join("tmp/proot_rs_test_fake_ownership_link");
+        let _ = std::fs::remove_file(&link_path);
+        std::os::unix::fs::symlink("/mnt/fake", &link_path).unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .synthetic_mountpoints(true)
+                .binding(&host_dir, "/mnt/fake")
+                .fake_ownership(true)
+                .build(),
+            move |tracee, is_sysenter, before_translation| {
+                if is_sysenter || before_translation || tracee.regs.get_sys_num(Original) != CHOWN {
+                    return;
+                }
+                // the symlink is followed into the binding
+                if tracee.regs.get(Current, SysResult) == 0 {
+                    assert_eq!(
+                        get_file_id(tracee, CHOWN),
+                        FileId::of_path(&host_file, true)
+                    );
+                }
+            },
+            || {
+                let alias = "/tmp/proot_rs_test_fake_ownership_link/file";
+                let path = "/mnt/fake/file";
+
+                assert_eq!(nc::chown(alias, 4321, 8765), Ok(()));
+                let mut stat = nc::stat_t::default();
+                nc::stat(path, &mut stat).unwrap();
+                assert_eq!((stat.st_uid, stat.st_gid), (4321, 8765));
+
+                // -1 leaves the owner unchanged
+                assert_eq!(nc::chown(path, -1i64 as _, 1111), Ok(()));
+                nc::lstat(alias, &mut stat).unwrap();
+                assert_eq!((stat.st_uid, stat.st_gid), (4321, 1111));
+
+                assert_eq!(nc::chmod(alias, 0o4750), Ok(()));
+                let file = File::open(path).unwrap();
+                nc::fstat(file.as_raw_fd(), &mut stat).unwrap();
+                assert_eq!(stat.st_mode & 0o7777, 0o4750);
+                assert_eq!((stat.st_uid, stat.st_gid), (4321, 1111));
+
+                assert_eq!(nc::chown("/mnt/fake/missing", 0, 0), Err(nc::ENOENT));
+            },
+        );
+
+        std::fs::remove_file(&link_path).unwrap();
+        std::fs::remove_dir_all(&host_dir).unwrap();
+    }
+
+    #[test]
+    /// Changes the owners of a file through one of its hard links, and
+    /// removes them one after the other.
+    fn test_fake_ownership_hard_links() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .fake_ownership(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                let path = "/tmp/proot_rs_test_fake_ownership_hard_link_1";
+                let other_path = "/tmp/proot_rs_test_fake_ownership_hard_link_2";
+                let _ = std::fs::remove_file(other_path);
+                std::fs::write(path, "").unwrap();
+                std::fs::hard_link(path, other_path).unwrap();
+                // as root, the host changes the owners itself
+                let real_uid = match unsafe { libc::getuid() } {
+                    0 => 4321,
+                    uid => uid,
+                };
+
+                assert_eq!(nc::chown(path, 4321, 8765), Ok(()));
+                let mut stat = nc::stat_t::default();
+                nc::stat(other_path, &mut stat).unwrap();
+                assert_eq!((stat.st_uid, stat.st_gid), (4321, 8765));
+
+                // the other link keeps the owners
+                let file = File::open(other_path).unwrap();
+                nc::unlink(path).unwrap();
+                nc::stat(other_path, &mut stat).unwrap();
+                assert_eq!(stat.st_uid, 4321);
+                nc::fstat(file.as_raw_fd(), &mut stat).unwrap();
+                assert_eq!(stat.st_uid, 4321);
+
+                // the last link is removed, and so are the fake owners, even
+                // if the file is still open
+                nc::unlink(other_path).unwrap();
+                nc::fstat(file.as_raw_fd(), &mut stat).unwrap();
+                assert_eq!(stat.st_uid, real_uid);
+            },
+        );
+    }
+}
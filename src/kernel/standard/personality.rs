@@ -0,0 +1,105 @@
+use libc::c_ulong;
+
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, SysArg, SysArg1, Word};
+
+/// Flag of the persona disabling the randomization of the stack, the mmap()
+/// base and the brk() area, see `include/uapi/linux/personality.h`.
+pub const ADDR_NO_RANDOMIZE: c_ulong = 0x0040000;
+/// Persona given to personality() to query the current one, without
+/// changing it.
+pub const PERSONALITY_QUERY: c_ulong = 0xffff_ffff;
+
+/// personality(persona) sets the execution domain of the tracee, inherited
+/// by the programs it executes.
+///
+/// With `no_randomize`, the first tracee starts with `ADDR_NO_RANDOMIZE`
+/// (see `PRoot::launch_process()`), and it's ORed into any new persona, so
+/// that a tracee can't turn the randomization back on. Queries are left
+/// untouched, and report it.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if !info_bag.config.no_randomize {
+        return Ok(());
+    }
+
+    let persona = tracee.regs.get(Current, SysArg(SysArg1)) as c_ulong;
+    // only the low 32 bits are used by the kernel
+    if persona & PERSONALITY_QUERY == PERSONALITY_QUERY {
+        return Ok(());
+    }
+    tracee.regs.set(
+        SysArg(SysArg1),
+        (persona | ADDR_NO_RANDOMIZE) as Word,
+        "during enter personality translation, keeping ADDR_NO_RANDOMIZE",
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+
+    use libc::c_ulong;
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use nix::unistd::{self, execv, fork, ForkResult};
+
+    use super::{ADDR_NO_RANDOMIZE, PERSONALITY_QUERY};
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    /// Executes `cat /proc/self/maps` in a child of the tracee, and returns
+    /// its output.
+    fn read_maps_of_new_program() -> String {
+        let (reader, writer) = unistd::pipe().unwrap();
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                unistd::dup2(writer, libc::STDOUT_FILENO).unwrap();
+                execv(
+                    &CString::new("/bin/busybox").unwrap(),
+                    &[
+                        CString::new("cat").unwrap(),
+                        CString::new("/proc/self/maps").unwrap(),
+                    ],
+                )
+                .expect("failed execv busybox");
+                unreachable!()
+            }
+            ForkResult::Parent { child } => {
+                unistd::close(writer).unwrap();
+                let mut maps = String::new();
+                unsafe { std::fs::File::from_raw_fd(reader) }
+                    .read_to_string(&mut maps)
+                    .unwrap();
+                assert_eq!(waitpid(child, None), Ok(WaitStatus::Exited(child, 0)));
+                maps
+            }
+        }
+    }
+
+    #[test]
+    fn test_personality_no_randomize() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .proc_shim(true)
+                .no_randomize(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // clearing the persona keeps ADDR_NO_RANDOMIZE
+                unsafe { libc::personality(0) };
+                let persona = unsafe { libc::personality(PERSONALITY_QUERY) } as c_ulong;
+                assert_ne!(persona & ADDR_NO_RANDOMIZE, 0);
+
+                // the programs are mapped at the same addresses on each run
+                let maps = read_maps_of_new_program();
+                assert!(maps.contains("[stack]"));
+                assert_eq!(maps, read_maps_of_new_program());
+            },
+        )
+    }
+}
@@ -0,0 +1,107 @@
+use std::os::unix::prelude::RawFd;
+use std::path::PathBuf;
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::kernel::groups::nr::{PIDFD_GETFD, PIDFD_OPEN};
+use crate::process::fd_table::{PIDFD_MARKER, RECEIVED_FD_MARKER};
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceReader, SysResult};
+
+/// Records the file descriptors created by pidfd_open(pid, flags) and
+/// pidfd_getfd(pidfd, targetfd, flags) in the fd table. The kernel always
+/// sets their close-on-exec flag.
+///
+/// The pids of the guest are the host ones (they aren't virtualized), so
+/// the pid of pidfd_open() is passed through, and the kernel fails with
+/// `ESRCH` if it doesn't exist. The file descriptor copied from another
+/// process by pidfd_getfd() is recorded under its guest path if it refers
+/// to a file of the guest filesystem, like the ones received with
+/// `SCM_RIGHTS`.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let fd = tracee.regs.get(Current, SysResult) as RawFd;
+    if fd < 0 {
+        return Ok(());
+    }
+
+    let path: PathBuf = match tracee.regs.get_sys_num(Original) {
+        PIDFD_OPEN => PIDFD_MARKER.into(),
+        PIDFD_GETFD => tracee
+            .get_path_from_fd(fd, Side::Guest)
+            .unwrap_or_else(|_| RECEIVED_FD_MARKER.into()),
+        _ => return Ok(()),
+    };
+    tracee.fd_table.borrow_mut().insert(fd, path, true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use nix::errno::Errno;
+    use nix::sys::signal::Signal;
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use nix::unistd::{fork, pause, ForkResult};
+
+    use super::*;
+    use crate::utils::tests::test_with_proot;
+
+    #[test]
+    fn test_pidfd_open_send_signal() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != PIDFD_OPEN
+                {
+                    return;
+                }
+                let fd = tracee.regs.get(Current, SysResult) as RawFd;
+                if fd >= 0 {
+                    let fd_table = tracee.fd_table.borrow();
+                    let entry = fd_table.get(fd).unwrap();
+                    assert_eq!(entry.path, Path::new(PIDFD_MARKER));
+                    assert!(entry.cloexec);
+                }
+            },
+            || {
+                let child = match unsafe { fork() }.unwrap() {
+                    ForkResult::Child => loop {
+                        pause();
+                    },
+                    ForkResult::Parent { child } => child,
+                };
+
+                let pidfd = unsafe { libc::syscall(PIDFD_OPEN as libc::c_long, child.as_raw(), 0) };
+                if pidfd < 0 && Errno::last() == Errno::ENOSYS {
+                    // the syscall is recent
+                    nix::sys::signal::kill(child, Signal::SIGKILL).unwrap();
+                    waitpid(child, None).unwrap();
+                    return;
+                }
+                assert!(pidfd >= 0);
+                let result = unsafe {
+                    libc::syscall(
+                        crate::kernel::groups::nr::PIDFD_SEND_SIGNAL as libc::c_long,
+                        pidfd,
+                        libc::SIGKILL,
+                        std::ptr::null::<libc::siginfo_t>(),
+                        0,
+                    )
+                };
+                assert_eq!(result, 0);
+                assert_eq!(
+                    waitpid(child, None),
+                    Ok(WaitStatus::Signaled(child, Signal::SIGKILL, false))
+                );
+
+                // the pid doesn't exist
+                let result =
+                    unsafe { libc::syscall(PIDFD_OPEN as libc::c_long, libc::pid_t::MAX, 0) };
+                assert_eq!(result, -1);
+                assert_eq!(Errno::last(), Errno::ESRCH);
+            },
+        )
+    }
+}
@@ -0,0 +1,108 @@
+use std::mem::size_of;
+use std::os::unix::prelude::RawFd;
+
+use libc::{c_int, c_void};
+use nix::fcntl::OFlag;
+use sc::nr::PIPE2;
+
+use crate::errors::*;
+use crate::process::fd_table::PIPE_MARKER;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceReader, SysArg, SysArg1, SysArg2, SysResult};
+
+/// Reads the two file descriptors written by pipe() or pipe2() into the
+/// `pipefd` array of the tracee.
+fn read_pipe_fds(tracee: &Tracee) -> Result<[RawFd; 2]> {
+    let pipefd = tracee.regs.get(Original, SysArg(SysArg1));
+    let data = tracee
+        .regs
+        .read_data(pipefd as *const c_void, 2 * size_of::<c_int>())?;
+
+    let mut fds = [0; 2];
+    for (fd, bytes) in fds.iter_mut().zip(data.chunks_exact(size_of::<c_int>())) {
+        let mut raw = [0u8; size_of::<c_int>()];
+        raw.copy_from_slice(bytes);
+        *fd = c_int::from_ne_bytes(raw);
+    }
+    Ok(fds)
+}
+
+/// Records both ends of the pipe in the fd table with the synthetic
+/// `PIPE_MARKER` path, so that close() and dup() bookkeeping stays correct.
+///
+/// The pipe is already created at this point, so a failure only results in
+/// the file descriptors not being tracked.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let result = tracee.regs.get(Current, SysResult) as i32;
+    if result < 0 {
+        return Ok(());
+    }
+
+    let cloexec = tracee.regs.get_sys_num(Original) == PIPE2
+        && OFlag::from_bits_truncate(tracee.regs.get(Original, SysArg(SysArg2)) as _)
+            .contains(OFlag::O_CLOEXEC);
+
+    match read_pipe_fds(tracee) {
+        Ok(fds) => {
+            let mut fd_table = tracee.fd_table.borrow_mut();
+            for &fd in fds.iter() {
+                fd_table.insert(fd, PIPE_MARKER, cloexec);
+            }
+        }
+        Err(error) => debug!("failed to track pipe file descriptors: {}", error),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use nix::fcntl::OFlag;
+    use nix::unistd;
+    use sc::nr::{CLOSE, PIPE2};
+
+    use crate::process::fd_table::PIPE_MARKER;
+    use crate::register::{Current, Original, SysArg, SysArg1, SysResult};
+    use crate::utils::tests::test_with_proot;
+
+    use super::read_pipe_fds;
+
+    #[test]
+    fn test_pipe_fd_tracking() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter || before_translation {
+                    return;
+                }
+                let sys_num = tracee.regs.get_sys_num(Original);
+                if sys_num == PIPE2 && (tracee.regs.get(Current, SysResult) as i32) == 0 {
+                    let fd_table = tracee.fd_table.borrow();
+                    for &fd in read_pipe_fds(tracee).unwrap().iter() {
+                        let entry = fd_table.get(fd).unwrap();
+                        assert_eq!(entry.path, Path::new(PIPE_MARKER));
+                        assert!(entry.cloexec);
+                    }
+                } else if sys_num == CLOSE {
+                    let fd = tracee.regs.get(Original, SysArg(SysArg1)) as i32;
+                    assert_eq!(tracee.fd_table.borrow().get(fd), None);
+                }
+            },
+            || {
+                let (read_fd, write_fd) = unistd::pipe2(OFlag::O_CLOEXEC).unwrap();
+                unistd::write(write_fd, b"proot").unwrap();
+                let mut buf = [0u8; 5];
+                assert_eq!(unistd::read(read_fd, &mut buf).unwrap(), 5);
+                assert_eq!(&buf, b"proot");
+
+                // the target of the fds is the one reported by the kernel
+                let link =
+                    nix::fcntl::readlink(format!("/proc/self/fd/{}", read_fd).as_str()).unwrap();
+                assert!(link.to_str().unwrap().starts_with("pipe:["));
+
+                unistd::close(read_fd).unwrap();
+                unistd::close(write_fd).unwrap();
+            },
+        )
+    }
+}
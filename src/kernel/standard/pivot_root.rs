@@ -1,11 +1,136 @@
-use crate::errors::Result;
+use std::path::PathBuf;
 
-pub fn enter() -> Result<()> {
+use crate::errors::*;
+use crate::filesystem::{Canonicalizer, Translator};
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{PtraceReader, PtraceWriter, SysArg1, SysArg2, SysArgIndex, SysResult};
+
+/// Returns the canonical guest path pointed to by `sys_arg`.
+pub fn get_canonical_guest_path(tracee: &Tracee, sys_arg: SysArgIndex) -> Result<PathBuf> {
+    let raw_path = tracee.regs.get_sysarg_path(sys_arg)?;
+    let fs = tracee.fs.borrow();
+
+    if raw_path.is_relative() {
+        fs.canonicalize(fs.get_cwd().join(raw_path), true)
+    } else {
+        fs.canonicalize(raw_path, true)
+    }
+}
+
+/// pivot_root(new_root, put_old) requires `CAP_SYS_ADMIN`, so it can be
+/// emulated (`emulate_pivot_root`) by remapping the bindings of the tracee,
+/// instead of being sent to the host with both paths translated. The
+/// emulation fails with `EPERM` when the paths can't be pivoted.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if info_bag.config.emulate_pivot_root {
+        let new_root = get_canonical_guest_path(tracee, SysArg1)?;
+        let put_old = get_canonical_guest_path(tracee, SysArg2)?;
+
+        tracee
+            .fs
+            .borrow_mut()
+            .pivot_root(&new_root, &put_old)
+            .map_err(|error| {
+                Error::errno_with_msg(
+                    EPERM,
+                    format!(
+                        "can't pivot the root to {:?} with {:?}: {}",
+                        new_root, put_old, error
+                    ),
+                )
+            })?;
+        tracee
+            .regs
+            .cancel_syscall("pivot_root() is emulated, avoid syscall");
+        return Ok(());
+    }
+
+    for &sys_arg in [SysArg1, SysArg2].iter() {
+        let raw_path = tracee.regs.get_sysarg_path(sys_arg)?;
+        let host_path = tracee.fs.borrow().translate_path(raw_path, true)?;
+
+        tracee.regs.set_sysarg_path(
+            sys_arg,
+            &host_path,
+            "during enter pivot_root translation, setting host path",
+        )?;
+    }
+    Ok(())
+}
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.is_syscall_cancelled() {
+        tracee
+            .regs
+            .set(SysResult, 0, "pivot_root() was emulated successfully");
+    }
     Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+    use std::path::Path;
+
+    use libc::SYS_pivot_root;
+    use nix::errno::Errno;
+    use nix::unistd;
+
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    fn pivot_root(new_root: &str, put_old: &str) -> Result<(), Errno> {
+        let new_root = CString::new(new_root).unwrap();
+        let put_old = CString::new(put_old).unwrap();
+        match unsafe { libc::syscall(SYS_pivot_root, new_root.as_ptr(), put_old.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(Errno::last()),
+        }
+    }
+
+    #[test]
+    fn test_pivot_root_emulation() {
+        let new_root = get_test_rootfs_path().join("tmp/dir_for_test_pivot_root");
+        fs::create_dir_all(new_root.join("old")).unwrap();
+        fs::write(new_root.join("marker"), "").unwrap();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .emulate_pivot_root(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // failing cases: put_old outside of new_root, new_root not a
+                // directory, or already the root
+                assert_eq!(
+                    pivot_root("/tmp/dir_for_test_pivot_root", "/tmp"),
+                    Err(Errno::EPERM)
+                );
+                assert_eq!(
+                    pivot_root("/tmp/dir_for_test_pivot_root/marker", "/tmp"),
+                    Err(Errno::EPERM)
+                );
+                assert_eq!(pivot_root("/", "/tmp"), Err(Errno::EPERM));
+
+                unistd::chdir("/tmp/dir_for_test_pivot_root").unwrap();
+                assert_eq!(pivot_root(".", "old"), Ok(()));
+
+                // the new root view
+                assert!(Path::new("/marker").exists());
+                assert!(Path::new("/old/tmp/dir_for_test_pivot_root/marker").exists());
+                assert!(Path::new("/old/bin").exists());
+                assert!(!Path::new("/bin").exists());
+                assert_eq!(unistd::getcwd().unwrap(), Path::new("/"));
+                assert_eq!(
+                    fs::read_link("/proc/self/root").unwrap(),
+                    Path::new("/tmp/dir_for_test_pivot_root")
+                );
+            },
+        );
 
-    //                 status = translate_sysarg(tracee, SYSARG_1, REGULAR);
-    //                 if (status < 0)
-    //                     break;
-    //
-    //                 status = translate_sysarg(tracee, SYSARG_2, REGULAR);
+        fs::remove_dir_all(&new_root).unwrap();
+    }
 }
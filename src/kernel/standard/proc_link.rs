@@ -0,0 +1,91 @@
+use std::os::unix::prelude::RawFd;
+use std::path::{Path, PathBuf};
+
+use crate::errors::*;
+use crate::filesystem::binding::Side;
+use crate::filesystem::Translator;
+use crate::process::tracee::Tracee;
+
+/// If `guest_path` is a virtualized `/proc/<pid>/` link of this tracee,
+/// returns its guest target:
+/// - `exe`, the program given to the last execve();
+/// - `cwd` and `root`, from the file-system of the tracee, the latter being
+///   composed by the emulated chroot() calls;
+/// - `fd/<n>`, if the file descriptor `<n>` is tracked: the live target of
+///   the host `/proc` detranslated, which follows the renames of the file
+///   since it was opened, or the guest path it was opened with when the live
+///   target isn't available (e.g. it's outside of the guest rootfs). Virtual
+///   files always keep the latter. Anonymous entries (e.g. pipes) are left to
+///   the kernel, which reports them as `pipe:[<inode>]`.
+///
+/// These entries always override the ones of the host `/proc` (see the
+/// `--proc-shim` option), whose other links are detranslated by
+/// `readlink_at::exit()`.
+pub fn get_virtual_proc_link(tracee: &Tracee, guest_path: &Path) -> Option<PathBuf> {
+    let relative_path = guest_path.strip_prefix("/proc").ok()?.to_str()?;
+    let parts: Vec<&str> = relative_path.split('/').collect();
+
+    let is_this_tracee = match parts[0] {
+        "self" | "thread-self" => true,
+        pid => pid.parse::<i32>().ok()? == tracee.pid.as_raw(),
+    };
+    if !is_this_tracee {
+        return None;
+    }
+
+    match parts[1..] {
+        ["exe"] => tracee.exe.as_ref().map(|exe| exe.borrow().clone()),
+        ["cwd"] => Some(tracee.fs.borrow().get_cwd().to_path_buf()),
+        ["root"] => Some(tracee.fs.borrow().get_chroot_path().to_path_buf()),
+        ["fd", fd] => {
+            let fd = fd.parse::<RawFd>().ok()?;
+            let fd_table = tracee.fd_table.borrow();
+            let entry = fd_table.get(fd).filter(|entry| !entry.is_anonymous())?;
+            if entry.virtual_content.is_some() {
+                return Some(entry.path.clone());
+            }
+            match tracee.get_path_from_fd(fd, Side::Guest) {
+                Ok(path) => Some(path),
+                Err(_) => Some(entry.path.clone()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Same as `Tracee::translate_path_at_or_dirfd()`, except that a virtualized
+/// `/proc/<pid>/` link is resolved to its guest target (see
+/// `get_virtual_proc_link()`) when its final component is dereferenced,
+/// instead of following the host target written by the kernel.
+///
+/// Like the kernel's magic links, the target itself is the result, it isn't
+/// dereferenced again: stating the link of a file descriptor opened on a
+/// symlink with `O_PATH | O_NOFOLLOW` gives the symlink.
+///
+/// With `for_write`, the path is about to be modified, see
+/// `Translator::translate_path_for_write()`.
+pub fn translate_proc_path_at_or_dirfd(
+    tracee: &Tracee,
+    dirfd: RawFd,
+    raw_path: &Path,
+    empty_path: bool,
+    deref_final: bool,
+    for_write: bool,
+) -> Result<Option<PathBuf>> {
+    let guest_path = match tracee.get_guest_path_at(dirfd, raw_path, empty_path)? {
+        Some(guest_path) => guest_path,
+        None => return Ok(None),
+    };
+    let (guest_path, deref_final) = match get_virtual_proc_link(tracee, &guest_path) {
+        Some(target) if deref_final => (target, false),
+        _ => (guest_path, deref_final),
+    };
+    let fs = tracee.fs.borrow();
+    if for_write {
+        fs.translate_path_for_write(guest_path, deref_final)
+            .map(Some)
+    } else {
+        fs.translate_absolute_path(guest_path, deref_final)
+            .map(Some)
+    }
+}
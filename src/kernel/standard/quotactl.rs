@@ -0,0 +1,125 @@
+use crate::errors::*;
+
+use crate::filesystem::Translator;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, PtraceReader, PtraceWriter, SysArg, SysArg2};
+
+/// quotactl(cmd, special, id, addr): `special` is the path of the block device
+/// of the mounted filesystem, and may be NULL for some commands.
+///
+/// quotactl_fd(fd, cmd, id, addr) takes a file descriptor of the filesystem
+/// instead, and is passed through untouched.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.get(Current, SysArg(SysArg2)) == 0 {
+        return Ok(());
+    }
+
+    let raw_path = tracee.regs.get_sysarg_path(SysArg2)?;
+
+    let host_path = tracee.fs.borrow().translate_path(raw_path, true)?;
+
+    tracee.regs.set_sysarg_path(
+        SysArg2,
+        &host_path,
+        "during enter quotactl translation, setting host path",
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    use libc::{c_char, c_int, SYS_quotactl};
+    use nix::errno::Errno;
+
+    use crate::kernel::groups::nr::QUOTACTL_FD;
+    use crate::kernel::groups::{syscall_group_from_sysnum, SyscallGroup};
+    use crate::register::{Current, Original, SysArg, SysArg1, SysArg2, SysArg3, SysArg4};
+    use crate::utils::tests::test_with_proot;
+
+    /// Q_GETQUOTA for the user quotas, see quotactl(2).
+    const Q_GETQUOTA_USRQUOTA: c_int = (0x80_0007_u32 << 8) as c_int;
+
+    fn quotactl(special: &str) -> Errno {
+        let special = CString::new(special).unwrap();
+        let mut data = [0u8; 128];
+        let result = unsafe {
+            libc::syscall(
+                SYS_quotactl,
+                Q_GETQUOTA_USRQUOTA,
+                special.as_ptr(),
+                0 as c_int,
+                data.as_mut_ptr() as *mut c_char,
+            )
+        };
+        assert_eq!(result, -1);
+        Errno::last()
+    }
+
+    #[test]
+    fn test_quotactl() {
+        test_with_proot(
+            |_tracee, _is_sysenter, _before_translation| {},
+            || {
+                // this file only exists in the guest rootfs
+                let filepath = "/tmp/file_for_test_quotactl";
+                File::create(filepath).unwrap();
+
+                let result = std::panic::catch_unwind(|| {
+                    // the file is found, but it isn't a block device (or the
+                    // operation isn't permitted)
+                    assert_ne!(quotactl(filepath), Errno::ENOENT);
+                    assert_eq!(quotactl("/tmp/impossible_path"), Errno::ENOENT);
+                });
+                std::fs::remove_file(filepath).unwrap();
+                if let Err(err) = result {
+                    std::panic::resume_unwind(err);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_quotactl_fd_untouched() {
+        assert_eq!(
+            syscall_group_from_sysnum(QUOTACTL_FD),
+            SyscallGroup::Ignored
+        );
+
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != QUOTACTL_FD
+                {
+                    return;
+                }
+                for &index in [SysArg1, SysArg2, SysArg3, SysArg4].iter() {
+                    assert_eq!(
+                        tracee.regs.get(Current, SysArg(index)),
+                        tracee.regs.get(Original, SysArg(index))
+                    );
+                }
+            },
+            || {
+                let dir = File::open("/etc").unwrap();
+                let mut data = [0u8; 128];
+                let result = unsafe {
+                    libc::syscall(
+                        QUOTACTL_FD as libc::c_long,
+                        dir.as_raw_fd(),
+                        Q_GETQUOTA_USRQUOTA,
+                        0 as c_int,
+                        data.as_mut_ptr() as *mut c_char,
+                    )
+                };
+                // the rootfs has no quotas (or the syscall is too recent)
+                assert_eq!(result, -1);
+            },
+        )
+    }
+}
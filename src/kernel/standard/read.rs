@@ -0,0 +1,250 @@
+use std::convert::TryInto;
+use std::mem::size_of;
+use std::os::unix::prelude::RawFd;
+use std::rc::Rc;
+
+use libc::{c_int, c_void, iovec};
+use sc::nr::{LSEEK, PREAD64, PREADV, PREADV2, READV};
+
+use crate::errors::*;
+use crate::process::fd_table::VirtualContent;
+use crate::process::tracee::Tracee;
+use crate::register::{
+    Original, PtraceReader, PtraceWriter, SysArg, SysArg1, SysArg2, SysArg3, SysArg4, SysResult,
+    Word,
+};
+
+/// Maximum number of buffers of readv(), like the kernel (`UIO_MAXIOV`).
+const UIO_MAXIOV: usize = 1024;
+
+/// Returns the virtual content of the file descriptor read by read(),
+/// pread64(), readv(), preadv(), preadv2() or moved by lseek(), if any.
+fn get_virtual_content(tracee: &Tracee) -> Option<Rc<VirtualContent>> {
+    let fd = tracee.regs.get(Original, SysArg(SysArg1)) as RawFd;
+
+    tracee
+        .fd_table
+        .borrow()
+        .get(fd)
+        .and_then(|entry| entry.virtual_content.clone())
+}
+
+/// Reads the buffers of readv(), as `(address, length)` pairs.
+fn read_iovecs(tracee: &Tracee, address: Word, count: usize) -> Result<Vec<(Word, usize)>> {
+    if count > UIO_MAXIOV {
+        return Err(Error::errno(EINVAL));
+    }
+    let bytes = tracee
+        .regs
+        .read_data(address as *const c_void, count * size_of::<iovec>())
+        .map_err(|_| Error::errno(EFAULT))?;
+    let iovecs = bytes
+        .chunks_exact(size_of::<iovec>())
+        .map(|iovec| {
+            let (base, len) = iovec.split_at(size_of::<Word>());
+            (
+                Word::from_ne_bytes(base.try_into().unwrap()),
+                Word::from_ne_bytes(len.try_into().unwrap()) as usize,
+            )
+        })
+        .collect();
+    Ok(iovecs)
+}
+
+/// The reads of a file descriptor with a virtual content (see
+/// `FdTable::set_virtual_content`), and the moves of its offset, are served
+/// by proot-rs in `exit()`, the host isn't asked.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    if get_virtual_content(tracee).is_some() {
+        tracee
+            .regs
+            .cancel_syscall("Cancel read of a virtual file and return our own content");
+    }
+    Ok(())
+}
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    if !tracee.regs.is_syscall_cancelled() {
+        return Ok(());
+    }
+    let virtual_content = get_virtual_content(tracee).ok_or_else(|| Error::errno(EBADF))?;
+    let sysnum = tracee.regs.get_sys_num(Original);
+    let result = match sysnum {
+        LSEEK => {
+            let offset = tracee.regs.get(Original, SysArg(SysArg2)) as i64;
+            let whence = tracee.regs.get(Original, SysArg(SysArg3)) as c_int;
+            virtual_content
+                .seek(offset, whence)
+                .ok_or_else(|| Error::errno(EINVAL))?
+        }
+        READV | PREADV | PREADV2 => {
+            let address = tracee.regs.get(Original, SysArg(SysArg2));
+            let count = tracee.regs.get(Original, SysArg(SysArg3)) as usize;
+            let iovecs = read_iovecs(tracee, address, count)?;
+            let total = iovecs.iter().map(|&(_, len)| len).sum();
+            // the offset -1 of preadv2() stands for the current offset
+            let offset = tracee.regs.get(Original, SysArg(SysArg4)) as i64;
+            let data = match (sysnum, offset) {
+                (READV, _) | (PREADV2, -1) => virtual_content.read(total),
+                (_, offset) if offset >= 0 => virtual_content.read_at(offset as usize, total),
+                _ => return Err(Error::errno(EINVAL)),
+            };
+
+            let mut remaining = data;
+            for (base, len) in iovecs {
+                if remaining.is_empty() {
+                    break;
+                }
+                let (chunk, rest) = remaining.split_at(len.min(remaining.len()));
+                if !chunk.is_empty() {
+                    tracee.regs.write_data(base as *mut c_void, chunk, false)?;
+                }
+                remaining = rest;
+            }
+            data.len()
+        }
+        _ => {
+            let output = tracee.regs.get(Original, SysArg(SysArg2));
+            let count = tracee.regs.get(Original, SysArg(SysArg3)) as usize;
+
+            let data = if sysnum == PREAD64 {
+                let offset = tracee.regs.get(Original, SysArg(SysArg4)) as i64;
+                if offset < 0 {
+                    return Err(Error::errno(EINVAL));
+                }
+                virtual_content.read_at(offset as usize, count)
+            } else {
+                virtual_content.read(count)
+            };
+
+            if !data.is_empty() {
+                tracee.regs.write_data(output as *mut c_void, data, false)?;
+            }
+            data.len()
+        }
+    };
+
+    tracee.regs.set(
+        SysResult,
+        result as Word,
+        "update return value in read::exit()",
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::AsRawFd;
+
+    use nix::errno::Errno;
+    use nix::sys::uio::{pread, preadv, readv, IoVec};
+    use nix::unistd::{dup, lseek, read, Whence};
+
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    fn test_read_virtual_content_in_chunks() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .binding("/etc", "/home")
+                .binding("/tmp", "/root")
+                .virtual_mounts(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                let expected = "rootfs / rootfs rw 0 0\n\
+                                none /home none rw,bind 0 0\n\
+                                none /root none rw,bind 0 0\n";
+                let file = std::fs::File::open("/proc/mounts").unwrap();
+                let fd = file.as_raw_fd();
+
+                // like the files of /proc, the host file has no size
+                assert_eq!(file.metadata().unwrap().len(), 0);
+
+                let mut content = vec![];
+                let mut buffer = [0u8; 7];
+                loop {
+                    // the duplicates share the same offset
+                    let read_fd = if content.len() % 2 == 0 {
+                        fd
+                    } else {
+                        dup(fd).unwrap()
+                    };
+                    let size = read(read_fd, &mut buffer).unwrap();
+                    if read_fd != fd {
+                        nix::unistd::close(read_fd).unwrap();
+                    }
+                    if size == 0 {
+                        break;
+                    }
+                    assert!(size <= buffer.len());
+                    content.extend_from_slice(&buffer[..size]);
+                }
+                assert_eq!(String::from_utf8(content).unwrap(), expected);
+
+                // pread64() doesn't depend on the offset
+                let mut buffer = [0u8; 6];
+                assert_eq!(pread(fd, &mut buffer, 9).unwrap(), 6);
+                assert_eq!(&buffer, b"rootfs");
+                assert_eq!(pread(fd, &mut buffer, 1000).unwrap(), 0);
+            },
+        )
+    }
+
+    #[test]
+    fn test_read_virtual_content_after_lseek_and_readv() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .binding("/etc", "/home")
+                .virtual_mounts(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                let expected = b"rootfs / rootfs rw 0 0\n\
+                                 none /home none rw,bind 0 0\n";
+                let file = std::fs::File::open("/proc/mounts").unwrap();
+                let fd = file.as_raw_fd();
+
+                // rewind() then read()
+                let mut buffer = [0u8; 64];
+                assert_eq!(read(fd, &mut buffer).unwrap(), expected.len());
+                assert_eq!(read(fd, &mut buffer).unwrap(), 0);
+                assert_eq!(lseek(fd, 0, Whence::SeekSet).unwrap(), 0);
+                assert_eq!(read(fd, &mut buffer[..6]).unwrap(), 6);
+                assert_eq!(&buffer[..6], b"rootfs");
+                assert_eq!(lseek(fd, 1, Whence::SeekCur).unwrap(), 7);
+                assert_eq!(
+                    lseek(fd, -5, Whence::SeekEnd).unwrap(),
+                    expected.len() as i64 - 5
+                );
+                assert_eq!(read(fd, &mut buffer).unwrap(), 5);
+                assert_eq!(&buffer[..5], b" 0 0\n");
+                assert_eq!(
+                    lseek(fd, -1, Whence::SeekSet),
+                    Err(nix::Error::Sys(Errno::EINVAL))
+                );
+
+                // readv() fills the buffers in order, from the current offset
+                assert_eq!(lseek(fd, 2, Whence::SeekSet).unwrap(), 2);
+                let (mut first, mut second) = ([0u8; 4], [0u8; 3]);
+                let mut iov = [
+                    IoVec::from_mut_slice(&mut first),
+                    IoVec::from_mut_slice(&mut second),
+                ];
+                assert_eq!(readv(fd, &mut iov).unwrap(), 7);
+                assert_eq!((&first, &second), (b"otfs", b" / "));
+                assert_eq!(read(fd, &mut buffer[..6]).unwrap(), 6);
+                assert_eq!(&buffer[..6], b"rootfs");
+
+                // preadv() doesn't depend on the offset
+                let mut first = [0u8; 4];
+                let iov = [IoVec::from_mut_slice(&mut first)];
+                assert_eq!(preadv(fd, &iov, 23).unwrap(), 4);
+                assert_eq!(&first, b"none");
+            },
+        )
+    }
+}
@@ -1,84 +1,224 @@
-use crate::errors::Result;
-
-pub fn exit() -> Result<()> {
-    // TODO: Find out why we need to call detranslate_path() on the result of
-    // readlinkat() here.
-
-    //    char referee[PATH_MAX];
-    //    char referer[PATH_MAX];
-    //    size_t old_size;
-    //    size_t new_size;
-    //    size_t max_size;
-    //    word_t input;
-    //    word_t output;
-    //
-    //    /* Error reported by the kernel.  */
-    //    if ((int) syscall_result < 0)
-    //        return SyscallExitResult::None;
-    //
-    //    old_size = syscall_result;
-    //
-    //    if (syscall_number == PR_readlink) {
-    //        output   = peek_reg(tracee, ORIGINAL, SYSARG_2);
-    //        max_size = peek_reg(tracee, ORIGINAL, SYSARG_3);
-    //        input    = peek_reg(tracee, MODIFIED, SYSARG_1);
-    //    }
-    //    else {
-    //        output   = peek_reg(tracee, ORIGINAL,  SYSARG_3);
-    //        max_size = peek_reg(tracee, ORIGINAL, SYSARG_4);
-    //        input    = peek_reg(tracee, MODIFIED, SYSARG_2);
-    //    }
-    //
-    //    if (max_size > PATH_MAX)
-    //        max_size = PATH_MAX;
-    //
-    //    if (max_size == 0) {
-    //        return SyscallExitResult::Value(-EINVAL);
-    //    }
-    //
-    //    /* The kernel does NOT put the NULL terminating byte for
-    //     * readlink(2).  */
-    //    status = read_data(tracee, referee, output, old_size);
-    //    if (status < 0)
-    //        break;
-    //    referee[old_size] = '\0';
-    //
-    //    /* Not optimal but safe (path is fully translated).  */
-    //    status = read_path(tracee, referer, input);
-    //    if (status < 0)
-    //        return SyscallExitResult::Value(status);
-    //
-    //    if (status >= PATH_MAX) {
-    //        return SyscallExitResult::Value(-ENAMETOOLONG);
-    //    }
-    //
-    //    status = detranslate_path(tracee, referee, referer);
-    //    if (status < 0)
-    //        return SyscallExitResult::Value(status);
-    //
-    //    /* The original path doesn't require any transformation, i.e
-    //     * it is a symetric binding.  */
-    //    if (status == 0)
-    //        return SyscallExitResult::None;
-    //
-    //    /* Overwrite the path.  Note: the output buffer might be
-    //     * initialized with zeros but it was updated with the kernel
-    //     * result, and then with the detranslated result.  This later
-    //     * might be shorter than the former, so it's safier to add a
-    //     * NULL terminating byte when possible.  This problem was
-    //     * exposed by IDA Demo 6.3.  */
-    //    if ((size_t) status < max_size) {
-    //        new_size = status - 1;
-    //        status = write_data(tracee, output, referee, status);
-    //    }
-    //    else {
-    //        new_size = max_size;
-    //        status = write_data(tracee, output, referee, max_size);
-    //    }
-    //    if (status < 0)
-    //        return SyscallExitResult::Value(status);
-    //
-    // The value of "status" is used to update the returned value in
-    // translate_syscall_exit().
+use std::cmp::min;
+use std::ffi::OsStr;
+use std::os::unix::prelude::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use libc::{c_void, PATH_MAX};
+use sc::nr::READLINK;
+
+use crate::errors::*;
+use crate::filesystem::Translator;
+use crate::kernel::standard::proc_link::get_virtual_proc_link;
+use crate::kernel::standard::{dir_link_attr, unlink_mkdir_at};
+use crate::process::tracee::Tracee;
+use crate::register::{
+    Current, Original, PtraceReader, PtraceWriter, SysArg, SysArg1, SysArg2, SysArg3, SysArg4,
+    SysArgIndex, SysResult, Word,
+};
+
+/// Returns the indexes of the `path`, `buf` and `bufsiz` arguments of
+/// readlink() or readlinkat().
+fn get_sysarg_indexes(tracee: &Tracee) -> (SysArgIndex, SysArgIndex, SysArgIndex) {
+    if tracee.regs.get_sys_num(Original) == READLINK {
+        (SysArg1, SysArg2, SysArg3)
+    } else {
+        (SysArg2, SysArg3, SysArg4)
+    }
+}
+
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    let (path_sysarg, _, _) = get_sysarg_indexes(tracee);
+    let raw_path = tracee.regs.get_sysarg_path(path_sysarg)?;
+
+    // The targets of some "/proc/<pid>/" links are emulated, see `exit()`.
+    if get_virtual_proc_link(tracee, &raw_path).is_some() {
+        tracee
+            .regs
+            .cancel_syscall("Cancel readlink of a virtual /proc link and return our own value");
+        return Ok(());
+    }
+
+    if tracee.regs.get_sys_num(Original) == READLINK {
+        dir_link_attr::enter(tracee)
+    } else {
+        unlink_mkdir_at::enter(tracee)
+    }
+}
+
+/// Writes the target of a symlink into the output buffer of readlink(), and
+/// updates the result. Like the kernel, the target is truncated to `max_size`
+/// bytes.
+fn write_link_target(
+    tracee: &mut Tracee,
+    output: Word,
+    max_size: usize,
+    target: &[u8],
+) -> Result<()> {
+    let size = min(target.len(), max_size);
+
+    // The kernel does NOT put the NULL terminating byte for readlink(2), but
+    // it's safer to add one when possible, since the detranslated target might
+    // be shorter than the one written by the kernel.
+    tracee
+        .regs
+        .write_data(output as *mut c_void, &target[..size], size < max_size)?;
+    tracee.regs.set(
+        SysResult,
+        size as Word,
+        "update return value in readlink_at::exit()",
+    );
     Ok(())
 }
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    let (path_sysarg, output_sysarg, size_sysarg) = get_sysarg_indexes(tracee);
+    let output = tracee.regs.get(Original, SysArg(output_sysarg));
+    let max_size = min(
+        tracee.regs.get(Original, SysArg(size_sysarg)) as usize,
+        PATH_MAX as usize,
+    );
+
+    if tracee.regs.is_syscall_cancelled() {
+        // The path argument wasn't modified during the enter stage.
+        let raw_path = tracee.regs.get_sysarg_path(path_sysarg)?;
+        let guest_path =
+            get_virtual_proc_link(tracee, &raw_path).ok_or_else(|| Error::errno(ENOENT))?;
+
+        if (tracee.regs.get(Original, SysArg(size_sysarg)) as i32) <= 0 {
+            return Err(Error::errno(EINVAL));
+        }
+        return write_link_target(tracee, output, max_size, guest_path.as_os_str().as_bytes());
+    }
+
+    // Error reported by the kernel.
+    let result = tracee.regs.get(Current, SysResult) as isize;
+    if result < 0 {
+        return Ok(());
+    }
+
+    let referee = tracee
+        .regs
+        .read_data(output as *const c_void, result as usize)?;
+    // Not optimal but safe (path is fully translated).
+    let referer = tracee.regs.get_sysarg_path(path_sysarg)?;
+
+    let maybe_path = tracee
+        .fs
+        .borrow()
+        .detranslate_path(Path::new(OsStr::from_bytes(&referee)), Some(&referer))?;
+
+    match maybe_path {
+        Some(path) => write_link_target(tracee, output, max_size, path.as_os_str().as_bytes()),
+        // The original path doesn't require any transformation, i.e it is a
+        // symetric binding.
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::path::PathBuf;
+
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+    use nix::unistd::{self, execv, getpid};
+
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot, test_with_proot_config};
+
+    #[test]
+    fn test_readlink_proc_fd() {
+        test_with_proot(
+            |_tracee, _is_sysenter, _before_translation| {},
+            || {
+                let fd = fcntl::open("/etc/passwd", OFlag::O_RDONLY, Mode::empty()).unwrap();
+                let dup_fd = unistd::dup(fd).unwrap();
+
+                // the target is the guest path, not the host one
+                assert_eq!(
+                    PathBuf::from(
+                        fcntl::readlink(format!("/proc/self/fd/{}", fd).as_str()).unwrap()
+                    ),
+                    PathBuf::from("/etc/passwd")
+                );
+                assert_eq!(
+                    PathBuf::from(
+                        fcntl::readlink(format!("/proc/{}/fd/{}", getpid(), dup_fd).as_str())
+                            .unwrap()
+                    ),
+                    PathBuf::from("/etc/passwd")
+                );
+
+                // a closed fd isn't tracked anymore
+                unistd::close(fd).unwrap();
+                assert!(fcntl::readlink(format!("/proc/self/fd/{}", fd).as_str()).is_err());
+                unistd::close(dup_fd).unwrap();
+            },
+        )
+    }
+
+    #[test]
+    fn test_readlink_proc_fd_after_rename() {
+        let old_path = get_test_rootfs_path().join("tmp/proot_rs_test_readlink_fd_old");
+        std::fs::write(&old_path, "").unwrap();
+
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                let fd = fcntl::open(
+                    "/tmp/proot_rs_test_readlink_fd_old",
+                    OFlag::O_RDONLY,
+                    Mode::empty(),
+                )
+                .unwrap();
+                std::fs::rename(
+                    "/tmp/proot_rs_test_readlink_fd_old",
+                    "/tmp/proot_rs_test_readlink_fd_new",
+                )
+                .unwrap();
+
+                // the target follows the rename, instead of the path the file
+                // was opened with
+                assert_eq!(
+                    PathBuf::from(
+                        fcntl::readlink(format!("/proc/self/fd/{}", fd).as_str()).unwrap()
+                    ),
+                    PathBuf::from("/tmp/proot_rs_test_readlink_fd_new")
+                );
+
+                unistd::close(fd).unwrap();
+                std::fs::remove_file("/tmp/proot_rs_test_readlink_fd_new").unwrap();
+            },
+        )
+    }
+
+    #[test]
+    fn test_readlink_proc_shim() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .proc_shim(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // "/proc/meminfo" comes from the host, whereas "/proc/self/exe"
+                // is the guest path of the program, not its host path.
+                execv(
+                    &CString::new("/bin/sh").unwrap(),
+                    &[
+                        CString::new("sh").unwrap(),
+                        CString::new("-c").unwrap(),
+                        CString::new(
+                            "grep -q MemTotal /proc/meminfo \
+                             && [ \"$(/bin/busybox readlink /proc/self/exe)\" = /bin/busybox ] \
+                             && [ \"$(/bin/busybox readlink /proc/self/cwd)\" = / ]",
+                        )
+                        .unwrap(),
+                    ],
+                )
+                .expect("failed execv /bin/sh");
+            },
+        )
+    }
+}
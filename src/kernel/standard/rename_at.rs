@@ -12,8 +12,8 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let old_path = tracee.regs.get_sysarg_path(SysArg2)?;
     let new_path = tracee.regs.get_sysarg_path(SysArg4)?;
 
-    let old_host_path = tracee.translate_path_at(olddirfd, old_path, false)?;
-    let new_host_path = tracee.translate_path_at(newdirfd, new_path, false)?;
+    let old_host_path = tracee.translate_path_at_for_write(olddirfd, old_path, false)?;
+    let new_host_path = tracee.translate_path_at_for_write(newdirfd, new_path, false)?;
 
     tracee.regs.set_sysarg_path(
         SysArg2,
@@ -0,0 +1,49 @@
+use libc::c_void;
+use sc::nr::SETDOMAINNAME;
+
+use crate::cli::HOST_NAME_MAX;
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{Original, PtraceReader, SysArg, SysArg1, SysArg2, SysResult};
+
+/// When the UTS names are virtualized by the configuration (`hostname` or
+/// `domainname`), sethostname(name, len) and setdomainname(name, len) are
+/// not sent to the host, which would require `CAP_SYS_ADMIN`: they change
+/// the name reported by uname() (see `VirtualUts`), and pretend to succeed
+/// in `exit()`.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if !info_bag.uts.borrow().is_enabled() {
+        return Ok(());
+    }
+
+    let size = tracee.regs.get(Original, SysArg(SysArg2)) as usize;
+    if size > HOST_NAME_MAX {
+        return Err(Error::errno(EINVAL));
+    }
+    let address = tracee.regs.get(Original, SysArg(SysArg1));
+    let name = tracee
+        .regs
+        .read_data(address as *const c_void, size)
+        .map_err(|_| Error::errno(EFAULT))?;
+
+    let mut uts = info_bag.uts.borrow_mut();
+    if tracee.regs.get_sys_num(Original) == SETDOMAINNAME {
+        uts.domainname = Some(name);
+    } else {
+        uts.hostname = Some(name);
+    }
+    tracee
+        .regs
+        .cancel_syscall("sethostname() is faked, avoid syscall");
+    Ok(())
+}
+
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    if tracee.regs.is_syscall_cancelled() {
+        tracee
+            .regs
+            .set(SysResult, 0, "sethostname() is faked, simulate success");
+    }
+    Ok(())
+}
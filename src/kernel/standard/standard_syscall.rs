@@ -1,14 +1,29 @@
+use sc::nr::ACCT;
+
 use crate::errors::*;
 
 use crate::filesystem::Translator;
+use crate::kernel::groups::modifies_paths;
 use crate::process::tracee::Tracee;
 use crate::register::PtraceWriter;
-use crate::register::{PtraceReader, SysArg1};
+use crate::register::{Current, PtraceReader, SysArg, SysArg1};
 
 pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    // acct(NULL) disables the process accounting, there is no path.
+    if tracee.regs.get_sys_num(Current) == ACCT && tracee.regs.get(Current, SysArg(SysArg1)) == 0 {
+        return Ok(());
+    }
+
     let raw_path = tracee.regs.get_sysarg_path(SysArg1)?;
 
-    let host_path = tracee.fs.borrow().translate_path(raw_path, true)?;
+    let host_path = if modifies_paths(tracee.regs.get_sys_num(Current)) {
+        tracee
+            .fs
+            .borrow()
+            .translate_path_for_write(raw_path, true)?
+    } else {
+        tracee.fs.borrow().translate_path(raw_path, true)?
+    };
 
     tracee.regs.set_sysarg_path(
         SysArg1,
@@ -21,14 +36,17 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use std::ffi::CString;
     use std::fs::File;
 
-    use crate::utils::tests::test_with_proot;
+    use sc::nr::ACCT;
+
+    use crate::register::{Current, Original, PtraceReader, SysArg, SysArg1};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot, test_with_read_only_binding};
 
     /// Unit test for all the standard syscalls:
-    /// access, acct, chmod, chown, chroot, getxattr, listxattr, mknod, creat,
-    /// removexattr, setxattr, stat, swapoff, swapon, truncate, umount2, uselib,
-    /// utime, utimes
+    /// access, acct, chmod, chown, getxattr, listxattr, mknod,
+    /// removexattr, setxattr, stat, truncate, umount2, uselib, utime, utimes
     ///
     /// Since the arguments of those syscalls follow a certain pattern, only the
     /// stat() call is tested in our unit tests.
@@ -63,4 +81,76 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_standard_syscall_acct() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation {
+                    return;
+                }
+                if tracee.regs.get_sys_num(Original) == ACCT {
+                    if tracee.regs.get(Original, SysArg(SysArg1)) == 0 {
+                        // NULL is passed through untranslated
+                        assert_eq!(tracee.regs.get(Current, SysArg(SysArg1)), 0);
+                    } else {
+                        assert_eq!(
+                            tracee.regs.get_sysarg_path(SysArg1).unwrap(),
+                            get_test_rootfs_path().join("tmp/proot_rs_acct_dir/missing")
+                        );
+                    }
+                }
+            },
+            || {
+                // the parent directory exists, so that the path is translated
+                std::fs::create_dir_all("/tmp/proot_rs_acct_dir").unwrap();
+                let path = CString::new("/tmp/proot_rs_acct_dir/missing").unwrap();
+                // it fails with EPERM unprivileged, ENOENT otherwise
+                assert_eq!(unsafe { libc::acct(path.as_ptr()) }, -1);
+                std::fs::remove_dir("/tmp/proot_rs_acct_dir").unwrap();
+
+                // disabling the accounting doesn't fail with EFAULT
+                if unsafe { libc::acct(std::ptr::null()) } == -1 {
+                    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::EPERM);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_standard_syscall_read_only_binding() {
+        test_with_read_only_binding("standard_syscall", || {
+            assert_eq!(nc::chmod("/mnt/ro/file", 0o600), Err(nc::EROFS));
+            assert_eq!(nc::chown("/mnt/ro/file", 0, 0), Err(nc::EROFS));
+            assert_eq!(nc::truncate("/mnt/ro/file", 0), Err(nc::EROFS));
+            let time = [
+                nc::timeval_t {
+                    tv_sec: 100,
+                    tv_usec: 0,
+                },
+                nc::timeval_t {
+                    tv_sec: 10,
+                    tv_usec: 0,
+                },
+            ];
+            assert_eq!(nc::utimes("/mnt/ro/file", &time), Err(nc::EROFS));
+            assert_eq!(
+                nc::mknod("/mnt/ro/node", nc::S_IFREG | nc::S_IRUSR, 0),
+                Err(nc::EROFS)
+            );
+            let attr_value = "value";
+            assert_eq!(
+                nc::setxattr(
+                    "/mnt/ro/file",
+                    "user.proot-rs-unit-test",
+                    attr_value.as_ptr() as usize,
+                    attr_value.len(),
+                    0,
+                ),
+                Err(nc::EROFS)
+            );
+            // the reads are allowed
+            assert_eq!(nc::access("/mnt/ro/file", nc::R_OK), Ok(()));
+        })
+    }
 }
@@ -3,6 +3,8 @@ use std::os::unix::prelude::RawFd;
 use nix::fcntl::AtFlags;
 
 use crate::errors::*;
+use crate::kernel::groups::modifies_paths;
+use crate::kernel::standard::proc_link;
 use crate::kernel::syscall;
 use crate::register::PtraceWriter;
 use crate::register::{Current, PtraceReader, SysArg, SysArg1, SysArg2, SysArg3, SysArg4, SysArg5};
@@ -44,13 +46,26 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
         _ => true,
     };
 
-    let host_path = tracee.translate_path_at(dirfd, raw_path, deref_final)?;
+    // With `AT_EMPTY_PATH` (or a null path for utimensat()), an empty path
+    // refers to `dirfd` itself.
+    let empty_path = flags.contains(AtFlags::AT_EMPTY_PATH)
+        || (sys_num == sc::nr::UTIMENSAT && tracee.regs.get(Current, SysArg(SysArg2)) == 0);
 
-    tracee.regs.set_sysarg_path(
-        SysArg2,
-        &host_path,
-        "during enter open translation, setting host path",
-    )?;
+    // The `/proc/<pid>/` links are resolved like readlink() does.
+    if let Some(host_path) = proc_link::translate_proc_path_at_or_dirfd(
+        tracee,
+        dirfd,
+        &raw_path,
+        empty_path,
+        deref_final,
+        modifies_paths(sys_num),
+    )? {
+        tracee.regs.set_sysarg_path(
+            SysArg2,
+            &host_path,
+            "during enter open translation, setting host path",
+        )?;
+    }
 
     Ok(())
 }
@@ -58,11 +73,18 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use std::fs::File;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::PathBuf;
 
     use nc::file_handle_t;
     use nix::{fcntl::OFlag, sys::stat::Mode};
 
-    use crate::utils::tests::test_with_proot;
+    use crate::config::ProotConfig;
+    use crate::register::{Current, Original, PtraceReader, SysArg, SysArg2, SysArg4, SysArg5};
+    use crate::utils::tests::{
+        get_test_rootfs_path, test_with_proot, test_with_proot_config, test_with_read_only_binding,
+    };
 
     // TODO: reference MAX_HANDLE_SZ which is defined in <fcntl.h>. see:
     // https://elixir.bootlin.com/linux/v5.12.12/source/include/linux/exportfs.h#L15
@@ -202,4 +224,139 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_stat_at_synthetic_mountpoint() {
+        let host_dir = get_test_rootfs_path().join("etc");
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .binding(&host_dir, "/missing_dir/mountpoint")
+                .synthetic_mountpoints(true)
+                .build(),
+            |_, _, _| {},
+            || {
+                // the mountpoint has the metadata of the bound target
+                let expected = std::fs::metadata("/etc").unwrap();
+                for metadata in [
+                    std::fs::metadata("/missing_dir/mountpoint").unwrap(),
+                    std::fs::symlink_metadata("/missing_dir/mountpoint").unwrap(),
+                ]
+                .iter()
+                {
+                    assert!(metadata.is_dir());
+                    assert_eq!(metadata.ino(), expected.ino());
+                    assert_eq!(metadata.dev(), expected.dev());
+                }
+                assert!(std::path::Path::new("/missing_dir/mountpoint/passwd").exists());
+            },
+        )
+    }
+
+    #[test]
+    fn test_stat_at_empty_path() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if !is_sysenter || before_translation {
+                    return;
+                }
+                let flags_arg_index = match tracee.regs.get_sys_num(Original) {
+                    sc::nr::NEWFSTATAT => SysArg4,
+                    sc::nr::FCHOWNAT => SysArg5,
+                    _ => return,
+                };
+                // the empty path isn't translated, the syscall operates on the fd
+                if tracee.regs.get(Current, SysArg(flags_arg_index)) as i32 & libc::AT_EMPTY_PATH
+                    != 0
+                {
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg2).unwrap(),
+                        PathBuf::new()
+                    );
+                }
+            },
+            || {
+                let file = File::open("/etc/passwd").unwrap();
+                let fd = file.as_raw_fd();
+
+                let mut stat = nc::stat_t::default();
+                nc::newfstatat(fd, "", &mut stat, nc::AT_EMPTY_PATH).unwrap();
+                assert_eq!((stat.st_mode & nc::S_IFMT), nc::S_IFREG);
+                assert_eq!(
+                    stat.st_ino as u64,
+                    std::fs::metadata("/etc/passwd").unwrap().ino()
+                );
+                nc::fchownat(fd, "", -1i64 as _, -1i64 as _, nc::AT_EMPTY_PATH).unwrap();
+
+                // an empty path requires `AT_EMPTY_PATH`
+                assert_eq!(nc::newfstatat(fd, "", &mut stat, 0), Err(nc::ENOENT));
+                assert_eq!(
+                    nc::fchownat(fd, "", -1i64 as _, -1i64 as _, 0),
+                    Err(nc::ENOENT)
+                );
+            },
+        )
+    }
+
+    #[test]
+    fn test_stat_at_proc_fd_symlink() {
+        let rootfs_path = get_test_rootfs_path();
+        let link_path = rootfs_path.join("tmp/link_for_test_stat_at_proc_fd");
+        let target_path = rootfs_path.join("tmp/target_for_test_stat_at_proc_fd");
+        let _ = std::fs::remove_file(&link_path);
+        std::fs::write(&target_path, "").unwrap();
+        // the target is a guest path, which doesn't exist on the host (unless
+        // the rootfs is "/")
+        std::os::unix::fs::symlink("/tmp/target_for_test_stat_at_proc_fd", &link_path).unwrap();
+
+        test_with_proot(
+            |_, _, _| {},
+            || {
+                let linkpath = "/tmp/link_for_test_stat_at_proc_fd";
+                let fd =
+                    nix::fcntl::open(linkpath, OFlag::O_PATH | OFlag::O_NOFOLLOW, Mode::empty())
+                        .unwrap();
+                let proc_path = format!("/proc/self/fd/{}", fd);
+
+                // the link of the fd leads to the symlink itself
+                let metadata = std::fs::metadata(&proc_path).unwrap();
+                assert!(metadata.file_type().is_symlink());
+                assert_eq!(
+                    metadata.ino(),
+                    std::fs::symlink_metadata(linkpath).unwrap().ino()
+                );
+                assert_eq!(
+                    std::fs::read_link(&proc_path).unwrap(),
+                    PathBuf::from(linkpath)
+                );
+                nix::unistd::close(fd).unwrap();
+            },
+        );
+
+        std::fs::remove_file(&link_path).unwrap();
+        std::fs::remove_file(&target_path).unwrap();
+    }
+
+    #[test]
+    fn test_stat_at_read_only_binding() {
+        test_with_read_only_binding("stat_at", || {
+            let fd = nix::fcntl::open("/mnt/ro", OFlag::O_RDONLY, Mode::empty()).unwrap();
+            let time = [
+                nc::timespec_t {
+                    tv_sec: 100,
+                    tv_nsec: 0,
+                },
+                nc::timespec_t {
+                    tv_sec: 101,
+                    tv_nsec: 0,
+                },
+            ];
+            assert_eq!(nc::utimensat(fd, "file", &time, 0), Err(nc::EROFS));
+            assert_eq!(nc::fchownat(fd, "file", 0, 0, 0), Err(nc::EROFS));
+            // the reads are allowed
+            let mut statbuf = nc::stat_t::default();
+            assert_eq!(nc::newfstatat(fd, "file", &mut statbuf, 0), Ok(()));
+        })
+    }
 }
@@ -0,0 +1,93 @@
+use crate::errors::*;
+
+use crate::kernel::standard::standard_syscall;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+
+/// swapon(path, flags) and swapoff(path) require `CAP_SYS_ADMIN`, so they
+/// almost always fail under proot. The path is translated anyway, so that the
+/// host reports an accurate error, unless they are denied by the configuration
+/// (`deny_swap`), in which case the host isn't even asked.
+pub fn enter(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    if info_bag.config.deny_swap {
+        return Err(Error::errno_with_msg(
+            EPERM,
+            "swapon() and swapoff() are denied by the configuration",
+        ));
+    }
+
+    standard_syscall::enter(tracee)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs::File;
+
+    use nix::errno::Errno;
+    use sc::nr::{SWAPOFF, SWAPON};
+
+    use crate::config::ProotConfig;
+    use crate::register::{Current, Original, PtraceReader, SysArg1};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    const FILEPATH: &str = "/tmp/file_for_test_swap";
+
+    fn swapon_swapoff() -> (Errno, Errno) {
+        let path = CString::new(FILEPATH).unwrap();
+
+        assert_eq!(unsafe { libc::swapon(path.as_ptr(), 0) }, -1);
+        let swapon_errno = Errno::last();
+        assert_eq!(unsafe { libc::swapoff(path.as_ptr()) }, -1);
+        (swapon_errno, Errno::last())
+    }
+
+    fn test_swap(deny_swap: bool) {
+        let config = ProotConfig::builder()
+            .rootfs(get_test_rootfs_path())
+            .deny_swap(deny_swap)
+            .build();
+
+        test_with_proot_config(
+            config,
+            move |tracee, is_sysenter, before_translation| {
+                let sys_num = tracee.regs.get_sys_num(Original);
+                if !is_sysenter || before_translation || (sys_num != SWAPON && sys_num != SWAPOFF) {
+                    return;
+                }
+                if deny_swap {
+                    // the host isn't asked
+                    assert_ne!(tracee.regs.get_sys_num(Current), sys_num);
+                } else {
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg1).unwrap(),
+                        get_test_rootfs_path().join(FILEPATH.trim_start_matches('/'))
+                    );
+                }
+            },
+            || {
+                File::create(FILEPATH).unwrap();
+                let result = std::panic::catch_unwind(|| {
+                    let errnos = swapon_swapoff();
+                    if deny_swap {
+                        assert_eq!(errnos, (Errno::EPERM, Errno::EPERM));
+                    }
+                });
+                std::fs::remove_file(FILEPATH).unwrap();
+                if let Err(err) = result {
+                    std::panic::resume_unwind(err);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_swap_translation() {
+        test_swap(false);
+    }
+
+    #[test]
+    fn test_swap_denied() {
+        test_swap(true);
+    }
+}
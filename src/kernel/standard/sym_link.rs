@@ -7,7 +7,10 @@ use crate::register::{PtraceReader, SysArg2};
 
 pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let raw_path = tracee.regs.get_sysarg_path(SysArg2)?;
-    let host_path = tracee.fs.borrow().translate_path(raw_path, false)?;
+    let host_path = tracee
+        .fs
+        .borrow()
+        .translate_path_for_write(raw_path, false)?;
 
     tracee.regs.set_sysarg_path(
         SysArg2,
@@ -22,7 +25,7 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
 mod tests {
     use std::fs::File;
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - symlink
@@ -65,4 +68,11 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_sym_link_read_only_binding() {
+        test_with_read_only_binding("sym_link", || {
+            assert_eq!(nc::symlink("file", "/mnt/ro/link"), Err(nc::EROFS));
+        })
+    }
 }
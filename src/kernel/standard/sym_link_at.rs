@@ -9,7 +9,7 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let dirfd = tracee.regs.get(Current, SysArg(SysArg2)) as RawFd;
     let raw_path = tracee.regs.get_sysarg_path(SysArg3)?;
 
-    let host_path = tracee.translate_path_at(dirfd, raw_path, false)?;
+    let host_path = tracee.translate_path_at_for_write(dirfd, raw_path, false)?;
 
     tracee.regs.set_sysarg_path(
         SysArg3,
@@ -26,7 +26,7 @@ mod tests {
 
     use nix::{fcntl::OFlag, sys::stat::Mode};
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - symlinkat
@@ -73,4 +73,12 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_sym_link_at_read_only_binding() {
+        test_with_read_only_binding("sym_link_at", || {
+            let fd = nix::fcntl::open("/mnt/ro", OFlag::O_RDONLY, Mode::empty()).unwrap();
+            assert_eq!(nc::symlinkat("file", fd, "link"), Err(nc::EROFS));
+        })
+    }
 }
@@ -0,0 +1,87 @@
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use libc::c_void;
+
+use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceReader, PtraceWriter, SysArg, SysArg1, SysResult};
+
+/// Caps the values of the `struct sysinfo` filled by the kernel with the ones
+/// of the configuration (`sysinfo_overlay`).
+pub fn exit(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    let overlay = &info_bag.config.sysinfo_overlay;
+    if overlay.total_ram.is_none() && overlay.free_ram.is_none() && overlay.uptime.is_none() {
+        return Ok(());
+    }
+
+    // Error reported by the kernel.
+    if (tracee.regs.get(Current, SysResult) as i32) < 0 {
+        return Ok(());
+    }
+
+    let address = tracee.regs.get(Original, SysArg(SysArg1)) as *mut c_void;
+    let size = mem::size_of::<libc::sysinfo>();
+    let data = tracee.regs.read_data(address, size)?;
+    let mut info: libc::sysinfo = unsafe { ptr::read_unaligned(data.as_ptr() as *const _) };
+
+    // The sizes of memory are given as multiples of `mem_unit` bytes.
+    let mem_unit = (info.mem_unit as u64).max(1);
+    if let Some(total_ram) = overlay.total_ram {
+        info.totalram = info.totalram.min((total_ram / mem_unit) as _);
+    }
+    if let Some(free_ram) = overlay.free_ram {
+        info.freeram = info.freeram.min((free_ram / mem_unit) as _);
+    }
+    // there can't be more free memory than memory
+    info.freeram = info.freeram.min(info.totalram);
+    if let Some(uptime) = overlay.uptime {
+        info.uptime = info.uptime.min(uptime as _);
+    }
+
+    let data = unsafe { slice::from_raw_parts(&info as *const _ as *const u8, size) };
+    tracee.regs.write_data(address, data, false)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use crate::config::{ProotConfig, SysinfoOverlay};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    fn sysinfo() -> libc::sysinfo {
+        let mut info: libc::sysinfo = unsafe { mem::zeroed() };
+        assert_eq!(unsafe { libc::sysinfo(&mut info) }, 0);
+        info
+    }
+
+    #[test]
+    fn test_sysinfo_overlay() {
+        const TOTAL_RAM: u64 = 64 * 1024 * 1024;
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .sysinfo_overlay(SysinfoOverlay {
+                    total_ram: Some(TOTAL_RAM),
+                    free_ram: None,
+                    uptime: Some(1),
+                })
+                .build(),
+            |_, _, _| {},
+            || {
+                let info = sysinfo();
+                let mem_unit = info.mem_unit as u64;
+                assert_eq!(info.totalram as u64 * mem_unit, TOTAL_RAM);
+                assert!(info.freeram <= info.totalram);
+                assert!(info.uptime <= 1);
+                // the other values are untouched
+                assert!(info.procs > 0);
+            },
+        )
+    }
+}
@@ -0,0 +1,312 @@
+use std::convert::TryInto;
+use std::ffi::OsStr;
+use std::fs::Permissions;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use libc::{c_void, mode_t, AF_UNIX, O_CREAT, O_TMPFILE};
+use nix::sys::stat::Mode;
+use sc::nr::{BIND, CREAT, MKDIR, MKDIRAT, MKNOD, MKNODAT, OPEN, OPENAT};
+
+use crate::errors::*;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceReader, PtraceWriter, SysArg, SysArg1, SysArg2};
+use crate::register::{SysArg3, SysArg4, SysArgIndex, SysResult, Word};
+
+/// Offset of the `mode` field of `struct open_how`, after `flags`, see
+/// openat2(2).
+const OPEN_HOW_MODE_OFFSET: usize = 8;
+/// Largest `struct open_how` accepted by openat2(), which fails with `E2BIG`
+/// above a page.
+const OPEN_HOW_MAX_SIZE: usize = 4096;
+
+/// Returns the argument holding the mode of the file created by the syscall
+/// of `tracee`, if it creates one.
+fn get_mode_sysarg(tracee: &Tracee) -> Option<SysArgIndex> {
+    let creates = |flags_sysarg| {
+        let flags = tracee.regs.get(Current, SysArg(flags_sysarg)) as i32;
+        flags & O_CREAT != 0 || flags & O_TMPFILE == O_TMPFILE
+    };
+
+    match tracee.regs.get_sys_num(Original) {
+        OPEN if creates(SysArg2) => Some(SysArg3),
+        OPENAT if creates(SysArg3) => Some(SysArg4),
+        CREAT | MKDIR | MKNOD => Some(SysArg2),
+        MKDIRAT | MKNODAT => Some(SysArg3),
+        _ => None,
+    }
+}
+
+/// Applies the virtual umask of the tracee (if any) to the mode of the file
+/// created by its syscall, since the kernel only applies the host one. The
+/// host umask of the first tracee is cleared in `PRoot::launch_process()`,
+/// and never changed since umask() is emulated.
+///
+/// It's called on the enter stage of every translated syscall, before its
+/// handler. The `struct open_how` of openat2() and the sockets bound by
+/// bind(), which have no mode argument, are handled by `apply_to_open_how()`
+/// and `apply_to_bound_socket()`.
+pub fn apply(tracee: &mut Tracee) -> Result<()> {
+    let umask = match tracee.fs.borrow().get_umask() {
+        Some(umask) => umask,
+        None => return Ok(()),
+    };
+    if let Some(mode_sysarg) = get_mode_sysarg(tracee) {
+        let mode = tracee.regs.get(Current, SysArg(mode_sysarg)) as mode_t;
+        tracee.regs.set(
+            SysArg(mode_sysarg),
+            (mode & !umask.bits()) as Word,
+            "applying the virtual umask to the mode of the created file",
+        );
+    }
+    Ok(())
+}
+
+/// Applies the virtual umask of the tracee (if any) to the mode of the
+/// `struct open_how` of openat2(dirfd, path, how, size). The structure of
+/// the guest is left untouched, since it may be reused: the masked mode is
+/// written in a copy, in a scratch block given to the syscall instead.
+pub fn apply_to_open_how(tracee: &mut Tracee) -> Result<()> {
+    let umask = match tracee.fs.borrow().get_umask() {
+        Some(umask) => umask,
+        None => return Ok(()),
+    };
+    let address = tracee.regs.get(Current, SysArg(SysArg3)) as usize;
+    let size = tracee.regs.get(Current, SysArg(SysArg4)) as usize;
+    // the kernel reports the invalid structures
+    if address == 0 || size < OPEN_HOW_MODE_OFFSET + 8 || size > OPEN_HOW_MAX_SIZE {
+        return Ok(());
+    }
+    let mut how = tracee.regs.read_data(address as *const c_void, size)?;
+    let flags = u64::from_ne_bytes(how[..8].try_into().unwrap()) as i32;
+    if flags & O_CREAT == 0 && flags & O_TMPFILE != O_TMPFILE {
+        return Ok(());
+    }
+    let mode_bytes = &mut how[OPEN_HOW_MODE_OFFSET..OPEN_HOW_MODE_OFFSET + 8];
+    let mode = u64::from_ne_bytes((&*mode_bytes).try_into().unwrap());
+    mode_bytes.copy_from_slice(&(mode & !(umask.bits() as u64)).to_ne_bytes());
+
+    let copy_address = tracee.regs.write_scratch(&how)?;
+    tracee.regs.set(
+        SysArg(SysArg3),
+        copy_address,
+        "applying the virtual umask to a copy of the struct open_how",
+    );
+    Ok(())
+}
+
+/// Applies the virtual umask of the tracee (if any) to the socket file
+/// created by a successful bind() of a `AF_UNIX` socket, which the kernel
+/// creates with the mode `0o777` masked by the host umask only.
+pub fn apply_to_bound_socket(tracee: &Tracee) -> Result<()> {
+    let umask = match tracee.fs.borrow().get_umask() {
+        Some(umask) => umask,
+        None => return Ok(()),
+    };
+    if tracee.regs.get_sys_num(Original) != BIND || tracee.regs.get(Current, SysResult) != 0 {
+        return Ok(());
+    }
+    let path = match read_socket_path(tracee)? {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    // it's relative to the cwd of the tracee, as given to the kernel
+    let path = Path::new(&format!("/proc/{}/cwd", tracee.pid)).join(path);
+    std::fs::set_permissions(&path, Permissions::from_mode(0o777 & !umask.bits() as u32))?;
+    Ok(())
+}
+
+/// Returns the path of the `struct sockaddr_un` of bind(sockfd, addr,
+/// addrlen), or `None` for the other families and the abstract sockets.
+fn read_socket_path(tracee: &Tracee) -> Result<Option<PathBuf>> {
+    let address = tracee.regs.get(Original, SysArg(SysArg2)) as usize;
+    let size = tracee.regs.get(Original, SysArg(SysArg3)) as usize;
+    let family_size = std::mem::size_of::<libc::sa_family_t>();
+    if size <= family_size || size > std::mem::size_of::<libc::sockaddr_un>() {
+        return Ok(None);
+    }
+    let addr = tracee.regs.read_data(address as *const c_void, size)?;
+    let family = libc::sa_family_t::from_ne_bytes(addr[..family_size].try_into().unwrap());
+    let sun_path = &addr[family_size..];
+    if family as i32 != AF_UNIX || sun_path[0] == 0 {
+        return Ok(None);
+    }
+    let length = sun_path
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(sun_path.len());
+    Ok(Some(PathBuf::from(OsStr::from_bytes(&sun_path[..length]))))
+}
+
+/// umask(mask) is emulated when the guest has a virtual umask: the syscall
+/// is cancelled, and `exit()` swaps the virtual umask.
+pub fn enter(tracee: &mut Tracee) -> Result<()> {
+    if tracee.fs.borrow().get_umask().is_some() {
+        tracee
+            .regs
+            .cancel_syscall("umask() is emulated, avoid syscall");
+    }
+    Ok(())
+}
+
+/// Records the new virtual umask, and returns the previous one.
+pub fn exit(tracee: &mut Tracee) -> Result<()> {
+    if !tracee.regs.is_syscall_cancelled() {
+        return Ok(());
+    }
+
+    let new_umask =
+        Mode::from_bits_truncate(tracee.regs.get(Original, SysArg(SysArg1)) as mode_t & 0o777);
+    let old_umask = tracee.fs.borrow().get_umask().unwrap_or_else(Mode::empty);
+    tracee.fs.borrow_mut().set_umask(Some(new_umask));
+    tracee.regs.set(
+        SysResult,
+        old_umask.bits() as Word,
+        "umask() is emulated, returning the previous virtual umask",
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    use nix::errno::Errno;
+    use nix::sys::socket::{bind, socket, AddressFamily, SockAddr, SockFlag, SockType, UnixAddr};
+    use nix::sys::stat::{fstat, mkdir, umask, Mode};
+
+    use super::*;
+
+    use crate::config::ProotConfig;
+    use crate::kernel::groups::nr::OPENAT2;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    fn test_umask_virtual() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .umask(0o027)
+                .build(),
+            |_, _, _| {},
+            || {
+                let dir_path = "/tmp/dir_for_test_umask_virtual";
+                let file_path = "/tmp/file_for_test_umask_virtual";
+
+                // the query reports the virtual umask, not the host one
+                assert_eq!(umask(Mode::from_bits_truncate(0o077)).bits(), 0o027);
+                assert_eq!(umask(Mode::from_bits_truncate(0o027)).bits(), 0o077);
+
+                mkdir(dir_path, Mode::from_bits_truncate(0o777)).unwrap();
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .mode(0o666)
+                    .open(file_path)
+                    .unwrap();
+
+                let dir_mode = fs::metadata(dir_path).unwrap().permissions().mode();
+                let file_mode = fs::metadata(file_path).unwrap().permissions().mode();
+                fs::remove_dir(dir_path).unwrap();
+                fs::remove_file(file_path).unwrap();
+                assert_eq!(dir_mode & 0o777, 0o750);
+                assert_eq!(file_mode & 0o777, 0o640);
+            },
+        )
+    }
+
+    #[test]
+    fn test_umask_virtual_openat2_and_bind() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .umask(0o027)
+                .build(),
+            |_, _, _| {},
+            || {
+                let file_path = "/tmp/file_for_test_umask_virtual_openat2";
+                let socket_name = "socket_for_test_umask_virtual_bind";
+
+                // int openat2(int dirfd, const char *path, struct open_how *how, size_t size)
+                #[repr(C)]
+                struct OpenHow {
+                    flags: u64,
+                    mode: u64,
+                    resolve: u64,
+                }
+                let how = OpenHow {
+                    flags: (libc::O_CREAT | libc::O_WRONLY) as u64,
+                    mode: 0o666,
+                    resolve: 0,
+                };
+                let path = CString::new(file_path).unwrap();
+                let fd = unsafe {
+                    libc::syscall(
+                        OPENAT2 as libc::c_long,
+                        libc::AT_FDCWD,
+                        path.as_ptr(),
+                        &how as *const OpenHow,
+                        std::mem::size_of::<OpenHow>(),
+                    )
+                };
+                // the syscall is recent
+                if fd >= 0 || Errno::last() != Errno::ENOSYS {
+                    assert!(fd >= 0);
+                    let mode = fstat(fd as i32).unwrap().st_mode;
+                    nix::unistd::close(fd as i32).unwrap();
+                    nix::unistd::unlink(file_path).unwrap();
+                    assert_eq!(mode & 0o777, 0o640);
+                    // the mode is masked in a copy of the structure
+                    assert_eq!(unsafe { std::ptr::read_volatile(&how.mode) }, 0o666);
+                }
+
+                // the path of bind() is relative to the cwd
+                nix::unistd::chdir("/tmp").unwrap();
+                let _ = fs::remove_file(socket_name);
+                let fd = socket(
+                    AddressFamily::Unix,
+                    SockType::Stream,
+                    SockFlag::empty(),
+                    None,
+                )
+                .unwrap();
+                bind(fd, &SockAddr::Unix(UnixAddr::new(socket_name).unwrap())).unwrap();
+                let mode = fs::metadata(socket_name).unwrap().permissions().mode();
+                fs::remove_file(socket_name).unwrap();
+                assert_eq!(mode & 0o777, 0o750);
+            },
+        )
+    }
+
+    #[test]
+    fn test_umask_virtual_bypassed_syscall() {
+        // the bypassed syscalls use the host paths
+        let dir_path = std::env::temp_dir().join("dir_for_test_umask_virtual_bypassed");
+        let _ = fs::remove_dir(&dir_path);
+        let dir_path_in_tracee = dir_path.clone();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .umask(0o027)
+                .bypass_syscall("mkdir")
+                .build(),
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter && !before_translation && tracee.regs.get_sys_num(Original) == MKDIR
+                {
+                    assert_eq!(
+                        tracee.regs.get(Current, SysArg(SysArg2)),
+                        tracee.regs.get(Original, SysArg(SysArg2))
+                    );
+                }
+            },
+            move || {
+                mkdir(&dir_path_in_tracee, Mode::from_bits_truncate(0o777)).unwrap();
+            },
+        );
+        fs::remove_dir(&dir_path).unwrap();
+    }
+}
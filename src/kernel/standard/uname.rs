@@ -1,7 +1,35 @@
+use libc::c_void;
+
 use crate::errors::*;
+use crate::process::proot::InfoBag;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, PtraceWriter, SysArg, SysArg1, SysResult};
+
+/// Size of the fields of `struct utsname`, see uname(2).
+const UTS_FIELD_SIZE: usize = 65;
+/// Offset of the `nodename` field, which follows `sysname`.
+const UTS_NODENAME_OFFSET: usize = UTS_FIELD_SIZE;
+/// Offset of the `domainname` field, the last one (after `release`,
+/// `version` and `machine`).
+const UTS_DOMAINNAME_OFFSET: usize = 5 * UTS_FIELD_SIZE;
 
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-pub fn exit() -> Result<()> {
+pub fn exit(tracee: &mut Tracee, info_bag: &InfoBag) -> Result<()> {
+    // Error reported by the kernel.
+    if (tracee.regs.get(Current, SysResult) as i32) < 0 {
+        return Ok(());
+    }
+
+    // The names are virtualized, see `VirtualUts`.
+    let address = tracee.regs.get(Original, SysArg(SysArg1)) as usize;
+    let uts = info_bag.uts.borrow();
+    if let Some(hostname) = &uts.hostname {
+        write_field(tracee, address + UTS_NODENAME_OFFSET, hostname)?;
+    }
+    if let Some(domainname) = &uts.domainname {
+        write_field(tracee, address + UTS_DOMAINNAME_OFFSET, domainname)?;
+    }
+
     //    struct utsname utsname;
     //    word_t address;
     //    size_t size;
@@ -31,3 +59,84 @@ pub fn exit() -> Result<()> {
     //
     Ok(())
 }
+
+/// Writes `name` in the field of `struct utsname` at `address`, truncated and
+/// null-terminated.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn write_field(tracee: &Tracee, address: usize, name: &[u8]) -> Result<()> {
+    let size = name.len().min(UTS_FIELD_SIZE - 1);
+    tracee
+        .regs
+        .write_data(address as *mut c_void, &name[..size], true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use nix::sys::utsname::uname;
+    use nix::unistd::{gethostname, sethostname};
+
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+
+    #[test]
+    fn test_uname_hostname() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .hostname("virtual-host")
+                .build(),
+            |_, _, _| {},
+            || {
+                let host_utsname = uname();
+                assert_eq!(uname().nodename(), "virtual-host");
+                // the other fields are untouched
+                assert_eq!(uname().sysname(), host_utsname.sysname());
+                assert_eq!(uname().release(), host_utsname.release());
+
+                let mut buffer = [0u8; 64];
+                assert_eq!(
+                    gethostname(&mut buffer).unwrap().to_str().unwrap(),
+                    "virtual-host"
+                );
+
+                // sethostname() changes the virtual hostname, even without
+                // privileges
+                sethostname("another-host").unwrap();
+                assert_eq!(uname().nodename(), "another-host");
+            },
+        )
+    }
+
+    #[test]
+    fn test_uname_domainname() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .hostname("virtual-host")
+                .build(),
+            |_, _, _| {},
+            || {
+                let name = b"virtual.domain";
+                let result = unsafe {
+                    libc::setdomainname(name.as_ptr() as *const libc::c_char, name.len())
+                };
+                assert_eq!(result, 0);
+
+                // getdomainname() reads it with uname()
+                let mut utsname: libc::utsname = unsafe { std::mem::zeroed() };
+                assert_eq!(unsafe { libc::uname(&mut utsname) }, 0);
+                let domainname = unsafe { CStr::from_ptr(utsname.domainname.as_ptr()) };
+                assert_eq!(domainname.to_str().unwrap(), "virtual.domain");
+                assert_eq!(uname().nodename(), "virtual-host");
+
+                // the name is limited like a hostname
+                let result =
+                    unsafe { libc::setdomainname(name.as_ptr() as *const libc::c_char, 65) };
+                assert_eq!(result, -1);
+                assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::EINVAL);
+            },
+        )
+    }
+}
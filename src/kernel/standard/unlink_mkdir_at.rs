@@ -9,7 +9,7 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
     let dirfd = tracee.regs.get(Current, SysArg(SysArg1)) as RawFd;
     let raw_path = tracee.regs.get_sysarg_path(SysArg2)?;
 
-    let host_path = tracee.translate_path_at(dirfd, raw_path, false)?;
+    let host_path = tracee.translate_path_at_for_write(dirfd, raw_path, false)?;
 
     tracee.regs.set_sysarg_path(
         SysArg2,
@@ -24,7 +24,7 @@ pub fn enter(tracee: &mut Tracee) -> Result<()> {
 mod tests {
     use nix::{fcntl::OFlag, sys::stat::Mode};
 
-    use crate::utils::tests::test_with_proot;
+    use crate::utils::tests::{test_with_proot, test_with_read_only_binding};
 
     /// Unit test for the following syscalls:
     /// - unlinkat
@@ -84,4 +84,18 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn test_unlink_mkdir_at_read_only_binding() {
+        test_with_read_only_binding("unlink_mkdir_at", || {
+            let fd = nix::fcntl::open("/mnt/ro", OFlag::O_RDONLY, Mode::empty()).unwrap();
+            assert_eq!(nc::mkdirat(fd, "new_dir", 0o755), Err(nc::EROFS));
+            assert_eq!(nc::unlinkat(fd, "file", 0), Err(nc::EROFS));
+            assert_eq!(nc::unlinkat(fd, "dir", nc::AT_REMOVEDIR), Err(nc::EROFS));
+            assert_eq!(
+                nc::unlinkat(nc::AT_FDCWD, "/mnt/ro/file", 0),
+                Err(nc::EROFS)
+            );
+        })
+    }
 }
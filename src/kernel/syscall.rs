@@ -343,6 +343,74 @@ lazy_static! {
         (sc::nr::WAITID, "waitid"),
         (sc::nr::WRITE, "write"),
         (sc::nr::WRITEV, "writev"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (
+            crate::kernel::groups::nr::PIDFD_SEND_SIGNAL,
+            "pidfd_send_signal"
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::IO_URING_SETUP, "io_uring_setup"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::IO_URING_ENTER, "io_uring_enter"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (
+            crate::kernel::groups::nr::IO_URING_REGISTER,
+            "io_uring_register"
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::RSEQ, "rseq"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::OPEN_TREE, "open_tree"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::MOVE_MOUNT, "move_mount"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::FSOPEN, "fsopen"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::FSCONFIG, "fsconfig"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::FSMOUNT, "fsmount"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::FSPICK, "fspick"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::PIDFD_OPEN, "pidfd_open"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::CLONE3, "clone3"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::CLOSE_RANGE, "close_range"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::OPENAT2, "openat2"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::PIDFD_GETFD, "pidfd_getfd"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::FACCESSAT2, "faccessat2"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (
+            crate::kernel::groups::nr::PROCESS_MADVISE,
+            "process_madvise"
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::MOUNT_SETATTR, "mount_setattr"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::QUOTACTL_FD, "quotactl_fd"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (
+            crate::kernel::groups::nr::LANDLOCK_CREATE_RULESET,
+            "landlock_create_ruleset"
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (
+            crate::kernel::groups::nr::LANDLOCK_ADD_RULE,
+            "landlock_add_rule"
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (
+            crate::kernel::groups::nr::LANDLOCK_RESTRICT_SELF,
+            "landlock_restrict_self"
+        ),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::CACHESTAT, "cachestat"),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        (crate::kernel::groups::nr::FCHMODAT2, "fchmodat2"),
         #[cfg(any(target_arch = "x86"))]
         (sc::nr::WAITPID, "waitpid"),
         #[cfg(any(target_arch = "x86"))]
@@ -371,6 +439,20 @@ lazy_static! {
     .collect();
 }
 
+/// Returns the numbers of all the syscalls known on this architecture, in no
+/// particular order.
+pub fn known_sysnums() -> impl Iterator<Item = usize> {
+    SYSNUM_TO_SYSCALL_NAME.keys().copied()
+}
+
+/// Returns the number of the syscall named `name`, on this architecture.
+pub fn sysnum_of_name(name: &str) -> Option<usize> {
+    SYSNUM_TO_SYSCALL_NAME
+        .iter()
+        .find(|(_, &syscall_name)| syscall_name == name)
+        .map(|(&sysnum, _)| sysnum)
+}
+
 pub fn name_of_syscall(sysnum: usize) -> Option<&'static str> {
     SYSNUM_TO_SYSCALL_NAME.get(&sysnum).map(|s| *s)
 }
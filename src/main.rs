@@ -13,6 +13,7 @@ extern crate byteorder;
 extern crate log;
 
 mod cli;
+mod config;
 mod errors;
 mod filesystem;
 mod kernel;
@@ -21,14 +22,15 @@ mod register;
 mod utils;
 
 use crate::errors::Result;
-use crate::process::proot::{show_info, stop_program, PRoot};
+use crate::process::proot::{show_info, stop_program, InfoBag, PRoot};
 use crate::process::sigactions;
 
 fn run() -> Result<()> {
     // step 1: CLI parsing
-    let (fs, command) = cli::parse_config()?;
+    let (config, command) = cli::parse_config();
+    let fs = config.create_filesystem()?;
 
-    let mut proot: PRoot = PRoot::new();
+    let mut proot: PRoot = PRoot::new(InfoBag::new(config));
 
     // step 2: initialize Proot and start the first tracee
     proot.init()?;
@@ -52,6 +54,8 @@ fn run() -> Result<()> {
 }
 
 fn main() {
+    // also prints the spans and events of the `tracing` feature, which are
+    // forwarded to `log`
     env_logger::init();
     if let Err(err) = run() {
         error!("Exited with error: {}", err);
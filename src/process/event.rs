@@ -8,6 +8,7 @@ use nix::unistd::Pid;
 
 use crate::errors::*;
 use crate::filesystem::FileSystem;
+use crate::process::fd_table::FdTable;
 use crate::process::proot::InfoBag;
 use crate::process::tracee::{Tracee, TraceeRestartMethod, TraceeStatus};
 use crate::process::translation::SyscallTranslator;
@@ -23,7 +24,8 @@ pub trait EventHandler {
     );
     fn handle_sigstop_event(&mut self);
     fn handle_seccomp_event(&mut self, info_bag: &mut InfoBag, event: PtraceEvent);
-    fn handle_exec_vfork_event(&mut self);
+    fn handle_exec_event(&mut self) -> Result<()>;
+    fn handle_vfork_done_event(&mut self);
     fn handle_new_child_event(&mut self) -> Result<Tracee>;
 }
 
@@ -80,8 +82,28 @@ impl EventHandler for Tracee {
         debug!("seccomp event! {:?}, {:?}", info_bag, signal);
     }
 
-    fn handle_exec_vfork_event(&mut self) {
-        debug!("EXEC or VFORK event");
+    /// The process image was replaced: the cached registers are re-fetched,
+    /// since their layout may have changed (e.g. a 32-bit program), and the
+    /// per-exec state is updated before the exit stage of execve() is
+    /// translated.
+    fn handle_exec_event(&mut self) -> Result<()> {
+        self.regs.fetch_regs()?;
+        self.commit_exec();
+        if self.exe.is_none() {
+            self.exe = self
+                .read_guest_exe(self.pid)
+                .map(|exe| Rc::new(RefCell::new(exe)));
+        }
+        debug!("EXEC event, {}", self.describe());
+        Ok(())
+    }
+
+    /// The child created by vfork() called execve() or exited: this tracee,
+    /// which was released by the event loop at that time, no longer shares
+    /// its address space.
+    fn handle_vfork_done_event(&mut self) {
+        debug!("VFORK_DONE event, child {:?}", self.vfork_child);
+        self.vfork_child = None;
     }
 
     fn handle_new_child_event(&mut self) -> Result<Tracee> {
@@ -156,10 +178,23 @@ impl EventHandler for Tracee {
             Rc::new(RefCell::new(fs))
         };
         let mut child_tracee = Tracee::new(child_pid, fs);
+        child_tracee.regs.set_path_max(self.regs.get_path_max());
+
+        child_tracee.fd_table = if clone_flags.contains(CloneFlags::CLONE_FILES) {
+            // share the same table of file descriptors
+            self.fd_table.clone()
+        } else {
+            let fd_table: FdTable = self.fd_table.borrow().clone();
+            Rc::new(RefCell::new(fd_table))
+        };
 
         // The path to the executable is unshared only once the child process does a
-        // call to execve(2).
-        child_tracee.exe = self.exe.clone();
+        // call to execve(2). If the program of the parent is unknown, the guest path
+        // of the one of the child is read from its "/proc/<pid>/exe" instead.
+        child_tracee.exe = self.exe.clone().or_else(|| {
+            self.read_guest_exe(child_pid)
+                .map(|exe| Rc::new(RefCell::new(exe)))
+        });
 
         // child->qemu = talloc_reference(child, parent->qemu);
         // child->glue = talloc_reference(child, parent->glue);
@@ -196,8 +231,133 @@ impl EventHandler for Tracee {
         //         (void) restart_tracee(child, 0);
         // }
 
+        // The parent of a vfork() is held by the event loop until the child
+        // calls execve() or exits, and its address space is borrowed by the
+        // child meanwhile: nothing of it is duplicated for the child.
+        if clone_flags.contains(CloneFlags::CLONE_VFORK) {
+            child_tracee.vfork_parent = Some(self.pid);
+            self.vfork_child = Some(child_pid);
+        }
+
         child_tracee.sigstop_status = SigStopStatus::RaisedByTraceClone;
 
         Ok(child_tracee)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::path::Path;
+
+    use nix::sys::wait::{self, WaitStatus};
+    use nix::unistd::{execv, fork, ForkResult, Pid};
+    use sc::nr::EXECVE;
+
+    use crate::register::{Current, Original, PtraceReader, SysArg1, SysResult};
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot};
+
+    #[test]
+    fn test_vfork_then_exec_guest_binary() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                let parent = match tracee.vfork_parent {
+                    Some(parent) => parent,
+                    None => return,
+                };
+                if !is_sysenter || before_translation || tracee.regs.get_sys_num(Current) != EXECVE
+                {
+                    return;
+                }
+                // the path is translated in the child, and the program is
+                // recorded with its guest path
+                assert_ne!(
+                    tracee.regs.get_sysarg_path(SysArg1).unwrap(),
+                    Path::new("/bin/busybox")
+                );
+                assert_eq!(
+                    tracee.new_exe.as_ref().map(|exe| exe.borrow().clone()),
+                    Some(Path::new("/bin/busybox").to_path_buf())
+                );
+                // meanwhile, the parent is held in a ptrace-stop
+                let stat = std::fs::read_to_string(format!("/proc/{}/stat", parent)).unwrap();
+                let state = stat
+                    .rsplitn(2, ')')
+                    .next()
+                    .unwrap()
+                    .split_whitespace()
+                    .next();
+                assert_eq!(state, Some("t"));
+            },
+            || {
+                // nothing but execv() or _exit() may be called by the child
+                let path = CString::new("/bin/busybox").unwrap();
+                let args = [
+                    CString::new("test").unwrap(),
+                    CString::new("-x").unwrap(),
+                    CString::new("/bin/busybox").unwrap(),
+                ];
+                match unsafe { libc::vfork() } {
+                    0 => {
+                        let _ = execv(&path, &args);
+                        unsafe { libc::_exit(127) };
+                    }
+                    child => {
+                        // "/bin/busybox" only exists in the guest rootfs
+                        assert!(child > 0);
+                        let child = Pid::from_raw(child);
+                        assert_eq!(wait::waitpid(child, None), Ok(WaitStatus::Exited(child, 0)));
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_vfork_then_exit_resumes_parent() {
+        test_with_proot(
+            |_, _, _| {},
+            || match unsafe { libc::vfork() } {
+                0 => unsafe { libc::_exit(3) },
+                child => {
+                    assert!(child > 0);
+                    let child = Pid::from_raw(child);
+                    assert_eq!(wait::waitpid(child, None), Ok(WaitStatus::Exited(child, 3)));
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_fork_then_exec_logs_guest_exe() {
+        test_with_proot(
+            |tracee, is_sysenter, before_translation| {
+                if is_sysenter
+                    || before_translation
+                    || tracee.regs.get_sys_num(Original) != EXECVE
+                    || tracee.regs.get(Current, SysResult) != 0
+                {
+                    return;
+                }
+                // the program is reported with its guest path, never the host one
+                let description = tracee.describe();
+                assert_eq!(description, format!("{} (/bin/busybox)", tracee.pid));
+                assert!(!description.contains(get_test_rootfs_path().to_str().unwrap()));
+                assert_eq!(
+                    tracee.read_guest_exe(tracee.pid),
+                    Some(Path::new("/bin/busybox").to_path_buf())
+                );
+            },
+            || match unsafe { fork() }.unwrap() {
+                ForkResult::Child => {
+                    let path = CString::new("/bin/busybox").unwrap();
+                    let _ = execv(&path, &[CString::new("true").unwrap()]);
+                    unsafe { libc::_exit(127) };
+                }
+                ForkResult::Parent { child } => {
+                    assert_eq!(wait::waitpid(child, None), Ok(WaitStatus::Exited(child, 0)));
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Owners and mode faked for a file, reported by stat() instead of the real
+/// ones. `None` keeps the real value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FakeAttributes {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Permission bits (`0o7777`), the type of the file being the real one.
+    pub mode: Option<u32>,
+}
+
+impl FakeAttributes {
+    /// Returns the `st_mode` of a file with the faked permission bits, if
+    /// any.
+    pub fn apply_to_mode(&self, st_mode: u32) -> u32 {
+        match self.mode {
+            Some(mode) => (st_mode & !0o7777) | (mode & 0o7777),
+            None => st_mode,
+        }
+    }
+}
+
+/// Identity of a host file, shared by all its hard links and all the paths
+/// reaching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+}
+
+impl FileId {
+    /// Returns the identity of the file at `host_path`, its final component
+    /// being dereferenced only if `deref_final` is set.
+    pub fn of_path(host_path: &Path, deref_final: bool) -> Option<FileId> {
+        let metadata = if deref_final {
+            host_path.metadata()
+        } else {
+            host_path.symlink_metadata()
+        };
+        metadata.ok().map(|metadata| FileId::from(&metadata))
+    }
+}
+
+impl From<&Metadata> for FileId {
+    fn from(metadata: &Metadata) -> FileId {
+        FileId {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        }
+    }
+}
+
+/// Owners and modes given to the files by the guest with `fake_ownership`,
+/// shared by all the tracees.
+///
+/// The files are known by their `FileId` (device and inode), so that the
+/// hard links of a file and its aliases (its guest paths through symlinks or
+/// bindings) share the same entry. An entry is forgotten when the last link
+/// of its file is removed, before the inode is reused.
+#[derive(Debug, Default)]
+pub struct FakeMetadata {
+    entries: HashMap<FileId, FakeAttributes>,
+}
+
+impl FakeMetadata {
+    pub fn new() -> FakeMetadata {
+        FakeMetadata::default()
+    }
+
+    pub fn get(&self, file_id: FileId) -> Option<&FakeAttributes> {
+        self.entries.get(&file_id)
+    }
+
+    /// Records the owners given by chown(), `None` (i.e. `-1`) leaving the
+    /// current one.
+    pub fn set_owners(&mut self, file_id: FileId, uid: Option<u32>, gid: Option<u32>) {
+        let attributes = self.entries.entry(file_id).or_default();
+        attributes.uid = uid.or(attributes.uid);
+        attributes.gid = gid.or(attributes.gid);
+    }
+
+    /// Records the mode given by chmod(), or forgets it with `None`, when the
+    /// host file has the real one.
+    pub fn set_mode(&mut self, file_id: FileId, mode: Option<u32>) {
+        match mode {
+            Some(mode) => {
+                let attributes = self.entries.entry(file_id).or_default();
+                attributes.mode = Some(mode & 0o7777);
+            }
+            None => {
+                if let Some(attributes) = self.entries.get_mut(&file_id) {
+                    attributes.mode = None;
+                }
+            }
+        }
+    }
+
+    /// Forgets the file `file_id`, whose last link was removed.
+    pub fn remove(&mut self, file_id: FileId) -> Option<FakeAttributes> {
+        self.entries.remove(&file_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::get_test_rootfs_path;
+
+    #[test]
+    fn test_fake_metadata_owners_and_mode() {
+        let file_id = FileId { dev: 1, ino: 2 };
+        let mut fake_metadata = FakeMetadata::new();
+        assert_eq!(fake_metadata.get(file_id), None);
+
+        fake_metadata.set_owners(file_id, Some(1000), None);
+        fake_metadata.set_owners(file_id, None, Some(100));
+        fake_metadata.set_mode(file_id, Some(0o104755));
+        let attributes = *fake_metadata.get(file_id).unwrap();
+        assert_eq!(attributes.uid, Some(1000));
+        assert_eq!(attributes.gid, Some(100));
+        assert_eq!(attributes.apply_to_mode(0o100644), 0o104755);
+        // a directory stays a directory
+        assert_eq!(attributes.apply_to_mode(0o040700), 0o044755);
+        assert_eq!(fake_metadata.get(FileId { dev: 2, ino: 2 }), None);
+
+        fake_metadata.set_mode(file_id, None);
+        let attributes = *fake_metadata.get(file_id).unwrap();
+        assert_eq!(attributes.apply_to_mode(0o100644), 0o100644);
+        assert_eq!(attributes.uid, Some(1000));
+
+        assert_eq!(fake_metadata.remove(file_id), Some(attributes));
+        assert_eq!(fake_metadata.get(file_id), None);
+    }
+
+    #[test]
+    fn test_fake_metadata_file_id() {
+        let tmp_path = get_test_rootfs_path().join("tmp");
+        let file_path = tmp_path.join("proot_rs_test_fake_metadata_id");
+        let hard_link_path = tmp_path.join("proot_rs_test_fake_metadata_id_hard_link");
+        let link_path = tmp_path.join("proot_rs_test_fake_metadata_id_link");
+        let _ = std::fs::remove_file(&hard_link_path);
+        let _ = std::fs::remove_file(&link_path);
+        std::fs::write(&file_path, "").unwrap();
+        std::fs::hard_link(&file_path, &hard_link_path).unwrap();
+        std::os::unix::fs::symlink(&file_path, &link_path).unwrap();
+
+        let file_id = FileId::of_path(&file_path, false).unwrap();
+        assert_eq!(FileId::of_path(&hard_link_path, false), Some(file_id));
+        assert_eq!(FileId::of_path(&link_path, true), Some(file_id));
+        assert_ne!(FileId::of_path(&link_path, false), Some(file_id));
+        assert_eq!(FileId::of_path(&tmp_path.join("missing"), true), None);
+
+        std::fs::remove_file(&link_path).unwrap();
+        std::fs::remove_file(&hard_link_path).unwrap();
+        std::fs::remove_file(&file_path).unwrap();
+    }
+}
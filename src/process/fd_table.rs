@@ -0,0 +1,314 @@
+use std::cell::Cell;
+use std::cmp::min;
+use std::collections::HashMap;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use libc::{c_int, SEEK_CUR, SEEK_END, SEEK_SET};
+
+/// Synthetic path recorded for both ends of a pipe, which aren't associated
+/// with any path.
+pub const PIPE_MARKER: &str = "pipe:";
+/// Synthetic paths recorded for the file descriptors of anonymous inodes,
+/// named after the targets reported by the kernel in `/proc/<pid>/fd`.
+pub const EVENTFD_MARKER: &str = "anon_inode:[eventfd]";
+pub const SIGNALFD_MARKER: &str = "anon_inode:[signalfd]";
+pub const TIMERFD_MARKER: &str = "anon_inode:[timerfd]";
+pub const EPOLL_MARKER: &str = "anon_inode:[eventpoll]";
+pub const PIDFD_MARKER: &str = "anon_inode:[pidfd]";
+/// Synthetic path recorded for the file descriptors received with
+/// `SCM_RIGHTS` which don't refer to a guest path.
+pub const RECEIVED_FD_MARKER: &str = "received:[scm_rights]";
+
+/// Content of a file generated by proot-rs (e.g. `/proc/mounts`), which is
+/// served to the reads of the file descriptors opened on it, instead of the
+/// content of the host file (see `kernel::standard::read`).
+#[derive(Debug, PartialEq)]
+pub struct VirtualContent {
+    content: Vec<u8>,
+    /// File offset of read() and lseek(), shared by the duplicates of the
+    /// file descriptor.
+    offset: Cell<usize>,
+}
+
+impl VirtualContent {
+    pub fn new(content: Vec<u8>) -> VirtualContent {
+        VirtualContent {
+            content: content,
+            offset: Cell::new(0),
+        }
+    }
+
+    /// Returns at most `count` bytes from `offset`, like pread().
+    pub fn read_at(&self, offset: usize, count: usize) -> &[u8] {
+        let start = min(offset, self.content.len());
+        let end = start + min(count, self.content.len() - start);
+        &self.content[start..end]
+    }
+
+    /// Returns at most `count` bytes from the current offset, which is
+    /// advanced, like read().
+    pub fn read(&self, count: usize) -> &[u8] {
+        let data = self.read_at(self.offset.get(), count);
+        self.offset.set(self.offset.get() + data.len());
+        data
+    }
+
+    /// Moves the current offset to `offset` bytes from the start
+    /// (`SEEK_SET`), the current offset (`SEEK_CUR`) or the end (`SEEK_END`),
+    /// like lseek(). Returns the new offset, or `None` if `whence` isn't
+    /// supported or the new offset would be negative.
+    pub fn seek(&self, offset: i64, whence: c_int) -> Option<usize> {
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => self.offset.get() as i64,
+            SEEK_END => self.content.len() as i64,
+            _ => return None,
+        };
+        let new_offset = base.checked_add(offset).filter(|&offset| offset >= 0)? as usize;
+        self.offset.set(new_offset);
+        Some(new_offset)
+    }
+}
+
+/// Information recorded about a file descriptor opened by a tracee.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FdEntry {
+    /// Canonical guest path the file descriptor was opened with.
+    pub path: PathBuf,
+    /// Whether the file descriptor is closed by the kernel on `execve`
+    /// (`O_CLOEXEC` / `FD_CLOEXEC`).
+    pub cloexec: bool,
+    /// Generated content of the file, if it's virtualized.
+    pub virtual_content: Option<Rc<VirtualContent>>,
+}
+
+impl FdEntry {
+    /// Returns `true` if the file descriptor doesn't refer to a path of the
+    /// guest filesystem, but to an object recorded with a synthetic marker
+    /// (e.g. `PIPE_MARKER`).
+    #[inline]
+    pub fn is_anonymous(&self) -> bool {
+        self.path.is_relative()
+    }
+}
+
+/// Table of the file descriptors opened by a tracee, associated with the
+/// guest path they were opened with.
+///
+/// Tracees created with `CLONE_FILES` share the same table (see
+/// `handle_new_child_event`), the others get their own copy. File descriptors
+/// inherited from outside proot (e.g. stdin/stdout/stderr) are not tracked.
+#[derive(Debug, Clone, Default)]
+pub struct FdTable {
+    entries: HashMap<RawFd, FdEntry>,
+}
+
+impl FdTable {
+    pub fn new() -> FdTable {
+        FdTable {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Records a newly opened file descriptor. Any previous entry for `fd` is
+    /// replaced, since the kernel reuses the numbers of closed descriptors.
+    pub fn insert<P: AsRef<Path>>(&mut self, fd: RawFd, path: P, cloexec: bool) {
+        self.entries.insert(
+            fd,
+            FdEntry {
+                path: path.as_ref().to_path_buf(),
+                cloexec: cloexec,
+                virtual_content: None,
+            },
+        );
+    }
+
+    /// Serves the reads of `fd` from `virtual_content`, if it's tracked.
+    pub fn set_virtual_content(&mut self, fd: RawFd, virtual_content: Rc<VirtualContent>) {
+        if let Some(entry) = self.entries.get_mut(&fd) {
+            entry.virtual_content = Some(virtual_content);
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, fd: RawFd) -> Option<&FdEntry> {
+        self.entries.get(&fd)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, fd: RawFd) -> Option<FdEntry> {
+        self.entries.remove(&fd)
+    }
+
+    /// Records `new_fd` as a duplicate of `old_fd`, as done by `dup()` and
+    /// friends. The close-on-exec flag isn't shared between duplicates,
+    /// whereas the virtual content (and its offset) is.
+    ///
+    /// If `old_fd` isn't tracked, `new_fd` is forgotten as well, since it may
+    /// have been silently closed by the kernel (`dup2()`).
+    pub fn duplicate(&mut self, old_fd: RawFd, new_fd: RawFd, cloexec: bool) {
+        match self.entries.get(&old_fd).cloned() {
+            Some(entry) => {
+                self.entries.insert(
+                    new_fd,
+                    FdEntry {
+                        cloexec: cloexec,
+                        ..entry
+                    },
+                );
+            }
+            None => {
+                self.entries.remove(&new_fd);
+            }
+        }
+    }
+
+    /// Forgets the file descriptors from `first` to `last` (included), as
+    /// done by close_range().
+    pub fn remove_range(&mut self, first: RawFd, last: RawFd) {
+        self.entries.retain(|&fd, _| fd < first || fd > last);
+    }
+
+    /// Sets the close-on-exec flag of the tracked file descriptors from
+    /// `first` to `last` (included), as done by close_range() with
+    /// `CLOSE_RANGE_CLOEXEC`.
+    pub fn set_cloexec_range(&mut self, first: RawFd, last: RawFd) {
+        for (_, entry) in self
+            .entries
+            .iter_mut()
+            .filter(|(&fd, _)| fd >= first && fd <= last)
+        {
+            entry.cloexec = true;
+        }
+    }
+
+    /// Forgets the file descriptors closed by the kernel on a successful
+    /// `execve`, i.e. the ones with the close-on-exec flag.
+    pub fn remove_cloexec(&mut self) {
+        self.entries.retain(|_, entry| !entry.cloexec);
+    }
+
+    /// Updates the close-on-exec flag of `fd`, if it's tracked.
+    pub fn set_cloexec(&mut self, fd: RawFd, cloexec: bool) {
+        if let Some(entry) = self.entries.get_mut(&fd) {
+            entry.cloexec = cloexec;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fd_table_insert_remove() {
+        let mut fd_table = FdTable::new();
+
+        assert_eq!(fd_table.get(3), None);
+
+        fd_table.insert(3, "/etc/passwd", false);
+        assert_eq!(fd_table.get(3).unwrap().path, PathBuf::from("/etc/passwd"));
+
+        // the number of a closed fd can be reused
+        fd_table.insert(3, "/etc/group", true);
+        assert_eq!(
+            fd_table.get(3),
+            Some(&FdEntry {
+                path: PathBuf::from("/etc/group"),
+                cloexec: true,
+                virtual_content: None,
+            })
+        );
+
+        assert!(fd_table.remove(3).is_some());
+        assert_eq!(fd_table.get(3), None);
+        assert_eq!(fd_table.remove(3), None);
+    }
+
+    #[test]
+    fn test_fd_table_duplicate() {
+        let mut fd_table = FdTable::new();
+
+        fd_table.insert(3, "/etc/passwd", true);
+        fd_table.duplicate(3, 4, false);
+        assert_eq!(fd_table.get(4).unwrap().path, PathBuf::from("/etc/passwd"));
+        assert!(!fd_table.get(4).unwrap().cloexec);
+        assert!(fd_table.get(3).unwrap().cloexec);
+
+        // dup2() on an untracked fd drops the stale entry of the new fd
+        fd_table.insert(5, "/etc/group", false);
+        fd_table.duplicate(0, 5, false);
+        assert_eq!(fd_table.get(5), None);
+
+        // duplicates of a pipe end are pipes as well
+        fd_table.insert(6, PIPE_MARKER, false);
+        fd_table.duplicate(6, 7, false);
+        assert!(fd_table.get(7).unwrap().is_anonymous());
+        assert!(!fd_table.get(4).unwrap().is_anonymous());
+
+        fd_table.set_cloexec(4, true);
+        assert!(fd_table.get(4).unwrap().cloexec);
+
+        fd_table.remove_cloexec();
+        assert_eq!(fd_table.get(3), None);
+        assert_eq!(fd_table.get(4), None);
+        assert!(fd_table.get(6).is_some());
+    }
+
+    #[test]
+    fn test_fd_table_virtual_content() {
+        let mut fd_table = FdTable::new();
+
+        fd_table.insert(3, "/proc/mounts", false);
+        fd_table.set_virtual_content(3, Rc::new(VirtualContent::new(b"0123456789".to_vec())));
+        // untracked fds can't be virtualized
+        fd_table.set_virtual_content(4, Rc::new(VirtualContent::new(vec![])));
+        assert_eq!(fd_table.get(4), None);
+
+        let content = fd_table.get(3).unwrap().virtual_content.clone().unwrap();
+        assert_eq!(content.read(4), b"0123");
+        assert_eq!(content.read_at(8, 4), b"89");
+        assert_eq!(content.read_at(12, 4), b"");
+
+        // the offset is shared by the duplicates
+        fd_table.duplicate(3, 5, false);
+        let duplicated_content = fd_table.get(5).unwrap().virtual_content.clone().unwrap();
+        assert_eq!(duplicated_content.read(4), b"4567");
+        assert_eq!(content.read(4), b"89");
+        assert_eq!(content.read(4), b"");
+
+        // lseek() moves the shared offset
+        assert_eq!(content.seek(0, libc::SEEK_SET), Some(0));
+        assert_eq!(duplicated_content.read(2), b"01");
+        assert_eq!(content.seek(3, libc::SEEK_CUR), Some(5));
+        assert_eq!(content.read(2), b"56");
+        assert_eq!(content.seek(-1, libc::SEEK_END), Some(9));
+        assert_eq!(content.read(4), b"9");
+        assert_eq!(content.seek(-11, libc::SEEK_END), None);
+        assert_eq!(content.seek(0, libc::SEEK_DATA), None);
+        assert_eq!(content.read(1), b"");
+    }
+
+    #[test]
+    fn test_fd_table_ranges() {
+        let mut fd_table = FdTable::new();
+        for fd in 3..10 {
+            fd_table.insert(fd, "/etc/passwd", false);
+        }
+
+        fd_table.set_cloexec_range(8, RawFd::MAX);
+        assert!(!fd_table.get(7).unwrap().cloexec);
+        assert!(fd_table.get(8).unwrap().cloexec);
+        assert!(fd_table.get(9).unwrap().cloexec);
+
+        fd_table.remove_range(4, 6);
+        assert!(fd_table.get(3).is_some());
+        assert!((4..=6).all(|fd| fd_table.get(fd).is_none()));
+        assert!(fd_table.get(7).is_some());
+
+        fd_table.remove_range(0, RawFd::MAX);
+        assert!((3..10).all(|fd| fd_table.get(fd).is_none()));
+    }
+}
@@ -1,5 +1,9 @@
 pub mod event;
+pub mod fake_metadata;
+pub mod fd_table;
 pub mod proot;
+pub mod replay;
 pub mod sigactions;
 pub mod tracee;
 pub mod translation;
+pub mod uts;
@@ -1,26 +1,39 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
 
 use std::process;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use std::{collections::HashMap, convert::TryFrom};
 
-use libc::{c_int, c_void, pid_t, siginfo_t};
+use libc::{c_int, c_ulong, c_void, pid_t, siginfo_t};
+use nix::errno::Errno;
 use nix::sys::ptrace::{self, Event as PtraceEvent};
-use nix::sys::signal::{self, Signal};
-use nix::sys::wait::{self, WaitPidFlag, WaitStatus::*};
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::stat::{self, Mode};
+use nix::sys::wait::{self, WaitPidFlag, WaitStatus, WaitStatus::*};
 use nix::unistd::{self, ForkResult, Pid};
 
+use crate::config::ProotConfig;
 use crate::kernel::execve::loader::LoaderFile;
+use crate::kernel::standard::personality::{ADDR_NO_RANDOMIZE, PERSONALITY_QUERY};
 use crate::process::event::EventHandler;
+use crate::process::fake_metadata::FakeMetadata;
+use crate::process::replay::ReplayLog;
 use crate::process::tracee::{SigStopStatus, Tracee};
+use crate::process::uts::VirtualUts;
 use crate::{
     errors::*,
-    filesystem::{temp::TempFile, FileSystem},
+    filesystem::{temp::TempFile, FileSystem, Translator},
 };
 
-/// Used to store global info common to all tracees. Rename into
-/// `Configuration`?
+/// Exit code of proot-rs when the tracees are killed after the timeout of the
+/// configuration, like timeout(1).
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Used to store global info common to all tracees.
 #[derive(Debug)]
 pub struct InfoBag {
     /// Used to know if the ptrace options is already set.
@@ -31,13 +44,38 @@ pub struct InfoBag {
     /// before use. This temporary file struct makes sure the file is
     /// deleted when it's dropped.
     pub loader: TempFile,
+    /// Options of this proot-rs session.
+    pub config: ProotConfig,
+    /// Log of the translated syscalls, if enabled by the configuration. It's
+    /// created by `PRoot::init()`.
+    pub replay_log: Option<RefCell<ReplayLog>>,
+    /// State of the pseudo-random generator of getrandom(), shared by all
+    /// the tracees, see `getrandom::exit()`.
+    pub random_state: Cell<u64>,
+    /// Number of processes traced at the moment, kept by `PRoot`.
+    pub tracee_count: Cell<usize>,
+    /// Owners and modes faked with `fake_ownership`, shared by all the
+    /// tracees.
+    pub fake_metadata: RefCell<FakeMetadata>,
+    /// Hostname and domain name reported by uname(), changed by
+    /// sethostname() and setdomainname().
+    pub uts: RefCell<VirtualUts>,
 }
 
 impl InfoBag {
-    pub fn new() -> InfoBag {
+    pub fn new(config: ProotConfig) -> InfoBag {
         InfoBag {
             options_already_set: false,
             loader: TempFile::new("prooted"),
+            random_state: Cell::new(config.random_seed.unwrap_or(0)),
+            tracee_count: Cell::new(0),
+            fake_metadata: RefCell::new(FakeMetadata::new()),
+            uts: RefCell::new(VirtualUts::new(
+                config.hostname.as_deref(),
+                config.domainname.as_deref(),
+            )),
+            replay_log: None,
+            config: config,
         }
     }
 }
@@ -46,10 +84,18 @@ pub struct PRoot {
     info_bag: InfoBag,
     tracees: HashMap<Pid, Tracee>,
     alive_tracees: Vec<Pid>,
+    /// Processes created beyond the maximum number of tracees, which are
+    /// detached on their first stop.
+    untraced_children: Vec<Pid>,
     /// The `pid` of init process (i.e. the first tracee)
     pub init_pid: Option<Pid>,
     /// The exit code of the init process (i.e. the first tracee)
     pub init_exit_code: Option<i32>,
+    /// Whether the tracees were killed after the timeout of the configuration.
+    timed_out: bool,
+    /// Stops of new processes received before the fork event of their
+    /// parent, which are handled once these processes are registered.
+    early_wait_statuses: Vec<WaitStatus>,
     /// A pointer to a function used to check the running status of Proot.
     /// For each syscall-stop, it will be called four times (at the beginning
     /// and end of both syscall-enter-stop and syscall-exit-stop).
@@ -58,18 +104,38 @@ pub struct PRoot {
     /// the running of Proot.
     #[cfg(test)]
     pub func_syscall_hook: Option<Box<dyn Fn(&Tracee, bool, bool)>>,
+    /// A pointer to a function choosing which tracee the event loop services
+    /// next, in order to make the tests with several tracees reproducible.
+    ///
+    /// It's given the events of the stopped tracees that are waiting to be
+    /// handled, and returns the index of the one to handle, or `None` to hold
+    /// all of them until the next event. The first event is handled anyway
+    /// when all the tracees are held, since there is nothing else to wait for.
+    #[cfg(test)]
+    pub func_scheduler_hook: Option<Box<dyn Fn(&[WaitStatus]) -> Option<usize>>>,
+    /// The events received by `waitpid()` that were not handled yet, used
+    /// with `func_scheduler_hook`.
+    #[cfg(test)]
+    pending_wait_statuses: Vec<WaitStatus>,
 }
 
 impl PRoot {
-    pub fn new() -> PRoot {
+    pub fn new(info_bag: InfoBag) -> PRoot {
         PRoot {
-            info_bag: InfoBag::new(),
+            info_bag: info_bag,
             tracees: HashMap::new(),
             alive_tracees: vec![],
+            untraced_children: vec![],
             init_pid: None,
             init_exit_code: None,
+            timed_out: false,
+            early_wait_statuses: vec![],
             #[cfg(test)]
             func_syscall_hook: None,
+            #[cfg(test)]
+            func_scheduler_hook: None,
+            #[cfg(test)]
+            pending_wait_statuses: vec![],
         }
     }
 
@@ -81,6 +147,9 @@ impl PRoot {
             .loader
             .prepare_loader()
             .context("Error while prepare loader file")?;
+        if let Some(ref path) = self.info_bag.config.replay_log {
+            self.info_bag.replay_log = Some(RefCell::new(ReplayLog::create(path)?));
+        }
         Ok(())
     }
 
@@ -108,7 +177,21 @@ impl PRoot {
                 })
             })
             .collect::<Result<Vec<_>>>()?;
-        let filename = &args[0];
+        // the init program is executed under its guest path, whatever its
+        // argv[0], see `ProotConfig::init_command()`
+        let filename = match &self.info_bag.config.init {
+            Some(init) => {
+                let host_path = initial_fs.translate_path(init, true)?;
+                FileSystem::check_host_path_executable(&host_path).with_context(|| {
+                    format!("The init program {:?} isn't executable in the guest", init)
+                })?;
+                CString::new(init.as_os_str().as_bytes())
+                    .context("Illegal init program, should not contain \0 bytes")?
+            }
+            None => args[0].clone(),
+        };
+        let no_randomize = self.info_bag.config.no_randomize;
+        let has_virtual_umask = self.info_bag.config.umask.is_some();
         match unsafe { unistd::fork() }.context("Failed to fork() when starting process")? {
             ForkResult::Parent { child } => {
                 // create the first tracee
@@ -121,6 +204,10 @@ impl PRoot {
             }
             ForkResult::Child => {
                 let init_child_func = || -> Result<()> {
+                    // The virtual umask replaces the host one, see `umask::apply()`
+                    if has_virtual_umask {
+                        stat::umask(Mode::empty());
+                    }
                     // Declare the tracee as ptraceable
                     ptrace::traceme()
                         .context("Failed to execute ptrace::traceme() in a child process")?;
@@ -128,6 +215,12 @@ impl PRoot {
                     // (otherwise the execvp is executed too quickly)
                     signal::kill(unistd::getpid(), Signal::SIGSTOP)
                         .context("Child process failed to synchronize with parent process")?;
+                    // The persona is inherited through execve(), see `personality::enter()`
+                    if no_randomize {
+                        let persona = unsafe { libc::personality(PERSONALITY_QUERY) } as c_ulong;
+                        Errno::result(unsafe { libc::personality(persona | ADDR_NO_RANDOMIZE) })
+                            .context("Failed to disable the address space randomization")?;
+                    }
                     //TODO: seccomp
                     //if (getenv("PROOT_NO_SECCOMP") == NULL)
                     //    (void) enable_syscall_filtering(tracee);
@@ -152,13 +245,32 @@ impl PRoot {
     /// The tracer will be notified through `waitpid` and will be able to alter
     /// the parameters of the system call, before restarting the tracee.
     pub fn event_loop(&mut self) -> Result<()> {
+        if let Some(timeout) = self.info_bag.config.timeout {
+            TIMEOUT_EXPIRED.store(false, Ordering::SeqCst);
+            arm_timeout_alarm(timeout)?;
+        }
+
         // TODO: what should we do if there is a terrible error in eventloop?
         while !self.alive_tracees.is_empty() {
-            match wait::waitpid(Pid::from_raw(-1), Some(WaitPidFlag::__WALL))
-                .context("Error calling waitpid() in event loop")?
-            {
+            // the alarm may also have been delivered while we were translating
+            // a syscall, so the flag is checked on each event
+            if TIMEOUT_EXPIRED.load(Ordering::SeqCst) {
+                self.kill_all_tracees();
+            }
+            let wait_status = match self.wait_next_status()? {
+                Some(wait_status) => wait_status,
+                // waitpid() was interrupted by the alarm of the timeout
+                None => continue,
+            };
+            if self.is_stop_of_unknown_process(&wait_status) {
+                trace!("-- {:?}, stop of an unknown process, delayed", wait_status);
+                self.early_wait_statuses.push(wait_status);
+                continue;
+            }
+            match wait_status {
                 Exited(pid, exit_status) => {
                     trace!("-- {}, Exited with status: {}", pid, exit_status);
+                    self.release_vfork_parent(pid);
                     self.register_tracee_finished(pid);
                     if Some(pid) == self.init_pid {
                         // The "init" process was exited. We need to record the exit code.
@@ -175,6 +287,7 @@ impl PRoot {
                         term_signal,
                         dumped_core
                     );
+                    self.release_vfork_parent(pid);
                     self.register_tracee_finished(pid);
                     if Some(pid) == self.init_pid {
                         // The "init" process was killed by a signal, the exit code should be
@@ -195,6 +308,19 @@ impl PRoot {
                         stop_signal as c_int
                     );
 
+                    // the first stop of a process left untraced
+                    if let Some(index) = self
+                        .untraced_children
+                        .iter()
+                        .position(|&child| child == pid)
+                    {
+                        self.untraced_children.remove(index);
+                        if let Err(error) = ptrace::detach(pid, None) {
+                            warn!("failed to detach process {}: {}", pid, error);
+                        }
+                        continue;
+                    }
+
                     let mut signal_to_delivery = Some(stop_signal);
 
                     let tracee = self.tracees.get_mut(&pid).expect("get stopped tracee");
@@ -271,8 +397,10 @@ impl PRoot {
                     };
 
                     trace!("-- {}, Ptrace event, {:?}, {:?}", pid, signal, maybe_event);
+                    let tracee_limit_reached = self.is_tracee_limit_reached();
                     let tracee = self.tracees.get_mut(&pid).expect("get stopped tracee");
                     tracee.reset_restart_how();
+                    let mut vfork_parent = None;
 
                     match maybe_event {
                         // handle_new_child_event
@@ -280,8 +408,18 @@ impl PRoot {
                         | Some(PtraceEvent::PTRACE_EVENT_VFORK)
                         | Some(PtraceEvent::PTRACE_EVENT_CLONE) => {
                             match tracee.handle_new_child_event() {
+                                Ok(child_tracee) if tracee_limit_reached => {
+                                    warn!(
+                                        "-- {}, new process with pid {} beyond the maximum number of tracees, left untraced",
+                                        pid, child_tracee.pid
+                                    );
+                                    self.untraced_children.push(child_tracee.pid);
+                                }
                                 Ok(child_tracee) => {
-                                    info!("-- {}, new process with pid {}", pid, child_tracee.pid);
+                                    info!("-- {}, new process {}", pid, child_tracee.describe());
+                                    // the parent of a vfork() is held until its
+                                    // child stops sharing its address space
+                                    tracee.held_by_vfork = child_tracee.vfork_parent.is_some();
                                     self.insert_new_tracee(child_tracee)
                                 }
                                 Err(error) => {
@@ -292,10 +430,17 @@ impl PRoot {
                                 }
                             }
                         }
-                        // handle_exec_vfork_event
-                        Some(PtraceEvent::PTRACE_EVENT_EXEC)
-                        | Some(PtraceEvent::PTRACE_EVENT_VFORK_DONE) => {
-                            tracee.handle_exec_vfork_event();
+                        Some(PtraceEvent::PTRACE_EVENT_EXEC) => {
+                            vfork_parent = tracee.vfork_parent;
+                            if let Err(error) = tracee.handle_exec_event() {
+                                error!(
+                                    "Error while handling exec event for pid {}. {}",
+                                    tracee.pid, error
+                                );
+                            }
+                        }
+                        Some(PtraceEvent::PTRACE_EVENT_VFORK_DONE) => {
+                            tracee.handle_vfork_done_event();
                         }
                         // handle_seccomp_event
                         Some(PtraceEvent::PTRACE_EVENT_SECCOMP) => {
@@ -310,7 +455,12 @@ impl PRoot {
                     // Re-acquire tracee as we cannot borrow `*self` as mutable more than once at a
                     // time in rust.
                     let tracee = self.tracees.get_mut(&pid).expect("get stopped tracee");
-                    tracee.restart(None);
+                    if !tracee.held_by_vfork {
+                        tracee.restart(None);
+                    }
+                    if let Some(parent) = vfork_parent {
+                        self.resume_held_tracee(parent);
+                    }
                 }
                 // The tracee was stopped by execution of a system call (syscall-stop), and
                 // PTRACE_O_TRACESYSGOOD was effect. PTRACE_O_TRACESYSGOOD is used to make it
@@ -335,9 +485,118 @@ impl PRoot {
             }
         }
 
+        if self.info_bag.config.timeout.is_some() {
+            disarm_timeout_alarm()?;
+        }
+        if self.timed_out {
+            self.init_exit_code = Some(TIMEOUT_EXIT_CODE);
+        }
         Ok(())
     }
 
+    /// Waits for the next event of the tracees. Returns `None` if `waitpid()`
+    /// was interrupted by a signal.
+    fn wait_next_status(&mut self) -> Result<Option<WaitStatus>> {
+        let tracees = &self.tracees;
+        let untraced_children = &self.untraced_children;
+        if let Some(index) = self.early_wait_statuses.iter().position(|status| {
+            status.pid().map_or(false, |pid| {
+                tracees.contains_key(&pid) || untraced_children.contains(&pid)
+            })
+        }) {
+            return Ok(Some(self.early_wait_statuses.remove(index)));
+        }
+        #[cfg(test)]
+        {
+            if self.func_scheduler_hook.is_some() {
+                return self.schedule_next_status();
+            }
+        }
+        match wait::waitpid(Pid::from_raw(-1), Some(WaitPidFlag::__WALL)) {
+            Err(nix::Error::Sys(Errno::EINTR)) => Ok(None),
+            result => Ok(Some(
+                result.context("Error calling waitpid() in event loop")?,
+            )),
+        }
+    }
+
+    /// Same as `wait_next_status()`, but lets `func_scheduler_hook` choose
+    /// the event to handle among all the events received so far.
+    ///
+    /// The events of the new tracees that are not registered yet (i.e. whose
+    /// parent's fork event wasn't handled yet) are kept pending, without
+    /// being given to the hook.
+    #[cfg(test)]
+    fn schedule_next_status(&mut self) -> Result<Option<WaitStatus>> {
+        loop {
+            // collect the events that are already available
+            loop {
+                match wait::waitpid(
+                    Pid::from_raw(-1),
+                    Some(WaitPidFlag::__WALL | WaitPidFlag::WNOHANG),
+                ) {
+                    Ok(StillAlive) | Err(nix::Error::Sys(Errno::ECHILD)) => break,
+                    Err(nix::Error::Sys(Errno::EINTR)) => return Ok(None),
+                    result => self
+                        .pending_wait_statuses
+                        .push(result.context("Error calling waitpid() in event loop")?),
+                }
+            }
+
+            let tracees = &self.tracees;
+            let untraced_children = &self.untraced_children;
+            let schedulable: Vec<WaitStatus> = self
+                .pending_wait_statuses
+                .iter()
+                .filter(|status| {
+                    status.pid().map_or(false, |pid| {
+                        tracees.contains_key(&pid) || untraced_children.contains(&pid)
+                    })
+                })
+                .cloned()
+                .collect();
+            if !schedulable.is_empty() {
+                let func_scheduler_hook = self.func_scheduler_hook.as_ref().unwrap();
+                let chosen = match func_scheduler_hook(&schedulable) {
+                    Some(index) if index < schedulable.len() => Some(index),
+                    _ if schedulable.len() >= self.alive_tracees.len() => Some(0),
+                    _ => None,
+                };
+                if let Some(index) = chosen {
+                    let wait_status = schedulable[index];
+                    let position = self
+                        .pending_wait_statuses
+                        .iter()
+                        .position(|status| *status == wait_status)
+                        .unwrap();
+                    self.pending_wait_statuses.remove(position);
+                    return Ok(Some(wait_status));
+                }
+            }
+
+            match wait::waitpid(Pid::from_raw(-1), Some(WaitPidFlag::__WALL)) {
+                Err(nix::Error::Sys(Errno::EINTR)) => return Ok(None),
+                result => self
+                    .pending_wait_statuses
+                    .push(result.context("Error calling waitpid() in event loop")?),
+            }
+        }
+    }
+
+    /// Kills all the tracees, whose exits are then handled by the event loop.
+    fn kill_all_tracees(&mut self) {
+        if self.timed_out {
+            return;
+        }
+        warn!("timeout reached, killing all the tracees");
+        self.timed_out = true;
+        for &pid in &self.alive_tracees {
+            if let Err(error) = signal::kill(pid, Signal::SIGKILL) {
+                debug!("failed to kill tracee {}: {}", pid, error);
+            }
+        }
+    }
+
     pub fn create_tracee(
         &mut self,
         pid: Pid,
@@ -346,6 +605,9 @@ impl PRoot {
     ) -> Option<&Tracee> {
         let mut tracee = Tracee::new(pid, fs);
         tracee.sigstop_status = sigstop_status;
+        if let Some(path_max) = self.info_bag.config.path_max {
+            tracee.regs.set_path_max(path_max);
+        }
         self.tracees.insert(pid, tracee);
         self.register_alive_tracee(pid);
         self.tracees.get(&pid)
@@ -359,12 +621,115 @@ impl PRoot {
 
     fn register_alive_tracee(&mut self, pid: Pid) {
         self.alive_tracees.push(pid);
+        self.info_bag.tracee_count.set(self.alive_tracees.len());
+    }
+
+    /// Returns `true` if `wait_status` is a stop of a process that isn't
+    /// registered yet, i.e. a new process stopped before the fork event of
+    /// its parent was handled.
+    fn is_stop_of_unknown_process(&self, wait_status: &WaitStatus) -> bool {
+        match *wait_status {
+            Stopped(pid, _) | PtraceEvent(pid, _, _) | PtraceSyscall(pid) => {
+                !self.tracees.contains_key(&pid) && !self.untraced_children.contains(&pid)
+            }
+            _ => false,
+        }
+    }
+
+    /// Resumes the parent held since the vfork() which created `child_pid`,
+    /// if this child was still sharing its address space.
+    fn release_vfork_parent(&mut self, child_pid: Pid) {
+        if let Some(parent) = self
+            .tracees
+            .get(&child_pid)
+            .and_then(|child| child.vfork_parent)
+        {
+            self.resume_held_tracee(parent);
+        }
+    }
+
+    /// Restarts a tracee held at its PTRACE_EVENT_VFORK event, which is then
+    /// suspended by the kernel until its PTRACE_EVENT_VFORK_DONE event.
+    fn resume_held_tracee(&mut self, pid: Pid) {
+        if let Some(tracee) = self.tracees.get_mut(&pid) {
+            if tracee.held_by_vfork {
+                tracee.held_by_vfork = false;
+                tracee.restart(None);
+            }
+        }
     }
 
     fn register_tracee_finished(&mut self, finished_pid: Pid) {
         self.alive_tracees.retain(|pid| *pid != finished_pid);
         self.tracees.remove(&finished_pid);
+        self.info_bag.tracee_count.set(self.alive_tracees.len());
     }
+
+    /// Returns `true` if a new process would exceed the maximum number of
+    /// tracees, and has to be left untraced (see `TraceeLimitPolicy`). With
+    /// the `Fail` policy, its creation already failed in `clone::enter()`,
+    /// unless several tracees were creating processes at the same time.
+    fn is_tracee_limit_reached(&self) -> bool {
+        self.info_bag
+            .config
+            .max_tracees
+            .map_or(false, |max_tracees| self.alive_tracees.len() >= max_tracees)
+    }
+}
+
+/// Set by the SIGALRM handler once the timeout of the event loop expired.
+static TIMEOUT_EXPIRED: AtomicBool = AtomicBool::new(false);
+
+/// Period of the SIGALRM sent after the timeout expired, so that an alarm
+/// delivered between the check of `TIMEOUT_EXPIRED` and `waitpid()` can't
+/// leave the event loop blocked forever.
+const TIMEOUT_ALARM_INTERVAL: Duration = Duration::from_millis(100);
+
+extern "C" fn handle_timeout_alarm(_: c_int) {
+    TIMEOUT_EXPIRED.store(true, Ordering::SeqCst);
+}
+
+/// Sends a SIGALRM to proot-rs after `timeout`, and then every
+/// `TIMEOUT_ALARM_INTERVAL`, in order to interrupt the waitpid() of the event
+/// loop (the handler is installed without `SA_RESTART`).
+fn arm_timeout_alarm(timeout: Duration) -> Result<()> {
+    let signal_action = SigAction::new(
+        SigHandler::Handler(handle_timeout_alarm),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    unsafe { signal::sigaction(Signal::SIGALRM, &signal_action) }
+        .context("Failed to set the action of SIGALRM for the timeout")?;
+
+    // a zero `it_value` would disarm the timer instead
+    set_timeout_timer(
+        timeout.max(Duration::from_micros(1)),
+        TIMEOUT_ALARM_INTERVAL,
+    )
+    .context("Failed to arm the timer of the timeout")?;
+    Ok(())
+}
+
+/// Stops the SIGALRM sent periodically once the timeout expired.
+fn disarm_timeout_alarm() -> Result<()> {
+    set_timeout_timer(Duration::from_secs(0), Duration::from_secs(0))
+        .context("Failed to disarm the timer of the timeout")?;
+    Ok(())
+}
+
+fn set_timeout_timer(value: Duration, interval: Duration) -> nix::Result<()> {
+    let timer = libc::itimerval {
+        it_interval: libc::timeval {
+            tv_sec: interval.as_secs() as libc::time_t,
+            tv_usec: interval.subsec_micros() as libc::suseconds_t,
+        },
+        it_value: libc::timeval {
+            tv_sec: value.as_secs() as libc::time_t,
+            tv_usec: value.subsec_micros() as libc::suseconds_t,
+        },
+    };
+    Errno::result(unsafe { libc::setitimer(libc::ITIMER_REAL, &timer, std::ptr::null_mut()) })
+        .map(drop)
 }
 
 /// Proot has received a fatal error from one of the tracee,
@@ -381,12 +746,19 @@ pub extern "C" fn show_info(pid: pid_t) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::TraceeLimitPolicy;
+    use crate::register::Original;
+    use crate::utils::tests::{
+        get_test_rootfs_path, test_in_subprocess, test_with_proot_config_and_exit_code,
+        test_with_proot_scheduler,
+    };
     use nix::unistd::Pid;
+    use std::os::unix::fs::PermissionsExt;
 
     #[test]
     fn create_proot_and_tracee() {
         let fs = FileSystem::new();
-        let mut proot = PRoot::new();
+        let mut proot = PRoot::new(InfoBag::new(ProotConfig::default()));
 
         // tracee 0 shouldn't exist
         {
@@ -408,4 +780,222 @@ mod tests {
             assert!(tracee.is_some());
         }
     }
+
+    #[test]
+    fn test_early_stop_of_new_process_is_delayed() {
+        let mut proot = PRoot::new(InfoBag::new(ProotConfig::default()));
+        let child = Pid::from_raw(4242);
+        let early_stop = Stopped(child, Signal::SIGSTOP);
+
+        // the child stopped before the fork event of its parent was handled
+        assert!(proot.is_stop_of_unknown_process(&early_stop));
+        assert!(!proot.is_stop_of_unknown_process(&Exited(child, 0)));
+        proot.early_wait_statuses.push(early_stop);
+
+        // its stop is handled first once it's registered
+        proot.create_tracee(
+            child,
+            Rc::new(RefCell::new(FileSystem::new())),
+            SigStopStatus::RaisedByTraceClone,
+        );
+        assert!(!proot.is_stop_of_unknown_process(&early_stop));
+        assert_eq!(proot.wait_next_status().unwrap(), Some(early_stop));
+        assert!(proot.early_wait_statuses.is_empty());
+    }
+
+    #[test]
+    fn test_timeout_kills_runaway_tracee() {
+        test_with_proot_config_and_exit_code(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .timeout(Duration::from_secs(1))
+                .build(),
+            TIMEOUT_EXIT_CODE,
+            |_, _, _| {},
+            || loop {
+                std::thread::sleep(Duration::from_secs(60));
+            },
+        )
+    }
+
+    #[test]
+    fn test_timeout_expiring_before_first_wait() {
+        // the alarm is delivered before the event loop waits for any event
+        test_with_proot_config_and_exit_code(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .timeout(Duration::from_micros(1))
+                .build(),
+            TIMEOUT_EXIT_CODE,
+            |_, _, _| {},
+            || loop {
+                std::thread::sleep(Duration::from_secs(60));
+            },
+        )
+    }
+
+    #[test]
+    fn test_launch_init_program() {
+        let test_dir = get_test_rootfs_path().join("tmp/proot_rs_test_launch_init_program");
+        std::fs::create_dir_all(&test_dir).unwrap();
+        // this stub only exists in the guest rootfs
+        std::fs::write(
+            test_dir.join("init"),
+            "#!/bin/sh\n[ \"$1\" = single ] && exit 7\nexit 1\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(
+            test_dir.join("init"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+
+        test_in_subprocess(|| {
+            let config = ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .init("/tmp/proot_rs_test_launch_init_program/init")
+                .init_argv0("init")
+                .build();
+            let command = config.init_command(vec!["single".into()]).unwrap();
+            assert_eq!(command, vec!["init".to_string(), "single".to_string()]);
+
+            let fs = config.create_filesystem().unwrap();
+            let mut proot = PRoot::new(InfoBag::new(config));
+            proot.init().unwrap();
+            proot.launch_process(fs, command).unwrap();
+            proot.event_loop().unwrap();
+            assert_eq!(proot.init_exit_code, Some(7));
+        });
+
+        std::fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_scheduler_hook_interleaves_tracees() {
+        let (reader, writer) = unistd::pipe().unwrap();
+        // the pid of the first tracee, and whether its child was created and
+        // exited, as seen by proot-rs
+        let init = Cell::new(None);
+        let forked = Rc::new(Cell::new(false));
+        let child_exited = Cell::new(false);
+
+        let forked_by_syscall_hook = forked.clone();
+        test_with_proot_scheduler(
+            move |wait_statuses| {
+                let init_pid = match init.get() {
+                    Some(pid) => pid,
+                    None => {
+                        let pid = wait_statuses[0].pid().unwrap();
+                        init.set(Some(pid));
+                        pid
+                    }
+                };
+                // the child runs first, and the first tracee is held from its
+                // fork until the child has exited
+                match wait_statuses
+                    .iter()
+                    .position(|status| status.pid() != Some(init_pid))
+                {
+                    Some(index) => {
+                        if let Exited(..) | Signaled(..) = wait_statuses[index] {
+                            child_exited.set(true);
+                        }
+                        Some(index)
+                    }
+                    None if forked.get() && !child_exited.get() => None,
+                    None => Some(0),
+                }
+            },
+            move |tracee, is_sysenter, before_translation| {
+                if !is_sysenter
+                    && !before_translation
+                    && tracee.regs.get_sys_num(Original) == sc::nr::CLONE
+                {
+                    forked_by_syscall_hook.set(true);
+                }
+            },
+            || match unsafe { unistd::fork() }.unwrap() {
+                ForkResult::Child => {
+                    unistd::write(writer, b"child").unwrap();
+                    unsafe { libc::_exit(0) };
+                }
+                ForkResult::Parent { child } => {
+                    // without the scheduler, the parent would usually write first
+                    unistd::write(writer, b"parent").unwrap();
+                    assert_eq!(wait::waitpid(child, None), Ok(Exited(child, 0)));
+                    unistd::close(writer).unwrap();
+
+                    let mut output = [0u8; 16];
+                    let size = unistd::read(reader, &mut output).unwrap();
+                    assert_eq!(&output[..size], b"childparent");
+                }
+            },
+        );
+    }
+
+    /// Forks a child of the first tracee, which exits with 0 if it sees the
+    /// host filesystem (i.e. it's not traced), and returns its exit status.
+    fn fork_child_seeing_host(host_rootfs_path: &std::path::Path) -> nix::Result<wait::WaitStatus> {
+        match unsafe { unistd::fork() }? {
+            ForkResult::Child => {
+                let sees_host = host_rootfs_path.join("bin").exists();
+                unsafe { libc::_exit(if sees_host { 0 } else { 1 }) };
+            }
+            ForkResult::Parent { child } => wait::waitpid(child, None),
+        }
+    }
+
+    #[test]
+    fn test_max_tracees_fail() {
+        let host_rootfs_path = get_test_rootfs_path();
+        test_with_proot_config_and_exit_code(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .max_tracees(1)
+                .build(),
+            0,
+            |_, _, _| {},
+            move || {
+                assert_eq!(
+                    fork_child_seeing_host(&host_rootfs_path),
+                    Err(nix::Error::Sys(Errno::EAGAIN))
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_max_tracees_untrace() {
+        let host_rootfs_path = get_test_rootfs_path();
+        test_with_proot_config_and_exit_code(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .max_tracees(1)
+                .tracee_limit_policy(TraceeLimitPolicy::Untrace)
+                .build(),
+            0,
+            |_, _, _| {},
+            move || {
+                // the paths of the child aren't translated anymore
+                let child_status = fork_child_seeing_host(&host_rootfs_path).unwrap();
+                assert!(matches!(child_status, Exited(_, 0)));
+            },
+        );
+
+        // below the limit, the child is traced
+        let host_rootfs_path = get_test_rootfs_path();
+        test_with_proot_config_and_exit_code(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .max_tracees(2)
+                .tracee_limit_policy(TraceeLimitPolicy::Untrace)
+                .build(),
+            0,
+            |_, _, _| {},
+            move || {
+                let child_status = fork_child_seeing_host(&host_rootfs_path).unwrap();
+                assert!(matches!(child_status, Exited(_, 1)));
+            },
+        );
+    }
 }
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+
+use nix::unistd::Pid;
+
+use crate::errors::*;
+use crate::kernel::syscall::name_of_syscall;
+use crate::process::tracee::Tracee;
+use crate::register::{Current, Original, SysArg, SysArg1, SysArg2, SysArg3};
+use crate::register::{SysArg4, SysArg5, SysArg6, SysResult};
+
+/// Log of the syscalls translated by proot-rs (see the `--replay-log`
+/// option), one line per syscall, written on its exit stage:
+///
+/// ```text
+/// 0 openat = 3 paths=["/etc/passwd" -> "/rootfs/etc/passwd"] args=(0xffffff9c, ...)
+/// ```
+///
+/// The tracees are numbered in the order of their first syscall rather than
+/// identified by their pid, and the arguments (often addresses) come last,
+/// so that the logs of two runs can be compared once the arguments are
+/// stripped (see `scripts/diff-replay-logs.sh`).
+#[derive(Debug)]
+pub struct ReplayLog {
+    writer: LineWriter<File>,
+    tracee_numbers: HashMap<Pid, usize>,
+}
+
+impl ReplayLog {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<ReplayLog> {
+        let file = File::create(path.as_ref())
+            .with_context(|| format!("Failed to create the replay log {:?}", path.as_ref()))?;
+        Ok(ReplayLog {
+            writer: LineWriter::new(file),
+            tracee_numbers: HashMap::new(),
+        })
+    }
+
+    /// Logs the syscall of `tracee`, at the end of its exit stage.
+    /// `translations` are the paths translated during its enter stage.
+    pub fn record(&mut self, tracee: &Tracee, translations: &[(PathBuf, PathBuf)]) {
+        let next_number = self.tracee_numbers.len();
+        let number = *self.tracee_numbers.entry(tracee.pid).or_insert(next_number);
+        let line = format_entry(number, tracee, translations);
+
+        // the log is best effort, it mustn't disturb the tracees
+        if let Err(error) = writeln!(self.writer, "{}", line) {
+            warn!("Failed to write the replay log: {}", error);
+        }
+    }
+}
+
+/// Formats the line of the syscall of `tracee`: its original number and
+/// arguments, and its final result.
+fn format_entry(number: usize, tracee: &Tracee, translations: &[(PathBuf, PathBuf)]) -> String {
+    let sysnum = tracee.regs.get_sys_num(Original);
+    let paths: Vec<String> = translations
+        .iter()
+        .map(|(guest_path, host_path)| format!("{:?} -> {:?}", guest_path, host_path))
+        .collect();
+    let args: Vec<String> = [SysArg1, SysArg2, SysArg3, SysArg4, SysArg5, SysArg6]
+        .iter()
+        .map(|&index| format!("{:#x}", tracee.regs.get(Original, SysArg(index))))
+        .collect();
+
+    format!(
+        "{} {} = {} paths=[{}] args=({})",
+        number,
+        name_of_syscall(sysnum).unwrap_or("unknown"),
+        tracee.regs.get(Current, SysResult) as isize,
+        paths.join(", "),
+        args.join(", "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProotConfig;
+    use crate::utils::tests::{get_test_rootfs_path, test_with_proot_config};
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+    use nix::unistd;
+
+    #[test]
+    fn test_replay_log_entries() {
+        let log_path = std::env::temp_dir().join("proot_rs_test_replay_log");
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .replay_log(&log_path)
+                .build(),
+            |_, _, _| {},
+            || {
+                let fd = fcntl::open("/etc/passwd", OFlag::O_RDONLY, Mode::empty()).unwrap();
+                unistd::close(fd).unwrap();
+                assert!(fcntl::open("/proot_rs_missing", OFlag::O_RDONLY, Mode::empty()).is_err());
+            },
+        );
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        let host_path = get_test_rootfs_path().join("etc/passwd");
+
+        // the first tracee is numbered 0, whatever its pid
+        assert!(lines.iter().all(|line| line.starts_with("0 ")));
+        assert!(lines.iter().any(|line| line.starts_with("0 openat = ")
+            && line.contains(&format!("paths=[\"/etc/passwd\" -> {:?}]", host_path))));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("0 openat = -2 paths=[] args=(")));
+        assert!(lines.iter().any(|line| line.starts_with("0 close = 0 ")));
+
+        std::fs::remove_file(&log_path).unwrap();
+    }
+}
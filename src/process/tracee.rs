@@ -8,10 +8,13 @@ use nix::sys::signal::Signal;
 use nix::unistd::Pid;
 
 use crate::errors::*;
+use crate::filesystem::temp::TempFile;
 use crate::filesystem::Substitutor;
 use crate::filesystem::Translator;
 use crate::filesystem::{binding::Side, FileSystem};
 use crate::kernel::execve::load_info::LoadInfo;
+use crate::process::fake_metadata::FileId;
+use crate::process::fd_table::{FdTable, VirtualContent};
 use crate::process::proot::InfoBag;
 use crate::register::{Registers, Word};
 
@@ -69,6 +72,18 @@ pub enum SigStopStatus {
     EventloopSync,
 }
 
+/// A file whose content is generated by proot-rs, being opened.
+#[derive(Debug)]
+pub struct VirtualFile {
+    /// Guest path of the file.
+    pub guest_path: PathBuf,
+    /// Empty host file opened instead, like the files of `/proc` it has no
+    /// size. It's removed once opened.
+    pub temp_file: TempFile,
+    /// Content served to the reads of the file descriptor.
+    pub content: Rc<VirtualContent>,
+}
+
 #[derive(Debug)]
 pub struct Tracee {
     /// Process identifier.
@@ -80,6 +95,8 @@ pub struct Tracee {
     pub restart_how: TraceeRestartMethod,
     /// Contains the bindings and functions used for path translation.
     pub fs: Rc<RefCell<FileSystem>>,
+    /// Guest paths of the file descriptors opened by the tracee.
+    pub fd_table: Rc<RefCell<FdTable>>,
     /// Cached version of the process' general purpose registers.
     pub regs: Registers,
     /// State of the seccomp acceleration for this tracee.
@@ -97,6 +114,27 @@ pub struct Tracee {
     pub load_info: Option<LoadInfo>,
     /// State for the special handling of SIGSTOP.
     pub sigstop_status: SigStopStatus,
+    /// File whose content is generated by proot-rs (e.g. `/proc/mounts`),
+    /// being opened by the current syscall.
+    pub virtual_file: Option<VirtualFile>,
+    /// File with fake metadata whose last link is being removed by the
+    /// current syscall, see `ownership::unlink_enter()`.
+    pub unlinked_file: Option<FileId>,
+    /// Parent suspended by the vfork() which created this tracee: both share
+    /// the same address space until this tracee calls execve() or exits.
+    pub vfork_parent: Option<Pid>,
+    /// Child created by the vfork() of this tracee, which is suspended by the
+    /// kernel until the PTRACE_EVENT_VFORK_DONE event.
+    pub vfork_child: Option<Pid>,
+    /// This tracee is kept stopped at its PTRACE_EVENT_VFORK event, until its
+    /// `vfork_child` calls execve() or exits.
+    pub held_by_vfork: bool,
+    /// Paths translated during the enter stage of the current syscall, kept
+    /// for the replay log until its exit stage.
+    pub translations: Vec<(PathBuf, PathBuf)>,
+    /// Parent span of the spans of the syscalls of this tracee.
+    #[cfg(feature = "tracing")]
+    pub span: tracing::Span,
 }
 
 impl Tracee {
@@ -106,6 +144,7 @@ impl Tracee {
             status: TraceeStatus::SysEnter, // it always starts by the enter stage
             restart_how: TraceeRestartMethod::None,
             fs: fs,
+            fd_table: Rc::new(RefCell::new(FdTable::new())),
             regs: Registers::new(pid),
             seccomp: false,
             sysexit_pending: false,
@@ -113,6 +152,14 @@ impl Tracee {
             exe: None,
             load_info: None,
             sigstop_status: SigStopStatus::AllowDelivery,
+            virtual_file: None,
+            unlinked_file: None,
+            vfork_parent: None,
+            vfork_child: None,
+            held_by_vfork: false,
+            translations: vec![],
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(parent: None, "tracee", pid = pid.as_raw()),
         }
     }
 
@@ -235,6 +282,112 @@ impl Tracee {
         // pathbuf) instead.
     }
 
+    /// Returns the guest path `fd` was opened with, if it refers to a file.
+    ///
+    /// The path recorded in the fd table comes first, since a same host file
+    /// can be bound both read-only and writable, and the file descriptors of
+    /// a mount tree cloned by open_tree() have no meaningful path in `/proc`.
+    pub fn get_guest_path_of_fd(&self, fd: RawFd) -> Option<PathBuf> {
+        let recorded_path = self
+            .fd_table
+            .borrow()
+            .get(fd)
+            .filter(|entry| !entry.is_anonymous())
+            .map(|entry| entry.path.clone());
+        recorded_path.or_else(|| self.get_path_from_fd(fd, Side::Guest).ok())
+    }
+
+    /// Updates the state which changes on a successful execve(): the new
+    /// "/proc/self/exe" is committed, and the file descriptor table is
+    /// unshared and pruned from the close-on-exec file descriptors, like the
+    /// kernel does. The cwd is kept.
+    ///
+    /// It's called on `PTRACE_EVENT_EXEC` and again on the exit stage of
+    /// execve(), which has no effect the second time.
+    pub fn commit_exec(&mut self) {
+        if self.new_exe.is_some() {
+            self.exe = self.new_exe.take();
+        }
+
+        let mut fd_table = self.fd_table.borrow().clone();
+        fd_table.remove_cloexec();
+        self.fd_table = Rc::new(RefCell::new(fd_table));
+
+        // the new program has its own address space
+        self.vfork_parent = None;
+    }
+
+    /// Creates the span of the translation of the current syscall, in the
+    /// span of this tracee. The paths are recorded during the translation
+    /// (see `Translator::translate_path`).
+    #[cfg(feature = "tracing")]
+    pub fn syscall_span(&self, is_sysenter: bool) -> tracing::Span {
+        let sysnum = self.regs.get_sys_num(crate::register::Current);
+
+        tracing::debug_span!(
+            parent: &self.span,
+            "syscall",
+            pid = self.pid.as_raw(),
+            syscall = crate::kernel::syscall::name_of_syscall(sysnum).unwrap_or("unknown"),
+            sysnum = sysnum,
+            stage = if is_sysenter { "enter" } else { "exit" },
+            guest_path = tracing::field::Empty,
+            host_path = tracing::field::Empty,
+        )
+    }
+
+    /// Returns the guest path of the program run by the process `pid`, from
+    /// its `/proc/<pid>/exe` link detranslated, or `None` if the program isn't
+    /// in the guest filesystem: the host path is never returned.
+    pub fn read_guest_exe(&self, pid: Pid) -> Option<PathBuf> {
+        let proc_exe = format!("/proc/{}/exe", pid);
+        let host_path = PathBuf::from(nix::fcntl::readlink(proc_exe.as_str()).ok()?);
+        let fs = self.fs.borrow();
+        match fs.detranslate_path(&host_path, None).ok()? {
+            Some(guest_path) => Some(guest_path),
+            // the path is the same on both sides (e.g. a symmetric binding)
+            None if fs
+                .get_first_appropriate_binding(&host_path, Side::Host)
+                .is_some() =>
+            {
+                Some(host_path)
+            }
+            None => None,
+        }
+    }
+
+    /// Describes this tracee in the logs: its pid, and the guest path of its
+    /// program if it's known.
+    pub fn describe(&self) -> String {
+        match &self.exe {
+            Some(exe) => format!("{} ({})", self.pid, exe.borrow().display()),
+            None => format!("{} (unknown program)", self.pid),
+        }
+    }
+
+    /// Logs the guest paths which tried to escape from the guest root during
+    /// the translation of the current syscall (see
+    /// `FileSystem::set_audit_escapes`), and returns the log entries.
+    pub fn log_escape_attempts(&self) -> Vec<String> {
+        let entries: Vec<String> = self
+            .fs
+            .borrow()
+            .take_escape_attempts()
+            .iter()
+            .map(|path| {
+                format!(
+                    "-- {}, escape attempt: {:?} was clamped at the guest root",
+                    self.pid, path
+                )
+            })
+            .collect();
+
+        for entry in &entries {
+            warn!("{}", entry);
+        }
+        entries
+    }
+
     /// This function is similar to `Translator::translate_path()`, which has a
     /// relationship similar to `openat()` and `open()`, except that it accepts
     /// a `dirfd` argument.
@@ -258,6 +411,76 @@ impl Tracee {
                 .translate_absolute_path(guest_path, deref_final)
         }
     }
+
+    /// Same as `translate_path_at()`, for a path which is about to be created
+    /// or modified, see `Translator::translate_path_for_write()`.
+    pub fn translate_path_at_for_write<P: AsRef<Path>>(
+        &self,
+        dirfd: RawFd,
+        guest_path: P,
+        deref_final: bool,
+    ) -> Result<PathBuf> {
+        if guest_path.as_ref().is_relative() {
+            let mut dir_path = self.get_path_from_fd(dirfd, Side::Guest)?;
+            dir_path.push(guest_path);
+            self.fs
+                .borrow()
+                .translate_path_for_write(dir_path, deref_final)
+        } else {
+            self.fs
+                .borrow()
+                .translate_path_for_write(guest_path, deref_final)
+        }
+    }
+
+    /// Returns the guest path of `guest_path` relative to `dirfd`, for the
+    /// `*at` syscalls accepting `AT_EMPTY_PATH`.
+    ///
+    /// With `empty_path` set, an empty `guest_path` refers to the file
+    /// descriptor `dirfd` itself, and `None` is returned. It fails with
+    /// `ENOENT` otherwise, like in the kernel.
+    pub fn get_guest_path_at<P: AsRef<Path>>(
+        &self,
+        dirfd: RawFd,
+        guest_path: P,
+        empty_path: bool,
+    ) -> Result<Option<PathBuf>> {
+        let guest_path = guest_path.as_ref();
+        if guest_path.as_os_str().is_empty() && empty_path {
+            Ok(None)
+        } else if guest_path.as_os_str().is_empty() {
+            Err(Error::errno_with_msg(ENOENT, "the path is empty"))
+        } else if guest_path.is_relative() {
+            let mut dir_path = self.get_path_from_fd(dirfd, Side::Guest)?;
+            dir_path.push(guest_path);
+            Ok(Some(dir_path))
+        } else {
+            Ok(Some(guest_path.to_path_buf()))
+        }
+    }
+
+    /// Same as `translate_path_at()`, for the `*at` syscalls accepting
+    /// `AT_EMPTY_PATH`, see `get_guest_path_at()`.
+    ///
+    /// Returns `None` if the syscall operates on `dirfd` itself: its path was
+    /// translated when it was opened, so the empty path is left untouched
+    /// instead of being translated.
+    pub fn translate_path_at_or_dirfd<P: AsRef<Path>>(
+        &self,
+        dirfd: RawFd,
+        guest_path: P,
+        empty_path: bool,
+        deref_final: bool,
+    ) -> Result<Option<PathBuf>> {
+        match self.get_guest_path_at(dirfd, guest_path, empty_path)? {
+            Some(guest_path) => self
+                .fs
+                .borrow()
+                .translate_absolute_path(guest_path, deref_final)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -273,6 +496,25 @@ mod tests {
     use nix::unistd;
     use nix::unistd::Pid;
 
+    #[test]
+    fn test_tracee_log_escape_attempts() {
+        let mut fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+        fs.set_audit_escapes(true);
+        let tracee = Tracee::new(Pid::from_raw(42), Rc::new(RefCell::new(fs)));
+
+        assert_eq!(
+            tracee.fs.borrow().translate_path("/../../etc/passwd", true),
+            Ok(get_test_rootfs_path().join("etc/passwd"))
+        );
+        assert_eq!(
+            tracee.log_escape_attempts(),
+            vec![String::from(
+                "-- 42, escape attempt: \"/../../etc/passwd\" was clamped at the guest root"
+            )]
+        );
+        assert!(tracee.log_escape_attempts().is_empty());
+    }
+
     #[test]
     fn create_tracee() {
         let tracee = Tracee::new(Pid::from_raw(42), Rc::new(RefCell::new(FileSystem::new())));
@@ -393,4 +635,104 @@ mod tests {
             },
         )
     }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracee_syscall_span() {
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+
+        use sc::nr::OPEN;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        use crate::register::SysNum;
+
+        /// Name, parent and fields of a span.
+        type SpanInfo = (&'static str, Option<u64>, Vec<(&'static str, String)>);
+
+        struct FieldsVisitor<'a>(&'a mut Vec<(&'static str, String)>);
+
+        impl<'a> Visit for FieldsVisitor<'a> {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.push((field.name(), value.to_string()));
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0.push((field.name(), format!("{:?}", value)));
+            }
+        }
+
+        /// Records all the spans, in the order they are created.
+        #[derive(Clone, Default)]
+        struct SpansRecorder(Arc<Mutex<Vec<SpanInfo>>>);
+
+        impl Subscriber for SpansRecorder {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes) -> Id {
+                let mut spans = self.0.lock().unwrap();
+                let mut fields = vec![];
+                span.record(&mut FieldsVisitor(&mut fields));
+                spans.push((
+                    span.metadata().name(),
+                    span.parent().map(|id| id.into_u64()),
+                    fields,
+                ));
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, span: &Id, values: &Record) {
+                let mut spans = self.0.lock().unwrap();
+                let fields = &mut spans[span.into_u64() as usize - 1].2;
+                values.record(&mut FieldsVisitor(fields));
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let recorder = SpansRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let fs = FileSystem::with_root(get_test_rootfs_path()).unwrap();
+            let mut tracee = Tracee::new(Pid::from_raw(42), Rc::new(RefCell::new(fs)));
+            tracee.regs = Registers::from(Pid::from_raw(42), unsafe { std::mem::zeroed() });
+            tracee.regs.set(SysNum, OPEN as Word, "");
+
+            let span = tracee.syscall_span(true);
+            let _entered_span = span.enter();
+            tracee
+                .fs
+                .borrow()
+                .translate_path("/etc/passwd", true)
+                .unwrap();
+        });
+
+        let spans = recorder.0.lock().unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0], ("tracee", None, vec![("pid", "42".into())]));
+
+        let (name, parent, fields) = &spans[1];
+        assert_eq!(*name, "syscall");
+        // the span of the syscall is in the span of its tracee
+        assert_eq!(*parent, Some(1));
+        let host_path = get_test_rootfs_path().join("etc/passwd");
+        for field in &[
+            ("pid", "42".to_string()),
+            ("syscall", "open".to_string()),
+            ("stage", "enter".to_string()),
+            ("guest_path", "\"/etc/passwd\"".to_string()),
+            ("host_path", format!("{:?}", host_path)),
+        ] {
+            assert!(fields.contains(field), "{:?} not in {:?}", field, fields);
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use crate::kernel::syscall;
-use crate::kernel::{enter, exit};
+use crate::kernel::{enter, exit, leak_check, registry};
 use crate::process::proot::InfoBag;
 use crate::process::tracee::{Tracee, TraceeRestartMethod, TraceeStatus};
 use crate::register::{Current, Modified, Original, StackPointer, SysResult, Word};
@@ -11,7 +11,7 @@ pub trait SyscallTranslator {
         #[cfg(test)] func_syscall_hook: &Option<Box<dyn Fn(&Tracee, bool, bool)>>,
     );
     fn translate_syscall_enter(&mut self, info_bag: &InfoBag);
-    fn translate_syscall_exit(&mut self);
+    fn translate_syscall_exit(&mut self, info_bag: &InfoBag);
 }
 
 impl SyscallTranslator for Tracee {
@@ -28,6 +28,11 @@ impl SyscallTranslator for Tracee {
             return;
         }
 
+        #[cfg(feature = "tracing")]
+        let span = self.syscall_span(matches!(self.status, TraceeStatus::SysEnter));
+        #[cfg(feature = "tracing")]
+        let _entered_span = span.enter();
+
         let is_sysenter = match self.status {
             TraceeStatus::SysEnter => {
                 #[cfg(test)]
@@ -42,7 +47,7 @@ impl SyscallTranslator for Tracee {
                 func_syscall_hook
                     .as_ref()
                     .map(|func| func(self, false, true));
-                self.translate_syscall_exit();
+                self.translate_syscall_exit(info_bag);
                 false
             }
         };
@@ -51,6 +56,17 @@ impl SyscallTranslator for Tracee {
             error!("proot error: Error while pushing regs: {}", error);
         }
 
+        if let Some(replay_log) = &info_bag.replay_log {
+            let translations = self.fs.borrow().take_translations();
+            self.translations.extend(translations);
+            if !is_sysenter {
+                let translations = std::mem::take(&mut self.translations);
+                replay_log.borrow_mut().record(self, &translations);
+            }
+        }
+
+        self.log_escape_attempts();
+
         #[cfg(test)]
         func_syscall_hook
             .as_ref()
@@ -110,6 +126,19 @@ impl SyscallTranslator for Tracee {
             self.status = TraceeStatus::SysExit;
         }
 
+        // Under seccomp, the sysexit stage is skipped when the syscall doesn't
+        // need it (e.g. the syscalls passed through), unless an error has to be
+        // reported there.
+        let sys_num = self.regs.get_sys_num(Original);
+        if self.seccomp
+            && self.status.is_ok()
+            && (info_bag.config.is_syscall_bypassed(sys_num)
+                || !registry::handlers_of_sysnum(sys_num).needs_exit_stage)
+        {
+            self.restart_how = TraceeRestartMethod::WithoutExitStage;
+            self.sysexit_pending = false;
+        }
+
         // Restore tracee's stack pointer now if it won't hit
         // the sysexit stage (i.e. when seccomp is enabled and
         // there's nothing else to do).
@@ -122,7 +151,7 @@ impl SyscallTranslator for Tracee {
         }
     }
 
-    fn translate_syscall_exit(&mut self) {
+    fn translate_syscall_exit(&mut self, info_bag: &InfoBag) {
         // By default, restore original register values at the end of this stage.
         self.regs.set_restore_original_regs(true);
 
@@ -138,7 +167,10 @@ impl SyscallTranslator for Tracee {
         //     return;
 
         if self.status.is_ok() {
-            exit::translate(self);
+            exit::translate(info_bag, self);
+            if info_bag.config.check_host_leaks {
+                leak_check::check(self);
+            }
         } else {
             self.regs.set(
                 SysResult,
@@ -0,0 +1,27 @@
+/// Names reported to the guest by uname() instead of the host ones (its
+/// `nodename` and `domainname` fields), shared by all the tracees. `None`
+/// keeps the host one.
+///
+/// They're virtualized when the configuration sets one of them (`hostname`,
+/// `domainname`): sethostname() and setdomainname() then change them here
+/// instead of on the host, which would require `CAP_SYS_ADMIN`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VirtualUts {
+    pub hostname: Option<Vec<u8>>,
+    pub domainname: Option<Vec<u8>>,
+}
+
+impl VirtualUts {
+    pub fn new(hostname: Option<&str>, domainname: Option<&str>) -> VirtualUts {
+        VirtualUts {
+            hostname: hostname.map(|name| name.as_bytes().to_vec()),
+            domainname: domainname.map(|name| name.as_bytes().to_vec()),
+        }
+    }
+
+    /// Tells whether the names are virtualized, i.e. whether sethostname()
+    /// and setdomainname() are faked.
+    pub fn is_enabled(&self) -> bool {
+        self.hostname.is_some() || self.domainname.is_some()
+    }
+}
@@ -7,8 +7,12 @@ const RED_ZONE_SIZE: isize = 128;
 #[cfg(all(target_os = "linux", not(target_arch = "x86_64")))]
 const RED_ZONE_SIZE: isize = 0;
 
+/// Alignment of the scratch blocks, enough for any struct of the ABI.
+const SCRATCH_ALIGNMENT: Word = 16;
+
 pub trait PtraceMemoryAllocator {
     fn alloc_mem_on_stack(&mut self, size: isize) -> Result<Word>;
+    fn alloc_scratch(&mut self, size: usize) -> Result<Word>;
 }
 
 impl PtraceMemoryAllocator for Registers {
@@ -63,6 +67,30 @@ impl PtraceMemoryAllocator for Registers {
 
         Ok(new_stack_pointer)
     }
+
+    /// Reserves a block of `size` bytes on the tracee's stack, aligned on
+    /// `SCRATCH_ALIGNMENT`, for a handler to write structs or strings the
+    /// syscall will read.
+    ///
+    /// Like `alloc_mem_on_stack()`, it should only be called in sysenter:
+    /// the stack pointer is restored with the other original registers when
+    /// they are pushed at the end of sysexit (or at the end of sysenter when
+    /// there is no exit stage), which frees all the blocks at once.
+    ///
+    /// Returns the address of the block in the tracee's memory space.
+    fn alloc_scratch(&mut self, size: usize) -> Result<Word> {
+        if size > isize::MAX as usize {
+            return Err(Error::errno_with_msg(
+                EFAULT,
+                "when allocating scratch memory, under/overflow detected",
+            ));
+        }
+        let address = self.alloc_mem_on_stack(size as isize)? & !(SCRATCH_ALIGNMENT - 1);
+
+        self.set(StackPointer, address, "aligning scratch memory");
+
+        Ok(address)
+    }
 }
 
 #[cfg(test)]
@@ -96,6 +124,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mem_alloc_scratch() {
+        let mut raw_regs: user_regs_struct = unsafe { mem::zeroed() };
+        let starting_stack_pointer = 100003;
+
+        get_reg!(raw_regs, StackPointer) = starting_stack_pointer;
+
+        let mut regs = Registers::from(getpid(), raw_regs);
+
+        regs.save_current_regs(Original);
+
+        let first_block = regs.alloc_scratch(5).unwrap();
+        let second_block = regs.alloc_scratch(24).unwrap();
+
+        // the blocks are aligned, don't overlap, and are below the red zone
+        assert_eq!(first_block % SCRATCH_ALIGNMENT, 0);
+        assert_eq!(second_block % SCRATCH_ALIGNMENT, 0);
+        assert!(first_block + 5 <= starting_stack_pointer - RED_ZONE_SIZE as Word);
+        assert!(second_block + 24 <= first_block);
+        assert_eq!(regs.get(Current, StackPointer), second_block);
+
+        // the stack pointer is restored as done in the exit stage
+        regs.restore_original(StackPointer, "restoring stack pointer for test");
+        assert_eq!(regs.get(Current, StackPointer), starting_stack_pointer);
+    }
+
     #[test]
     fn test_mem_alloc_overflow() {
         let mut raw_regs: user_regs_struct = unsafe { mem::zeroed() };
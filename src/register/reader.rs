@@ -1,6 +1,6 @@
 use crate::errors::*;
 use crate::register::{Current, Registers, SysArg, SysArgIndex, Word};
-use libc::{c_void, PATH_MAX};
+use libc::c_void;
 use nix::sys::ptrace;
 use nix::unistd::Pid;
 use std::mem::{size_of, transmute};
@@ -20,6 +20,8 @@ pub fn convert_word_to_bytes(value_to_convert: Word) -> [u8; 8] {
 
 pub trait PtraceReader {
     fn get_sysarg_path(&self, sys_arg: SysArgIndex) -> Result<PathBuf>;
+    fn read_data(&self, src_tracee: *const c_void, size: usize) -> Result<Vec<u8>>;
+    fn read_string(&self, src_tracee: *const c_void, max_size: usize) -> Result<Vec<u8>>;
 }
 
 impl PtraceReader for Registers {
@@ -39,7 +41,7 @@ impl PtraceReader for Registers {
             Ok(PathBuf::new())
         } else {
             // Get the path from the tracee's memory space.
-            let path = read_path(self.get_pid(), src_sysarg);
+            let path = read_path(self.get_pid(), src_sysarg, self.get_path_max());
             match &path {
                 Ok(path) => trace!("{:?}({:x?}) => {:?}", sys_arg, src_sysarg, path),
                 Err(error) => trace!(
@@ -52,23 +54,52 @@ impl PtraceReader for Registers {
             path
         }
     }
+
+    /// Copies `size` bytes from the tracee's memory space, starting at
+    /// `src_tracee`.
+    ///
+    /// It uses `ptrace(PEEK_DATA)` to read them word by word, so up to a word
+    /// past `size` may be read (but not returned).
+    fn read_data(&self, src_tracee: *const c_void, size: usize) -> Result<Vec<u8>> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(size + size_of::<Word>());
+        let mut i = 0;
+
+        while bytes.len() < size {
+            let src_addr = unsafe { (src_tracee as *const Word).add(i) as *mut c_void };
+            let word = ptrace::read(self.get_pid(), src_addr)? as Word;
+
+            bytes.extend_from_slice(&convert_word_to_bytes(word));
+            i += 1;
+        }
+        bytes.truncate(size);
+
+        Ok(bytes)
+    }
+
+    /// Copies a null-terminated string (without its null byte) from the
+    /// tracee's memory space, starting at `src_tracee`. At most `max_size`
+    /// bytes are copied, see `read_string()`.
+    fn read_string(&self, src_tracee: *const c_void, max_size: usize) -> Result<Vec<u8>> {
+        read_string(self.get_pid(), src_tracee as *mut Word, max_size)
+    }
 }
 
 /// Intermediary function that retrieves bytes from the tracee's memory space
 /// and collects them into a NON null-terminated CString.
 ///
-/// It also checks that the number of bytes isn't too long.
+/// It also checks that the number of bytes isn't too long: `max_size` is the
+/// maximum length of the path, null terminator included.
 #[inline]
-fn read_path(pid: Pid, src_path: *mut Word) -> Result<PathBuf> {
-    let bytes = read_string(pid, src_path, PATH_MAX as usize)?;
+fn read_path(pid: Pid, src_path: *mut Word, max_size: usize) -> Result<PathBuf> {
+    let bytes = read_string(pid, src_path, max_size)?;
 
-    if bytes.len() >= PATH_MAX as usize {
+    if bytes.len() >= max_size {
         return Err(Error::errno_with_msg(
             ENAMETOOLONG,
             format!(
-                "Error when reading sys arg path, path length {} exceed PATH_MAX {}",
+                "Error when reading sys arg path, path length {} exceed max size {}",
                 bytes.len(),
-                PATH_MAX
+                max_size
             ),
         ));
     }
@@ -84,6 +115,10 @@ fn read_path(pid: Pid, src_path: *mut Word) -> Result<PathBuf> {
 /// added), The bytes contained at the string's address are returned as a Vector
 /// of u8.
 ///
+/// If no null character is found within the first `max_size` bytes, exactly
+/// `max_size` bytes are returned, and it's up to the caller to consider the
+/// string as truncated.
+///
 /// * `pid` is the pid of the tracee.
 /// * `src_string` is the address of the string in tracee's memory space
 ///   (obtained for instance with `get_reg`).
@@ -121,39 +156,37 @@ fn read_string(pid: Pid, src_string: *mut Word, max_size: usize) -> Result<Vec<u
         }
     }
 
-    //todo: add trailing bytes processing (when necessary, need an example where
-    // it's actually used)
-    unimplemented!("trailing bytes not supported!")
-
-    /*
-
-    /* Copy the bytes from the last word carefully since we have
-     * to not overwrite the bytes lying beyond @dest_tracer. */
-
-    word = ptrace(PTRACE_PEEKDATA, tracee->pid, src + i, NULL);
-    if (errno != 0)
-        return -EFAULT;
+    // Copy the bytes from the last word carefully since we have
+    // to not read more than `max_size` bytes.
+    if nb_trailing_bytes > 0 {
+        let src_addr = unsafe { src_string.offset(nb_full_words) as *mut c_void };
+        let word = ptrace::read(pid, src_addr)? as Word;
+        let letters = convert_word_to_bytes(word);
 
-    dest_word = (uint8_t *)&dest[i];
-    src_word  = (uint8_t *)&word;
+        for &letter in letters.iter().take(nb_trailing_bytes as usize) {
+            if letter as char == '\0' {
+                bytes.shrink_to_fit();
 
-    for (j = 0; j < nb_trailing_bytes; j++) {
-        dest_word[j] = src_word[j];
-        if (src_word[j] == '\0')
-            break;
+                return Ok(bytes);
+            }
+            bytes.push(letter);
+        }
     }
 
-    return i * sizeof(word_t) + j + 1;
-    */
+    // No end-of-string was found in the first `max_size` bytes.
+    Ok(bytes)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ProotConfig;
     use crate::register::*;
-    use crate::utils::tests::{fork_test, get_test_rootfs_path};
-    use libc::user_regs_struct;
-    use nix::unistd::{execvp, getpid};
+    use crate::utils::tests::{fork_test, get_test_rootfs_path, test_with_proot_config};
+    use libc::{user_regs_struct, PATH_MAX};
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::{self, Mode};
+    use nix::unistd::{self, execvp, getpid, UnlinkatFlags};
     use sc::nr::MKDIR;
     use std::ffi::CString;
     use std::mem;
@@ -233,4 +266,124 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    /// Tests that `get_sysarg_path` rejects paths which are longer than the
+    /// limit configured with `set_path_max`, and accepts them again once the
+    /// limit is large enough.
+    fn test_reader_get_sysarg_path_respects_path_max() {
+        let rootfs_path = get_test_rootfs_path();
+        let test_path = "my/impossible/test/path";
+
+        fork_test(
+            rootfs_path,
+            // expecting an error (because the path doesn't exit)
+            1,
+            // parent
+            |tracee, _| {
+                if tracee.regs.get_sys_num(Current) == MKDIR {
+                    // the null terminator doesn't fit
+                    tracee.regs.set_path_max(test_path.len());
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg1),
+                        Err(Error::errno(ENAMETOOLONG))
+                    );
+
+                    // not even a full word can be read
+                    tracee.regs.set_path_max(3);
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg1),
+                        Err(Error::errno(ENAMETOOLONG))
+                    );
+
+                    tracee.regs.set_path_max(test_path.len() + 1);
+                    assert_eq!(
+                        tracee.regs.get_sysarg_path(SysArg1).unwrap(),
+                        PathBuf::from(test_path)
+                    );
+
+                    // we can stop here
+                    true
+                } else {
+                    false
+                }
+            },
+            // child
+            || {
+                execvp(
+                    &CString::new("mkdir").unwrap(),
+                    &[CString::new(".").unwrap(), CString::new(test_path).unwrap()],
+                )
+                .expect("failed execvp mkdir");
+            },
+        );
+    }
+
+    #[test]
+    fn test_reader_path_max_config() {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .path_max(16)
+                .build(),
+            |_, _, _| {},
+            || {
+                // 15 bytes and the null terminator
+                assert_eq!(nc::access("/tmp/0123456789", nc::F_OK), Err(nc::ENOENT));
+                assert_eq!(
+                    nc::access("/tmp/0123456789a", nc::F_OK),
+                    Err(nc::ENAMETOOLONG)
+                );
+            },
+        );
+    }
+
+    #[test]
+    /// Tests that a guest path which fits in `PATH_MAX`, but not once prefixed
+    /// by the rootfs, fails with `ENAMETOOLONG`.
+    fn test_reader_translated_path_past_path_max() {
+        let rootfs_path = get_test_rootfs_path();
+        let top_name = "proot_rs_test_path_max";
+        let mut guest_path = format!("/tmp/{}", top_name);
+        // the guest path of mkdir() takes 4094 bytes, null terminator included
+        let mut names = vec![];
+        while guest_path.len() < 4092 {
+            let name = "d".repeat((4092 - 1 - guest_path.len()).min(200));
+            guest_path = format!("{}/{}", guest_path, name);
+            names.push(name);
+        }
+        let new_guest_path = format!("{}/n", guest_path);
+        assert!(new_guest_path.len() < PATH_MAX as usize);
+
+        // the host path is too long, so the directories are created one by one
+        let tmp_fd =
+            fcntl::open(&rootfs_path.join("tmp"), OFlag::O_DIRECTORY, Mode::empty()).unwrap();
+        let mut fds = vec![tmp_fd];
+        for name in std::iter::once(top_name).chain(names.iter().map(String::as_str)) {
+            let parent_fd = *fds.last().unwrap();
+            stat::mkdirat(parent_fd, name, Mode::from_bits_truncate(0o755)).unwrap();
+            fds.push(fcntl::openat(parent_fd, name, OFlag::O_DIRECTORY, Mode::empty()).unwrap());
+        }
+
+        test_with_proot_config(
+            ProotConfig::builder().rootfs(&rootfs_path).build(),
+            |_, _, _| {},
+            || {
+                assert_eq!(
+                    nc::mkdir(new_guest_path.as_str(), 0o755),
+                    Err(nc::ENAMETOOLONG)
+                );
+            },
+        );
+
+        let names: Vec<&str> = std::iter::once(top_name)
+            .chain(names.iter().map(String::as_str))
+            .collect();
+        for (name, parent_fd) in names.iter().zip(fds.iter()).rev() {
+            unistd::unlinkat(Some(*parent_fd), *name, UnlinkatFlags::RemoveDir).unwrap();
+        }
+        for fd in fds {
+            unistd::close(fd).unwrap();
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use crate::errors::Result;
 use crate::register::Word;
-use libc::user_regs_struct;
+use libc::{user_regs_struct, PATH_MAX};
 use nix::sys::ptrace;
 use nix::unistd::Pid;
 use std::fmt;
@@ -27,6 +27,21 @@ pub enum SysArgIndex {
 }
 use self::SysArgIndex::*;
 
+impl SysArgIndex {
+    /// Returns the index of the `n`-th (from 1 to 6) syscall argument.
+    pub fn from_number(n: usize) -> SysArgIndex {
+        match n {
+            1 => SysArg1,
+            2 => SysArg2,
+            3 => SysArg3,
+            4 => SysArg4,
+            5 => SysArg5,
+            6 => SysArg6,
+            _ => panic!("syscall argument number out of range: {}", n),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum Register {
     SysNum,
@@ -70,6 +85,9 @@ pub struct Registers {
     registers: [Option<user_regs_struct>; 3],
     regs_were_changed: bool,
     restore_original_regs: bool,
+    /// Maximum number of bytes (including the null terminator) read when
+    /// retrieving a path from the tracee's memory. Defaults to `PATH_MAX`.
+    path_max: usize,
 }
 
 #[allow(dead_code)]
@@ -81,6 +99,7 @@ impl Registers {
             registers: [None, None, None],
             regs_were_changed: false,
             restore_original_regs: false,
+            path_max: PATH_MAX as usize,
         }
     }
 
@@ -92,6 +111,7 @@ impl Registers {
             registers: [Some(raw_regs), None, None],
             regs_were_changed: false,
             restore_original_regs: false,
+            path_max: PATH_MAX as usize,
         }
     }
 
@@ -113,6 +133,32 @@ impl Registers {
         self.get_raw(raw_regs, register)
     }
 
+    /// Retrieves the `n`-th (from 1 to 6) argument of the syscall from the
+    /// `Current` registers, whatever the architecture is.
+    ///
+    /// # Safety
+    ///
+    /// The program panics if `n` is out of range.
+    #[inline]
+    pub fn get_arg(&self, n: usize) -> Word {
+        self.get(Current, SysArg(SysArgIndex::from_number(n)))
+    }
+
+    /// Modifies the `n`-th (from 1 to 6) argument of the syscall in the
+    /// `Current` registers, whatever the architecture is. See `set()`.
+    ///
+    /// # Safety
+    ///
+    /// The program panics if `n` is out of range.
+    #[inline]
+    pub fn set_arg(&mut self, n: usize, new_value: Word, justification: &'static str) {
+        self.set(
+            SysArg(SysArgIndex::from_number(n)),
+            new_value,
+            justification,
+        )
+    }
+
     /// Modifies the value of one of the `Current` registers.
     ///
     /// If `new_value` is the same as the current one, `regs_were_changed`
@@ -187,6 +233,31 @@ impl Registers {
         let current_regs = self.get_mut_regs(Current);
 
         ptrace::setregs(pid, *current_regs)?;
+        #[cfg(target_arch = "aarch64")]
+        self.push_sysnum()?;
+        Ok(())
+    }
+
+    /// On aarch64, the syscall number is not taken back from the general
+    /// purpose registers (`x8`) by the kernel, it has to be written with its
+    /// own regset.
+    #[cfg(target_arch = "aarch64")]
+    fn push_sysnum(&self) -> Result<()> {
+        const NT_ARM_SYSTEM_CALL: libc::c_int = 0x404;
+        let mut sysnum = self.get(Current, SysNum) as libc::c_int;
+        let mut iov = libc::iovec {
+            iov_base: &mut sysnum as *mut _ as *mut libc::c_void,
+            iov_len: std::mem::size_of::<libc::c_int>(),
+        };
+        let ret = unsafe {
+            libc::ptrace(
+                libc::PTRACE_SETREGSET,
+                self.pid.as_raw(),
+                NT_ARM_SYSTEM_CALL,
+                &mut iov as *mut _ as *mut libc::c_void,
+            )
+        };
+        nix::errno::Errno::result(ret)?;
         Ok(())
     }
 
@@ -261,6 +332,19 @@ impl Registers {
         self.pid
     }
 
+    /// Maximum length (null terminator included) of a path read from the
+    /// tracee's memory. Longer paths are rejected with `ENAMETOOLONG`.
+    #[inline]
+    pub fn get_path_max(&self) -> usize {
+        self.path_max
+    }
+
+    /// Change the maximum length of the paths read from the tracee's memory.
+    #[inline]
+    pub fn set_path_max(&mut self, path_max: usize) {
+        self.path_max = path_max;
+    }
+
     #[inline]
     fn get_regs(&self, version: RegVersion) -> &user_regs_struct {
         match self.registers[version as usize] {
@@ -283,16 +367,35 @@ impl Registers {
         self.get(version, SysNum) as usize
     }
 
-    /// Little utility method to quickly modify the syscall number.
+    /// Replaces the syscall that the tracee is about to enter by the
+    /// `new_value` one, whatever the architecture is.
+    ///
+    /// Only the `Current` registers are modified, like with `set()`: save
+    /// them afterwards in the `Modified` ones to remember the new number in
+    /// the exit stage, the `Original` ones still hold the initial number.
+    /// The new number reaches the tracee with `push_regs()`.
     #[inline]
-    pub fn set_sys_num(&mut self, new_value: usize, justification: &'static str) {
+    pub fn set_sysnum(&mut self, new_value: usize, justification: &'static str) {
         self.set(SysNum, new_value as Word, justification);
     }
 
     /// Little utility method to quickly void the syscall number.
+    ///
+    /// Only the `Current` registers are modified: the number of the cancelled
+    /// syscall is still available from the `Original` registers, which is the
+    /// one used to dispatch the exit stage (see `kernel::exit::translate`).
     #[inline]
     pub fn cancel_syscall(&mut self, justification: &'static str) {
-        self.set(SysNum, VOID, justification);
+        self.set_sysnum(VOID as usize, justification);
+    }
+
+    /// Whether the syscall was cancelled during the enter stage.
+    ///
+    /// Requires the `Modified` registers to be defined, i.e. it's only
+    /// meaningful in the exit stage.
+    #[inline]
+    pub fn is_syscall_cancelled(&self) -> bool {
+        self.get(Modified, SysNum) == VOID
     }
 
     #[inline]
@@ -346,8 +449,9 @@ mod tests {
     use std::mem;
 
     use nix::unistd::{execvp, Pid};
-    use sc::nr::{CLOCK_NANOSLEEP, NANOSLEEP};
+    use sc::nr::{CLOCK_NANOSLEEP, GETPID, NANOSLEEP, READLINK};
 
+    use crate::kernel::groups::{syscall_group_from_sysnum, SyscallGroup};
     use crate::utils::tests::{fork_test, get_test_rootfs_path};
 
     #[test]
@@ -362,6 +466,31 @@ mod tests {
         assert_eq!(123456, regs.get(Current, SysNum));
     }
 
+    #[test]
+    fn test_regs_cancel_syscall_keeps_original_sysnum() {
+        let mut regs = Registers::from(Pid::from_raw(-1), unsafe { mem::zeroed() });
+        regs.set(SysNum, READLINK as Word, "");
+
+        // enter stage
+        regs.save_current_regs(Original);
+        regs.cancel_syscall("");
+        regs.save_current_regs(Modified);
+
+        // exit stage: the kernel reports the voided syscall
+        assert_eq!(regs.get_sys_num(Current), VOID as usize);
+        assert!(regs.is_syscall_cancelled());
+        assert_eq!(regs.get_sys_num(Original), READLINK);
+        assert_eq!(
+            syscall_group_from_sysnum(regs.get_sys_num(Original)),
+            SyscallGroup::ReadLink
+        );
+
+        // the original number is given back to the tracee at the end of the
+        // exit stage
+        regs.restore_regs();
+        assert_eq!(regs.get_sys_num(Current), READLINK);
+    }
+
     #[test]
     fn test_fetch_regs_should_fail_test() {
         let mut regs = Registers::new(Pid::from_raw(-1));
@@ -473,4 +602,129 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_regs_get_set_arg() {
+        let raw_regs: user_regs_struct = unsafe { mem::zeroed() };
+        let mut regs = Registers::from(Pid::from_raw(-1), raw_regs);
+
+        for n in 1..=6 {
+            regs.set_arg(n, n as Word * 0x10, "setting argument for test");
+        }
+        for n in 1..=6 {
+            assert_eq!(regs.get_arg(n), n as Word * 0x10);
+            assert_eq!(
+                regs.get(Current, SysArg(SysArgIndex::from_number(n))),
+                n as Word * 0x10
+            );
+        }
+
+        // they are the registers of the syscall arguments on the host arch
+        let raw_regs = regs.get_regs(Current);
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(
+            [
+                raw_regs.rdi,
+                raw_regs.rsi,
+                raw_regs.rdx,
+                raw_regs.r10,
+                raw_regs.r8,
+                raw_regs.r9
+            ],
+            [0x10, 0x20, 0x30, 0x40, 0x50, 0x60]
+        );
+        #[cfg(target_arch = "x86")]
+        assert_eq!(
+            [
+                raw_regs.ebx,
+                raw_regs.ecx,
+                raw_regs.edx,
+                raw_regs.esi,
+                raw_regs.edi,
+                raw_regs.ebp
+            ],
+            [0x10, 0x20, 0x30, 0x40, 0x50, 0x60]
+        );
+        assert!(regs.regs_were_changed);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_regs_get_arg_out_of_range() {
+        let raw_regs: user_regs_struct = unsafe { mem::zeroed() };
+        Registers::from(Pid::from_raw(-1), raw_regs).get_arg(7);
+    }
+
+    #[test]
+    fn test_regs_set_sysnum_snapshots() {
+        let mut regs = Registers::from(Pid::from_raw(-1), unsafe { mem::zeroed() });
+        regs.set(SysNum, NANOSLEEP as Word, "");
+
+        // enter stage
+        regs.save_current_regs(Original);
+        regs.set_sysnum(GETPID, "");
+        regs.save_current_regs(Modified);
+
+        assert!(regs.regs_were_changed);
+        assert_eq!(regs.get_sys_num(Current), GETPID);
+        assert_eq!(regs.get_sys_num(Modified), GETPID);
+        assert_eq!(regs.get_sys_num(Original), NANOSLEEP);
+        assert!(!regs.is_syscall_cancelled());
+
+        regs.restore_regs();
+        assert_eq!(regs.get_sys_num(Current), NANOSLEEP);
+    }
+
+    #[test]
+    /// Tests that `set_sysnum` replaces the syscall executed by the tracee:
+    /// NANOSLEEP is turned into GETPID, whose result is the pid of the
+    /// tracee. The tracee would sleep for 9999 secs otherwise.
+    fn test_push_regs_set_sysnum_sleep_test() {
+        let rootfs_path = get_test_rootfs_path();
+        let mut sleep_exit = false;
+
+        fork_test(
+            rootfs_path,
+            // expecting a normal execution
+            0,
+            // parent
+            |tracee, _| {
+                let sys_num = tracee.regs.get_sys_num(Current);
+                if !sleep_exit && (sys_num == NANOSLEEP || sys_num == CLOCK_NANOSLEEP) {
+                    // NANOSLEEP enter stage
+                    tracee.regs.set_restore_original_regs(false);
+                    tracee.regs.save_current_regs(Original);
+                    tracee
+                        .regs
+                        .set_sysnum(GETPID, "replace sleep for set sysnum test");
+                    tracee.regs.save_current_regs(Modified);
+                    tracee.regs.push_regs().expect("pushing regs");
+
+                    sleep_exit = true;
+                } else if sleep_exit {
+                    // exit stage of the new syscall
+                    assert_eq!(sys_num, GETPID);
+                    assert_eq!(
+                        tracee.regs.get(Current, SysResult),
+                        tracee.pid.as_raw() as Word
+                    );
+
+                    tracee.regs.set_restore_original_regs(true);
+                    tracee.regs.set(SysResult, 0, "simulate successful sleep");
+                    tracee.regs.push_regs().expect("pushing regs");
+                    return true;
+                }
+
+                false
+            },
+            // child
+            || {
+                execvp(
+                    &CString::new("/bin/sleep").unwrap(),
+                    &[CString::new(".").unwrap(), CString::new("9999").unwrap()],
+                )
+                .expect("failed execvp sleep");
+            },
+        );
+    }
 }
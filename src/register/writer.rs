@@ -1,6 +1,8 @@
-use crate::errors::Result;
+use crate::errors::*;
 use crate::register::reader::convert_word_to_bytes;
-use crate::register::{PtraceMemoryAllocator, Registers, SysArg, SysArgIndex, Word};
+use crate::register::{
+    Current, PtraceMemoryAllocator, Registers, StackPointer, SysArg, SysArgIndex, Word,
+};
 use byteorder::NativeEndian;
 use byteorder::ReadBytesExt;
 use libc::c_void;
@@ -37,6 +39,7 @@ pub trait PtraceWriter {
         justification: &'static str,
         append_null: bool,
     ) -> Result<*const c_void>;
+    fn write_scratch(&mut self, data: &[u8]) -> Result<Word>;
     fn write_data(&self, dest_tracee: *mut c_void, data: &[u8], append_null: bool) -> Result<()>;
 }
 
@@ -45,14 +48,32 @@ impl PtraceWriter for Registers {
     /// `sys_arg` point to it. A null byte (b'\0') is implicitly appended to the
     /// end.
     ///
+    /// The original string is never overwritten in place: the new path is
+    /// always written into a fresh block, so it can be longer than the
+    /// original one (which is the common case, since translated paths are
+    /// prefixed by the rootfs).
+    ///
     /// Note that this will "allocate" a block of memory on stack, which means
     /// the value of the stack pointer register will be implicitly modified.
+    /// The stack pointer is restored to its original value at the end of the
+    /// exit stage (or at the end of the enter stage if there is no exit
+    /// stage).
+    ///
+    /// Fails with `ENAMETOOLONG` if the path is longer than what the kernel
+    /// accepts, and with `EFAULT` if it can't be written on the stack; the
+    /// registers are left untouched in both cases.
     fn set_sysarg_path(
         &mut self,
         sys_arg: SysArgIndex,
         path: &Path,
         justification: &'static str,
     ) -> Result<()> {
+        if path.as_os_str().len() + 1 > self.get_path_max() {
+            return Err(Error::errno_with_msg(
+                ENAMETOOLONG,
+                format!("when setting the path {:?}, it's too long", path),
+            ));
+        }
         let result =
             self.set_sysarg_data(sys_arg, path.as_os_str().as_bytes(), justification, true);
         match &result {
@@ -71,6 +92,8 @@ impl PtraceWriter for Registers {
     ///
     /// Note that this will "allocate" a block of memory on stack, which means
     /// the value of the stack pointer register will be implicitly modified.
+    /// If the data can't be written, the allocation is undone and the
+    /// argument is left untouched.
     fn set_sysarg_data(
         &mut self,
         sys_arg: SysArgIndex,
@@ -78,12 +101,27 @@ impl PtraceWriter for Registers {
         justification: &'static str,
         append_null: bool,
     ) -> Result<*const c_void> {
+        let stack_pointer = self.get(Current, StackPointer);
+
         // Allocate space into the tracee's memory to host the new data.
         let tracee_ptr =
             self.alloc_mem_on_stack(data.len() as isize + if append_null { 1 } else { 0 })?;
 
         // Copy the new data into the previously allocated space.
-        self.write_data(tracee_ptr as *mut c_void, data, append_null)?;
+        if let Err(error) = self.write_data(tracee_ptr as *mut c_void, data, append_null) {
+            self.set(
+                StackPointer,
+                stack_pointer,
+                "undo the allocation of data which couldn't be written",
+            );
+            return Err(Error::errno_with_msg(
+                EFAULT,
+                format!(
+                    "when writing data at {:#x} in the tracee's stack: {}",
+                    tracee_ptr, error
+                ),
+            ));
+        }
 
         // Make this argument point to the new data.
         self.set(SysArg(sys_arg), tracee_ptr, justification);
@@ -91,6 +129,35 @@ impl PtraceWriter for Registers {
         Ok(tracee_ptr as _)
     }
 
+    /// Copy the `data` into a scratch block reserved on the tracee's stack
+    /// (see `alloc_scratch()`), e.g. a struct whose address is given to the
+    /// syscall. The block is freed when the stack pointer is restored.
+    ///
+    /// If the data can't be written, the allocation is undone.
+    ///
+    /// Returns the address of the block in the tracee's memory space.
+    fn write_scratch(&mut self, data: &[u8]) -> Result<Word> {
+        let stack_pointer = self.get(Current, StackPointer);
+        let address = self.alloc_scratch(data.len())?;
+
+        if let Err(error) = self.write_data(address as *mut c_void, data, false) {
+            self.set(
+                StackPointer,
+                stack_pointer,
+                "undo the allocation of scratch memory which couldn't be written",
+            );
+            return Err(Error::errno_with_msg(
+                EFAULT,
+                format!(
+                    "when writing scratch memory at {:#x} in the tracee's stack: {}",
+                    address, error
+                ),
+            ));
+        }
+
+        Ok(address)
+    }
+
     /// Copy the `data` to tracee's memory space by ptrace(PTRACE_POKEDATA) and
     /// ptrace(PTRACE_PEEKDATA). It transmits one word at a time, and the
     /// boundary case is carefully handled.
@@ -154,9 +221,11 @@ impl PtraceWriter for Registers {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::process::translation::SyscallTranslator;
+    use crate::register::StackPointer;
     use crate::utils::tests::fork_test;
     use crate::{
-        register::{Current, Original, PtraceReader, SysArg1},
+        register::{Current, Original, PtraceReader, SysArg1, SysResult},
         utils::tests::get_test_rootfs_path,
     };
     use nix::unistd::execvp;
@@ -220,4 +289,206 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    /// Tests that `set_sysarg_path` is able to write a path much longer than
+    /// the original one, that the argument register points to the freshly
+    /// allocated block on the stack, and that the stack pointer can be
+    /// restored afterwards.
+    fn test_write_set_sysarg_path_write_longer_path() {
+        let rootfs_path = get_test_rootfs_path();
+
+        let test_path = "my/impossible/test/path";
+        let long_path = PathBuf::from(format!(
+            "/a/very/long/rootfs/prefix/{}/{}",
+            "x".repeat(1000),
+            test_path
+        ));
+
+        fork_test(
+            rootfs_path,
+            // expecting an error (because the first path doesn't exit)
+            1,
+            // parent
+            |tracee, _| {
+                if tracee.regs.get_sys_num(Current) == MKDIR {
+                    tracee.regs.set_restore_original_regs(false);
+                    tracee.regs.save_current_regs(Original);
+
+                    let original_stack_pointer = tracee.regs.get(Original, StackPointer);
+                    let original_path_pointer = tracee.regs.get(Original, SysArg(SysArg1));
+
+                    assert!(tracee
+                        .regs
+                        .set_sysarg_path(
+                            SysArg1,
+                            &long_path,
+                            "setting long path for set_sysarg_path test",
+                        )
+                        .is_ok());
+
+                    let stack_pointer = tracee.regs.get(Current, StackPointer);
+                    let path_pointer = tracee.regs.get(Current, SysArg(SysArg1));
+
+                    // the new path is in a fresh block on the stack, below the
+                    // original stack pointer, and large enough for the path
+                    assert_ne!(path_pointer, original_path_pointer);
+                    assert_eq!(path_pointer, stack_pointer);
+                    assert!(
+                        original_stack_pointer - stack_pointer
+                            >= long_path.as_os_str().len() as Word + 1
+                    );
+
+                    // the written and newly read paths must be the same
+                    assert_eq!(tracee.regs.get_sysarg_path(SysArg1).unwrap(), long_path);
+
+                    // the stack pointer can be restored as done in the exit stage
+                    tracee
+                        .regs
+                        .restore_original(StackPointer, "restoring stack pointer for test");
+                    assert_eq!(
+                        tracee.regs.get(Current, StackPointer),
+                        original_stack_pointer
+                    );
+
+                    // we don't push the regs, we stop here
+                    true
+                } else {
+                    false
+                }
+            },
+            // child
+            || {
+                // calling the mkdir function, which should call the MKDIR syscall
+                execvp(
+                    &CString::new("mkdir").unwrap(),
+                    &[CString::new(".").unwrap(), CString::new(test_path).unwrap()],
+                )
+                .expect("failed execvp mkdir");
+            },
+        );
+    }
+
+    #[test]
+    /// Tests that scratch blocks can be reserved and written on the stack of
+    /// a real tracee, and that pushing the registers at the end of a stage
+    /// restores the stack pointer.
+    fn test_write_scratch() {
+        let rootfs_path = get_test_rootfs_path();
+
+        fork_test(
+            rootfs_path,
+            // expecting an error (because the path doesn't exit)
+            1,
+            // parent
+            |tracee, _| {
+                if tracee.regs.get_sys_num(Current) == MKDIR {
+                    tracee.regs.save_current_regs(Original);
+                    let original_stack_pointer = tracee.regs.get(Original, StackPointer);
+
+                    let struct_data = [0x42u8; 24];
+                    let struct_address = tracee.regs.write_scratch(&struct_data).unwrap();
+                    let string_address = tracee.regs.write_scratch(b"scratch\0").unwrap();
+
+                    assert!(struct_address < original_stack_pointer);
+                    assert!(string_address + 8 <= struct_address);
+                    assert_eq!(tracee.regs.get(Current, StackPointer), string_address);
+                    assert_eq!(
+                        tracee
+                            .regs
+                            .read_data(struct_address as *const c_void, struct_data.len())
+                            .unwrap(),
+                        struct_data
+                    );
+                    assert_eq!(
+                        tracee
+                            .regs
+                            .read_string(string_address as *const c_void, 8)
+                            .unwrap(),
+                        b"scratch"
+                    );
+
+                    // the original registers are restored when pushed, as done
+                    // at the end of the exit stage
+                    tracee.regs.set_restore_original_regs(true);
+                    tracee.regs.push_regs().unwrap();
+                    assert_eq!(
+                        tracee.regs.get(Current, StackPointer),
+                        original_stack_pointer
+                    );
+
+                    true
+                } else {
+                    false
+                }
+            },
+            // child
+            || {
+                execvp(
+                    &CString::new("mkdir").unwrap(),
+                    &[
+                        CString::new(".").unwrap(),
+                        CString::new("my/impossible/test/path").unwrap(),
+                    ],
+                )
+                .expect("failed execvp mkdir");
+            },
+        );
+    }
+
+    #[test]
+    /// Tests that a path which can't be written on the stack makes the enter
+    /// stage fail cleanly: the syscall is cancelled with `EFAULT`, and the
+    /// argument and the stack pointer are left untouched.
+    fn test_write_set_sysarg_path_write_failure() {
+        let rootfs_path = get_test_rootfs_path();
+
+        fork_test(
+            rootfs_path,
+            // expecting an error, since "/tmp" already exists
+            1,
+            // parent
+            |tracee, info_bag| {
+                if tracee.regs.get_sys_num(Current) == MKDIR {
+                    // the page at this address can never be mapped
+                    let bad_stack_pointer = 0x1000;
+                    tracee
+                        .regs
+                        .set(StackPointer, bad_stack_pointer, "make the stack unwritable");
+                    let path_pointer = tracee.regs.get(Current, SysArg(SysArg1));
+
+                    tracee.translate_syscall_enter(info_bag);
+
+                    assert_eq!(tracee.regs.get_sys_num(Current), Word::MAX as usize);
+                    assert_eq!(tracee.regs.get(Current, SysResult) as i32, -(EFAULT as i32));
+                    assert_eq!(tracee.regs.get(Current, SysArg(SysArg1)), path_pointer);
+                    assert_eq!(tracee.regs.get(Current, StackPointer), bad_stack_pointer);
+
+                    // we don't push the regs, we stop here
+                    true
+                } else {
+                    false
+                }
+            },
+            // child
+            || {
+                execvp(
+                    &CString::new("mkdir").unwrap(),
+                    &[CString::new(".").unwrap(), CString::new("/tmp").unwrap()],
+                )
+                .expect("failed execvp mkdir");
+            },
+        );
+    }
+
+    #[test]
+    fn test_write_set_sysarg_path_too_long() {
+        let mut regs = Registers::new(nix::unistd::getpid());
+        let long_path = PathBuf::from(format!("/{}", "x".repeat(regs.get_path_max())));
+
+        let error = regs
+            .set_sysarg_path(SysArg1, &long_path, "setting a path too long")
+            .unwrap_err();
+        assert_eq!(error.get_errno(), ENAMETOOLONG);
+    }
 }
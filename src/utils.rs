@@ -1,10 +1,13 @@
 #[cfg(test)]
 pub mod tests {
     use std::cell::RefCell;
+    use std::ffi::CString;
     use std::panic::AssertUnwindSafe;
     use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::{
-        env,
+        env, fs,
+        os::unix::fs::{symlink, MetadataExt},
         path::{Path, PathBuf},
     };
 
@@ -12,12 +15,13 @@ pub mod tests {
     use nix::sys::signal::kill;
     use nix::sys::signal::Signal::SIGSTOP;
     use nix::sys::wait;
-    use nix::sys::wait::WaitStatus::*;
+    use nix::sys::wait::WaitStatus::{self, *};
     use nix::sys::{ptrace, wait::WaitPidFlag};
     use nix::unistd;
-    use nix::unistd::{fork, getpid, ForkResult, Pid};
+    use nix::unistd::{execv, fork, getpid, ForkResult, Pid};
     use signal::Signal;
 
+    use crate::config::ProotConfig;
     use crate::errors::*;
     use crate::filesystem::FileSystem;
     use crate::process::proot::InfoBag;
@@ -29,7 +33,7 @@ pub mod tests {
     /// Since each rust unit tests is executed in a different thread, we
     /// should fork a child process to test the proot, otherwise the
     /// calls to `waitpid(-1)` from different unit tests may affect each other
-    fn test_in_subprocess<F: FnOnce()>(func: F) {
+    pub fn test_in_subprocess<F: FnOnce()>(func: F) {
         let pid = unsafe { fork() };
         match pid {
             Ok(ForkResult::Child) => {
@@ -67,7 +71,8 @@ pub mod tests {
         test_in_subprocess(|| {
             match unsafe { fork() }.expect("fork in test") {
                 ForkResult::Parent { child } => {
-                    let mut info_bag = InfoBag::new();
+                    let mut info_bag =
+                        InfoBag::new(ProotConfig::builder().rootfs(fs_root.as_ref()).build());
                     let mut tracee = Tracee::new(
                         child,
                         Rc::new(RefCell::new(
@@ -173,16 +178,93 @@ pub mod tests {
     >(
         func_syscall_hook: FuncSyscallHook,
         func_tracee: FuncTracee,
+    ) {
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .build(),
+            func_syscall_hook,
+            func_tracee,
+        )
+    }
+
+    /// Same as `test_with_proot()`, but runs `proot-rs` with the options of
+    /// `config`.
+    pub fn test_with_proot_config<
+        FuncSyscallHook: Fn(&Tracee, bool, bool) + 'static,
+        FuncTracee: FnOnce(),
+    >(
+        config: ProotConfig,
+        func_syscall_hook: FuncSyscallHook,
+        func_tracee: FuncTracee,
+    ) {
+        test_with_proot_config_and_exit_code(config, 0, func_syscall_hook, func_tracee)
+    }
+
+    /// Same as `test_with_proot_config()`, but expects the first tracee to
+    /// end with `expected_exit_code` instead of 0.
+    pub fn test_with_proot_config_and_exit_code<
+        FuncSyscallHook: Fn(&Tracee, bool, bool) + 'static,
+        FuncTracee: FnOnce(),
+    >(
+        config: ProotConfig,
+        expected_exit_code: i32,
+        func_syscall_hook: FuncSyscallHook,
+        func_tracee: FuncTracee,
+    ) {
+        run_test_with_proot(
+            config,
+            expected_exit_code,
+            None,
+            func_syscall_hook,
+            func_tracee,
+        )
+    }
+
+    /// Same as `test_with_proot()`, but the event loop services the tracees
+    /// in the order chosen by `func_scheduler_hook` (see
+    /// `PRoot::func_scheduler_hook`).
+    ///
+    /// The hook is given the events of the stopped tracees, and returns the
+    /// index of the one to handle next, or `None` to wait for another event.
+    pub fn test_with_proot_scheduler<
+        FuncSchedulerHook: Fn(&[WaitStatus]) -> Option<usize> + 'static,
+        FuncSyscallHook: Fn(&Tracee, bool, bool) + 'static,
+        FuncTracee: FnOnce(),
+    >(
+        func_scheduler_hook: FuncSchedulerHook,
+        func_syscall_hook: FuncSyscallHook,
+        func_tracee: FuncTracee,
+    ) {
+        run_test_with_proot(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .build(),
+            0,
+            Some(Box::new(func_scheduler_hook)),
+            func_syscall_hook,
+            func_tracee,
+        )
+    }
+
+    fn run_test_with_proot<
+        FuncSyscallHook: Fn(&Tracee, bool, bool) + 'static,
+        FuncTracee: FnOnce(),
+    >(
+        config: ProotConfig,
+        expected_exit_code: i32,
+        func_scheduler_hook: Option<Box<dyn Fn(&[WaitStatus]) -> Option<usize>>>,
+        func_syscall_hook: FuncSyscallHook,
+        func_tracee: FuncTracee,
     ) {
         test_in_subprocess(|| {
             let func = || -> Result<()> {
                 // setup FileSystem and PRoot
-                let root_path = get_test_rootfs_path();
-                let mut fs = FileSystem::with_root(root_path)?;
-                fs.set_cwd("/")?;
-                let mut proot: PRoot = PRoot::new();
+                let fs = config.create_filesystem()?;
+                let mut proot: PRoot = PRoot::new(InfoBag::new(config));
                 proot.init()?;
                 proot.func_syscall_hook = Some(Box::new(func_syscall_hook));
+                proot.func_scheduler_hook = func_scheduler_hook;
                 // fork first child process as tracee
                 match unsafe { unistd::fork() }.context("Failed to fork() when starting process")? {
                     ForkResult::Parent { child } => {
@@ -217,7 +299,7 @@ pub mod tests {
 
                 assert_eq!(
                     proot.init_exit_code,
-                    Some(0),
+                    Some(expected_exit_code),
                     "tracee exited with a bad exit code: {:?}",
                     proot.init_exit_code
                 );
@@ -227,6 +309,43 @@ pub mod tests {
         })
     }
 
+    /// Runs `func_tracee` inside `proot-rs` with a new host directory bound
+    /// read-only at `/mnt/ro`, holding the regular file `file` and the empty
+    /// directory `dir`. The host directory must be left unchanged by the
+    /// tracee.
+    pub fn test_with_read_only_binding<FuncTracee: FnOnce()>(name: &str, func_tracee: FuncTracee) {
+        let host_dir = env::temp_dir().join(format!("proot_rs_test_read_only_binding_{}", name));
+        let _ = fs::remove_dir_all(&host_dir);
+        fs::create_dir_all(host_dir.join("dir")).unwrap();
+        fs::write(host_dir.join("file"), "proot").unwrap();
+        let snapshot = || {
+            let mut entries: Vec<(PathBuf, u32, u64, i64)> = fs::read_dir(&host_dir)
+                .unwrap()
+                .map(|entry| {
+                    let path = entry.unwrap().path();
+                    let metadata = path.symlink_metadata().unwrap();
+                    (path, metadata.mode(), metadata.len(), metadata.mtime())
+                })
+                .collect();
+            entries.sort();
+            entries
+        };
+        let initial_entries = snapshot();
+
+        test_with_proot_config(
+            ProotConfig::builder()
+                .rootfs(get_test_rootfs_path())
+                .synthetic_mountpoints(true)
+                .read_only_binding(&host_dir, "/mnt/ro")
+                .build(),
+            |_, _, _| {},
+            func_tracee,
+        );
+
+        assert_eq!(snapshot(), initial_entries);
+        fs::remove_dir_all(&host_dir).unwrap();
+    }
+
     /// Get the path to the new root fs for the unit test, which is specified by
     /// the environment variable `PROOT_TEST_ROOTFS`.
     pub fn get_test_rootfs_path() -> PathBuf {
@@ -242,6 +361,139 @@ pub mod tests {
         panic!("Unknown guest rootfs path: Please set the environment variable PROOT_TEST_ROOTFS to the path of the guest rootfs")
     }
 
+    /// Applets of busybox linked in the `/bin` of a `MinimalRootfs`.
+    const MINIMAL_ROOTFS_APPLETS: &[&str] = &[
+        "sh", "ls", "cat", "echo", "true", "false", "mkdir", "rm", "ln", "readlink", "sleep",
+        "pwd", "grep",
+    ];
+
+    /// A minimal busybox-based guest rootfs, assembled in a new temporary
+    /// directory from the busybox (and its libraries, if any) of the test
+    /// rootfs (see `get_test_rootfs_path`).
+    ///
+    /// Unlike the test rootfs, it's owned by a single test, which can freely
+    /// modify it. It's removed when dropped.
+    pub struct MinimalRootfs {
+        path: PathBuf,
+    }
+
+    impl MinimalRootfs {
+        pub fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+            let path = env::temp_dir().join(format!(
+                "proot_rs_test_rootfs-{}-{}",
+                getpid(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = fs::remove_dir_all(&path);
+            let rootfs = MinimalRootfs { path: path };
+            rootfs.populate().expect("populate minimal rootfs");
+            rootfs
+        }
+
+        fn populate(&self) -> Result<()> {
+            let test_rootfs = get_test_rootfs_path();
+
+            for dir in &["bin", "etc", "tmp", "proc", "dev", "root"] {
+                fs::create_dir_all(self.path.join(dir))?;
+            }
+            fs::copy(
+                test_rootfs.join("bin/busybox"),
+                self.path.join("bin/busybox"),
+            )?;
+            for applet in MINIMAL_ROOTFS_APPLETS {
+                symlink("busybox", self.path.join("bin").join(applet))?;
+            }
+            // busybox may be dynamically linked
+            for dir in &["lib", "lib64"] {
+                let lib_path = test_rootfs.join(dir);
+                if lib_path.symlink_metadata().is_ok() {
+                    copy_tree(&lib_path, &self.path.join(dir))?;
+                }
+            }
+            fs::write(
+                self.path.join("etc/passwd"),
+                "root:x:0:0:root:/root:/bin/sh\n",
+            )?;
+            fs::write(self.path.join("etc/group"), "root:x:0:\n")?;
+            Ok(())
+        }
+
+        /// Host path of the rootfs.
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Creates the initial `FileSystem` of a proot-rs session in this
+        /// rootfs.
+        pub fn create_filesystem(&self) -> FileSystem {
+            self.config().create_filesystem().unwrap()
+        }
+
+        /// Default options of a proot-rs session in this rootfs.
+        pub fn config(&self) -> ProotConfig {
+            ProotConfig::builder().rootfs(&self.path).build()
+        }
+
+        /// Runs `func_tracee` inside `proot-rs` in this rootfs, see
+        /// `test_with_proot()`.
+        pub fn run<FuncTracee: FnOnce()>(&self, func_tracee: FuncTracee) {
+            test_with_proot_config(self.config(), |_, _, _| {}, func_tracee)
+        }
+    }
+
+    impl Drop for MinimalRootfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// Copies `src` into `dest` recursively, symbolic links included.
+    fn copy_tree(src: &Path, dest: &Path) -> Result<()> {
+        let metadata = src.symlink_metadata()?;
+        if metadata.file_type().is_symlink() {
+            symlink(fs::read_link(src)?, dest)?;
+        } else if metadata.is_dir() {
+            fs::create_dir_all(dest)?;
+            for entry in fs::read_dir(src)? {
+                let entry = entry?;
+                copy_tree(&entry.path(), &dest.join(entry.file_name()))?;
+            }
+        } else {
+            fs::copy(src, dest)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimal_rootfs_ls() {
+        let rootfs_path = {
+            let rootfs = MinimalRootfs::new();
+            assert!(rootfs.path().join("bin/busybox").is_file());
+            assert_eq!(
+                rootfs.create_filesystem().get_root(),
+                fs::canonicalize(rootfs.path()).unwrap()
+            );
+
+            // the exit code of `ls` is checked by the harness
+            rootfs.run(|| {
+                execv(
+                    &CString::new("/bin/ls").unwrap(),
+                    &[
+                        CString::new("ls").unwrap(),
+                        CString::new("/bin/sh").unwrap(),
+                    ],
+                )
+                .expect("failed execv /bin/ls");
+            });
+            rootfs.path().to_path_buf()
+        };
+
+        // the rootfs is removed when dropped
+        assert!(!rootfs_path.exists());
+    }
+
     #[test]
     #[should_panic]
     fn test_test_in_subprocess_assert_false() {